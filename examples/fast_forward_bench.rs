@@ -0,0 +1,71 @@
+// 循环快进基准测试：cargo run --release --example fast_forward_bench
+// 参考程序是 256×256 次 DJNZ R7,$ 组成的延时（约 1700 万条指令），期间定时器0每 1024 个机器周期中断一次，
+// 分别用默认方式（逐条 step，带循环快进）和极速模式 (run_turbo) 运行，比较速度并检查两者的结果完全相同
+
+use mcs51_emulator::emulator::{Emulator, HaltReason};
+use std::process;
+use std::time::Instant;
+
+const EXIT_PORT: u8 = 0xFF;
+const RUNS: usize = 5;
+
+// 0x0000: LJMP 0x0030
+// 0x000B: 定时器0中断: MOV TH0,#0xFC; INC 0x30; RETI
+// 0x0030: MOV TMOD,#0x01; MOV TH0,#0xFC; SETB TR0; MOV IE,#0x82
+//         MOV R6,#0 / 外层: MOV R7,#0 / DJNZ R7,$ / DJNZ R6,外层 / 写退出端口停机
+fn reference_program() -> Vec<u8> {
+    let mut code = vec![0; 0x30];
+    code[..3].copy_from_slice(&[0x02, 0x00, 0x30]);
+    code[0x0B..0x12].copy_from_slice(&[0x75, 0x8C, 0xFC, 0x05, 0x30, 0x32, 0x00]);
+    code.extend([0x75, 0x89, 0x01, 0x75, 0x8C, 0xFC, 0xD2, 0x8C, 0x75, 0xA8, 0x82, 0x7E, 0x00]);
+    let outer = code.len();
+    code.extend([0x7F, 0x00, 0xDF, 0xFE]);
+    code.extend([0xDE, (outer as isize - (code.len() + 2) as isize) as u8]);
+    code.extend([0x75, EXIT_PORT, 0x00, 0x80, 0xFE]);
+    code
+}
+
+// 运行一次，返回每秒模拟的机器周期数和运行结束时的状态
+fn run(turbo: bool, program: &[u8]) -> (f64, Vec<u8>) {
+    let mut emulator = Emulator::builder().turbo(turbo).build();
+    emulator.cpu.rom[..program.len()].copy_from_slice(program);
+    emulator.cpu.exit_port = Some(EXIT_PORT);
+
+    let start = Instant::now();
+    if turbo {
+        emulator.run_turbo();
+    } else {
+        while emulator.step() == HaltReason::Running {}
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    if emulator.halt_reason != HaltReason::ExitCode(0) {
+        eprintln!("参考程序没有正常结束: {:?}", emulator.halt_reason);
+        process::exit(1);
+    }
+
+    let mut state = emulator.cpu.ram.to_vec();
+    state.extend_from_slice(&emulator.cpu.sfr);
+    state.extend_from_slice(&emulator.cpu.registers.pc.to_le_bytes());
+    state.extend_from_slice(&emulator.instruction_count.to_le_bytes());
+    state.extend_from_slice(&emulator.clock_cycles.to_le_bytes());
+    state.extend_from_slice(&emulator.cpu.interrupt_entries.to_le_bytes());
+    (emulator.clock_cycles as f64 / 12.0 / elapsed, state)
+}
+
+fn main() {
+    let program = reference_program();
+    let (mut default_best, mut turbo_best) = (0.0f64, 0.0f64);
+    for _ in 0..RUNS {
+        let (default_speed, default_state) = run(false, &program);
+        let (turbo_speed, turbo_state) = run(true, &program);
+        if default_state != turbo_state {
+            eprintln!("循环快进的运行结果与极速模式不同");
+            process::exit(1);
+        }
+        default_best = default_best.max(default_speed);
+        turbo_best = turbo_best.max(turbo_speed);
+    }
+    println!("循环快进：{:.0} 个机器周期/秒", default_best);
+    println!("极速模式：{:.0} 个机器周期/秒", turbo_best);
+    println!("循环快进相对极速模式：{:.2} 倍，运行结果相同", default_best / turbo_best);
+}
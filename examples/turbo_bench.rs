@@ -0,0 +1,70 @@
+// 极速模式基准测试：cargo run --release --example turbo_bench
+// 参考程序是 256×256 次展开的校验和计算（约 400 万条指令，循环体超过循环检测的范围，默认方式不会快进），
+// 分别用默认方式（逐条 step）和极速模式 (run_turbo) 运行，比较速度并检查两者的结果完全相同
+
+use mcs51_emulator::emulator::{Emulator, HaltReason};
+use std::process;
+use std::time::Instant;
+
+const EXIT_PORT: u8 = 0xFF;
+const RUNS: usize = 5;
+
+// MOV R6,#0 / 外层: MOV R7,#0 / 内层: 12 组 MOV A,d; ADD A,R7; RL A; XRL A,d+1; MOV d,A / DJNZ R7,内层 / DJNZ R6,外层
+// 最后写退出端口停机
+fn reference_program() -> Vec<u8> {
+    let mut code = vec![0x7E, 0x00];
+    let outer = code.len();
+    code.extend([0x7F, 0x00]);
+    let inner = code.len();
+    for k in 0..12u8 {
+        code.extend([0xE5, 0x30 + k, 0x2F, 0x23, 0x65, 0x31 + k, 0xF5, 0x30 + k]);
+    }
+    code.extend([0xDF, (inner as isize - (code.len() + 2) as isize) as u8]);
+    code.extend([0xDE, (outer as isize - (code.len() + 2) as isize) as u8]);
+    code.extend([0x75, EXIT_PORT, 0x00, 0x80, 0xFE]);
+    code
+}
+
+// 运行一次，返回每秒执行的指令数和运行结束时的状态
+fn run(turbo: bool, program: &[u8]) -> (f64, Vec<u8>) {
+    let mut emulator = Emulator::builder().turbo(turbo).build();
+    emulator.cpu.rom[..program.len()].copy_from_slice(program);
+    emulator.cpu.exit_port = Some(EXIT_PORT);
+
+    let start = Instant::now();
+    if turbo {
+        emulator.run_turbo();
+    } else {
+        while emulator.step() == HaltReason::Running {}
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    if emulator.halt_reason != HaltReason::ExitCode(0) {
+        eprintln!("参考程序没有正常结束: {:?}", emulator.halt_reason);
+        process::exit(1);
+    }
+
+    let mut state = emulator.cpu.ram.to_vec();
+    state.extend_from_slice(&emulator.cpu.sfr);
+    state.extend_from_slice(&emulator.cpu.registers.pc.to_le_bytes());
+    state.extend_from_slice(&emulator.instruction_count.to_le_bytes());
+    state.extend_from_slice(&emulator.clock_cycles.to_le_bytes());
+    (emulator.instruction_count as f64 / elapsed, state)
+}
+
+fn main() {
+    let program = reference_program();
+    let (mut default_best, mut turbo_best) = (0.0f64, 0.0f64);
+    for _ in 0..RUNS {
+        let (default_speed, default_state) = run(false, &program);
+        let (turbo_speed, turbo_state) = run(true, &program);
+        if default_state != turbo_state {
+            eprintln!("极速模式的运行结果与默认方式不同");
+            process::exit(1);
+        }
+        default_best = default_best.max(default_speed);
+        turbo_best = turbo_best.max(turbo_speed);
+    }
+    println!("默认方式：{:.0} 条指令/秒", default_best);
+    println!("极速模式：{:.0} 条指令/秒", turbo_best);
+    println!("加速比：{:.2}，运行结果相同", turbo_best / default_best);
+}
@@ -0,0 +1,143 @@
+// ROM 代码覆盖率（--coverage）：找出已加载的映像中从未执行过的代码
+// 每个 ROM 地址一位，执行一条指令时把它的每个字节（长度由反汇编器的指令长度表给出）都标记为已执行
+// 统计只针对已加载的字节；映像中的数据表（查表常量、字符串）本来就不会被执行，同样列为未执行
+
+use crate::symbols::{self, SymbolTable};
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+pub struct Coverage {
+    executed: Vec<u64>, // 每个地址一位，共 64K 位
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage { executed: vec![0; 0x10000 / 64] }
+    }
+
+    // 标记从 address 开始的一条 length 字节的指令已执行（超过 0xFFFF 时回绕到 0x0000）
+    pub fn mark(&mut self, address: u16, length: u8) {
+        for offset in 0..length as u16 {
+            let address = address.wrapping_add(offset) as usize;
+            self.executed[address / 64] |= 1 << (address % 64);
+        }
+    }
+
+    // 地址处的字节是否作为指令的一部分执行过
+    pub fn is_executed(&self, address: u16) -> bool {
+        let address = address as usize;
+        self.executed[address / 64] & (1 << (address % 64)) != 0
+    }
+
+    // 已加载（loaded 返回 true）的字节中执行过的字节数和总字节数
+    pub fn summary(&self, loaded: impl Fn(u16) -> bool) -> (usize, usize) {
+        let mut covered = 0;
+        let mut total = 0;
+        for address in 0..=0xFFFF {
+            if loaded(address) {
+                total += 1;
+                if self.is_executed(address) {
+                    covered += 1;
+                }
+            }
+        }
+        (covered, total)
+    }
+
+    // 已加载但从未执行过的连续地址范围，按地址排列
+    pub fn uncovered_ranges(&self, loaded: impl Fn(u16) -> bool) -> Vec<RangeInclusive<u16>> {
+        let mut ranges = Vec::new();
+        let mut start = None;
+        for address in 0..=0xFFFF {
+            let uncovered = loaded(address) && !self.is_executed(address);
+            match (uncovered, start) {
+                (true, None) => start = Some(address),
+                (false, Some(first)) => {
+                    ranges.push(first..=address - 1);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(first) = start {
+            ranges.push(first..=0xFFFF);
+        }
+        ranges
+    }
+
+    // 文本报告：覆盖的字节数和百分比，然后是未执行的范围（起止地址、字节数和所在标号）
+    pub fn report(&self, loaded: impl Fn(u16) -> bool, symbols: &SymbolTable) -> String {
+        let (covered, total) = self.summary(&loaded);
+        let ranges = self.uncovered_ranges(&loaded);
+        let mut out = String::new();
+
+        let percent = if total == 0 { 0.0 } else { covered as f64 * 100.0 / total as f64 };
+        let _ = writeln!(out, "代码覆盖率：已执行 {} / {} 字节（{:.2}%）", covered, total, percent);
+        let _ = writeln!(out);
+        let _ = writeln!(out, "未执行的范围（{} 段）：", ranges.len());
+        for range in ranges {
+            let (start, end) = (*range.start(), *range.end());
+            let label = symbols::symbolize(symbols, start).unwrap_or_default();
+            let line = format!("  {:#06x}-{:#06x} {:>6} 字节  {}", start, end, end as usize - start as usize + 1, label);
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::{Emulator, HaltReason};
+    use crate::symbols::parse_symbols;
+    use crate::test_support::run_to_halt;
+
+    // MOV A,#1; JNZ done; never: MOV 0x30,#0x55; NOP; NOP; done: MOV 0xFF,#0
+    // A 不为零，0x0004-0x0008 永远不会执行
+    const UNREACHABLE_BRANCH: &str = ":0C00000074017005753055000075FF009C\n:00000001FF\n";
+
+    fn run_with_coverage() -> Emulator {
+        let mut emulator = Emulator::new(false);
+        emulator.load_hex(UNREACHABLE_BRANCH).unwrap();
+        emulator.set_symbols(parse_symbols("0x0004 never\n0x0009 done\n"));
+        emulator.cpu.exit_port = Some(0xFF);
+        emulator.enable_coverage();
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::ExitCode(0));
+        emulator
+    }
+
+    #[test]
+    fn unreachable_branch_is_reported_as_uncovered() {
+        let emulator = run_with_coverage();
+        let coverage = emulator.coverage.as_ref().unwrap();
+        let loaded = |address| emulator.cpu.is_loaded(address);
+        assert_eq!(coverage.summary(loaded), (7, 12));
+        assert_eq!(coverage.uncovered_ranges(loaded), [0x0004..=0x0008]);
+        // 多字节指令的每个字节都标记为已执行
+        assert!(coverage.is_executed(0x000A) && coverage.is_executed(0x000B));
+
+        let report = emulator.coverage_report().unwrap();
+        assert_eq!(
+            report,
+            "代码覆盖率：已执行 7 / 12 字节（58.33%）\n\n未执行的范围（1 段）：\n  0x0004-0x0008      5 字节  never\n"
+        );
+    }
+
+    #[test]
+    fn ranges_run_to_the_end_of_memory_and_wrap_when_marking() {
+        let mut coverage = Coverage::new();
+        coverage.mark(0xFFFF, 3);
+        assert!(coverage.is_executed(0xFFFF) && coverage.is_executed(0x0000) && coverage.is_executed(0x0001));
+        let ranges = coverage.uncovered_ranges(|address| !(4..0xFFF0).contains(&address));
+        assert_eq!(ranges, [0x0002..=0x0003, 0xFFF0..=0xFFFE]);
+
+        let coverage = Coverage::new();
+        assert_eq!(coverage.uncovered_ranges(|address| address >= 0xFFFE), [0xFFFE..=0xFFFF]);
+    }
+}
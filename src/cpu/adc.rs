@@ -0,0 +1,182 @@
+// 片上 ADC（可选）
+// 标准 8051 没有 ADC，许多派生型号（如 ADuC812、C8051F 系列）有；这里实现一个通用的 8 位模型，
+// 默认不存在，用 enable_adc 挂上后才响应 ADCCON/ADCDATA 的访问
+// ADCCON：bit7 ADCI 转换完成标志（中断请求，需软件清除），bit6 ADCS 写1启动转换（完成时硬件清除），
+//         bit2-0 通道号
+// 启动时锁存所选通道的输入值，经过 conversion_cycles 个机器周期后写入 ADCDATA 并置位 ADCI
+// ADCI 在 IE.6 (EADC) 使能、IP.6 (PADC) 设定优先级时产生中断
+
+use super::CPU;
+use log::debug;
+
+// ADCCON 位定义
+pub const ADCCON_ADCI: u8 = 0x80; // 转换完成标志
+pub const ADCCON_ADCS: u8 = 0x40; // 启动转换 / 转换进行中
+pub const ADCCON_CHANNEL: u8 = 0x07; // 通道选择
+
+// 中断使能和优先级位（IE.6 / IP.6 在标准 8051 上未使用）
+pub const IE_EADC: u8 = 0x40;
+pub const IP_PADC: u8 = 0x40;
+
+// ADC 寄存器地址、中断向量和转换时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdcConfig {
+    pub adccon: u8,             // 控制寄存器地址（放在可位寻址的地址上，程序可以用 JNB ADCI,$ 等待）
+    pub adcdata: u8,            // 转换结果寄存器地址
+    pub vector: u16,            // 中断向量地址
+    pub conversion_cycles: u32, // 一次转换占用的机器周期数
+}
+
+impl Default for AdcConfig {
+    // 与 ADuC812 的布局相同：ADCCON2 在 0xD8，结果在 0xD9，中断向量 0x0033
+    fn default() -> Self {
+        AdcConfig { adccon: 0xD8, adcdata: 0xD9, vector: 0x0033, conversion_cycles: 16 }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Adc {
+    pub config: AdcConfig,
+    pub channels: [u8; 8],  // 各通道的输入值（由宿主程序设置）
+    latched: u8,            // 启动转换时锁存的输入值
+    pub(crate) remaining: u32, // 距离转换完成还剩的机器周期数（0 表示空闲）
+}
+
+impl Adc {
+    pub fn new(config: AdcConfig) -> Self {
+        Adc { config, ..Default::default() }
+    }
+
+    // 复位：停止正在进行的转换，寄存器地址和通道输入属于外部环境，保留
+    pub fn reset(&mut self) {
+        self.latched = 0;
+        self.remaining = 0;
+    }
+}
+
+impl CPU {
+    // 挂上片上 ADC，ADCCON/ADCDATA 放在 config 给出的 SFR 地址上
+    pub fn enable_adc(&mut self, config: AdcConfig) {
+        self.adc = Some(Adc::new(config));
+    }
+
+    // 设置 ADC 通道的输入值（channel 0-7），下一次启动该通道的转换时生效
+    // 没有挂上 ADC 时不起作用
+    pub fn set_adc_channel(&mut self, channel: u8, value: u8) {
+        if let Some(adc) = self.adc.as_mut() {
+            adc.channels[(channel & ADCCON_CHANNEL) as usize] = value;
+        }
+    }
+
+    // ADCCON 的地址（没有 ADC 时为 None）
+    pub(crate) fn adccon_address(&self) -> Option<u8> {
+        self.adc.as_ref().map(|adc| adc.config.adccon)
+    }
+
+    // 写 ADCCON：ADCS 由0变1时锁存所选通道并开始转换，转换进行中再写 ADCS 不会重新开始
+    pub(crate) fn adc_write_con(&mut self, value: u8) {
+        let Some(adc) = self.adc.as_mut() else {
+            return;
+        };
+        let address = (adc.config.adccon - 0x80) as usize;
+        if (value & ADCCON_ADCS) != 0 && adc.remaining == 0 {
+            adc.latched = adc.channels[(value & ADCCON_CHANNEL) as usize];
+            adc.remaining = adc.config.conversion_cycles.max(1);
+            if self.debug {
+                debug!("[ADC] 通道 {} 开始转换", value & ADCCON_CHANNEL);
+            }
+        }
+        // 转换进行中 ADCS 保持为1
+        let busy = if adc.remaining > 0 { ADCCON_ADCS } else { 0 };
+        self.sfr[address] = (value & !ADCCON_ADCS) | busy;
+    }
+
+    // ADC 转换计时（每个机器周期调用一次）
+    pub(crate) fn update_adc(&mut self) {
+        let Some(adc) = self.adc.as_mut() else {
+            return;
+        };
+        if adc.remaining == 0 {
+            return;
+        }
+        adc.remaining -= 1;
+        if adc.remaining == 0 {
+            let (adccon, adcdata, result) = (adc.config.adccon, adc.config.adcdata, adc.latched);
+            self.sfr[(adcdata - 0x80) as usize] = result;
+            let con = &mut self.sfr[(adccon - 0x80) as usize];
+            *con = (*con & !ADCCON_ADCS) | ADCCON_ADCI;
+        }
+    }
+
+    // 距离转换完成还剩多少个机器周期（空闲返回0，用于快进优化）
+    pub(crate) fn cycles_until_adc_done(&self) -> u64 {
+        self.adc.as_ref().map_or(0, |adc| adc.remaining as u64)
+    }
+
+    // ADC 转换完成标志 ADCI（没有 ADC 时为 0）
+    pub(crate) fn adc_flag(&self) -> u8 {
+        self.adccon_address().map_or(0, |address| self.sfr[(address - 0x80) as usize] & ADCCON_ADCI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Emulator;
+    use crate::test_support::{emulator_with_blocks, run_steps};
+
+    // 执行到 pc 为止，返回执行的步数
+    fn run_until_pc(emulator: &mut Emulator, pc: u16) -> usize {
+        let mut steps = 0;
+        while emulator.cpu.registers.pc != pc {
+            emulator.step();
+            steps += 1;
+            assert!(steps < 1000, "没有到达 {:#06x}", pc);
+        }
+        steps
+    }
+
+    #[test]
+    fn polled_conversion_returns_the_channel_value() {
+        // MOV ADCCON,#0x43（ADCS，通道 3）; JNB ADCI,$; MOV A,ADCDATA; SJMP $
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x75, 0xD8, 0x43, 0x30, 0xDF, 0xFD, 0xE5, 0xD9, 0x80, 0xFE])]);
+        emulator.cpu.enable_adc(AdcConfig::default());
+        emulator.cpu.set_adc_channel(3, 0xA7);
+        emulator.cpu.set_adc_channel(4, 0x11);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.peek_sfr(0xD8) & ADCCON_ADCS, ADCCON_ADCS);
+
+        run_until_pc(&mut emulator, 0x0008);
+        assert_eq!(emulator.cpu.registers.acc, 0xA7);
+        assert_eq!(emulator.cpu.peek_sfr(0xD8), ADCCON_ADCI | 0x03);
+        // 启动之后至少经过 16 个机器周期才得到结果
+        assert!(emulator.clock_cycles >= (2 + 16) * 12);
+    }
+
+    #[test]
+    fn completion_interrupt_reads_result() {
+        // 主程序：MOV IE,#0xC0 (EA|EADC); MOV ADCCON,#0x45; SJMP $
+        // ADC 中断 (0x0033)：MOV 0x40,ADCDATA; CLR ADCI; RETI
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x75, 0xA8, 0xC0, 0x75, 0xD8, 0x45, 0x80, 0xFE]),
+            (0x0033, &[0x85, 0xD9, 0x40, 0xC2, 0xDF, 0x32]),
+        ]);
+        emulator.cpu.enable_adc(AdcConfig::default());
+        emulator.cpu.set_adc_channel(5, 0x3C);
+        run_until_pc(&mut emulator, 0x0033);
+        run_until_pc(&mut emulator, 0x0006);
+        assert_eq!(emulator.cpu.ram[0x40], 0x3C);
+        assert_eq!(emulator.cpu.peek_sfr(0xD8) & ADCCON_ADCI, 0);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+    }
+
+    #[test]
+    fn no_adc_unless_enabled() {
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x75, 0xD8, 0x41, 0x80, 0xFE])]);
+        emulator.cpu.set_adc_channel(1, 0x99);
+        run_steps(&mut emulator, 20);
+        assert!(emulator.cpu.adc.is_none());
+        assert_eq!(emulator.cpu.adc_flag(), 0);
+        assert_ne!(emulator.cpu.peek_sfr(0xD9), 0x99);
+    }
+}
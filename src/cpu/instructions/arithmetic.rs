@@ -1,4 +1,5 @@
 // 算术指令模块
+use super::super::peripherals::{ACC, B};
 use super::super::CPU;
 use super::{InstructionInfo, InstructionTable};
 
@@ -11,6 +12,16 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // INC direct指令
     table[0x05] = Some(InstructionInfo { handler: |cpu, _| cpu.inc_direct(), mnemonic: "INC" });
     
+    // INC @Ri指令 (0x06-0x07)
+    table[0x06] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.inc_ri(op - 0x06), 
+        mnemonic: "INC" 
+    });
+    table[0x07] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.inc_ri(op - 0x06), 
+        mnemonic: "INC" 
+    });
+    
     // INC Rn指令 (0x08-0x0F)
     for opcode in 0x08..=0x0F {
         table[opcode] = Some(InstructionInfo { 
@@ -22,6 +33,19 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // DEC A指令
     table[0x14] = Some(InstructionInfo { handler: |cpu, _| cpu.dec_acc(), mnemonic: "DEC" });
     
+    // DEC direct指令
+    table[0x15] = Some(InstructionInfo { handler: |cpu, _| cpu.dec_direct(), mnemonic: "DEC" });
+    
+    // DEC @Ri指令 (0x16-0x17)
+    table[0x16] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.dec_rn_indirect(op - 0x16), 
+        mnemonic: "DEC" 
+    });
+    table[0x17] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.dec_rn_indirect(op - 0x16), 
+        mnemonic: "DEC" 
+    });
+    
     // DEC Rn指令 (0x18-0x1F)
     for opcode in 0x18..=0x1F {
         table[opcode] = Some(InstructionInfo { 
@@ -36,6 +60,16 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // ADD A, direct指令
     table[0x25] = Some(InstructionInfo { handler: |cpu, _| cpu.add_a_direct(), mnemonic: "ADD" });
     
+    // ADD A, @Ri指令 (0x26-0x27)
+    table[0x26] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.add_a_ri(op - 0x26), 
+        mnemonic: "ADD" 
+    });
+    table[0x27] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.add_a_ri(op - 0x26), 
+        mnemonic: "ADD" 
+    });
+    
     // ADD A, Rn指令 (0x28-0x2F)
     for opcode in 0x28..=0x2F {
         table[opcode] = Some(InstructionInfo { 
@@ -47,9 +81,22 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // ADDC A, #data指令
     table[0x34] = Some(InstructionInfo { handler: |cpu, _| cpu.addc_acc_immediate(), mnemonic: "ADDC" });
     
+    // SUBB A, #data指令
+    table[0x94] = Some(InstructionInfo { handler: |cpu, _| cpu.subb_a_immediate(), mnemonic: "SUBB" });
+    
     // SUBB A, direct指令
     table[0x95] = Some(InstructionInfo { handler: |cpu, _| cpu.subb_a_direct(), mnemonic: "SUBB" });
     
+    // SUBB A, @Ri指令 (0x96-0x97)
+    table[0x96] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.subb_a_ri(op - 0x96), 
+        mnemonic: "SUBB" 
+    });
+    table[0x97] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.subb_a_ri(op - 0x96), 
+        mnemonic: "SUBB" 
+    });
+    
     // SUBB A, Rn指令 (0x98-0x9F)
     for opcode in 0x98..=0x9F {
         table[opcode] = Some(InstructionInfo { 
@@ -68,17 +115,17 @@ pub fn register_instructions(table: &mut InstructionTable) {
 impl CPU {
     // INC A - 累加器加1
     pub(crate) fn inc_acc(&mut self) {
-        self.registers.acc = self.registers.acc.wrapping_add(1);
+        self.set_acc(self.registers.acc.wrapping_add(1));
         if self.debug {
-            println!("inc A");
+            trace_line!(self, "inc A");
         }
     }
 
     // DEC A - 累加器减1
     pub(crate) fn dec_acc(&mut self) {
-        self.registers.acc = self.registers.acc.wrapping_sub(1);
+        self.set_acc(self.registers.acc.wrapping_sub(1));
         if self.debug {
-            println!("dec A");
+            trace_line!(self, "dec A");
         }
     }
 
@@ -87,16 +134,31 @@ impl CPU {
         let value = self.read_register(reg_num).wrapping_sub(1);
         self.write_register(reg_num, value);
         if self.debug {
-            println!("dec R{}", reg_num);
+            trace_line!(self, "dec R{}", reg_num);
         }
     }
 
+    // 加法 A = A + operand + carry，ADD（carry 为 0）和 ADDC（carry 为 CY）各寻址方式共用
+    // CY：第7位向上进位；AC：第3位向第4位进位；OV：两个符号相同的数相加、结果符号与它们不同
+    pub(crate) fn add8(&mut self, operand: u8, carry: u8) {
+        let acc = self.registers.acc;
+        let sum = acc as u16 + operand as u16 + carry as u16;
+        let result = sum as u8;
+
+        let mut flags = self.psw_flags();
+        flags.cy = sum > 0xFF;
+        flags.ac = (acc & 0x0F) + (operand & 0x0F) + carry > 0x0F;
+        flags.ov = ((acc ^ result) & (operand ^ result) & 0x80) != 0;
+        self.set_acc(result);
+        self.set_psw_flags(flags);
+    }
+
     // ADD A, #data - 累加器加立即数
     pub(crate) fn add_acc_immediate(&mut self) {
         let immediate = self.fetch_next_byte();
-        self.registers.acc = self.registers.acc.wrapping_add(immediate);
+        self.add8(immediate, 0);
         if self.debug {
-            println!("add A, #{:#04x}", immediate);
+            trace_line!(self, "add A, #{:#04x}", immediate);
         }
     }
 
@@ -104,9 +166,10 @@ impl CPU {
     pub(crate) fn add_a_rn(&mut self, reg_num: u8) {
         let value = self.read_register(reg_num);
         let old_acc = self.registers.acc;
-        self.registers.acc = self.registers.acc.wrapping_add(value);
+        self.add8(value, 0);
         if self.debug {
-            println!(
+            trace_line!(
+                self,
                 "{:<30}\t(A: {} + R{}: {} = {})",
                 format!("add A, R{}", reg_num), old_acc, reg_num, value, self.registers.acc
             );
@@ -116,16 +179,22 @@ impl CPU {
     // ADD A, direct - 累加器加直接地址
     pub(crate) fn add_a_direct(&mut self) {
         let direct_address = self.fetch_next_byte();
-        let value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
-        
-        self.registers.acc = self.registers.acc.wrapping_add(value);
-        
+        let value = self.read_internal(direct_address);
+        self.add8(value, 0);
+
         if self.debug {
-            println!("add A, {:#04x}", direct_address);
+            trace_line!(self, "add A, {:#04x}", direct_address);
+        }
+    }
+
+    // ADD A, @Ri - 累加器加Ri指向的内部RAM单元
+    pub(crate) fn add_a_ri(&mut self, reg_num: u8) {
+        let addr = self.read_register(reg_num);
+        let value = self.read_indirect(addr);
+        self.add8(value, 0);
+
+        if self.debug {
+            trace_line!(self, "add A, @R{}", reg_num);
         }
     }
 
@@ -133,115 +202,106 @@ impl CPU {
     pub(crate) fn addc_acc_immediate(&mut self) {
         let immediate = self.fetch_next_byte();
         let carry = self.get_carry_flag();
-        self.registers.acc = self
-            .registers
-            .acc
-            .wrapping_add(immediate)
-            .wrapping_add(carry);
+        self.add8(immediate, carry);
         if self.debug {
-            println!("addc A, #{:#04x}", immediate);
+            trace_line!(self, "addc A, #{:#04x}", immediate);
         }
     }
 
-    // MUL AB - 乘法指令（使用加法模拟）
+    // MUL AB - 累加器乘以B寄存器，16位乘积低字节在A、高字节在B
     pub(crate) fn mul_ab(&mut self) {
         let a = self.registers.acc;
         let b = self.registers.b;
-        let mut result: u16 = 0;
+        let result = (a as u16) * (b as u16);
 
-        for _ in 0..b {
-            result = result.wrapping_add(a as u16);
-        }
+        self.write_sfr(ACC, (result & 0xFF) as u8); // 低8位存入A
+        self.write_sfr(B, (result >> 8) as u8); // 高8位存入B
 
-        self.registers.acc = (result & 0xFF) as u8; // 低8位存入A
-        self.registers.b = (result >> 8) as u8; // 高8位存入B
+        // CY 总是清零；乘积大于 0xFF 时置位 OV
+        let mut flags = self.psw_flags();
+        flags.cy = false;
+        flags.ov = result > 0xFF;
+        self.set_psw_flags(flags);
 
         if self.debug {
-            println!("{:<30}\t(A = {}, B = {}, Result = {})", "mul AB", a, b, result);
+            trace_line!(self, "{:<30}\t(A = {}, B = {}, Result = {})", "mul AB", a, b, result);
         }
     }
 
-    // DIV AB - 累加器除以B寄存器
+    // DIV AB - 累加器除以B寄存器，商在A、余数在B
+    // CY 总是清零；除数为0时置位 OV，A 和 B 保持原值（数据手册规定结果不确定）
     pub(crate) fn div_ab(&mut self) {
-        let a = self.registers.acc;
-        let b = self.read_sfr(0xF0); // B寄存器在0xF0
-
-        if b == 0 {
-            // 除以0，设置溢出标志
-            let psw = self.read_sfr(0xD0);
-            self.write_sfr(0xD0, psw | 0x04); // 设置OV位
-        } else {
-            let quotient = a / b;
-            let remainder = a % b;
-
-            self.registers.acc = quotient;
-            self.write_sfr(0xF0, remainder); // 余数到B寄存器
-
-            // 清除进位和溢出标志
-            let psw = self.read_sfr(0xD0);
-            self.write_sfr(0xD0, psw & 0x7B); // 清除CY和OV位
+        let a = self.read_sfr(ACC);
+        let b = self.read_sfr(B);
+        let mut flags = self.psw_flags();
+        flags.cy = false;
+        flags.ov = b == 0;
+
+        if let Some(quotient) = a.checked_div(b) {
+            self.write_sfr(ACC, quotient);
+            self.write_sfr(B, a % b);
+        }
+        self.set_psw_flags(flags);
+
+        if self.debug {
+            trace_line!(self, "{:<30}\t(A = {}, B = {})", "div AB", a, b);
         }
+    }
+
+    // 带借位减法 A = A - operand - CY，SUBB 各寻址方式共用
+    // CY：第7位向上借位；AC：第3位向第4位借位；OV：两个符号不同的数相减、结果符号与被减数不同
+    pub(crate) fn sub8(&mut self, operand: u8) {
+        let acc = self.registers.acc;
+        let borrow = self.get_carry_flag();
+        let result = acc.wrapping_sub(operand).wrapping_sub(borrow);
+
+        let mut flags = self.psw_flags();
+        flags.cy = (acc as u16) < (operand as u16) + (borrow as u16);
+        flags.ac = (acc & 0x0F) < (operand & 0x0F) + borrow;
+        flags.ov = ((acc ^ operand) & (acc ^ result) & 0x80) != 0;
+        self.set_acc(result);
+        self.set_psw_flags(flags);
+    }
+
+    // SUBB A, #data - 累加器减去立即数和进位标志
+    pub(crate) fn subb_a_immediate(&mut self) {
+        let immediate = self.fetch_next_byte();
+        self.sub8(immediate);
 
         if self.debug {
-            println!("div AB");
+            trace_line!(self, "subb A, #{:#04x}", immediate);
         }
     }
 
     // SUBB A, direct - 累加器减去直接地址和进位标志
     pub(crate) fn subb_a_direct(&mut self) {
         let direct_address = self.fetch_next_byte();
-        let value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
-
-        let psw = self.read_sfr(0xD0);
-        let carry = (psw >> 7) & 1; // 获取进位标志
-
-        // 使用扩展精度计算以检测借位
-        let acc = self.registers.acc as u16;
-        let operand = (value as u16) + (carry as u16);
-        let result = acc.wrapping_sub(operand);
-        
-        self.registers.acc = result as u8;
-        
-        // 设置进位标志：如果发生借位（acc < operand），CY = 1
-        let new_psw = if acc < operand {
-            psw | 0x80  // 设置CY位
-        } else {
-            psw & 0x7F  // 清除CY位
-        };
-        self.write_sfr(0xD0, new_psw);
+        let value = self.read_internal(direct_address);
+        self.sub8(value);
+
+        if self.debug {
+            trace_line!(self, "subb A, {:#04x}", direct_address);
+        }
+    }
+
+    // SUBB A, @Ri - 累加器减去Ri指向的内部RAM单元和进位标志
+    pub(crate) fn subb_a_ri(&mut self, reg_num: u8) {
+        let addr = self.read_register(reg_num);
+        let value = self.read_indirect(addr);
+        self.sub8(value);
 
         if self.debug {
-            println!("subb A, {:#04x}", direct_address);
+            trace_line!(self, "subb A, @R{}", reg_num);
         }
     }
 
     // SUBB A, Rn - 累加器减去寄存器Rn和进位标志
     pub(crate) fn subb_a_rn(&mut self, reg_num: u8) {
         let value = self.read_register(reg_num);
-        let psw = self.read_sfr(0xD0);
-        let carry = (psw >> 7) & 1; // 获取进位标志
-        
-        // 使用扩展精度计算以检测借位
-        let acc = self.registers.acc as u16;
-        let operand = (value as u16) + (carry as u16);
-        let result = acc.wrapping_sub(operand);
-        
-        self.registers.acc = result as u8;
-        
-        // 设置进位标志：如果发生借位（acc < operand），CY = 1
-        let new_psw = if acc < operand {
-            psw | 0x80  // 设置CY位
-        } else {
-            psw & 0x7F  // 清除CY位
-        };
-        self.write_sfr(0xD0, new_psw);
-        
+        self.sub8(value);
+
         if self.debug {
-            println!("subb A, R{}", reg_num);
+            trace_line!(self, "subb A, R{}", reg_num);
         }
     }
 
@@ -251,7 +311,7 @@ impl CPU {
         self.write_register(reg_num, value);
 
         if self.debug {
-            println!("inc R{}", reg_num);
+            trace_line!(self, "inc R{}", reg_num);
         }
     }
 
@@ -259,22 +319,278 @@ impl CPU {
     pub(crate) fn inc_direct(&mut self) {
         let direct_address = self.fetch_next_byte();
         
-        let value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
+        let value = self.read_internal(direct_address);
         
         let new_value = value.wrapping_add(1);
         
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = new_value;
-        } else {
-            self.write_sfr(direct_address, new_value);
+        self.write_internal(direct_address, new_value);
+        
+        if self.debug {
+            trace_line!(self, "inc {:#04x}", direct_address);
+        }
+    }
+
+    // INC @Ri - Ri指向的内部RAM单元加1
+    pub(crate) fn inc_ri(&mut self, reg_num: u8) {
+        let addr = self.read_register(reg_num);
+        let value = self.read_indirect(addr).wrapping_add(1);
+        self.write_indirect(addr, value);
+        
+        if self.debug {
+            trace_line!(self, "inc @R{}", reg_num);
+        }
+    }
+
+    // DEC direct - 直接地址减1
+    pub(crate) fn dec_direct(&mut self) {
+        let direct_address = self.fetch_next_byte();
+        
+        let value = self.read_internal(direct_address);
+        
+        let new_value = value.wrapping_sub(1);
+        
+        self.write_internal(direct_address, new_value);
+        
+        if self.debug {
+            trace_line!(self, "dec {:#04x}", direct_address);
         }
+    }
+
+    // DEC @Ri - Ri指向的内部RAM单元减1
+    pub(crate) fn dec_rn_indirect(&mut self, reg_num: u8) {
+        let addr = self.read_register(reg_num);
+        let value = self.read_indirect(addr).wrapping_sub(1);
+        self.write_indirect(addr, value);
         
         if self.debug {
-            println!("inc {:#04x}", direct_address);
+            trace_line!(self, "dec @R{}", reg_num);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::instructions::PswFlags;
+    use crate::cpu::peripherals::{ACC, P1, PSW};
+    use crate::cpu::{Variant, CPU};
+    use crate::emulator::Emulator;
+    use crate::test_support::{emulator_with, load, run_steps, shared_output};
+
+    #[test]
+    fn inc_and_dec_a_update_parity_and_mirror() {
+        // CLR A; INC A
+        let mut emulator = emulator_with(&[0xE4, 0x04]);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.peek_sfr(ACC), 0x01);
+        assert!(emulator.cpu.psw_flags().p);
+
+        // CLR A; DEC A
+        let mut emulator = emulator_with(&[0xE4, 0x14]);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.peek_sfr(ACC), 0xFF);
+        assert!(!emulator.cpu.psw_flags().p);
+    }
+
+    #[test]
+    fn dec_direct_and_indirect_wrap_at_zero() {
+        // MOV 0x30,#0x01; DEC 0x30; DEC 0x30; MOV B,#0x00; DEC B; MOV R1,#0x31; DEC @R1; DEC @R1
+        let mut emulator =
+            emulator_with(&[0x75, 0x30, 0x01, 0x15, 0x30, 0x15, 0x30, 0x75, 0xF0, 0x00, 0x15, 0xF0, 0x79, 0x31, 0x17, 0x17]);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.ram[0x30], 0x00);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.ram[0x30], 0xFF);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.b, 0xFF);
+        assert_eq!(emulator.cpu.ram[0xF0], 0x00);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.ram[0x31], 0xFE);
+    }
+
+    #[test]
+    fn inc_at_r1_increments_and_wraps() {
+        // MOV 0x40,#0xFE; MOV R1,#0x40; INC @R1; INC @R1
+        let mut emulator = emulator_with(&[0x75, 0x40, 0xFE, 0x79, 0x40, 0x07, 0x07]);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.ram[0x40], 0xFF);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.ram[0x40], 0x00);
+        assert_eq!(emulator.cpu.ram[1], 0x40);
+    }
+
+    #[test]
+    fn mul_ab_sets_ov_and_clears_cy() {
+        // MOV A,#0xFF; MOV B,#0xFF; SETB C; MUL AB; MOV A,#0x10; MOV B,#0x0F; MUL AB
+        let mut emulator = emulator_with(&[0x74, 0xFF, 0x75, 0xF0, 0xFF, 0xD3, 0xA4, 0x74, 0x10, 0x75, 0xF0, 0x0F, 0xA4]);
+        run_steps(&mut emulator, 4);
+        let flags = emulator.cpu.psw_flags();
+        assert_eq!((emulator.cpu.registers.acc, emulator.cpu.registers.b), (0x01, 0xFE));
+        assert!(flags.ov);
+        assert!(!flags.cy);
+
+        // 积不超过 0xFF 时清除 OV
+        run_steps(&mut emulator, 3);
+        assert_eq!((emulator.cpu.registers.acc, emulator.cpu.registers.b), (0xF0, 0x00));
+        assert!(!emulator.cpu.psw_flags().ov);
+    }
+
+    #[test]
+    fn div_ab_writes_quotient_remainder_and_flags() {
+        // MOV A,#13; MOV B,#4; SETB C; DIV AB
+        let mut emulator = emulator_with(&[0x74, 0x0D, 0x75, 0xF0, 0x04, 0xD3, 0x84]);
+        run_steps(&mut emulator, 4);
+        let cpu = &emulator.cpu;
+        assert_eq!((cpu.peek_sfr(ACC), cpu.registers.acc, cpu.registers.b), (3, 3, 1));
+        assert!(!cpu.psw_flags().cy);
+        assert!(!cpu.psw_flags().ov);
+    }
+
+    #[test]
+    fn div_ab_by_zero_sets_ov() {
+        // MOV A,#13; MOV B,#0; SETB C; DIV AB
+        let mut emulator = emulator_with(&[0x74, 0x0D, 0x75, 0xF0, 0x00, 0xD3, 0x84]);
+        run_steps(&mut emulator, 4);
+        assert!(emulator.cpu.psw_flags().ov);
+        assert!(!emulator.cpu.psw_flags().cy);
+    }
+
+    #[test]
+    fn subb_borrow_sets_cy_in_psw_flags() {
+        // MOV PSW,#0x18（寄存器组3）; MOV A,#0x10; SUBB A,#0x21; MOV R0,A
+        let mut emulator = emulator_with(&[0x75, 0xD0, 0x18, 0x74, 0x10, 0x94, 0x21, 0xF8]);
+        run_steps(&mut emulator, 4);
+        let flags = emulator.cpu.psw_flags();
+        assert_eq!(emulator.cpu.registers.acc, 0xEF);
+        assert!(flags.cy);
+        assert!(flags.ac);
+        assert!(!flags.ov);
+        assert_eq!(flags.bank, 3);
+        assert_eq!(emulator.cpu.ram[0x18], 0xEF);
+
+        // 通过 set_psw_flags 切回寄存器组0
+        emulator.cpu.set_psw_flags(PswFlags { bank: 0, ..flags });
+        assert_eq!(emulator.cpu.peek_sfr(PSW) & 0x18, 0);
+        assert!(emulator.cpu.psw_flags().cy);
+    }
+
+    // A、操作数、执行前的 CY → 结果和 CY、AC、OV
+    const SUBB_CASES: [(u8, u8, bool, u8, bool, bool, bool); 5] = [
+        (0x00, 0x01, false, 0xFF, true, true, false),  // 借位
+        (0x80, 0x01, false, 0x7F, false, true, true),  // 负数减正数得正数：溢出
+        (0x7F, 0xFF, false, 0x80, true, false, true),  // 正数减负数得负数：溢出
+        (0x10, 0x0F, true, 0x00, false, true, false),  // 借位标志参与：低4位借位
+        (0x53, 0x21, true, 0x31, false, false, false), // 没有借位
+    ];
+
+    #[test]
+    fn every_subb_mode_sets_cy_ac_and_ov() {
+        for &(acc, operand, carry, result, cy, ac, ov) in &SUBB_CASES {
+            // SETB/CLR 0xD7 (CY); MOV A,#acc
+            let setup = [if carry { 0xD2 } else { 0xC2 }, 0xD7, 0x74, acc];
+            // SUBB A,#data / SUBB A,direct / SUBB A,R3 / SUBB A,@R1，操作数先放在 0x30 或 R3
+            // 每种寻址方式的代码和包括 SUBB 在内的指令条数
+            let modes: [(&str, Vec<u8>, usize); 4] = [
+                ("#data", vec![0x94, operand], 1),
+                ("direct", vec![0x75, 0x30, operand, 0x95, 0x30], 2),
+                ("Rn", vec![0x7B, operand, 0x9B], 2),
+                ("@Ri", vec![0x75, 0x30, operand, 0x79, 0x30, 0x97], 3),
+            ];
+            for (mode, code, steps) in modes {
+                let program: Vec<u8> = setup.iter().chain(&code).copied().collect();
+                let mut emulator = emulator_with(&program);
+                run_steps(&mut emulator, 2 + steps);
+                let flags = emulator.cpu.psw_flags();
+                let name = format!("SUBB A,{} {:#04x}-{:#04x}-{}", mode, acc, operand, carry as u8);
+                assert_eq!(emulator.cpu.registers.acc, result, "{}", name);
+                assert_eq!((flags.cy, flags.ac, flags.ov), (cy, ac, ov), "{}", name);
+                assert_eq!(flags.p, result.count_ones() % 2 == 1, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn subb_immediate_subtracts_the_carry_too() {
+        // SETB 0xD7 (CY); MOV A,#0x05; SUBB A,#0x05：相等的数再减去借位，结果 0xFF 并借位
+        let mut emulator = emulator_with(&[0xD2, 0xD7, 0x74, 0x05, 0x94, 0x05]);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.acc, 0xFF);
+        assert!(emulator.cpu.psw_flags().cy);
+
+        // CLR 0xD7 (CY); MOV A,#0x05; SUBB A,#0x05
+        let mut emulator = emulator_with(&[0xC2, 0xD7, 0x74, 0x05, 0x94, 0x05]);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.acc, 0x00);
+        assert!(!emulator.cpu.psw_flags().cy);
+    }
+
+    #[test]
+    fn subb_at_r0_reads_upper_ram_not_the_sfr() {
+        // MOV R0,#0x90; MOV A,#0x05; MOV @R0,A; SETB 0xD7 (CY); MOV A,#0x20; SUBB A,@R0
+        // 0x90 间接寻址是 8052 的高128字节RAM，不是 P1 (0xFF)
+        let mut emulator = Emulator::builder().variant(Variant::I8052).build();
+        load(&mut emulator, &[0x78, 0x90, 0x74, 0x05, 0xF6, 0xD2, 0xD7, 0x74, 0x20, 0x96]);
+        let (output, out) = shared_output();
+        emulator.set_trace_output(out);
+        emulator.cpu.debug = true;
+        run_steps(&mut emulator, 6);
+        assert_eq!(emulator.cpu.peek_sfr(P1), 0xFF);
+        assert_eq!(emulator.cpu.registers.acc, 0x1A);
+        let flags = emulator.cpu.psw_flags();
+        assert_eq!((flags.cy, flags.ac, flags.ov), (false, true, false));
+        assert!(String::from_utf8(output.borrow().clone()).unwrap().contains("subb A, @R0"));
+
+        for opcode in [0x94, 0x95, 0x96, 0x97, 0x98, 0x9F] {
+            assert_eq!(CPU::mnemonic(opcode), Some("SUBB"), "{:#04x}", opcode);
+        }
+    }
+
+    // A、操作数 → 结果和 CY、AC、OV
+    const ADD_CASES: [(u8, u8, u8, bool, bool, bool); 4] = [
+        (0x7F, 0x01, 0x80, false, true, true), // 正数相加得负数：溢出
+        (0xFF, 0x01, 0x00, true, true, false), // 进位
+        (0x80, 0x80, 0x00, true, false, true), // 负数相加得正数：溢出并进位
+        (0x12, 0x34, 0x46, false, false, false),
+    ];
+
+    #[test]
+    fn every_add_mode_shares_one_flag_path() {
+        for &(acc, operand, result, cy, ac, ov) in &ADD_CASES {
+            // 每种寻址方式的代码和包括 ADD 在内的指令条数；SETB 0xD7 先置 CY，ADD 不加进位
+            let modes: [(&str, Vec<u8>, usize); 5] = [
+                ("#data", vec![0x24, operand], 1),
+                ("direct", vec![0x75, 0x30, operand, 0x25, 0x30], 2),
+                ("R3", vec![0x7B, operand, 0x2B], 2),
+                ("@R0", vec![0x75, 0x30, operand, 0x78, 0x30, 0x26], 3),
+                ("@R1", vec![0x75, 0x30, operand, 0x79, 0x30, 0x27], 3),
+            ];
+            for (mode, code, steps) in modes {
+                let program: Vec<u8> = [0xD2, 0xD7, 0x74, acc].iter().chain(&code).copied().collect();
+                let mut emulator = emulator_with(&program);
+                run_steps(&mut emulator, 2 + steps);
+                let flags = emulator.cpu.psw_flags();
+                let name = format!("ADD A,{} {:#04x}+{:#04x}", mode, acc, operand);
+                assert_eq!(emulator.cpu.registers.acc, result, "{}", name);
+                assert_eq!((flags.cy, flags.ac, flags.ov), (cy, ac, ov), "{}", name);
+                assert_eq!(flags.p, result.count_ones() % 2 == 1, "{}", name);
+            }
         }
     }
+
+    #[test]
+    fn add_at_r0_adds_the_pointed_cell() {
+        // MOV 0x40,#0x88; MOV R0,#0x40; MOV A,#0x99; ADD A,@R0
+        let mut emulator = emulator_with(&[0x75, 0x40, 0x88, 0x78, 0x40, 0x74, 0x99, 0x26]);
+        run_steps(&mut emulator, 4);
+        let flags = emulator.cpu.psw_flags();
+        assert_eq!(emulator.cpu.registers.acc, 0x21);
+        assert_eq!((flags.cy, flags.ac, flags.ov, flags.p), (true, true, true, false));
+        assert_eq!(emulator.cpu.ram[0x40], 0x88);
+
+        // ADDC A,#data 同样经过这里：SETB 0xD7; MOV A,#0x0F; ADDC A,#0x00 → 0x10，低4位进位
+        let mut emulator = emulator_with(&[0xD2, 0xD7, 0x74, 0x0F, 0x34, 0x00]);
+        run_steps(&mut emulator, 3);
+        let flags = emulator.cpu.psw_flags();
+        assert_eq!(emulator.cpu.registers.acc, 0x10);
+        assert_eq!((flags.cy, flags.ac, flags.ov, flags.p), (false, true, false, true));
+    }
 }
@@ -18,12 +18,24 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // SJMP指令
     table[0x80] = Some(InstructionInfo { handler: |cpu, _| cpu.sjmp(), mnemonic: "SJMP" });
     
+    // JMP @A+DPTR指令
+    table[0x73] = Some(InstructionInfo { handler: |cpu, _| cpu.jmp_a_dptr(), mnemonic: "JMP" });
+    
     // JZ指令
     table[0x60] = Some(InstructionInfo { handler: |cpu, _| cpu.jz(), mnemonic: "JZ" });
     
     // JNZ指令
     table[0x70] = Some(InstructionInfo { handler: |cpu, _| cpu.jnz(), mnemonic: "JNZ" });
     
+    // JC rel指令
+    table[0x40] = Some(InstructionInfo { handler: |cpu, _| cpu.jc(), mnemonic: "JC" });
+    
+    // JNC rel指令
+    table[0x50] = Some(InstructionInfo { handler: |cpu, _| cpu.jnc(), mnemonic: "JNC" });
+    
+    // JB bit, rel指令
+    table[0x20] = Some(InstructionInfo { handler: |cpu, _| cpu.jb_bit(), mnemonic: "JB" });
+    
     // JNB bit, rel指令
     table[0x30] = Some(InstructionInfo { handler: |cpu, _| cpu.jnb_bit(), mnemonic: "JNB" });
     
@@ -35,10 +47,17 @@ pub fn register_instructions(table: &mut InstructionTable) {
     
     // CJNE A, direct, rel指令
     table[0xB5] = Some(InstructionInfo { handler: |cpu, _| cpu.cjne_a_direct(), mnemonic: "CJNE" });
-    table[0xBE] = Some(InstructionInfo { handler: |cpu, _| cpu.cjne_a_direct(), mnemonic: "CJNE" });
     
     // CJNE A, #data, rel指令
-    table[0xBC] = Some(InstructionInfo { handler: |cpu, _| cpu.cjne_a_immediate(), mnemonic: "CJNE" });
+    table[0xB4] = Some(InstructionInfo { handler: |cpu, _| cpu.cjne_a_immediate(), mnemonic: "CJNE" });
+    
+    // CJNE Rn, #data, rel指令 (0xB8-0xBF)
+    for opcode in 0xB8..=0xBF {
+        table[opcode] = Some(InstructionInfo { 
+            handler: |cpu, op| cpu.cjne_rn_immediate(op - 0xB8), 
+            mnemonic: "CJNE" 
+        });
+    }
     
     // DJNZ direct, rel指令
     table[0xD5] = Some(InstructionInfo { handler: |cpu, _| cpu.djnz_direct(), mnemonic: "DJNZ" });
@@ -60,7 +79,7 @@ impl CPU {
         let address = ((high_byte as u16) << 8) | (low_byte as u16);
 
         if self.debug {
-            println!("ljmp {:#06x}", address);
+            trace_line!(self, "ljmp {}", self.format_address(address));
         }
         self.registers.pc = address;
     }
@@ -75,18 +94,34 @@ impl CPU {
         let address = pc_high | addr11;
         
         if self.debug {
-            println!("ajmp {:#06x}", address);
+            trace_line!(self, "ajmp {}", self.format_address(address));
         }
         self.registers.pc = address;
     }
 
-   
+    // 相对转移的目标地址：基准是转移指令之后的地址（取完偏移量后的 PC），
+    // 偏移量为有符号数 (-128..=127)，越过 0x0000/0xFFFF 时回绕
+    fn relative_target(&self, offset: i8) -> u16 {
+        self.registers.pc.wrapping_add(offset as u16)
+    }
+
+    // SJMP rel - 短跳转
     pub(crate) fn sjmp(&mut self) {
         let offset = self.fetch_next_byte() as i8;
         let current_pc = self.registers.pc;
-        let target = (current_pc as i32 + offset as i32) as u16;
+        let target = self.relative_target(offset);
         if self.debug {
-            println!("{:<30}\t(offset={}, from PC={:#06x})", format!("sjmp {:#06x}", target), offset, current_pc);
+            trace_line!(self, "{:<30}\t(offset={}, from PC={:#06x})", format!("sjmp {}", self.format_address(target)), offset, current_pc);
+        }
+        self.registers.pc = target;
+    }
+
+    // JMP @A+DPTR - 间接跳转（跳转表），不压栈，不影响 A 和 DPTR
+    pub(crate) fn jmp_a_dptr(&mut self) {
+        let target = self.registers.dptr.wrapping_add(self.registers.acc as u16);
+
+        if self.debug {
+            trace_line!(self, "{:<30}\t(A={:#04x}, DPTR={:#06x})", format!("jmp @A+DPTR -> {}", self.format_address(target)), self.registers.acc, self.registers.dptr);
         }
         self.registers.pc = target;
     }
@@ -94,21 +129,10 @@ impl CPU {
     // JZ rel - 如果累加器为零则跳转
     pub(crate) fn jz(&mut self) {
         let offset = self.fetch_next_byte() as i8;
-        let target = (self.registers.pc as i32 + offset as i32) as u16;
-
-        // 检测 Delayms 函数退出条件（地址 0x0123，跳转到 0x0139）
-        if self.delay_skip_counter > 0 && target == 0x0139 && self.registers.pc >= 0x0120 && self.registers.pc <= 0x0139 {
-            self.delay_skip_counter = 0;
-            if self.debug {
-                println!("jz {:#06x}", target);
-            }
-            // 强制跳转到退出地址
-            self.registers.pc = 0x0139;
-            return;
-        }
+        let target = self.relative_target(offset);
 
         if self.debug {
-            println!("jz {:#06x}", target);
+            trace_line!(self, "jz {}", self.format_address(target));
         }
 
         if self.registers.acc == 0 {
@@ -119,19 +143,10 @@ impl CPU {
     // JNZ rel - 如果累加器不为零则跳转
     pub(crate) fn jnz(&mut self) {
         let offset = self.fetch_next_byte() as i8;
-        let target = (self.registers.pc as i32 + offset as i32) as u16;
-
-        // 快速跳过 Delayms 内部循环（地址 0x0129-0x0130 的循环）
-        if self.delay_skip_counter > 0 && target == 0x0129 && self.registers.pc >= 0x0120 && self.registers.pc <= 0x0139 {
-            // 将寄存器设为0以退出内层循环
-            self.write_register(4, 0); // R4
-            self.write_register(5, 0); // R5
-            self.registers.acc = 0;
-            return;
-        }
+        let target = self.relative_target(offset);
 
         if self.debug {
-            println!("jnz {:#06x}", target);
+            trace_line!(self, "jnz {}", self.format_address(target));
         }
 
         if self.registers.acc != 0 {
@@ -139,6 +154,34 @@ impl CPU {
         }
     }
 
+    // JC rel - 如果进位标志为1则跳转
+    pub(crate) fn jc(&mut self) {
+        let offset = self.fetch_next_byte() as i8;
+        let target = self.relative_target(offset);
+
+        if self.debug {
+            trace_line!(self, "jc {}", self.format_address(target));
+        }
+
+        if self.get_carry_flag() != 0 {
+            self.registers.pc = target;
+        }
+    }
+
+    // JNC rel - 如果进位标志为0则跳转
+    pub(crate) fn jnc(&mut self) {
+        let offset = self.fetch_next_byte() as i8;
+        let target = self.relative_target(offset);
+
+        if self.debug {
+            trace_line!(self, "jnc {}", self.format_address(target));
+        }
+
+        if self.get_carry_flag() == 0 {
+            self.registers.pc = target;
+        }
+    }
+
     // LCALL addr16 - 长调用
     pub(crate) fn lcall(&mut self) {
         let high_byte = self.fetch_next_byte();
@@ -146,7 +189,7 @@ impl CPU {
         let address = ((high_byte as u16) << 8) | (low_byte as u16);
 
         if self.debug {
-            println!("lcall {:#06x}", address);
+            trace_line!(self, "lcall {}", self.format_address(address));
         }
 
         // 将当前PC压入堆栈（注意：8051先++SP再压栈）
@@ -155,16 +198,8 @@ impl CPU {
         let high = (return_addr >> 8) as u8;
         
         // 8051 PUSH操作：先SP++，再存储
-        self.registers.sp = self.registers.sp.wrapping_add(1);
-        self.ram[self.registers.sp as usize] = low; // 低字节
-        self.registers.sp = self.registers.sp.wrapping_add(1);
-        self.ram[self.registers.sp as usize] = high; // 高字节
-
-        // 检测 Delayms 函数调用并优化执行
-        if address == 0x011d { // Delayms 函数地址
-            // 设置跳过计数器，在接下来的指令中快速跳过延迟循环
-            self.delay_skip_counter = 1;
-        }
+        self.push_stack(low); // 低字节
+        self.push_stack(high); // 高字节
 
         // 跳转到目标地址
         self.registers.pc = address;
@@ -173,13 +208,11 @@ impl CPU {
     // RET - 从子程序返回
     pub(crate) fn ret(&mut self) {
         if self.debug {
-            println!("ret");
+            trace_line!(self, "ret");
         }
         // 从堆栈弹出返回地址（8051 POP操作：先读取，再--SP）
-        let high_byte = self.ram[self.registers.sp as usize] as u16; // 读高字节
-        self.registers.sp = self.registers.sp.wrapping_sub(1);
-        let low_byte = self.ram[self.registers.sp as usize] as u16; // 读低字节
-        self.registers.sp = self.registers.sp.wrapping_sub(1);
+        let high_byte = self.pop_stack() as u16; // 读高字节
+        let low_byte = self.pop_stack() as u16; // 读低字节
 
         let return_address = (high_byte << 8) | low_byte;
         self.registers.pc = return_address;
@@ -192,10 +225,10 @@ impl CPU {
         let offset = self.fetch_next_byte() as i8;
         let value = self.read_register(reg_num).wrapping_sub(1);
         self.write_register(reg_num, value);
-        let target = (self.registers.pc as i32 + offset as i32) as u16;
+        let target = self.relative_target(offset);
         
         if self.debug {
-            println!("{:<30}\t(value={}, offset={:+})", format!("djnz R{}, {:#06x}", reg_num, target), value, offset);
+            trace_line!(self, "{:<30}\t(value={}, offset={:+})", format!("djnz R{}, {}", reg_num, self.format_address(target)), value, offset);
         }
         
         if value != 0 {
@@ -208,24 +241,16 @@ impl CPU {
         let direct_address = self.fetch_next_byte();
         let offset = self.fetch_next_byte() as i8;
         
-        let value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
+        let value = self.read_internal(direct_address);
         
         let new_value = value.wrapping_sub(1);
         
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = new_value;
-        } else {
-            self.write_sfr(direct_address, new_value);
-        }
+        self.write_internal(direct_address, new_value);
         
-        let target = (self.registers.pc as i32 + offset as i32) as u16;
+        let target = self.relative_target(offset);
         
         if self.debug {
-            println!("djnz {:#04x}, {:#06x}", direct_address, target);
+            trace_line!(self, "djnz {:#04x}, {}", direct_address, self.format_address(target));
         }
         
         if new_value != 0 {
@@ -238,13 +263,31 @@ impl CPU {
         let immediate = self.fetch_next_byte();
         let offset = self.fetch_next_byte() as i8;
 
+        // 第一个操作数小于第二个时 CY 置1，否则清0
+        self.set_carry_flag(self.registers.acc < immediate);
         if self.registers.acc != immediate {
-            let target = (self.registers.pc as i32 + offset as i32) as u16;
+            let target = self.relative_target(offset);
             self.registers.pc = target;
         }
 
         if self.debug {
-            println!("cjne A, #{:#04x}, {:+}", immediate, offset);
+            trace_line!(self, "cjne A, #{:#04x}, {:+}", immediate, offset);
+        }
+    }
+
+    // CJNE Rn, #data, rel - 比较寄存器和立即数，如果不相等则跳转
+    pub(crate) fn cjne_rn_immediate(&mut self, reg_num: u8) {
+        let immediate = self.fetch_next_byte();
+        let offset = self.fetch_next_byte() as i8;
+        let value = self.read_register(reg_num);
+
+        self.set_carry_flag(value < immediate);
+        if value != immediate {
+            self.registers.pc = self.relative_target(offset);
+        }
+
+        if self.debug {
+            trace_line!(self, "cjne R{}, #{:#04x}, {:+}", reg_num, immediate, offset);
         }
     }
 
@@ -253,20 +296,31 @@ impl CPU {
         let direct_address = self.fetch_next_byte();
         let offset = self.fetch_next_byte() as i8;
         
-        let direct_value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
+        let direct_value = self.read_internal(direct_address);
 
-        let target = (self.registers.pc as i32 + offset as i32) as u16;
+        let target = self.relative_target(offset);
 
+        self.set_carry_flag(self.registers.acc < direct_value);
         if self.registers.acc != direct_value {
             self.registers.pc = target;
         }
 
         if self.debug {
-            println!("{:<30}\t(direct_value={}, offset={:+})", format!("cjne A, {:#04x}, {:#06x}", direct_address, target), direct_value, offset);
+            trace_line!(self, "{:<30}\t(direct_value={}, offset={:+})", format!("cjne A, {:#04x}, {}", direct_address, self.format_address(target)), direct_value, offset);
+        }
+    }
+
+    // JB bit, rel - 如果指定位为1则跳转
+    pub(crate) fn jb_bit(&mut self) {
+        let bit_addr = self.fetch_next_byte();
+        let offset = self.fetch_next_byte() as i8;
+
+        if self.read_bit(bit_addr) {
+            self.registers.pc = self.relative_target(offset);
+        }
+
+        if self.debug {
+            trace_line!(self, "jb {:#04x}, {:+}", bit_addr, offset);
         }
     }
 
@@ -274,29 +328,122 @@ impl CPU {
     pub(crate) fn jnb_bit(&mut self) {
         let bit_addr = self.fetch_next_byte();
         let offset = self.fetch_next_byte() as i8;
-        
-        // 读取位的值
-        let bit_value = if bit_addr < 0x80 {
-            // 内部RAM位寻址
-            let byte_addr = 0x20 + (bit_addr >> 3) as usize;
-            let bit_pos = bit_addr & 0x07;
-            (self.ram[byte_addr] >> bit_pos) & 1
-        } else {
-            // SFR位寻址
-            let byte_addr = bit_addr & 0xF8;
-            let bit_pos = bit_addr & 0x07;
-            let value = self.read_sfr(byte_addr);
-            (value >> bit_pos) & 1
-        };
-        
-        // 如果位为0，则跳转
-        if bit_value == 0 {
-            let target = (self.registers.pc as i32 + offset as i32) as u16;
-            self.registers.pc = target;
+
+        if !self.read_bit(bit_addr) {
+            self.registers.pc = self.relative_target(offset);
         }
-        
+
         if self.debug {
-            println!("jnb {:#04x}, {:+}", bit_addr, offset);
+            trace_line!(self, "jnb {:#04x}, {:+}", bit_addr, offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::CPU;
+    use crate::test_support::{emulator_with, emulator_with_blocks, run_steps};
+
+    #[test]
+    fn jmp_a_dptr_lands_on_table_entry_without_pushing() {
+        // MOV DPTR,#0x0100; MOV A,#0x06; JMP @A+DPTR
+        let mut emulator = emulator_with(&[0x90, 0x01, 0x00, 0x74, 0x06, 0x73]);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.pc, 0x0106);
+        assert_eq!(emulator.cpu.registers.sp, 0x07);
+
+        // DPTR+A 超过 0xFFFF 时回绕
+        let mut emulator = emulator_with(&[0x90, 0xFF, 0xFE, 0x74, 0x04, 0x73]);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.pc, 0x0002);
+    }
+
+    #[test]
+    fn subroutine_at_0x011d_runs_unchanged() {
+        // LCALL 0x011D; SJMP $
+        // 0x011D: MOV R4,#2; MOV A,#1; JZ +3; MOV R5,A; JNZ +1; DEC R4; RET
+        // 这里曾经被当作 Delayms 特殊处理（清零 R4/R5/A，JZ 强制跳到 0x0139），现在和其它地址一样执行
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x12, 0x01, 0x1D, 0x80, 0xFE]),
+            (0x011D, &[0x7C, 0x02, 0x74, 0x01, 0x60, 0x03, 0xFD, 0x70, 0x01, 0x1C, 0x22]),
+        ]);
+        run_steps(&mut emulator, 7);
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+        assert_eq!((emulator.cpu.ram[4], emulator.cpu.ram[5], emulator.cpu.registers.acc), (2, 1, 1));
+    }
+
+    // 放在 address 处的一条相对转移指令，setup 让转移条件成立，target 为汇编器算出的目标地址
+    struct RelativeCase {
+        name: &'static str,
+        address: u16,
+        code: &'static [u8],
+        setup: fn(&mut CPU),
+        target: u16,
+    }
+
+    fn none(_: &mut CPU) {}
+
+    const RELATIVE_CASES: [RelativeCase; 16] = [
+        RelativeCase { name: "SJMP +2", address: 0x00FE, code: &[0x80, 0x02], setup: none, target: 0x0102 },
+        RelativeCase { name: "SJMP +127", address: 0x00FE, code: &[0x80, 0x7F], setup: none, target: 0x017F },
+        RelativeCase { name: "SJMP -128", address: 0x00FE, code: &[0x80, 0x80], setup: none, target: 0x0080 },
+        RelativeCase { name: "SJMP $", address: 0x0100, code: &[0x80, 0xFE], setup: none, target: 0x0100 },
+        RelativeCase { name: "SJMP 回绕到 0xFFFE", address: 0x0000, code: &[0x80, 0xFC], setup: none, target: 0xFFFE },
+        RelativeCase { name: "SJMP 越过 0xFFFF", address: 0xFFFE, code: &[0x80, 0x02], setup: none, target: 0x0002 },
+        RelativeCase { name: "JZ -3", address: 0x00FE, code: &[0x60, 0xFD], setup: none, target: 0x00FD },
+        RelativeCase { name: "JNZ +5", address: 0x00FE, code: &[0x70, 0x05], setup: |cpu| cpu.set_acc(1), target: 0x0105 },
+        RelativeCase {
+            name: "JC -2",
+            address: 0x00FE,
+            code: &[0x40, 0xFE],
+            setup: |cpu| cpu.set_carry_flag(true),
+            target: 0x00FE,
+        },
+        RelativeCase { name: "JNC +1", address: 0x00FE, code: &[0x50, 0x01], setup: none, target: 0x0101 },
+        RelativeCase { name: "DJNZ R7 -4", address: 0x00FE, code: &[0xDF, 0xFC], setup: |cpu| cpu.ram[7] = 2, target: 0x00FC },
+        RelativeCase {
+            name: "DJNZ direct -3",
+            address: 0x00FD,
+            code: &[0xD5, 0x30, 0xFD],
+            setup: |cpu| cpu.ram[0x30] = 2,
+            target: 0x00FD,
+        },
+        RelativeCase { name: "CJNE A,#data +16", address: 0x00FD, code: &[0xB4, 0x01, 0x10], setup: none, target: 0x0110 },
+        RelativeCase {
+            name: "CJNE A,direct -2",
+            address: 0x00FD,
+            code: &[0xB5, 0x30, 0xFE],
+            setup: |cpu| cpu.ram[0x30] = 1,
+            target: 0x00FE,
+        },
+        RelativeCase { name: "CJNE R4,#data +3", address: 0x00FD, code: &[0xBC, 0x05, 0x03], setup: none, target: 0x0103 },
+        RelativeCase { name: "JNB bit -128", address: 0x00FD, code: &[0x30, 0x00, 0x80], setup: none, target: 0x0080 },
+    ];
+
+    #[test]
+    fn relative_targets_are_based_on_the_next_instruction() {
+        for case in &RELATIVE_CASES {
+            let mut emulator = emulator_with(&[]);
+            let start = case.address as usize;
+            emulator.cpu.rom[start..start + case.code.len()].copy_from_slice(case.code);
+            emulator.cpu.registers.pc = case.address;
+            (case.setup)(&mut emulator.cpu);
+            emulator.step();
+            assert_eq!(emulator.cpu.registers.pc, case.target, "{}", case.name);
         }
     }
+
+    #[test]
+    fn cjne_falls_through_when_equal_and_sets_cy_when_less() {
+        // MOV R4,#5; CJNE R4,#5,+2; CJNE R4,#6,+0; MOV A,#7; CJNE A,#6,+0
+        let mut emulator = emulator_with(&[0x7C, 0x05, 0xBC, 0x05, 0x02, 0xBC, 0x06, 0x00, 0x74, 0x07, 0xB4, 0x06, 0x00]);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.pc, 0x0005);
+        assert!(!emulator.cpu.psw_flags().cy);
+        run_steps(&mut emulator, 1);
+        assert!(emulator.cpu.psw_flags().cy);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.pc, 0x000D);
+        assert!(!emulator.cpu.psw_flags().cy);
+    }
 }
@@ -1,6 +1,8 @@
 // 数据传输指令模块
+use super::super::peripherals::P2;
 use super::super::CPU;
 use super::{InstructionInfo, InstructionTable};
+use crate::memory_dump::MemorySpace;
 
 // 注册数据传输指令到指令表
 pub fn register_instructions(table: &mut InstructionTable) {
@@ -87,6 +89,16 @@ pub fn register_instructions(table: &mut InstructionTable) {
     
     // MOVX @DPTR, A指令
     table[0xF0] = Some(InstructionInfo { handler: |cpu, _| cpu.movx_dptr_a(), mnemonic: "MOVX" });
+
+    // MOVX A, @Ri指令 (0xE2-0xE3)
+    for opcode in 0xE2..=0xE3 {
+        table[opcode] = Some(InstructionInfo { handler: |cpu, op| cpu.movx_a_ri(op - 0xE2), mnemonic: "MOVX" });
+    }
+
+    // MOVX @Ri, A指令 (0xF2-0xF3)
+    for opcode in 0xF2..=0xF3 {
+        table[opcode] = Some(InstructionInfo { handler: |cpu, op| cpu.movx_ri_a(op - 0xF2), mnemonic: "MOVX" });
+    }
     
     // PUSH direct指令
     table[0xC0] = Some(InstructionInfo { handler: |cpu, _| cpu.push_direct(), mnemonic: "PUSH" });
@@ -99,6 +111,24 @@ pub fn register_instructions(table: &mut InstructionTable) {
     
     // XCH A, direct指令
     table[0xC5] = Some(InstructionInfo { handler: |cpu, _| cpu.xch_a_direct(), mnemonic: "XCH" });
+    
+    // XCH A, @Ri指令 (0xC6-0xC7)
+    table[0xC6] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.xch_a_rn_indirect(op - 0xC6), 
+        mnemonic: "XCH" 
+    });
+    table[0xC7] = Some(InstructionInfo { 
+        handler: |cpu, op| cpu.xch_a_rn_indirect(op - 0xC6), 
+        mnemonic: "XCH" 
+    });
+    
+    // XCH A, Rn指令 (0xC8-0xCF)
+    for opcode in 0xC8..=0xCF {
+        table[opcode] = Some(InstructionInfo { 
+            handler: |cpu, op| cpu.xch_a_rn(op - 0xC8), 
+            mnemonic: "XCH" 
+        });
+    }
 }
 
 impl CPU {
@@ -107,18 +137,13 @@ impl CPU {
         let direct_address = self.fetch_next_byte();
         
         // 读取直接地址的值
-        let value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
+        let value = self.read_internal(direct_address);
         
         // 8051 PUSH操作：先SP++，再存储
-        self.registers.sp = self.registers.sp.wrapping_add(1);
-        self.ram[self.registers.sp as usize] = value;
+        self.push_stack(value);
         
         if self.debug {
-            println!("push {:#04x}", direct_address);
+            trace_line!(self, "push {:#04x}", direct_address);
         }
     }
 
@@ -127,35 +152,30 @@ impl CPU {
         let direct_address = self.fetch_next_byte();
         
         // 8051 POP操作：先读取，再--SP
-        let value = self.ram[self.registers.sp as usize];
-        self.registers.sp = self.registers.sp.wrapping_sub(1);
+        let value = self.pop_stack();
         
         // 写入直接地址
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = value;
-        } else {
-            self.write_sfr(direct_address, value);
-        }
+        self.write_internal(direct_address, value);
         
         if self.debug {
-            println!("pop {:#04x}", direct_address);
+            trace_line!(self, "pop {:#04x}", direct_address);
         }
     }
 
     // CLR A - 清除累加器
     pub(crate) fn clr_acc(&mut self) {
-        self.registers.acc = 0;
+        self.set_acc(0);
         if self.debug {
-            println!("clr A");
+            trace_line!(self, "clr A");
         }
     }
 
     // MOV A, #data - 将立即数加载到累加器
     pub(crate) fn mov_a_immediate(&mut self) {
         let immediate = self.fetch_next_byte();
-        self.registers.acc = immediate;
+        self.set_acc(immediate);
         if self.debug {
-            println!("mov A, #{:#04x}", immediate);
+            trace_line!(self, "mov A, #{:#04x}", immediate);
         }
     }
 
@@ -165,28 +185,21 @@ impl CPU {
         let immediate = self.fetch_next_byte();
 
         if self.debug {
-            println!("mov {:#04x}, #{:#04x}", direct_address, immediate);
+            trace_line!(self, "mov {:#04x}, #{:#04x}", direct_address, immediate);
         }
 
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = immediate;
-        } else {
-            self.write_sfr(direct_address, immediate);
-        }
+        self.write_internal(direct_address, immediate);
     }
 
     // MOV A, direct - 将直接地址的值加载到累加器
     pub(crate) fn mov_a_direct(&mut self) {
         let direct_address = self.fetch_next_byte();
 
-        if direct_address < 0x80 {
-            self.registers.acc = self.ram[direct_address as usize];
-        } else {
-            self.registers.acc = self.read_sfr(direct_address);
-        }
+        let value = self.read_internal(direct_address);
+        self.set_acc(value);
 
         if self.debug {
-            println!("{:<30}\t(value={})", format!("mov A, {:#04x}", direct_address), self.registers.acc);
+            trace_line!(self, "{:<30}\t(value={})", format!("mov A, {:#04x}", direct_address), self.registers.acc);
         }
     }
 
@@ -195,14 +208,10 @@ impl CPU {
         let direct_address = self.fetch_next_byte();
 
         if self.debug {
-            println!("mov {:#04x}, A", direct_address);
+            trace_line!(self, "mov {:#04x}, A", direct_address);
         }
 
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = self.registers.acc;
-        } else {
-            self.write_sfr(direct_address, self.registers.acc);
-        }
+        self.write_internal(direct_address, self.registers.acc);
     }
 
     // MOV direct, direct - 将一个直接地址的内容复制到另一个直接地址
@@ -211,22 +220,14 @@ impl CPU {
         let dst_address = self.fetch_next_byte();
 
         // 读取源地址的值
-        let value = if src_address < 0x80 {
-            self.ram[src_address as usize]
-        } else {
-            self.read_sfr(src_address)
-        };
+        let value = self.read_internal(src_address);
 
         if self.debug {
-            println!("{:<30}\t(value={})", format!("mov {:#04x}, {:#04x}", dst_address, src_address), value);
+            trace_line!(self, "{:<30}\t(value={})", format!("mov {:#04x}, {:#04x}", dst_address, src_address), value);
         }
 
         // 写入目标地址
-        if dst_address < 0x80 {
-            self.ram[dst_address as usize] = value;
-        } else {
-            self.write_sfr(dst_address, value);
-        }
+        self.write_internal(dst_address, value);
     }
 
     // MOV Rn, #data - 将立即数加载到寄存器Rn
@@ -234,15 +235,16 @@ impl CPU {
         let immediate = self.fetch_next_byte();
         self.write_register(reg_num, immediate);
         if self.debug {
-            println!("mov R{}, #{:#04x}", reg_num, immediate);
+            trace_line!(self, "mov R{}, #{:#04x}", reg_num, immediate);
         }
     }
 
     // MOV A, Rn - 将寄存器Rn加载到累加器
     pub(crate) fn mov_a_rn(&mut self, reg_num: u8) {
-        self.registers.acc = self.read_register(reg_num);
+        let value = self.read_register(reg_num);
+        self.set_acc(value);
         if self.debug {
-            println!("{:<30}\t(value={})", format!("mov A, R{}", reg_num), self.registers.acc);
+            trace_line!(self, "{:<30}\t(value={})", format!("mov A, R{}", reg_num), self.registers.acc);
         }
     }
 
@@ -250,39 +252,37 @@ impl CPU {
     pub(crate) fn mov_rn_a(&mut self, reg_num: u8) {
         self.write_register(reg_num, self.registers.acc);
         if self.debug {
-            println!("{:<30}\t(value={})", format!("mov R{}, A", reg_num), self.registers.acc);
+            trace_line!(self, "{:<30}\t(value={})", format!("mov R{}, A", reg_num), self.registers.acc);
         }
     }
 
     // MOV A, @Rn - 间接寻址，从Rn指向的地址读取到累加器
     pub(crate) fn mov_a_rn_indirect(&mut self, reg_num: u8) {
-        let addr = self.read_register(reg_num) as usize;
-        self.registers.acc = self.ram[addr];
+        let addr = self.read_register(reg_num);
+        // 0x80 以上间接寻址访问高128字节RAM，而不是 SFR
+        let value = self.read_indirect(addr);
+        self.set_acc(value);
         if self.debug {
-            println!("mov A, @R{}", reg_num);
+            trace_line!(self, "mov A, @R{}", reg_num);
         }
     }
 
     // MOV @Rn, A - 间接寻址，将累加器写入Rn指向的地址
     pub(crate) fn mov_rn_indirect_a(&mut self, reg_num: u8) {
-        let addr = self.read_register(reg_num) as usize;
-        self.ram[addr] = self.registers.acc;
+        let addr = self.read_register(reg_num);
+        self.write_indirect(addr, self.registers.acc);
         if self.debug {
-            println!("mov @R{}, A", reg_num);
+            trace_line!(self, "mov @R{}, A", reg_num);
         }
     }
 
     // MOV Rn, direct - 从直接地址加载到寄存器Rn
     pub(crate) fn mov_rn_direct(&mut self, reg_num: u8) {
         let direct = self.fetch_next_byte();
-        let value = if direct < 0x80 {
-            self.ram[direct as usize]
-        } else {
-            self.read_sfr(direct)
-        };
+        let value = self.read_internal(direct);
         if self.debug {
             let reg_addr = self.get_register_address(reg_num);
-            println!("{:<30}\t(value={}, will write to RAM[{}])", format!("mov R{}, {:#04x}", reg_num, direct), value, reg_addr);
+            trace_line!(self, "{:<30}\t(value={}, will write to RAM[{}])", format!("mov R{}, {:#04x}", reg_num, direct), value, reg_addr);
         }
         self.write_register(reg_num, value);
     }
@@ -293,7 +293,7 @@ impl CPU {
         let low_byte = self.fetch_next_byte();
         self.registers.dptr = ((high_byte as u16) << 8) | (low_byte as u16);
         if self.debug {
-            println!("mov DPTR, #{:#06x}", self.registers.dptr);
+            trace_line!(self, "mov DPTR, #{:#06x}", self.registers.dptr);
         }
     }
 
@@ -302,69 +302,263 @@ impl CPU {
         let direct_address = self.fetch_next_byte();
         let value = self.read_register(reg_num);
         
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = value;
-        } else {
-            self.write_sfr(direct_address, value);
-        }
+        self.write_internal(direct_address, value);
         
         if self.debug {
-            println!("mov {:#04x}, R{}", direct_address, reg_num);
+            trace_line!(self, "mov {:#04x}, R{}", direct_address, reg_num);
         }
     }
 
     // MOVX @DPTR, A - 将累加器的值传送到DPTR指向的外部RAM
     pub(crate) fn movx_dptr_a(&mut self) {
-        // 注意：这里简化处理，将外部RAM映射到内部ROM的高地址
-        // 实际硬件中外部RAM是独立的
-        let dptr = self.registers.dptr;
-        if (dptr as usize) < self.rom.len() {
-            self.rom[dptr as usize] = self.registers.acc;
-        }
-        
+        self.write_xram(self.registers.dptr, self.registers.acc);
         if self.debug {
-            println!("movx @DPTR, A");
+            trace_line!(self, "movx @DPTR, A");
         }
     }
 
     // MOVX A, @DPTR - 从DPTR指向的外部RAM读取到累加器
     pub(crate) fn movx_a_dptr(&mut self) {
-        // 注意：这里简化处理，将外部RAM映射到内部ROM的高地址
-        let dptr = self.registers.dptr;
-        if (dptr as usize) < self.rom.len() {
-            self.registers.acc = self.rom[dptr as usize];
+        let value = self.read_xram(self.registers.dptr);
+        self.set_acc(value);
+        if self.debug {
+            trace_line!(self, "movx A, @DPTR");
         }
-        
+    }
+
+    // MOVX @Ri, A - 8位地址，高8位取 P2 锁存器（分页访问外部RAM）
+    pub(crate) fn movx_ri_a(&mut self, reg_num: u8) {
+        let address = self.paged_xram_address(reg_num);
+        self.write_xram(address, self.registers.acc);
         if self.debug {
-            println!("movx A, @DPTR");
+            trace_line!(self, "movx @R{}, A", reg_num);
         }
     }
 
+    // MOVX A, @Ri - 8位地址，高8位取 P2 锁存器
+    pub(crate) fn movx_a_ri(&mut self, reg_num: u8) {
+        let address = self.paged_xram_address(reg_num);
+        let value = self.read_xram(address);
+        self.set_acc(value);
+        if self.debug {
+            trace_line!(self, "movx A, @R{}", reg_num);
+        }
+    }
+
+    // MOVX @Ri 的16位地址：P2 锁存器（不是引脚电平）为高8位，Ri 为低8位
+    fn paged_xram_address(&self, reg_num: u8) -> u16 {
+        u16::from_be_bytes([self.peek_sfr(P2), self.read_register(reg_num)])
+    }
+
+    // 读外部数据存储器，超出 xram 长度的地址读出 0xFF（总线上没有器件应答）
+    pub(crate) fn read_xram(&self, address: u16) -> u8 {
+        let value = self.xram.get(address as usize).copied().unwrap_or(0xFF);
+        self.watch_read(MemorySpace::Xram, address, value);
+        value
+    }
+
+    // 写外部数据存储器，超出 xram 长度的写入被忽略
+    pub(crate) fn write_xram(&mut self, address: u16, value: u8) {
+        let Some(cell) = self.xram.get_mut(address as usize) else {
+            return;
+        };
+        let old = std::mem::replace(cell, value);
+        self.watch_write(MemorySpace::Xram, address, old, value);
+    }
+
     // XCH A, direct - 交换累加器和直接地址的内容
     pub(crate) fn xch_a_direct(&mut self) {
         let direct_address = self.fetch_next_byte();
         
         // 读取直接地址的值
-        let direct_value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
-        
-        // 保存累加器的值
-        let acc_value = self.registers.acc;
+        let direct_value = self.read_internal(direct_address);
         
         // 交换值
-        self.registers.acc = direct_value;
+        let acc_value = self.exchange_acc(direct_value);
         
-        if direct_address < 0x80 {
-            self.ram[direct_address as usize] = acc_value;
-        } else {
-            self.write_sfr(direct_address, acc_value);
-        }
+        self.write_internal(direct_address, acc_value);
         
         if self.debug {
-            println!("xch A, {:#04x}", direct_address);
+            trace_line!(self, "xch A, {:#04x}", direct_address);
         }
     }
+
+    // XCH A, Rn - 交换累加器和寄存器Rn的内容
+    pub(crate) fn xch_a_rn(&mut self, reg_num: u8) {
+        let value = self.read_register(reg_num);
+        let acc_value = self.exchange_acc(value);
+        self.write_register(reg_num, acc_value);
+
+        if self.debug {
+            trace_line!(self, "xch A, R{}", reg_num);
+        }
+    }
+
+    // XCH A, @Ri - 交换累加器和Ri指向的内部RAM单元
+    pub(crate) fn xch_a_rn_indirect(&mut self, reg_num: u8) {
+        let addr = self.read_register(reg_num);
+        let value = self.read_indirect(addr);
+        let acc_value = self.exchange_acc(value);
+        self.write_indirect(addr, acc_value);
+
+        if self.debug {
+            trace_line!(self, "xch A, @R{}", reg_num);
+        }
+    }
+
+    // XCH 指令共用：把新值放入累加器（经过 set_acc，同时更新奇偶标志和 SFR 0xE0），返回累加器原来的值
+    fn exchange_acc(&mut self, value: u8) -> u8 {
+        let acc_value = self.registers.acc;
+        self.set_acc(value);
+        acc_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::peripherals::ACC;
+    use crate::cpu::Variant;
+    use crate::emulator::Emulator;
+    use crate::test_support::{emulator_with, load, run_steps};
+
+    #[test]
+    fn clr_a_updates_parity_and_mirror() {
+        // MOV A,#0x01; CLR A
+        let mut emulator = emulator_with(&[0x74, 0x01, 0xE4]);
+        run_steps(&mut emulator, 1);
+        assert!(emulator.cpu.psw_flags().p);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.peek_sfr(ACC), 0x00);
+        assert!(!emulator.cpu.psw_flags().p);
+    }
+
+    #[test]
+    fn mov_a_forms_update_parity_and_mirror() {
+        // MOV 0x30,#0x07; MOV R1,#0x30; MOV A,#0x01; MOV A,0x30; MOV A,R1; MOV A,@R1
+        let program = [0x75, 0x30, 0x07, 0x79, 0x30, 0x74, 0x01, 0xE5, 0x30, 0xE9, 0xE7];
+        let mut emulator = emulator_with(&program);
+        run_steps(&mut emulator, 2);
+        for (acc, parity) in [(0x01, true), (0x07, true), (0x30, false), (0x07, true)] {
+            run_steps(&mut emulator, 1);
+            assert_eq!(emulator.cpu.registers.acc, acc);
+            assert_eq!(emulator.cpu.peek_sfr(ACC), acc);
+            assert_eq!(emulator.cpu.psw_flags().p, parity);
+        }
+    }
+
+    #[test]
+    fn xch_a_r3() {
+        // MOV A,#0x12; MOV R3,#0x07; XCH A,R3
+        let mut emulator = emulator_with(&[0x74, 0x12, 0x7B, 0x07, 0xCB]);
+        run_steps(&mut emulator, 3);
+        let cpu = &emulator.cpu;
+        assert_eq!(cpu.registers.acc, 0x07);
+        assert_eq!(cpu.ram[3], 0x12);
+        assert_eq!(cpu.peek_sfr(ACC), 0x07);
+        assert!(cpu.psw_flags().p); // 0x07 有三个 1
+    }
+
+    #[test]
+    fn xch_a_at_r0() {
+        // MOV 0x40,#0x03; MOV R0,#0x40; MOV A,#0x81; XCH A,@R0
+        let mut emulator = emulator_with(&[0x75, 0x40, 0x03, 0x78, 0x40, 0x74, 0x81, 0xC6]);
+        run_steps(&mut emulator, 4);
+        let cpu = &emulator.cpu;
+        assert_eq!(cpu.registers.acc, 0x03);
+        assert_eq!(cpu.ram[0x40], 0x81);
+        assert_eq!(cpu.peek_sfr(ACC), 0x03);
+        assert!(!cpu.psw_flags().p);
+    }
+
+    #[test]
+    fn xch_a_direct() {
+        // MOV 0x30,#0x01; MOV A,#0xFF; XCH A,0x30
+        let mut emulator = emulator_with(&[0x75, 0x30, 0x01, 0x74, 0xFF, 0xC5, 0x30]);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.acc, 0x01);
+        assert_eq!(emulator.cpu.ram[0x30], 0xFF);
+        assert!(emulator.cpu.psw_flags().p);
+    }
+
+    #[test]
+    fn movx_dptr_uses_xram_not_rom() {
+        // MOV DPTR,#0x0003; MOV A,#0x5A; MOVX @DPTR,A; CLR A; MOVX A,@DPTR
+        let program = [0x90, 0x00, 0x03, 0x74, 0x5A, 0xF0, 0xE4, 0xE0];
+        let mut emulator = emulator_with(&program);
+        run_steps(&mut emulator, 5);
+        assert_eq!(emulator.cpu.xram[3], 0x5A);
+        assert_eq!(emulator.cpu.rom[3], 0x74);
+        assert_eq!(emulator.cpu.registers.acc, 0x5A);
+        assert_eq!(emulator.cpu.peek_sfr(ACC), 0x5A);
+    }
+
+    #[test]
+    fn movx_ri_pages_through_p2() {
+        // MOV P2,#0x12; MOV R0,#0x34; MOV A,#0x77; MOVX @R0,A; MOV R1,#0x34; CLR A; MOVX A,@R1
+        let program = [0x75, 0xA0, 0x12, 0x78, 0x34, 0x74, 0x77, 0xF2, 0x79, 0x34, 0xE4, 0xE3];
+        let mut emulator = emulator_with(&program);
+        run_steps(&mut emulator, 7);
+        assert_eq!(emulator.cpu.xram[0x1234], 0x77);
+        assert_eq!(emulator.cpu.xram[0x0034], 0x00);
+        assert_eq!(emulator.cpu.registers.acc, 0x77);
+    }
+
+    #[test]
+    fn movx_beyond_xram_reads_ff() {
+        // MOV DPTR,#0x0100; MOV A,#0x11; MOVX @DPTR,A; MOVX A,@DPTR
+        let mut emulator = emulator_with(&[0x90, 0x01, 0x00, 0x74, 0x11, 0xF0, 0xE0]);
+        emulator.cpu.xram = vec![0; 0x100];
+        run_steps(&mut emulator, 4);
+        assert_eq!(emulator.cpu.registers.acc, 0xFF);
+        assert_eq!(emulator.cpu.xram.len(), 0x100);
+    }
+
+    #[test]
+    fn direct_f0_is_b_while_indirect_f0_is_upper_ram() {
+        // MOV B,#0x11; MOV R0,#0xF0; MOV A,#0x22; MOV @R0,A; MOV A,@R0; MOV R1,B
+        let program = [0x75, 0xF0, 0x11, 0x78, 0xF0, 0x74, 0x22, 0xF6, 0xE6, 0xA9, 0xF0];
+        for (variant, upper) in [(Variant::I8052, 0x22), (Variant::I8051, 0xFF)] {
+            let mut emulator = Emulator::builder().variant(variant).build();
+            load(&mut emulator, &program);
+            run_steps(&mut emulator, 6);
+            // 8051 没有高128字节RAM：间接写入被忽略，读出 0xFF
+            assert_eq!(emulator.cpu.registers.acc, upper, "{:?}", variant);
+            assert_eq!(emulator.cpu.registers.b, 0x11);
+            assert_eq!(emulator.cpu.ram[1], 0x11);
+        }
+    }
+
+    #[test]
+    fn indirect_access_never_reaches_sfrs() {
+        // MOV A,#0x5A; MOV R0,#0xFF; MOV @R0,A; MOV R0,#0x80; MOV @R0,A; CLR A; MOV A,@R0
+        let program = [0x74, 0x5A, 0x78, 0xFF, 0xF6, 0x78, 0x80, 0xF6, 0xE4, 0xE6];
+        let mut emulator = Emulator::builder().variant(Variant::I8052).build();
+        load(&mut emulator, &program);
+        run_steps(&mut emulator, 7);
+        assert_eq!(emulator.cpu.ram[0xFF], 0x5A);
+        assert_eq!(emulator.cpu.ram[0x80], 0x5A);
+        assert_eq!(emulator.cpu.registers.acc, 0x5A);
+        // @R0=0x80 写的是高128字节RAM，P0 锁存器不变
+        assert_eq!(emulator.cpu.peek_sfr(0x80), 0xFF);
+    }
+
+    #[test]
+    fn pop_psw_reselects_the_saved_register_bank() {
+        // MOV PSW,#0x10（第2组）; MOV R3,#0xB2; MOV R0,#0x40; PUSH PSW
+        // MOV PSW,#0（第0组）; MOV R3,#0x03; MOV R0,#0x50; POP PSW; MOV A,R3; MOV 0x60,A; MOV A,@R0
+        let mut emulator = emulator_with(&[
+            0x75, 0xD0, 0x10, 0x7B, 0xB2, 0x78, 0x40, 0xC0, 0xD0, 0x75, 0xD0, 0x00, 0x7B, 0x03, 0x78, 0x50, 0xD0, 0xD0,
+            0xEB, 0xF5, 0x60, 0xE6,
+        ]);
+        emulator.cpu.ram[0x40] = 0x44;
+        emulator.cpu.ram[0x50] = 0x55;
+        run_steps(&mut emulator, 7);
+        assert_eq!((emulator.cpu.ram[0x13], emulator.cpu.ram[0x03]), (0xB2, 0x03));
+
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.psw_flags().bank, 2);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.ram[0x60], 0xB2);
+        assert_eq!(emulator.cpu.registers.acc, 0x44);
+    }
 }
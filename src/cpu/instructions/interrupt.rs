@@ -1,81 +1,619 @@
 // 中断处理模块
-use super::super::CPU;
+use super::super::peripherals::{IE, IP, PCON, PCON_IDL, PCON_PD, SCON, T2CON, TCON};
+use super::super::adc::{ADCCON_ADCI, IE_EADC, IP_PADC};
+use super::super::serial::{SCON_RI, SCON_TI};
+use super::super::timers::{T2CON_EXF2, T2CON_TF2};
+use super::super::{Variant, CPU};
 use super::{InstructionInfo, InstructionTable};
 
+// IE 位定义
+pub const IE_EA: u8 = 0x80;  // 总中断使能
+pub const IE_ET2: u8 = 0x20; // 定时器2中断使能
+pub const IE_ES: u8 = 0x10;  // 串口中断使能
+pub const IE_ET1: u8 = 0x08; // 定时器1中断使能
+pub const IE_EX1: u8 = 0x04; // 外部中断1使能
+pub const IE_ET0: u8 = 0x02; // 定时器0中断使能
+pub const IE_EX0: u8 = 0x01; // 外部中断0使能
+
+// TCON 位定义
+const TCON_TF1: u8 = 0x80; // 定时器1溢出标志
+const TCON_TF0: u8 = 0x20; // 定时器0溢出标志
+const TCON_IE1: u8 = 0x08; // 外部中断1请求标志
+const TCON_IT1: u8 = 0x04; // 外部中断1触发方式（1=下降沿）
+const TCON_IE0: u8 = 0x02; // 外部中断0请求标志
+const TCON_IT0: u8 = 0x01; // 外部中断0触发方式（1=下降沿）
+
+// 响应中断时硬件如何处理请求标志
+#[derive(Debug, Clone, Copy)]
+pub enum AutoClear {
+    Always,             // 总是由硬件清除（TF0/TF1）
+    IfEdgeTriggered(u8), // 仅在 TCON 中对应的 ITx 位为1（边沿触发）时清除
+    Never,              // 必须由中断服务程序软件清除（RI/TI、TF2/EXF2）
+}
+
+// 中断源描述
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptSource {
+    pub name: &'static str,
+    pub vector: u16,            // 中断向量地址
+    pub enable_mask: u8,        // IE 中的使能位
+    pub priority_mask: u8,      // IP 中的优先级位
+    pub flag_sfr: u8,           // 请求标志所在的 SFR
+    pub flag_mask: u8,          // 请求标志位（任一位置位即请求）
+    pub auto_clear: AutoClear,  // 响应时的标志清除方式
+    pub present: fn(Variant) -> bool, // 该型号是否有此中断源
+}
+
+fn always_present(_: Variant) -> bool {
+    true
+}
+
+// 中断源表：表中顺序即同一优先级内的硬件查询顺序
+pub const INTERRUPT_SOURCES: [InterruptSource; 6] = [
+    InterruptSource {
+        name: "INT0",
+        vector: 0x0003,
+        enable_mask: IE_EX0,
+        priority_mask: 0x01,
+        flag_sfr: TCON,
+        flag_mask: TCON_IE0,
+        auto_clear: AutoClear::IfEdgeTriggered(TCON_IT0),
+        present: always_present,
+    },
+    InterruptSource {
+        name: "TF0",
+        vector: 0x000B,
+        enable_mask: IE_ET0,
+        priority_mask: 0x02,
+        flag_sfr: TCON,
+        flag_mask: TCON_TF0,
+        auto_clear: AutoClear::Always,
+        present: always_present,
+    },
+    InterruptSource {
+        name: "INT1",
+        vector: 0x0013,
+        enable_mask: IE_EX1,
+        priority_mask: 0x04,
+        flag_sfr: TCON,
+        flag_mask: TCON_IE1,
+        auto_clear: AutoClear::IfEdgeTriggered(TCON_IT1),
+        present: always_present,
+    },
+    InterruptSource {
+        name: "TF1",
+        vector: 0x001B,
+        enable_mask: IE_ET1,
+        priority_mask: 0x08,
+        flag_sfr: TCON,
+        flag_mask: TCON_TF1,
+        auto_clear: AutoClear::Always,
+        present: always_present,
+    },
+    InterruptSource {
+        name: "RI/TI",
+        vector: 0x0023,
+        enable_mask: IE_ES,
+        priority_mask: 0x10,
+        flag_sfr: SCON,
+        flag_mask: SCON_RI | SCON_TI,
+        auto_clear: AutoClear::Never,
+        present: always_present,
+    },
+    InterruptSource {
+        name: "TF2/EXF2",
+        vector: 0x002B,
+        enable_mask: IE_ET2,
+        priority_mask: 0x20,
+        flag_sfr: T2CON,
+        flag_mask: T2CON_TF2 | T2CON_EXF2,
+        auto_clear: AutoClear::Never,
+        present: Variant::has_timer2,
+    },
+];
+
 // 注册中断指令到指令表
 pub fn register_instructions(table: &mut InstructionTable) {
     // RETI指令
     table[0x32] = Some(InstructionInfo { handler: |cpu, _| cpu.reti(), mnemonic: "RETI" });
 }
 
+// 响应中断占用的机器周期数：查询请求标志的 1 个周期加上硬件产生的 LCALL 的 2 个周期，
+// 所以从标志置位到中断服务程序的第一条指令至少 3 个机器周期；
+// 标志在多周期指令中途置位时还要等这条指令执行完（中断只在指令之间检查），RETI 和写 IE/IP 之后再推迟一条指令
+pub const INTERRUPT_VECTOR_CYCLES: u64 = 3;
+
+// 中断优先级状态：记录正在执行的低/高优先级中断服务程序
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InterruptState {
+    pub low_active: bool,  // 低优先级中断服务程序执行中
+    pub high_active: bool, // 高优先级中断服务程序执行中
+}
+
+impl InterruptState {
+    // 是否有任何中断服务程序正在执行
+    pub fn in_progress(&self) -> bool {
+        self.low_active || self.high_active
+    }
+}
+
 impl CPU {
     // RETI - 从中断返回
     pub(crate) fn reti(&mut self) {
         // 从堆栈弹出返回地址
-        let high_byte = self.ram[self.registers.sp as usize] as u16;
-        self.registers.sp = self.registers.sp.wrapping_sub(1);
-        let low_byte = self.ram[self.registers.sp as usize] as u16;
-        self.registers.sp = self.registers.sp.wrapping_sub(1);
+        let high_byte = self.pop_stack() as u16;
+        let low_byte = self.pop_stack() as u16;
 
         let return_address = (high_byte << 8) | low_byte;
 
         if self.debug {
-            println!("reti");
+            trace_line!(self, "reti");
         }
 
         self.registers.pc = return_address;
 
-        // 清除中断标志
-        self.interrupt_in_progress = false;
+        // RETI 之后至少再执行一条指令才会响应新的中断
+        self.interrupt_blocked = true;
+
+        // 结束当前优先级的中断（高优先级中断一定是最后进入的）
+        if self.interrupt_state.high_active {
+            self.interrupt_state.high_active = false;
+        } else {
+            self.interrupt_state.low_active = false;
+        }
+    }
+
+    // 采样外部中断引脚 INT0 (P3.2) / INT1 (P3.3)
+    // ITx=1：下降沿触发，检测到 1→0 跳变时锁存 IEx，进入中断时由硬件清除；
+    //        引脚在响应之前回到高电平，请求仍然保留
+    // ITx=0：低电平触发，IEx 不锁存而是跟随引脚电平（软件写入的 IEx 也会被覆盖）：
+    //        引脚在响应之前回到高电平则请求消失；进入中断不清除 IEx，
+    //        RETI 之后引脚仍为低电平会再次进入中断服务程序
+    // 每次查询中断之前都会重新采样，所以查询看到的总是当前引脚状态
+    pub(crate) fn sample_external_interrupts(&mut self) {
+        for n in 0..2 {
+            let level = self.pin_level(3, 2 + n as u8);
+            let it_mask = TCON_IT0 << (2 * n);
+            let ie_mask = TCON_IE0 << (2 * n);
+            let tcon = &mut self.sfr[(TCON - 0x80) as usize];
+
+            if (*tcon & it_mask) != 0 {
+                if self.int_pin_last[n] && !level {
+                    *tcon |= ie_mask;
+                }
+            } else if level {
+                *tcon &= !ie_mask;
+            } else {
+                *tcon |= ie_mask;
+            }
+
+            self.int_pin_last[n] = level;
+        }
+    }
+
+    // 软件写 TCON 之后按新的触发方式整理 IE0/IE1（old_tcon 为写入之前的值）：
+    // 低电平触发的 IEx 立即跟随引脚，软件写入的值被覆盖，读回的就是引脚状态；
+    // 刚从低电平触发改为下降沿触发时，跟随引脚得到的 IEx 不是一次下降沿，清除后从当前引脚电平开始检测边沿
+    pub(crate) fn external_interrupt_mode_written(&mut self, old_tcon: u8) {
+        for n in 0..2 {
+            let level = self.pin_level(3, 2 + n as u8);
+            let it_mask = TCON_IT0 << (2 * n);
+            let ie_mask = TCON_IE0 << (2 * n);
+            let tcon = &mut self.sfr[(TCON - 0x80) as usize];
+
+            if (*tcon & it_mask) == 0 {
+                if level {
+                    *tcon &= !ie_mask;
+                } else {
+                    *tcon |= ie_mask;
+                }
+            } else if (old_tcon & it_mask) == 0 {
+                *tcon &= !ie_mask;
+                self.int_pin_last[n] = level;
+            }
+        }
+    }
+
+    // 外部中断 INT0 (n=0) / INT1 (n=1) 是否可能产生请求：IEx 已置位、引脚当前为低，或者有外部器件驱动该引脚
+    // 没有器件驱动时引脚电平只会因为宿主程序调用 set_pin 或程序自己写 P3 而改变，不会在运行中自行变化
+    pub(crate) fn external_interrupt_possible(&self, n: u8) -> bool {
+        let ie_mask = TCON_IE0 << (2 * n);
+        (self.sfr[(TCON - 0x80) as usize] & ie_mask) != 0 || !self.pin_level(3, 2 + n) || self.device_drives(3, 2 + n)
+    }
+
+    // 掉电模式下是否有已使能的外部中断可以唤醒 CPU（AT89S52 的行为）
+    pub fn external_wake_enabled(&self) -> bool {
+        let ie = self.sfr[(IE - 0x80) as usize];
+        (ie & IE_EA) != 0 && (ie & (IE_EX0 | IE_EX1)) != 0
+    }
+
+    // 掉电唤醒：已使能的 INTx 引脚被拉低时清除 PD，振荡器恢复后照常响应该中断
+    pub(crate) fn wake_from_power_down(&mut self) -> bool {
+        let ie = self.sfr[(IE - 0x80) as usize];
+        let wake = self.external_wake_enabled()
+            && (((ie & IE_EX0) != 0 && !self.pin_level(3, 2)) || ((ie & IE_EX1) != 0 && !self.pin_level(3, 3)));
+        if wake {
+            self.sfr[(PCON - 0x80) as usize] &= !PCON_PD;
+        }
+        wake
     }
 
     // 检查并处理中断
+    // 高优先级请求先于低优先级请求；同一优先级内按中断源表的顺序响应：
+    // IE0 → TF0 → IE1 → TF1 → RI/TI → TF2/EXF2 → ADC（挂上片上 ADC 时）
+    // 低优先级中断服务程序可以被高优先级请求打断，高优先级中断服务程序不会被任何中断打断
     pub fn check_interrupts(&mut self) -> bool {
-        let ie = self.sfr[0x28]; // IE寄存器 (0xA8 - 0x80)
-        let ea = (ie & 0x80) != 0; // EA位：总中断使能
+        // 引脚采样与中断是否使能无关
+        self.sample_external_interrupts();
+
+        // 刚执行过 RETI 或写过 IE/IP，本次不响应中断
+        if self.interrupt_blocked {
+            self.interrupt_blocked = false;
+            return false;
+        }
+
+        let ie = self.sfr[(IE - 0x80) as usize]; // IE寄存器
+        let ea = (ie & IE_EA) != 0; // EA位：总中断使能
 
         if !ea {
             return false; // 总中断未使能
         }
 
-        if self.interrupt_in_progress {
-            return false; // 正在处理中断
+        if self.interrupt_state.high_active {
+            return false; // 正在处理高优先级中断
         }
 
-        let tcon = self.sfr[0x08]; // TCON寄存器 (0x88 - 0x80)
-        let et0 = (ie & 0x02) != 0; // ET0位：定时器0中断使能
-        let tf0 = (tcon & 0x20) != 0; // TF0位：定时器0溢出标志
-
-        // 检查定时器0中断
-        if et0 && tf0 {
-            // 清除TF0标志
-            self.sfr[0x08] &= !0x20; // 清除TF0
-
-            // 保存当前PC到堆栈（先压低字节，再压高字节）
-            self.push_stack((self.registers.pc & 0xFF) as u8);
-            self.push_stack((self.registers.pc >> 8) as u8);
+        if let Some(source) = self.poll_interrupts(true) {
+            self.acknowledge_interrupt(source);
+            self.enter_interrupt(source.vector, true);
+            return true;
+        }
 
-            // 跳转到中断向量（定时器0在0x000B）
-            self.interrupt_return_pc = self.registers.pc;
-            self.registers.pc = 0x000B;
-            self.interrupt_in_progress = true;
+        if self.interrupt_state.low_active {
+            return false; // 低优先级中断执行期间只接受高优先级请求
+        }
 
+        if let Some(source) = self.poll_interrupts(false) {
+            self.acknowledge_interrupt(source);
+            self.enter_interrupt(source.vector, false);
             return true;
         }
 
         false
     }
 
+    // 按中断源表的顺序找出指定优先级上第一个已使能的中断请求
+    fn poll_interrupts(&self, high_priority: bool) -> Option<InterruptSource> {
+        let ie = self.sfr[(IE - 0x80) as usize];
+        let ip = self.sfr[(IP - 0x80) as usize];
+
+        INTERRUPT_SOURCES.iter().copied().chain(self.adc_interrupt_source()).find(|source| {
+            (source.present)(self.variant)
+                && (ie & source.enable_mask) != 0
+                && ((ip & source.priority_mask) != 0) == high_priority
+                && (self.sfr[(source.flag_sfr - 0x80) as usize] & source.flag_mask) != 0
+        })
+    }
+
+    // 片上 ADC 的中断源：寄存器地址和向量由 ADC 的配置决定，所以不在固定的中断源表里
+    fn adc_interrupt_source(&self) -> Option<InterruptSource> {
+        let adc = self.adc.as_ref()?;
+        Some(InterruptSource {
+            name: "ADC",
+            vector: adc.config.vector,
+            enable_mask: IE_EADC,
+            priority_mask: IP_PADC,
+            flag_sfr: adc.config.adccon,
+            flag_mask: ADCCON_ADCI,
+            auto_clear: AutoClear::Never,
+            present: always_present,
+        })
+    }
+
+    // 中断被响应时按中断源的规则清除请求标志
+    // 电平触发的 IEx 不清除，它由引脚采样维护
+    fn acknowledge_interrupt(&mut self, source: InterruptSource) {
+        let clear = match source.auto_clear {
+            AutoClear::Always => true,
+            AutoClear::IfEdgeTriggered(it_mask) => (self.sfr[(TCON - 0x80) as usize] & it_mask) != 0,
+            AutoClear::Never => false,
+        };
+        if clear {
+            self.sfr[(source.flag_sfr - 0x80) as usize] &= !source.flag_mask;
+        }
+    }
+
+    // 响应中断：硬件 LCALL 到中断向量（加上向量表基地址）
+    fn enter_interrupt(&mut self, vector: u16, high_priority: bool) {
+        self.interrupt_entries += 1;
+        // 保存当前PC到堆栈（先压低字节，再压高字节）
+        self.push_stack((self.registers.pc & 0xFF) as u8);
+        self.push_stack((self.registers.pc >> 8) as u8);
+
+        self.interrupt_return_pc = self.registers.pc;
+        self.registers.pc = self.vector_base.wrapping_add(vector);
+        // 响应中断时硬件清除 IDL，中断返回后从进入空闲模式的下一条指令继续执行
+        self.sfr[(PCON - 0x80) as usize] &= !PCON_IDL;
+        if high_priority {
+            self.interrupt_state.high_active = true;
+        } else {
+            self.interrupt_state.low_active = true;
+        }
+    }
+
     // 辅助函数：压栈
+    // 堆栈按间接寻址访问内部RAM
     pub(crate) fn push_stack(&mut self, value: u8) {
         self.registers.sp = self.registers.sp.wrapping_add(1);
-        self.ram[self.registers.sp as usize] = value;
+        self.write_indirect(self.registers.sp, value);
     }
 
     // 辅助函数：出栈
     pub(crate) fn pop_stack(&mut self) -> u8 {
-        let value = self.ram[self.registers.sp as usize];
+        let value = self.read_indirect(self.registers.sp);
         self.registers.sp = self.registers.sp.wrapping_sub(1);
         value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Emulator;
+    use crate::test_support::{emulator_with_blocks, run_steps};
+
+    // 复位后跳到 0x0030：edge 时 SETB IT0，否则两条 NOP；MOV IE,#0x01（只开 EX0）；NOP; NOP; SETB EA; SJMP $
+    // INT0 服务程序 (0x0003)：INC 0x40; RETI
+    fn int0_program(edge: bool) -> Emulator {
+        let mode: &[u8] = if edge { &[0xD2, 0x88] } else { &[0x00, 0x00] };
+        let main = [mode, &[0x75, 0xA8, 0x01, 0x00, 0x00, 0xD2, 0xAF, 0x80, 0xFE]].concat();
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x0003, &[0x05, 0x40, 0x32]), (0x0030, &main)]);
+        run_steps(&mut emulator, 3);
+        emulator
+    }
+
+    // EA 打开之前把 INT0 拉低一条指令再放开，然后运行到 SJMP $
+    fn pulse_before_enable(emulator: &mut Emulator) {
+        emulator.cpu.set_pin(3, 2, false);
+        run_steps(emulator, 1);
+        emulator.cpu.set_pin(3, 2, true);
+        run_steps(emulator, 10);
+    }
+
+    #[test]
+    fn level_request_released_before_service_is_cancelled() {
+        let mut emulator = int0_program(false);
+        emulator.cpu.set_pin(3, 2, false);
+        run_steps(&mut emulator, 1);
+        assert_ne!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+
+        emulator.cpu.set_pin(3, 2, true);
+        run_steps(&mut emulator, 10);
+        assert_eq!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+        assert_eq!(emulator.cpu.interrupt_entries, 0);
+        assert_eq!(emulator.cpu.ram[0x40], 0);
+    }
+
+    #[test]
+    fn edge_request_released_before_service_still_fires() {
+        let mut emulator = int0_program(true);
+        pulse_before_enable(&mut emulator);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+        // 进入中断时硬件清除 IE0
+        assert_eq!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+    }
+
+    #[test]
+    fn level_mode_overrides_software_written_ie0() {
+        let mut emulator = int0_program(false);
+        emulator.cpu.write_sfr(TCON, TCON_IE0);
+        run_steps(&mut emulator, 10);
+        assert_eq!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+        assert_eq!(emulator.cpu.interrupt_entries, 0);
+    }
+
+    #[test]
+    fn tcon_write_in_level_mode_reads_back_the_pin() {
+        let mut emulator = int0_program(false);
+        emulator.cpu.write_sfr(TCON, TCON_IE0);
+        assert_eq!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+        emulator.cpu.set_pin(3, 2, false);
+        emulator.cpu.write_sfr(TCON, 0);
+        assert_ne!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+    }
+
+    #[test]
+    fn switching_to_edge_mode_drops_the_level_request() {
+        let mut emulator = int0_program(false);
+        emulator.cpu.set_pin(3, 2, false);
+        run_steps(&mut emulator, 1);
+        assert_ne!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+
+        // 引脚一直为低，改为下降沿触发之后没有边沿，不进入中断
+        let tcon = emulator.cpu.peek_sfr(TCON);
+        emulator.cpu.write_sfr(TCON, tcon | TCON_IT0);
+        assert_eq!(emulator.cpu.peek_sfr(TCON) & TCON_IE0, 0);
+        run_steps(&mut emulator, 10);
+        assert_eq!(emulator.cpu.interrupt_entries, 0);
+
+        // 放开再拉低才是一次下降沿
+        emulator.cpu.set_pin(3, 2, true);
+        run_steps(&mut emulator, 1);
+        emulator.cpu.set_pin(3, 2, false);
+        run_steps(&mut emulator, 5);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+    }
+
+    // MOV TMOD,#0x01; MOV TH0,#0xFF; MOV TL0,#0xFE; SETB TR0; NOP; NOP（TF0 已经置位，中断没有打开）;
+    // clear 时 MOV TCON,#0（停止定时器0并清除 TF0），否则三条 NOP; MOV IE,#0x82; NOP; NOP; SJMP $
+    // 定时器0服务程序 (0x000B)：INC 0x40; CLR TR0; RETI
+    fn pending_tf0_program(clear: bool) -> Emulator {
+        let tcon: &[u8] = if clear { &[0x75, 0x88, 0x00] } else { &[0x00, 0x00, 0x00] };
+        let setup = [0x75, 0x89, 0x01, 0x75, 0x8C, 0xFF, 0x75, 0x8A, 0xFE, 0xD2, 0x8C, 0x00, 0x00];
+        let main = [&setup[..], tcon, &[0x75, 0xA8, 0x82, 0x00, 0x00, 0x80, 0xFE]].concat();
+        let mut emulator =
+            emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x000B, &[0x05, 0x40, 0xC2, 0x8C, 0x32]), (0x0030, &main)]);
+        run_steps(&mut emulator, 7);
+        assert_ne!(emulator.cpu.peek_sfr(TCON) & TCON_TF0, 0);
+        run_steps(&mut emulator, 20);
+        emulator
+    }
+
+    #[test]
+    fn pending_tf0_fires_once_interrupts_are_enabled() {
+        let emulator = pending_tf0_program(false);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+    }
+
+    #[test]
+    fn tf0_cleared_through_tcon_does_not_fire() {
+        let emulator = pending_tf0_program(true);
+        assert_eq!(emulator.cpu.interrupt_entries, 0);
+        assert_eq!(emulator.cpu.ram[0x40], 0);
+    }
+
+    // 0x0030: MOV TMOD,#0x01; MOV TL0,#0xF0; MOV TH0,#0xFF; MOV IE,#0x82; SETB TR0（第 9 个机器周期开始计数），然后是 main
+    // 定时器0从 0xFFF0 计 16 次，在第 24 个机器周期结束时溢出置位 TF0；服务程序 (0x000B)：SJMP $
+    fn timer0_program(main: &[u8]) -> Emulator {
+        let setup = [0x75, 0x89, 0x01, 0x75, 0x8A, 0xF0, 0x75, 0x8C, 0xFF, 0x75, 0xA8, 0x82, 0xD2, 0x8C];
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x000B, &[0x80, 0xFE]), (0x0030, &[&setup[..], main].concat())]);
+        while emulator.cpu.registers.pc != 0x000B {
+            emulator.step();
+        }
+        emulator
+    }
+
+    // LJMP 本身占 2 个机器周期，定时器从第 9 个机器周期开始计数
+    const TF0_SET_AT: u64 = 2 + 24;
+
+    #[test]
+    fn timer0_interrupt_takes_three_cycles_after_tf0() {
+        let emulator = timer0_program(&[0x00; 20]);
+        assert_eq!(emulator.clock_cycles / 12, TF0_SET_AT + 3);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+    }
+
+    #[test]
+    fn timer0_interrupt_waits_for_the_instruction_in_progress() {
+        // 13 个 NOP 之后是 MUL AB：TF0 在 MUL 的第 2 个机器周期置位，还要等 2 个周期 MUL 才执行完
+        let main = [[0x00; 13].as_slice(), &[0xA4], &[0x00; 4]].concat();
+        let emulator = timer0_program(&main);
+        assert_eq!(emulator.clock_cycles / 12, TF0_SET_AT + 2 + 3);
+    }
+
+    // 0x0030: SETB IT1; MOV IP,#ip; MOV IE,#0x86（EX1 和 ET0）; SJMP $
+    // 定时器0服务程序 (0x000B)：INC 0x40; RETI
+    // INT1 服务程序 (0x0013)：INC 0x41; JNB 0x00,$（等测试置位 0x20.0）; RETI
+    // 拉低 INT1 进入服务程序之后再置位 TF0
+    fn tf0_during_int1_program(ip: u8) -> Emulator {
+        let main = [0xD2, 0x8A, 0x75, 0xB8, ip, 0x75, 0xA8, 0x86, 0x80, 0xFE];
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x02, 0x00, 0x30]),
+            (0x000B, &[0x05, 0x40, 0x32]),
+            (0x0013, &[0x05, 0x41, 0x30, 0x00, 0xFD, 0x32]),
+            (0x0030, &main),
+        ]);
+        run_steps(&mut emulator, 4);
+        emulator.cpu.set_pin(3, 3, false);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.ram[0x41], 1);
+        let tcon = emulator.cpu.peek_sfr(TCON);
+        emulator.cpu.write_sfr(TCON, tcon | TCON_TF0);
+        run_steps(&mut emulator, 5);
+        emulator
+    }
+
+    #[test]
+    fn high_priority_timer0_preempts_int1_isr() {
+        let emulator = tf0_during_int1_program(0x02);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+        assert_eq!(emulator.cpu.interrupt_entries, 2);
+        // 定时器0的 RETI 回到仍在等待的 INT1 服务程序
+        assert_eq!(emulator.cpu.registers.pc, 0x0015);
+    }
+
+    #[test]
+    fn same_priority_timer0_waits_for_reti() {
+        let mut emulator = tf0_during_int1_program(0x00);
+        assert_eq!(emulator.cpu.ram[0x40], 0);
+        assert_ne!(emulator.cpu.peek_sfr(TCON) & TCON_TF0, 0);
+
+        emulator.cpu.ram[0x20] |= 0x01;
+        run_steps(&mut emulator, 5);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+        assert_eq!(emulator.cpu.interrupt_entries, 2);
+    }
+
+    // 复位后跳到 0x0030：MOV IP,#ip; MOV IE,#0x8F; SJMP $，四个向量处都是 SJMP $
+    // 打开中断之前同时置位 flags（两个外部中断为下降沿触发），返回进入的第一个中断向量
+    fn first_vector_taken(flags: u8, ip: u8) -> u16 {
+        let spin: &[u8] = &[0x80, 0xFE];
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x02, 0x00, 0x30]),
+            (0x0003, spin),
+            (0x000B, spin),
+            (0x0013, spin),
+            (0x001B, spin),
+            (0x0030, &[0x75, 0xB8, ip, 0x75, 0xA8, 0x8F, 0x80, 0xFE]),
+        ]);
+        // 先切换到下降沿触发（切换时会丢弃请求），再置位请求标志
+        emulator.cpu.write_sfr(TCON, TCON_IT0 | TCON_IT1);
+        emulator.cpu.write_sfr(TCON, TCON_IT0 | TCON_IT1 | flags);
+        for _ in 0..10 {
+            emulator.step();
+            if emulator.cpu.registers.pc < 0x0030 {
+                break;
+            }
+        }
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+        emulator.cpu.registers.pc
+    }
+
+    #[test]
+    fn simultaneous_requests_follow_polling_order() {
+        let all = TCON_IE0 | TCON_TF0 | TCON_IE1 | TCON_TF1;
+        let cases = [
+            (all, 0x00, 0x0003),
+            (TCON_TF0 | TCON_IE1 | TCON_TF1, 0x00, 0x000B),
+            (TCON_IE1 | TCON_TF1, 0x00, 0x0013),
+            (TCON_TF1, 0x00, 0x001B),
+            // 高优先级先于查询顺序：TF1 设为高优先级
+            (all, 0x08, 0x001B),
+            // 两个高优先级之间仍按查询顺序
+            (all, 0x0C, 0x0013),
+        ];
+        for (flags, ip, vector) in cases {
+            assert_eq!(first_vector_taken(flags, ip), vector, "TCON={:#04x} IP={:#04x}", flags, ip);
+        }
+    }
+
+    #[test]
+    fn relocated_vector_table_sends_timer0_to_0x200b() {
+        // 0x2000：LJMP 0x2030；0x2030：MOV TMOD,#0x02; MOV TH0,#0xF0; SETB TR0; MOV IE,#0x82; SJMP $
+        // 0x200B：INC 0x40; RETI；原来的向量 0x000B：INC 0x41; RETI（不应进入）
+        let mut emulator = emulator_with_blocks(&[
+            (0x000B, &[0x05, 0x41, 0x32]),
+            (0x2000, &[0x02, 0x20, 0x30]),
+            (0x200B, &[0x05, 0x40, 0x32]),
+            (0x2030, &[0x75, 0x89, 0x02, 0x75, 0x8C, 0xF0, 0xD2, 0x8C, 0x75, 0xA8, 0x82, 0x80, 0xFE]),
+        ]);
+        emulator.cpu.set_vector_base(0x2000);
+        assert_eq!(emulator.cpu.registers.pc, 0x2000);
+
+        let mut entered = false;
+        for _ in 0..200 {
+            emulator.step();
+            entered |= emulator.cpu.registers.pc == 0x200B;
+        }
+        assert!(entered);
+        assert!(emulator.cpu.ram[0x40] > 0);
+        assert_eq!(emulator.cpu.ram[0x41], 0);
+
+        // 复位同样回到搬移后的复位向量
+        emulator.cpu.reset();
+        assert_eq!(emulator.cpu.registers.pc, 0x2000);
+    }
+}
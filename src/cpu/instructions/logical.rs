@@ -7,6 +7,9 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // ORL A, #data指令
     table[0x44] = Some(InstructionInfo { handler: |cpu, _| cpu.orl_acc_immediate(), mnemonic: "ORL" });
     
+    // ORL A, direct指令
+    table[0x45] = Some(InstructionInfo { handler: |cpu, _| cpu.orl_a_direct(), mnemonic: "ORL" });
+    
     // ORL A, Rn指令 (0x48-0x4F)
     for opcode in 0x48..=0x4F {
         table[opcode] = Some(InstructionInfo { 
@@ -15,6 +18,9 @@ pub fn register_instructions(table: &mut InstructionTable) {
         });
     }
     
+    // ANL A, direct指令
+    table[0x55] = Some(InstructionInfo { handler: |cpu, _| cpu.anl_a_direct(), mnemonic: "ANL" });
+    
     // ANL A, Rn指令 (0x58-0x5F)
     for opcode in 0x58..=0x5F {
         table[opcode] = Some(InstructionInfo { 
@@ -23,9 +29,15 @@ pub fn register_instructions(table: &mut InstructionTable) {
         });
     }
     
+    // ORL direct, #data指令
+    table[0x43] = Some(InstructionInfo { handler: |cpu, _| cpu.orl_direct_immediate(), mnemonic: "ORL" });
+    
     // ANL direct, A指令
     table[0x82] = Some(InstructionInfo { handler: |cpu, _| cpu.anl_direct_a(), mnemonic: "ANL" });
     
+    // XRL A, direct指令
+    table[0x65] = Some(InstructionInfo { handler: |cpu, _| cpu.xrl_a_direct(), mnemonic: "XRL" });
+    
     // XRL A, Rn指令 (0x68-0x6F)
     for opcode in 0x68..=0x6F {
         table[opcode] = Some(InstructionInfo { 
@@ -49,6 +61,9 @@ pub fn register_instructions(table: &mut InstructionTable) {
     // SETB bit指令
     table[0xD2] = Some(InstructionInfo { handler: |cpu, _| cpu.setb_bit(), mnemonic: "SETB" });
     
+    // MOV C, bit指令
+    table[0xA2] = Some(InstructionInfo { handler: |cpu, _| cpu.mov_c_bit(), mnemonic: "MOV" });
+    
     // RL A指令
     table[0x23] = Some(InstructionInfo { handler: |cpu, _| cpu.rl_a(), mnemonic: "RL" });
     
@@ -63,195 +78,279 @@ impl CPU {
     // ORL A, #data - 累加器与立即数进行逻辑或
     pub(crate) fn orl_acc_immediate(&mut self) {
         let immediate = self.fetch_next_byte();
-        self.registers.acc |= immediate;
+        self.set_acc(self.registers.acc | immediate);
         if self.debug {
-            println!("orl A, #{:#04x}", immediate);
+            trace_line!(self, "orl A, #{:#04x}", immediate);
         }
     }
 
     // ORL A, Rn - 累加器与寄存器Rn进行逻辑或
     pub(crate) fn orl_a_rn(&mut self, reg_num: u8) {
-        self.registers.acc |= self.read_register(reg_num);
+        self.set_acc(self.registers.acc | self.read_register(reg_num));
+        if self.debug {
+            trace_line!(self, "orl A, R{}", reg_num);
+        }
+    }
+
+    // ORL A, direct - 累加器与直接地址进行逻辑或
+    pub(crate) fn orl_a_direct(&mut self) {
+        let direct_address = self.fetch_next_byte();
+        self.set_acc(self.registers.acc | self.read_internal(direct_address));
+        if self.debug {
+            trace_line!(self, "orl A, {:#04x}", direct_address);
+        }
+    }
+
+    // ANL A, direct - 累加器与直接地址进行逻辑与
+    pub(crate) fn anl_a_direct(&mut self) {
+        let direct_address = self.fetch_next_byte();
+        self.set_acc(self.registers.acc & self.read_internal(direct_address));
+        if self.debug {
+            trace_line!(self, "anl A, {:#04x}", direct_address);
+        }
+    }
+
+    // XRL A, direct - 累加器与直接地址进行逻辑异或
+    pub(crate) fn xrl_a_direct(&mut self) {
+        let direct_address = self.fetch_next_byte();
+        self.set_acc(self.registers.acc ^ self.read_internal(direct_address));
+        if self.debug {
+            trace_line!(self, "xrl A, {:#04x}", direct_address);
+        }
+    }
+
+    // ORL direct, #data - 直接地址与立即数进行逻辑或，结果写回直接地址
+    pub(crate) fn orl_direct_immediate(&mut self) {
+        let direct_address = self.fetch_next_byte();
+        let immediate = self.fetch_next_byte();
+        let value = self.read_internal(direct_address);
+        self.write_internal(direct_address, value | immediate);
+
         if self.debug {
-            println!("orl A, R{}", reg_num);
+            trace_line!(self, "orl {:#04x}, #{:#04x}", direct_address, immediate);
         }
     }
 
     // ANL direct, A - 直接地址与累加器进行逻辑与
     pub(crate) fn anl_direct_a(&mut self) {
         let direct_address = self.fetch_next_byte();
-        let value = if direct_address < 0x80 {
-            self.ram[direct_address as usize]
-        } else {
-            self.read_sfr(direct_address)
-        };
+        let value = self.read_internal(direct_address);
 
-        self.registers.acc &= value;
+        self.set_acc(self.registers.acc & value);
 
         if self.debug {
-            println!("anl {:#04x}, A", direct_address);
+            trace_line!(self, "anl {:#04x}, A", direct_address);
         }
     }
 
     // CLR C - 清除进位标志
     pub(crate) fn clr_c(&mut self) {
-        // PSW寄存器在地址0xD0，进位标志是bit 7
-        let psw = self.read_sfr(0xD0);
-        self.write_sfr(0xD0, psw & 0x7F); // 清除bit 7 (CY位)
+        self.set_carry_flag(false);
         
         if self.debug {
-            println!("clr C");
+            trace_line!(self, "clr C");
         }
     }
 
     // ANL A, Rn - 累加器与寄存器Rn进行逻辑与
     pub(crate) fn anl_a_rn(&mut self, reg_num: u8) {
         let value = self.read_register(reg_num);
-        self.registers.acc &= value;
+        self.set_acc(self.registers.acc & value);
         if self.debug {
-            println!("anl A, R{}", reg_num);
+            trace_line!(self, "anl A, R{}", reg_num);
         }
     }
 
     // XRL A, Rn - 累加器与寄存器Rn进行逻辑异或
     pub(crate) fn xrl_a_rn(&mut self, reg_num: u8) {
         let value = self.read_register(reg_num);
-        self.registers.acc ^= value;
+        self.set_acc(self.registers.acc ^ value);
         if self.debug {
-            println!("xrl A, R{}", reg_num);
+            trace_line!(self, "xrl A, R{}", reg_num);
         }
     }
 
     // CPL A - 累加器按位取反
     pub(crate) fn cpl_a(&mut self) {
-        self.registers.acc = !self.registers.acc;
+        self.set_acc(!self.registers.acc);
         if self.debug {
-            println!("cpl A");
+            trace_line!(self, "cpl A");
         }
     }
 
     // RLC A - 累加器左移循环通过进位
     pub(crate) fn rlc_a(&mut self) {
-        let psw = self.read_sfr(0xD0);
-        let old_carry = (psw >> 7) & 1;
+        let old_carry = self.get_carry_flag();
         let new_carry = (self.registers.acc >> 7) & 1;
         
-        self.registers.acc = (self.registers.acc << 1) | old_carry;
+        self.set_acc((self.registers.acc << 1) | old_carry);
         
         // 更新进位标志
-        let new_psw = if new_carry == 1 {
-            psw | 0x80
-        } else {
-            psw & 0x7F
-        };
-        self.write_sfr(0xD0, new_psw);
+        self.set_carry_flag(new_carry == 1);
         
         if self.debug {
-            println!("rlc A");
+            trace_line!(self, "rlc A");
         }
     }
 
     // RL A - 累加器左移（不通过进位）
     pub(crate) fn rl_a(&mut self) {
         let carry_out = (self.registers.acc >> 7) & 1;
-        self.registers.acc = (self.registers.acc << 1) | carry_out;
+        self.set_acc((self.registers.acc << 1) | carry_out);
         
         if self.debug {
-            println!("rl A");
+            trace_line!(self, "rl A");
         }
     }
 
     // RRC A - 累加器右移循环通过进位
     pub(crate) fn rrc_a(&mut self) {
-        let psw = self.read_sfr(0xD0);
-        let old_carry = (psw >> 7) & 1;
+        let old_carry = self.get_carry_flag();
         let new_carry = self.registers.acc & 1;
         
-        self.registers.acc = (self.registers.acc >> 1) | (old_carry << 7);
+        self.set_acc((self.registers.acc >> 1) | (old_carry << 7));
         
         // 更新进位标志
-        let new_psw = if new_carry == 1 {
-            psw | 0x80
-        } else {
-            psw & 0x7F
-        };
-        self.write_sfr(0xD0, new_psw);
+        self.set_carry_flag(new_carry == 1);
         
         if self.debug {
-            println!("rrc A");
+            trace_line!(self, "rrc A");
         }
     }
 
     // SETB bit - 设置指定的位
     pub(crate) fn setb_bit(&mut self) {
         let bit_addr = self.fetch_next_byte();
-        
-        // 位地址 0x00-0x7F 对应 RAM 的 0x20-0x2F (位寻址区)
-        // 位地址 0x80-0xFF 对应 SFR 的位寻址区
-        if bit_addr < 0x80 {
-            // 内部RAM位寻址
-            let byte_addr = 0x20 + (bit_addr >> 3) as usize;
-            let bit_pos = bit_addr & 0x07;
-            self.ram[byte_addr] |= 1 << bit_pos;
-        } else {
-            // SFR位寻址
-            // SFR位地址映射：0x80-0x87对应0x80, 0x88-0x8F对应0x88, 0x90-0x97对应0x90, ...
-            let byte_addr = bit_addr & 0xF8;  // 取高5位得到字节地址
-            let bit_pos = bit_addr & 0x07;
-            let value = self.read_sfr(byte_addr);
-            self.write_sfr(byte_addr, value | (1 << bit_pos));
-        }
-        
+        self.write_bit(bit_addr, true);
+
         if self.debug {
-            println!("setb {:#04x}", bit_addr);
+            trace_line!(self, "setb {:#04x}", bit_addr);
         }
     }
 
     // CPL bit - 对指定的位取反
     pub(crate) fn cpl_bit(&mut self) {
         let bit_addr = self.fetch_next_byte();
-        
-        // 位地址 0x00-0x7F 对应 RAM 的 0x20-0x2F (位寻址区)
-        // 位地址 0x80-0xFF 对应 SFR 的位寻址区
-        if bit_addr < 0x80 {
-            // 内部RAM位寻址
-            let byte_addr = 0x20 + (bit_addr >> 3) as usize;
-            let bit_pos = bit_addr & 0x07;
-            self.ram[byte_addr] ^= 1 << bit_pos; // 异或实现取反
-        } else {
-            // SFR位寻址
-            let byte_addr = bit_addr & 0xF8;  // 取高5位得到字节地址
-            let bit_pos = bit_addr & 0x07;
-            let value = self.read_sfr(byte_addr);
-            self.write_sfr(byte_addr, value ^ (1 << bit_pos)); // 异或实现取反
-        }
-        
+        let value = self.read_bit_latch(bit_addr);
+        self.write_bit(bit_addr, !value);
+
         if self.debug {
-            println!("cpl {:#04x}", bit_addr);
+            trace_line!(self, "cpl {:#04x}", bit_addr);
         }
     }
 
     // CLR bit - 清除指定的位
     pub(crate) fn clr_bit(&mut self) {
         let bit_addr = self.fetch_next_byte();
-        
-        // 位地址 0x00-0x7F 对应 RAM 的 0x20-0x2F (位寻址区)
-        // 位地址 0x80-0xFF 对应 SFR 的位寻址区
-        if bit_addr < 0x80 {
-            // 内部RAM位寻址
-            let byte_addr = 0x20 + (bit_addr >> 3) as usize;
-            let bit_pos = bit_addr & 0x07;
-            self.ram[byte_addr] &= !(1 << bit_pos);
-        } else {
-            // SFR位寻址
-            let byte_addr = bit_addr & 0xF8;  // 取高5位得到字节地址
-            let bit_pos = bit_addr & 0x07;
-            let value = self.read_sfr(byte_addr);
-            self.write_sfr(byte_addr, value & !(1 << bit_pos));
+        self.write_bit(bit_addr, false);
+
+        if self.debug {
+            trace_line!(self, "clr {:#04x}", bit_addr);
         }
-        
+    }
+
+    // MOV C, bit - 把指定的位送入进位标志
+    pub(crate) fn mov_c_bit(&mut self) {
+        let bit_addr = self.fetch_next_byte();
+        let value = self.read_bit(bit_addr);
+        self.set_carry_flag(value);
+
         if self.debug {
-            println!("clr {:#04x}", bit_addr);
+            trace_line!(self, "{:<30}\t(value={})", format!("mov C, {:#04x}", bit_addr), value as u8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::instructions::PSW_P;
+    use crate::cpu::peripherals::{ACC, PSW};
+    use crate::test_support::{emulator_with, run_steps};
+
+    #[test]
+    fn cpl_a_updates_parity_and_mirror() {
+        // CLR A; CPL A; MOV 0x30,0xE0（直接寻址读 ACC）
+        let mut emulator = emulator_with(&[0xE4, 0xF4, 0x85, 0xE0, 0x30]);
+        run_steps(&mut emulator, 3);
+        let cpu = &emulator.cpu;
+        assert_eq!(cpu.registers.acc, 0xFF);
+        assert!(!cpu.psw_flags().p); // 8 个 1，偶校验
+        assert_eq!(cpu.peek_sfr(ACC), 0xFF);
+        assert_eq!(cpu.ram[0x30], 0xFF);
+    }
+
+    #[test]
+    fn setb_cy_is_seen_by_jc() {
+        // SETB 0xD7; JC +2; MOV R0,#1; CLR 0xD7; JNC +2; MOV R1,#1; SETB ACC.7; SETB B.0; CPL 0xD2 (OV)
+        let mut emulator = emulator_with(&[
+            0xD2, 0xD7, 0x40, 0x02, 0x78, 0x01, 0xC2, 0xD7, 0x50, 0x02, 0x79, 0x01, 0xD2, 0xE7, 0xD2, 0xF0, 0xB2, 0xD2,
+        ]);
+        run_steps(&mut emulator, 7);
+        let cpu = &emulator.cpu;
+        assert_eq!(cpu.registers.pc, 0x0012);
+        assert_eq!(cpu.ram[0], 0);
+        assert_eq!(cpu.ram[1], 0);
+        assert!(!cpu.psw_flags().cy);
+        assert!(cpu.psw_flags().ov);
+        assert_eq!(cpu.registers.acc, 0x80);
+        assert!(cpu.psw_flags().p);
+        assert_eq!(cpu.registers.b, 0x01);
+    }
+
+    #[test]
+    fn logic_ops_with_direct_source_read_sfr_pins() {
+        // MOV A,#0x0F; ANL A,P1; MOV 0x30,#0xF0; ORL A,0x30; XRL A,0x30
+        let mut emulator = emulator_with(&[0x74, 0x0F, 0x55, 0x90, 0x75, 0x30, 0xF0, 0x45, 0x30, 0x65, 0x30]);
+        // P1.0 被外部拉低：读的是引脚
+        emulator.cpu.set_pin(1, 0, false);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.acc, 0x0E);
+        assert!(emulator.cpu.psw_flags().p);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.acc, 0xFE);
+        assert!(emulator.cpu.psw_flags().p);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.acc, 0x0E);
+        assert_eq!(emulator.cpu.peek_sfr(ACC), 0x0E);
+    }
+
+    // 写 A 的逻辑指令：指令字节（直接寻址的操作数在 0x30，寄存器操作数在 R3）
+    const WRITES_ACC: [(&str, &[u8]); 10] = [
+        ("ORL A,#data", &[0x44, OPERAND]),
+        ("ORL A,direct", &[0x45, 0x30]),
+        ("ORL A,R3", &[0x4B]),
+        ("ANL A,direct", &[0x55, 0x30]),
+        ("ANL A,R3", &[0x5B]),
+        ("XRL A,direct", &[0x65, 0x30]),
+        ("XRL A,R3", &[0x6B]),
+        ("CPL A", &[0xF4]),
+        ("RL A", &[0x23]),
+        ("RRC A", &[0x13]),
+    ];
+    const OPERAND: u8 = 0x13;
+
+    #[test]
+    fn every_logical_op_writing_a_recomputes_parity() {
+        for (name, op) in WRITES_ACC {
+            for initial in [0x00, 0x01, 0x0F, 0x5A, 0x80, 0xEF, 0xFF] {
+                // MOV A,#initial 之后 P 反映原来的 A；再把 P 取反一次，两种情况下指令没有重新计算 P 都会被发现
+                for stale in [false, true] {
+                    let mut program = vec![0x74, initial];
+                    program.extend_from_slice(op);
+                    let mut emulator = emulator_with(&program);
+                    emulator.cpu.ram[0x30] = OPERAND;
+                    emulator.cpu.ram[0x03] = OPERAND;
+                    emulator.step();
+                    if stale {
+                        emulator.cpu.sfr[(PSW - 0x80) as usize] ^= PSW_P;
+                    }
+                    emulator.step();
+
+                    let acc = emulator.cpu.registers.acc;
+                    let odd = acc.count_ones() % 2 == 1;
+                    assert_eq!(emulator.cpu.psw_flags().p, odd, "{}：A {:#04x} → {:#04x}", name, initial, acc);
+                }
+            }
         }
     }
 }
@@ -1,10 +1,94 @@
+// 指令处理函数的调试输出：写入 CPU 的 trace_line，由 Emulator 加上 [时钟周期][地址] 前缀后
+// 写到跟踪输出（标准输出或 --trace 指定的文件），不与程序自己的端口/串口输出混在一起
+macro_rules! trace_line {
+    ($cpu:expr, $($arg:tt)*) => {{
+        use std::fmt::Write as _;
+        let _ = writeln!($cpu.trace_line, $($arg)*);
+    }};
+}
+
 pub mod arithmetic;
 pub mod branch;
 pub mod data_transfer;
 pub mod interrupt;
 pub mod logical;
 
+use super::peripherals::{ACC, PSW};
 use super::CPU;
+use crate::memory_dump::MemorySpace;
+use log::{error, warn};
+
+// 保留操作码：标准 8051 指令集没有定义 0xA5（部分派生型号把它用作扩展指令前缀），
+// 这里明确按单字节空操作处理并给出警告，严格模式 (Emulator::strict_opcodes) 下停机
+pub const RESERVED_OPCODE: u8 = 0xA5;
+
+// PSW 位定义
+pub const PSW_CY: u8 = 0x80;  // 进位标志
+pub const PSW_AC: u8 = 0x40;  // 辅助进位标志
+pub const PSW_F0: u8 = 0x20;  // 用户标志0
+pub const PSW_RS1: u8 = 0x10; // 寄存器组选择位1
+pub const PSW_RS0: u8 = 0x08; // 寄存器组选择位0
+pub const PSW_OV: u8 = 0x04;  // 溢出标志
+pub const PSW_F1: u8 = 0x02;  // 用户标志1
+pub const PSW_P: u8 = 0x01;   // 奇偶标志
+
+// PSW 按位解码后的各个标志
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PswFlags {
+    pub cy: bool,  // 进位
+    pub ac: bool,  // 辅助进位
+    pub f0: bool,  // 用户标志0
+    pub bank: u8,  // 当前寄存器组 0-3（RS1:RS0）
+    pub ov: bool,  // 溢出
+    pub f1: bool,  // 用户标志1
+    pub p: bool,   // 奇偶
+}
+
+impl PswFlags {
+    // 从 PSW 字节解码
+    pub fn from_byte(psw: u8) -> Self {
+        PswFlags {
+            cy: (psw & PSW_CY) != 0,
+            ac: (psw & PSW_AC) != 0,
+            f0: (psw & PSW_F0) != 0,
+            bank: (psw & (PSW_RS1 | PSW_RS0)) >> 3,
+            ov: (psw & PSW_OV) != 0,
+            f1: (psw & PSW_F1) != 0,
+            p: (psw & PSW_P) != 0,
+        }
+    }
+
+    // 编码为 PSW 字节
+    pub fn to_byte(self) -> u8 {
+        let bit = |flag: bool, mask: u8| if flag { mask } else { 0 };
+        bit(self.cy, PSW_CY)
+            | bit(self.ac, PSW_AC)
+            | bit(self.f0, PSW_F0)
+            | ((self.bank & 0x03) << 3)
+            | bit(self.ov, PSW_OV)
+            | bit(self.f1, PSW_F1)
+            | bit(self.p, PSW_P)
+    }
+}
+
+// 位地址指向的字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitTarget {
+    Ram(u8), // 内部RAM的位寻址区 0x20-0x2F（位地址 0x00-0x7F）
+    Sfr(u8), // 可位寻址的 SFR，地址为8的倍数（位地址 0x80-0xFF）
+}
+
+// 把位地址分解为所在的字节和位号 (0-7)
+// 位地址 0x00-0x7F 对应 RAM 0x20-0x2F，每字节8位；
+// 位地址 0x80-0xFF 对应 SFR 0x80、0x88、0x90……，字节地址就是位地址的高5位
+pub fn bit_location(bit_addr: u8) -> (BitTarget, u8) {
+    let bit_pos = bit_addr & 0x07;
+    if bit_addr < 0x80 {
+        (BitTarget::Ram(0x20 + (bit_addr >> 3)), bit_pos)
+    } else {
+        (BitTarget::Sfr(bit_addr & 0xF8), bit_pos)
+    }
+}
 
 // 指令信息结构
 #[derive(Clone, Copy)]
@@ -16,61 +100,329 @@ pub struct InstructionInfo {
 // 指令表类型定义
 pub type InstructionTable = [Option<InstructionInfo>; 256];
 
+// 指令查找表，第一次使用时构建
+fn instruction_table() -> &'static InstructionTable {
+    static INSTRUCTION_TABLE: std::sync::OnceLock<InstructionTable> = std::sync::OnceLock::new();
+    INSTRUCTION_TABLE.get_or_init(crate::instruction_debug::build_instruction_table)
+}
+
 impl CPU {
-    pub fn execute_instruction(&mut self, opcode: u8, debug: bool, delay_skip_counter: &mut u32) {
-        // 设置临时调试和优化标志
-        self.debug = debug;
-        self.delay_skip_counter = *delay_skip_counter;
+    pub fn execute_instruction(&mut self, opcode: u8) {
+        self.trace_line.clear();
+        self.watch_hits.get_mut().clear();
+        self.opcode_counts[opcode as usize] += 1;
         
         // 首先增加PC指向下一条指令
         self.registers.pc = self.registers.pc.wrapping_add(1);
 
         // 边界检查：确保PC在内存范围内
         if self.registers.pc as usize >= self.rom.len() {
-            println!("错误: 程序计数器超出内存范围");
+            error!("错误: 程序计数器超出内存范围");
             return;
         }
 
         // 使用静态查找表执行指令
-        static INSTRUCTION_TABLE: std::sync::OnceLock<InstructionTable> = std::sync::OnceLock::new();
-        let table = INSTRUCTION_TABLE.get_or_init(|| crate::instruction_debug::build_instruction_table());
-        
-        if let Some(info) = &table[opcode as usize] {
+        if opcode == RESERVED_OPCODE {
+            self.reserved_opcode();
+        } else if let Some(info) = &instruction_table()[opcode as usize] {
             (info.handler)(self, opcode);
         } else {
-            println!("未知指令: 操作码 = {:#04x}", opcode);
+            warn!("未知指令: 操作码 = {:#04x}", opcode);
         }
-        
-        // 将修改后的计数器写回
-        *delay_skip_counter = self.delay_skip_counter;
     }
 
-    pub(crate) fn nop(&self) {
+    // 保留操作码 0xA5：只占一个字节，不改变任何状态
+    fn reserved_opcode(&mut self) {
+        let address = self.registers.pc.wrapping_sub(1);
+        if self.debug {
+            trace_line!(self, "db {:#04x}", RESERVED_OPCODE);
+        }
+        warn!("[警告] 在 {} 处执行保留操作码 {:#04x}，按单字节空操作跳过", self.format_address(address), RESERVED_OPCODE);
+    }
+
+    // 操作码是否已经实现（未实现的操作码执行时只输出"未知指令"并跳过；保留操作码 0xA5 不算已实现）
+    pub fn is_implemented(opcode: u8) -> bool {
+        instruction_table()[opcode as usize].is_some()
+    }
+
+    // 指令表中的助记符（未实现和保留的操作码为 None）
+    pub fn mnemonic(opcode: u8) -> Option<&'static str> {
+        instruction_table()[opcode as usize].map(|info| info.mnemonic)
+    }
+
+    // 指令的机器周期数（按标准 8051 的指令时序，每个机器周期 12 个时钟）：
+    // MUL/DIV 为 4；转移、调用和返回、MOVC/MOVX、PUSH/POP、16位的 MOV DPTR/INC DPTR、
+    // 两个操作数都在存储器里的 MOV 和 ORL/ANL/XRL direct,#data、进位与位的逻辑运算和 MOV bit,C 为 2；其余为 1
+    pub fn machine_cycles(opcode: u8) -> u8 {
+        match opcode {
+            0x84 | 0xA4 => 4,
+            // AJMP/ACALL、LJMP、LCALL、RET、RETI、SJMP、JMP @A+DPTR
+            0x01 | 0x11 | 0x21 | 0x31 | 0x41 | 0x51 | 0x61 | 0x71 | 0x81 | 0x91 | 0xA1 | 0xB1 | 0xC1
+            | 0xD1 | 0xE1 | 0xF1 | 0x02 | 0x12 | 0x22 | 0x32 | 0x80 | 0x73 => 2,
+            // JBC、JB、JNB、JC、JNC、JZ、JNZ、CJNE、DJNZ
+            0x10 | 0x20 | 0x30 | 0x40 | 0x50 | 0x60 | 0x70 | 0xB4..=0xBF | 0xD5 | 0xD8..=0xDF => 2,
+            // MOVC、MOVX、PUSH、POP、MOV DPTR,#data16、INC DPTR
+            0x83 | 0x93 | 0xE0 | 0xE2 | 0xE3 | 0xF0 | 0xF2 | 0xF3 | 0xC0 | 0xD0 | 0x90 | 0xA3 => 2,
+            // MOV direct,#data、MOV direct,direct、MOV direct,@Ri、MOV direct,Rn、MOV @Ri,direct、MOV Rn,direct
+            0x75 | 0x85 | 0x86 | 0x87 | 0x88..=0x8F | 0xA6 | 0xA7 | 0xA8..=0xAF => 2,
+            // ORL/ANL/XRL direct,#data、ORL/ANL C,bit 和 C,/bit、MOV bit,C
+            0x43 | 0x53 | 0x63 | 0x72 | 0x82 | 0xA0 | 0xB0 | 0x92 => 2,
+            _ => 1,
+        }
+    }
+
+    pub(crate) fn nop(&mut self) {
         if self.debug {
-            println!("nop");
+            trace_line!(self, "nop");
         }
     }
 
+    // 读取 PSW 并解码为各个标志
+    pub fn psw_flags(&self) -> PswFlags {
+        PswFlags::from_byte(self.read_sfr(PSW))
+    }
+
+    // 写入 PSW 的各个标志（P 由累加器决定，写入后按累加器重新计算）
+    pub fn set_psw_flags(&mut self, flags: PswFlags) {
+        self.write_sfr(PSW, flags.to_byte());
+        self.update_parity();
+    }
+
+    // 读取进位标志 CY (PSW.7)
     pub(crate) fn get_carry_flag(&self) -> u8 {
-        0 // 示例实现
+        self.psw_flags().cy as u8
+    }
+
+    // 设置进位标志 CY (PSW.7)
+    pub(crate) fn set_carry_flag(&mut self, carry: bool) {
+        let mut flags = self.psw_flags();
+        flags.cy = carry;
+        self.set_psw_flags(flags);
+    }
+
+    // 写累加器：同时更新 SFR 区中的 ACC 镜像和奇偶标志 P
+    pub(crate) fn set_acc(&mut self, value: u8) {
+        self.registers.acc = value;
+        self.sfr[(ACC - 0x80) as usize] = value;
+        self.update_parity();
+    }
+
+    // 更新奇偶标志 P (PSW.0)：累加器中1的个数为奇数时置1
+    pub(crate) fn update_parity(&mut self) {
+        let psw = &mut self.sfr[(PSW - 0x80) as usize];
+        if self.registers.acc.count_ones() % 2 == 1 {
+            *psw |= PSW_P;
+        } else {
+            *psw &= !PSW_P;
+        }
+    }
+
+    // 间接寻址（@Ri 和堆栈）能否访问到该地址的内部RAM
+    // 0x80-0xFF 直接寻址访问的是 SFR，间接寻址访问的是高128字节RAM，
+    // 而高128字节只有 8052 才有：8051 上读出 0xFF，写入被忽略
+    pub(crate) fn has_internal_ram(&self, addr: u8) -> bool {
+        addr < 0x80 || self.variant.has_upper_ram()
+    }
+
+    // 直接寻址读：0x00-0x7F 为内部RAM，0x80-0xFF 为 SFR
+    // 所有直接寻址的读取都经过这里（观察点在这里检查）
+    pub(crate) fn read_internal(&self, addr: u8) -> u8 {
+        if addr < 0x80 {
+            let value = self.ram[addr as usize];
+            self.watch_read(MemorySpace::Ram, addr as u16, value);
+            value
+        } else {
+            let value = self.read_sfr(addr);
+            self.watch_read(MemorySpace::Sfr, addr as u16, value);
+            value
+        }
+    }
+
+    // 直接寻址写：0x00-0x7F 为内部RAM，0x80-0xFF 交给 write_sfr（由它检查 SFR 观察点）
+    pub(crate) fn write_internal(&mut self, addr: u8, value: u8) {
+        if addr < 0x80 {
+            self.write_ram(addr, value);
+        } else {
+            self.write_sfr(addr, value);
+        }
+    }
+
+    // 写内部RAM的一个字节（直接、间接、寄存器和位寻址共用）
+    fn write_ram(&mut self, addr: u8, value: u8) {
+        let old = self.ram[addr as usize];
+        self.ram[addr as usize] = value;
+        self.watch_write(MemorySpace::Ram, addr as u16, old, value);
+    }
+
+    // 间接读内部RAM：所有 @Ri 指令和出栈都经过这里，地址 0x80-0xFF 永远不会访问 SFR
+    pub(crate) fn read_indirect(&self, addr: u8) -> u8 {
+        if self.has_internal_ram(addr) {
+            let value = self.ram[addr as usize];
+            self.watch_read(MemorySpace::Ram, addr as u16, value);
+            value
+        } else {
+            0xFF
+        }
+    }
+
+    // 间接写内部RAM：没有高128字节的型号上写入 0x80 以上的地址被忽略
+    pub(crate) fn write_indirect(&mut self, addr: u8, value: u8) {
+        if self.has_internal_ram(addr) {
+            self.write_ram(addr, value);
+        }
+    }
+
+    // 读取位（JB、JNB、MOV C,bit 等）：端口位读的是引脚电平
+    pub fn read_bit(&self, bit_addr: u8) -> bool {
+        let (target, bit_pos) = bit_location(bit_addr);
+        // 位寻址区 (0x20-0x2F) 和可位寻址的 SFR 都在直接寻址的范围内
+        let (BitTarget::Ram(address) | BitTarget::Sfr(address)) = target;
+        let byte = self.read_internal(address);
+        (byte >> bit_pos) & 1 != 0
+    }
+
+    // 写入位（SETB、CLR、CPL 等）：读-改-写整个字节，端口位读的是锁存器而不是引脚，
+    // 不会因为外部把引脚拉低而把同一端口的其他位清零
+    pub fn write_bit(&mut self, bit_addr: u8, value: bool) {
+        let (target, bit_pos) = bit_location(bit_addr);
+        let mask = 1 << bit_pos;
+        let update = |byte: u8| if value { byte | mask } else { byte & !mask };
+        match target {
+            BitTarget::Ram(address) => self.write_ram(address, update(self.ram[address as usize])),
+            BitTarget::Sfr(address) => self.write_sfr(address, update(self.peek_sfr(address))),
+        }
+    }
+
+    // 读-改-写指令看到的位值：端口位为锁存器，其余与 read_bit 相同
+    pub(crate) fn read_bit_latch(&self, bit_addr: u8) -> bool {
+        let (target, bit_pos) = bit_location(bit_addr);
+        match target {
+            BitTarget::Sfr(address) => (self.peek_sfr(address) >> bit_pos) & 1 != 0,
+            BitTarget::Ram(_) => self.read_bit(bit_addr),
+        }
     }
 
     // 辅助方法：获取当前寄存器组的寄存器地址
     pub(crate) fn get_register_address(&self, reg_num: u8) -> usize {
-        // 当前寄存器组由PSW的RS1和RS0位决定，这里暂时使用组0
-        let bank = 0; // 寄存器组0
+        // 当前寄存器组由PSW的RS1和RS0位决定；每次访问都重新读取 PSW，
+        // 任何写 PSW 的指令（MOV、POP、SETB RS0 等）之后的 Rn 和 @Ri 立即使用新的寄存器组
+        let bank = self.psw_flags().bank;
         (bank * 8 + reg_num) as usize
     }
 
     // 读取寄存器Rn
     pub(crate) fn read_register(&self, reg_num: u8) -> u8 {
         let addr = self.get_register_address(reg_num);
-        self.ram[addr]
+        self.read_indirect(addr as u8)
     }
 
     // 写入寄存器Rn
     pub(crate) fn write_register(&mut self, reg_num: u8, value: u8) {
         let addr = self.get_register_address(reg_num);
-        self.ram[addr] = value;
+        self.write_ram(addr as u8, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bit_location, BitTarget};
+    use crate::cpu::peripherals::{ACC, P0, P1, PSW};
+    use crate::cpu::CPU;
+    use crate::test_support::{capture_logs, emulator_with, run_steps};
+    use log::Level;
+
+    #[test]
+    fn reserved_opcode_is_a_one_byte_nop_with_a_warning() {
+        // MOV A,#0x33; DB 0xA5; INC A：0xA5 后面的字节作为下一条指令执行
+        let mut emulator = emulator_with(&[0x74, 0x33, 0xA5, 0x04]);
+        run_steps(&mut emulator, 1);
+        let (psw, sp, clocks) = (emulator.cpu.peek_sfr(PSW), emulator.cpu.registers.sp, emulator.clock_cycles);
+        let logs = capture_logs(|| run_steps(&mut emulator, 1));
+        assert_eq!(logs, [(Level::Warn, "[警告] 在 0x0002 处执行保留操作码 0xa5，按单字节空操作跳过".to_string())]);
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+        assert_eq!(emulator.clock_cycles - clocks, 12);
+        assert_eq!((emulator.cpu.registers.acc, emulator.cpu.peek_sfr(PSW), emulator.cpu.registers.sp), (0x33, psw, sp));
+
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.acc, 0x34);
+    }
+
+    #[test]
+    fn bit_location_splits_ram_and_sfr_bits() {
+        assert_eq!(bit_location(0x00), (BitTarget::Ram(0x20), 0));
+        assert_eq!(bit_location(0x0F), (BitTarget::Ram(0x21), 7));
+        assert_eq!(bit_location(0x7F), (BitTarget::Ram(0x2F), 7));
+        assert_eq!(bit_location(0x80), (BitTarget::Sfr(P0), 0));
+        assert_eq!(bit_location(0x93), (BitTarget::Sfr(P1), 3));
+        assert_eq!(bit_location(0xE7), (BitTarget::Sfr(ACC), 7));
+        assert_eq!(bit_location(0xD7), (BitTarget::Sfr(PSW), 7));
+    }
+
+    #[test]
+    fn bit_instructions_share_ram_and_sfr_addressing() {
+        // SETB 0x0F; CPL 0x00; CLR P1.3; SETB ACC.7; MOV C,0x0F; JB 0x00,+1; NOP; JNB P1.3,+1; NOP; CPL ACC.7
+        let mut emulator = emulator_with(&[
+            0xD2, 0x0F, 0xB2, 0x00, 0xC2, 0x93, 0xD2, 0xE7, 0xA2, 0x0F, 0x20, 0x00, 0x01, 0x00, 0x30, 0x93, 0x01, 0x00,
+            0xB2, 0xE7,
+        ]);
+        run_steps(&mut emulator, 4);
+        let cpu = &emulator.cpu;
+        assert_eq!(cpu.ram[0x21], 0x80);
+        assert_eq!(cpu.ram[0x20], 0x01);
+        assert_eq!(cpu.peek_sfr(P1), 0xF7);
+        assert_eq!(cpu.registers.acc, 0x80);
+        assert!(cpu.read_bit(0x0F) && cpu.read_bit(0x00) && !cpu.read_bit(0x93) && cpu.read_bit(0xE7));
+
+        // MOV C,bit 读 RAM 位；JB/JNB 分别读 RAM 位和 SFR 位，两次都跳过 NOP
+        run_steps(&mut emulator, 3);
+        assert!(emulator.cpu.psw_flags().cy);
+        assert_eq!(emulator.cpu.registers.pc, 0x0012);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.acc, 0x00);
+        assert!(!emulator.cpu.psw_flags().p);
+    }
+
+    #[test]
+    fn execute_instruction_takes_only_the_opcode() {
+        // 不经过 Emulator，直接在 CPU 上执行：MOV A,#0x42; INC A
+        let mut cpu = CPU::new();
+        cpu.rom[..3].copy_from_slice(&[0x74, 0x42, 0x04]);
+        cpu.debug = true;
+        cpu.execute_instruction(cpu.rom[0]);
+        assert_eq!((cpu.registers.acc, cpu.registers.pc), (0x42, 0x0002));
+        assert_eq!(cpu.opcode_counts[0x74], 1);
+        assert_eq!(cpu.trace_line, "mov A, #0x42\n");
+
+        // 调试标志由 CPU 自己持有，关闭后不再生成反汇编输出
+        cpu.debug = false;
+        cpu.execute_instruction(cpu.rom[2]);
+        assert_eq!((cpu.registers.acc, cpu.registers.pc), (0x43, 0x0003));
+        assert!(cpu.trace_line.is_empty());
+    }
+
+    #[test]
+    fn acc_bits_reach_the_accumulator_and_update_parity() {
+        // CLR A; SETB ACC.3; JB ACC.3,+3; MOV 0x30,#1; MOV 0x31,#2; CPL ACC.0; MOV 0E0h,#0x07; PUSH ACC; CLR A; POP ACC
+        let mut emulator = emulator_with(&[
+            0xE4, 0xD2, 0xE3, 0x20, 0xE3, 0x03, 0x75, 0x30, 0x01, 0x75, 0x31, 0x02, 0xB2, 0xE0, 0x75, 0xE0, 0x07, 0xC0,
+            0xE0, 0xE4, 0xD0, 0xE0,
+        ]);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.acc, 0x08);
+        assert_eq!(emulator.cpu.peek_sfr(PSW), 0x01);
+
+        // JB ACC.3 读到的是累加器，跳过 MOV 0x30,#1
+        run_steps(&mut emulator, 2);
+        assert_eq!((emulator.cpu.ram[0x30], emulator.cpu.ram[0x31]), (0, 2));
+
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.acc, 0x09);
+        assert!(!emulator.cpu.psw_flags().p);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.acc, 0x07);
+        assert!(emulator.cpu.psw_flags().p);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.acc, 0x07);
+        assert!(emulator.cpu.psw_flags().p);
     }
 }
@@ -1,13 +1,31 @@
+pub mod adc;
 pub mod instructions;
 pub mod memory;
 pub mod peripherals;
 pub mod registers;
+pub mod rng;
+pub mod serial;
+pub mod timers;
+pub mod watch;
+pub mod watchdog;
 
 
+use adc::Adc;
+use instructions::interrupt::InterruptState;
+use peripherals::PinDevice;
 use registers::Registers;
+use rng::Rng;
+use serial::Serial;
+use watch::{WatchHit, Watchpoint};
+use watchdog::Watchdog;
+use crate::omf;
+use crate::symbols::{self, SymbolTable};
+use log::warn;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::fs;
-use std::io::{self, BufRead, Read};
+use std::io::{self, BufRead};
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct HexError(pub hex::FromHexError);
@@ -32,141 +50,711 @@ impl From<HexError> for std::io::Error {
     }
 }
 
+// 单片机型号：决定有哪些片上外设
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    I8051, // 标准 8051
+    I8052, // 8052：增加定时器2和高128字节RAM
+    AT89S52, // AT89S52：8052 外加看门狗定时器
+}
+
+impl Variant {
+    // 是否带有间接寻址可访问的高128字节RAM (0x80-0xFF)
+    pub fn has_upper_ram(self) -> bool {
+        matches!(self, Variant::I8052 | Variant::AT89S52)
+    }
+
+    // 是否带有定时器2
+    pub fn has_timer2(self) -> bool {
+        matches!(self, Variant::I8052 | Variant::AT89S52)
+    }
+
+    // 是否带有看门狗定时器 (WDTRST)
+    pub fn has_watchdog(self) -> bool {
+        matches!(self, Variant::AT89S52)
+    }
+}
+
+// 默认晶振频率 (Hz)
+pub const DEFAULT_CLOCK_FREQUENCY: u32 = 12_000_000;
+
+// 默认外部数据存储器大小（MOVX 可寻址的全部 64KB）
+pub const DEFAULT_XRAM_SIZE: usize = 0x10000;
+
+// 运行信息的详细程度：错误和警告总是输出，程序自己的串口和端口控制台输出不受影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    Quiet,  // 不输出运行信息（端口写入提示等）
+    #[default]
+    Normal, // 非调试模式下每次写端口输出一行提示
+}
+
 pub struct CPU {
     pub registers: Registers,
-    pub ram: [u8; 256],              // 内部RAM (0x00-0xFF)
+    pub variant: Variant,            // 单片机型号
+    pub ram: [u8; 256],              // 内部RAM：直接寻址 0x00-0x7F，间接寻址 0x00-0xFF（高128字节仅 8052）
     pub sfr: [u8; 128],              // 特殊功能寄存器 (0x80-0xFF)
     pub rom: [u8; 65536],            // 程序存储器 (64KB)
-    pub interrupt_in_progress: bool, // 是否正在处理中断
+    pub rom_end: Option<u16>,        // 已加载程序映像的最高地址（没有加载过程序为 None）
+    pub xram: Vec<u8>,               // 外部数据存储器（MOVX 访问，默认 64KB，长度可由 EmulatorBuilder::xram_size 设定）
+    pub strict_load: bool,           // 加载 HEX 文件时记录重叠且内容不同按错误处理（否则只警告）
+    pub(crate) images: Vec<String>,  // 已加载的程序映像（文件名），按加载顺序
+    pub(crate) image_of: Vec<u8>,    // 每个 ROM 字节由第几个映像写入（0 表示没有加载过）
+    pub vector_base: u16,            // 向量表基地址：复位向量和中断向量都加上它（默认 0）
+    pub interrupt_state: InterruptState, // 正在处理的中断优先级
     pub interrupt_return_pc: u16,    // 中断返回地址
-    // 临时字段，用于在指令执行期间传递调试和优化信息
-    pub(crate) debug: bool,          // 当前是否处于调试模式
-    pub(crate) delay_skip_counter: u32, // 延迟跳过计数器（用于优化）
+    pub(crate) interrupt_blocked: bool, // 刚执行过 RETI 或写过 IE/IP，推迟一条指令再响应中断
+    pub clock_frequency: u32,        // 晶振频率 (Hz)，每个机器周期为12个时钟
+    pub serial: Serial,              // 串行口内部状态
+    pub watchdog: Watchdog,          // 看门狗内部状态（仅 AT89S52）
+    pub adc: Option<Adc>,            // 片上 ADC（可选，enable_adc 挂上）
+    pub port_pins: [u8; 4],          // 外部电路对 P0-P3 引脚的驱动电平（未驱动为1）
+    pub floating_pins: [u8; 4],      // 悬空（没有外部连接）的引脚，读入随机电平
+    pub(crate) rng: Cell<Rng>,       // 不确定行为使用的伪随机数发生器（读端口时也要取数，所以用 Cell）
+    pub(crate) int_pin_last: [bool; 2], // 上次采样的 INT0/INT1 引脚电平（用于边沿检测）
+    pub(crate) devices: RefCell<Vec<Box<dyn PinDevice>>>, // 挂接在端口引脚上的外部器件（读端口时也要采样，所以用 RefCell）
+    pub(crate) t2ex_pin_last: bool,  // 上次采样的 T2EX (P1.1) 引脚电平（用于边沿检测）
+    pub(crate) symbols: Rc<SymbolTable>, // 调试输出使用的符号表（与 Emulator 共享）
+    pub watchpoints: Vec<Watchpoint>, // 数据观察点
+    pub verbosity: Verbosity,        // 运行信息的详细程度
+    pub exit_port: Option<u8>,       // 退出端口：程序写这个 SFR 地址时记录退出码并停机（None 表示不拦截）
+    pub exit_code: Option<u8>,       // 程序写入退出端口的值
+    pub(crate) watch_hits: RefCell<Vec<WatchHit>>, // 当前指令命中的观察点
+    pub opcode_counts: [u64; 256],   // 每个操作码的执行次数（复位不清零）
+    pub interrupt_entries: u64,      // 响应中断的次数（复位不清零）
+    pub debug: bool,                 // 调试模式：指令处理函数生成反汇编输出 (trace_line)，由 Emulator 写到跟踪输出
+    pub(crate) trace_line: String,   // 调试模式下当前指令的反汇编输出（由 Emulator 写到跟踪输出）
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
     pub fn new() -> Self {
         let mut cpu = CPU {
             registers: Registers::new(),
+            variant: Variant::I8051,
             ram: [0; 256],
             sfr: [0; 128],
             rom: [0; 65536],
-            interrupt_in_progress: false,
+            rom_end: None,
+            xram: vec![0; DEFAULT_XRAM_SIZE],
+            strict_load: false,
+            images: Vec::new(),
+            image_of: vec![0; 65536],
+            vector_base: 0,
+            interrupt_state: InterruptState::default(),
             interrupt_return_pc: 0,
+            opcode_counts: [0; 256],
+            interrupt_entries: 0,
+            interrupt_blocked: false,
+            clock_frequency: DEFAULT_CLOCK_FREQUENCY,
+            serial: Serial::new(),
+            watchdog: Watchdog::default(),
+            adc: None,
+            port_pins: [0xFF; 4],
+            floating_pins: [0; 4],
+            rng: Cell::new(Rng::default()),
+            int_pin_last: [true; 2],
+            devices: RefCell::new(Vec::new()),
+            t2ex_pin_last: true,
+            symbols: Rc::new(SymbolTable::new()),
+            watchpoints: Vec::new(),
+            verbosity: Verbosity::Normal,
+            exit_port: None,
+            exit_code: None,
+            watch_hits: RefCell::new(Vec::new()),
             debug: false,
-            delay_skip_counter: 0,
+            trace_line: String::new(),
         };
         // 初始化外设端口
         cpu.init_ports();
         cpu
     }
 
-    // 从文件加载程序到内存
+    // 复位：寄存器、SFR 和片上外设恢复初始状态，程序存储器、内部RAM和外部数据存储器保持不变
+    // 复位后从向量表基地址（复位向量）开始执行
+    // 型号、向量表基地址、晶振频率、片上 ADC 的配置和通道输入、外部引脚驱动、外部器件、串口输出和符号表属于外部环境，同样保留
+    pub fn reset(&mut self) {
+        self.registers = Registers::new();
+        self.registers.pc = self.vector_base;
+        self.sfr = [0; 128];
+        self.interrupt_state = InterruptState::default();
+        self.interrupt_return_pc = 0;
+        self.interrupt_blocked = false;
+        self.serial.reset();
+        self.watchdog = Watchdog::default();
+        if let Some(adc) = self.adc.as_mut() {
+            adc.reset();
+        }
+        self.int_pin_last = [true; 2];
+        self.t2ex_pin_last = true;
+        self.exit_code = None;
+        self.init_ports();
+    }
+
+    // 设置向量表基地址：复位向量从 0x0000 移到 base，中断向量 0x0003、0x000B…… 同样加上 base，
+    // 用于向量表可以搬移的型号（例如应用程序放在引导程序之后）；PC 立即指向新的复位向量
+    pub fn set_vector_base(&mut self, base: u16) {
+        self.vector_base = base;
+        self.registers.pc = base;
+    }
+
+    // 设定伪随机数种子：同样的种子得到同样的悬空引脚电平等随机行为
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.set(Rng::new(seed));
+    }
+
+    // 从伪随机数发生器取一个字节
+    pub(crate) fn random_u8(&self) -> u8 {
+        let mut rng = self.rng.get();
+        let value = rng.next_u8();
+        self.rng.set(rng);
+        value
+    }
+
+    // 调试输出中的地址：有标号时显示标号
+    pub(crate) fn format_address(&self, address: u16) -> String {
+        symbols::format_address(&self.symbols, address)
+    }
+
+    // 已加载程序映像的大小：从 0 到最高已加载地址的字节数（没有加载过程序为 0）
+    pub fn program_size(&self) -> usize {
+        self.rom_end.map_or(0, |end| end as usize + 1)
+    }
+
+    // 地址处的 ROM 字节是否由加载的程序映像写入
+    pub fn is_loaded(&self, address: u16) -> bool {
+        self.image_of[address as usize] != 0
+    }
+
+    // 从二进制文件加载程序到程序存储器的 0x0000 处
+    // 早期版本误把文件写入内部RAM并截断到256字节，现在等同于 load_binary_program(file_path, 0)
+    #[deprecated(note = "使用 load_binary_program，它可以指定加载地址并返回加载的字节数")]
     pub fn load_program(&mut self, file_path: &str) -> io::Result<()> {
-        let mut file = fs::File::open(file_path)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-
-        // 将程序加载到内存
-        for (i, &byte) in buffer.iter().enumerate() {
-            if i < self.ram.len() {
-                self.ram[i] = byte;
-            } else {
-                break; // 防止程序超出内存范围
-            }
+        self.load_binary_program(file_path, 0).map(|_| ())
+    }
+
+    // 从二进制文件（ROM 映像）加载程序到程序存储器的 offset 处，返回加载的字节数
+    // 文件超出 64KB 程序存储器时报错，不会截断
+    pub fn load_binary_program(&mut self, file_path: &str, offset: u16) -> io::Result<usize> {
+        let data = fs::read(file_path)?;
+        let start = offset as usize;
+        let end = start + data.len();
+        if end > self.rom.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} 字节的映像放在 {:#06X} 处超出 64KB 程序存储器范围（结束于 {:#X}）",
+                    data.len(),
+                    offset,
+                    end
+                ),
+            ));
         }
 
+        let image = self.begin_image(file_path);
+        self.place_image(image, start, &data)?;
+        Ok(data.len())
+    }
+
+    // 从 OMF-51 绝对目标文件加载程序，返回加载的字节数
+    pub fn load_omf_program(&mut self, file_path: &str) -> io::Result<usize> {
+        let contents = omf::load_omf(file_path)?;
+        let image = self.begin_image(file_path);
+        let mut loaded = 0;
+        for content in contents {
+            self.place_image(image, content.address as usize, &content.data)?;
+            loaded += content.data.len();
+        }
+        Ok(loaded)
+    }
+
+    // 开始加载一个新的程序映像，返回它的编号（从 1 开始）
+    fn begin_image(&mut self, name: &str) -> u8 {
+        self.images.push(name.to_string());
+        self.images.len().min(u8::MAX as usize) as u8
+    }
+
+    // 检查映像的一段数据是否与之前加载的其他映像重叠且内容不同
+    // 内容相同的重叠无害（例如两个映像都带有同一份中断向量表），不报告
+    fn image_conflict(&self, image: u8, addresses: &[usize], data: &[u8]) -> Option<String> {
+        let conflicts: Vec<usize> = addresses
+            .iter()
+            .zip(data)
+            .filter(|&(&addr, &value)| self.image_of[addr] != 0 && self.image_of[addr] != image && self.rom[addr] != value)
+            .map(|(&addr, _)| addr)
+            .collect();
+        let (&first, &last) = (conflicts.first()?, conflicts.last()?);
+        Some(format!(
+            "与之前加载的 {} 在 {:#06X}-{:#06X} 重叠且内容不同（{} 字节）",
+            self.images[self.image_of[first] as usize - 1],
+            first,
+            last,
+            conflicts.len()
+        ))
+    }
+
+    // 把映像的一段连续数据写入 ROM，与其他映像冲突时报错
+    fn place_image(&mut self, image: u8, start: usize, data: &[u8]) -> io::Result<()> {
+        let addresses: Vec<usize> = (start..start + data.len()).collect();
+        if let Some(message) = self.image_conflict(image, &addresses, data) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        for (&addr, &value) in addresses.iter().zip(data) {
+            self.write_image_byte(image, addr, value);
+        }
         Ok(())
     }
 
-    // 从Intel HEX文件加载程序到内存
+    fn write_image_byte(&mut self, image: u8, address: usize, value: u8) {
+        self.rom[address] = value;
+        self.image_of[address] = image;
+        self.rom_end = self.rom_end.max(Some(address as u16));
+    }
+
+    // 从Intel HEX文件加载程序到内存，文件名为 "-" 时从标准输入读取
+    // 可以依次加载多个文件（如引导程序和应用程序），与之前加载的映像重叠且内容不同时报错
     pub fn load_hex_program(&mut self, file_path: &str) -> io::Result<()> {
+        if file_path == "-" {
+            return self.load_hex_image(io::stdin().lock(), "标准输入", false);
+        }
         let file = fs::File::open(file_path)?;
-        let reader = io::BufReader::new(file);
+        self.load_hex_image(io::BufReader::new(file), file_path, false)
+    }
 
-        for line in reader.lines() {
+    // 从任意输入流读取 Intel HEX 记录加载程序，读到文件结束记录或输入结束为止
+    pub fn load_hex<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        self.load_hex_image(reader, "HEX 数据", false)
+    }
+
+    // 把 HEX 文件作为补丁覆盖到已加载的映像上：与之前的映像重叠时后加载的字节生效，不报错
+    pub fn patch_hex_program(&mut self, file_path: &str) -> io::Result<()> {
+        let file = fs::File::open(file_path)?;
+        self.load_hex_image(io::BufReader::new(file), file_path, true)
+    }
+
+    // 从任意输入流读取 HEX 补丁，覆盖规则与 patch_hex_program 相同
+    pub fn patch_hex<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        self.load_hex_image(reader, "HEX 补丁", true)
+    }
+
+    // overlay 为 true 时覆盖之前加载的映像，否则与之前的映像重叠且内容不同时报错
+    // 同一个文件内部的记录重叠按 strict_load 处理，与 overlay 无关
+    fn load_hex_image<R: BufRead>(&mut self, reader: R, name: &str, overlay: bool) -> io::Result<()> {
+        let image = self.begin_image(name);
+        // 扩展段地址（记录类型 02）或扩展线性地址（记录类型 04）给出的基地址，以及设置它的记录类型
+        let mut base_address = 0;
+        let mut base_record = None;
+        // 起始地址记录（记录类型 03/05）给出的程序入口
+        let mut start_address = None;
+        // 每个 ROM 字节由第几行的记录写入（0 表示本文件尚未写入），用于检查记录重叠
+        let mut written_by = vec![0; self.rom.len()];
+
+        for (index, line) in reader.lines().enumerate() {
             let line = line?;
+            let line = line.trim_end();
             if !line.starts_with(":") {
                 continue; // 忽略无效行
             }
 
             // 解析HEX文件行
-            let bytes = hex::decode(&line[1..]).map_err(HexError::from)?;
+            let line_number = index + 1;
+            let invalid = |message: String| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("第 {} 行: {}: {}", line_number, message, line))
+            };
+            let bytes = hex::decode(&line[1..]).map_err(|e| invalid(HexError(e).to_string()))?;
+
+            // 记录至少包含长度、地址、类型和校验和，长度字段必须与实际数据字节数一致
+            if bytes.len() < 5 {
+                return Err(invalid("记录太短".to_string()));
+            }
             let byte_count = bytes[0] as usize;
+            if bytes.len() != byte_count + 5 {
+                return Err(invalid(format!(
+                    "长度字段为 {} 字节，实际数据为 {} 字节",
+                    byte_count,
+                    bytes.len() - 5
+                )));
+            }
+
+            // 校验和是前面所有字节之和的补码
+            let checksum = bytes[bytes.len() - 1];
+            let expected = bytes[..bytes.len() - 1].iter().fold(0u8, |sum, &b| sum.wrapping_add(b)).wrapping_neg();
+            if checksum != expected {
+                return Err(invalid(format!("校验和错误（应为 {:#04X}，实际为 {:#04X}）", expected, checksum)));
+            }
+
             let address = ((bytes[1] as u16) << 8) | (bytes[2] as u16);
             let record_type = bytes[3];
 
             if record_type == 0x00 {
-                // 数据记录
-                for i in 0..byte_count {
-                    let mem_address = address as usize + i;
-                    if mem_address < self.rom.len() {
-                        self.rom[mem_address] = bytes[4 + i];
+                // 数据记录：地址加上扩展地址记录给出的基地址
+                // 段地址方式下偏移在 64KB 段内回绕，线性地址方式下直接相加
+                // 超出 64KB 的字节不截掉，整条记录报错，给出第一个越界地址和越界字节数
+                let addresses: Vec<usize> = (0..byte_count)
+                    .map(|i| {
+                        let offset = if base_record == Some(0x02) {
+                            (address as usize + i) & 0xFFFF
+                        } else {
+                            address as usize + i
+                        };
+                        base_address + offset
+                    })
+                    .collect();
+                let overflow: Vec<usize> = addresses.iter().copied().filter(|&addr| addr >= self.rom.len()).collect();
+                if let Some(&first) = overflow.first() {
+                    return Err(invalid(format!(
+                        "{} 字节超出 64KB 程序存储器范围（第一个越界地址 {:#X}）",
+                        overflow.len(),
+                        first
+                    )));
+                }
+
+                // 与之前加载的其他映像冲突时报错（补丁直接覆盖）
+                if !overlay
+                    && let Some(message) = self.image_conflict(image, &addresses, &bytes[4..4 + byte_count])
+                {
+                    return Err(invalid(message));
+                }
+
+                // 与本文件之前的记录重叠且内容不同：内容相同的重叠无害，不报告
+                let conflicts: Vec<usize> = addresses
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, &addr)| written_by[addr] != 0 && self.rom[addr] != bytes[4 + i])
+                    .map(|(_, &addr)| addr)
+                    .collect();
+                if let (Some(&first), Some(&last)) = (conflicts.first(), conflicts.last()) {
+                    let message = format!(
+                        "与第 {} 行的记录在 {:#06X}-{:#06X} 重叠且内容不同（{} 字节）",
+                        written_by[first],
+                        first,
+                        last,
+                        conflicts.len()
+                    );
+                    if self.strict_load {
+                        return Err(invalid(message));
                     }
+                    warn!("[警告] 第 {} 行: {}，使用后出现的记录", line_number, message);
+                }
+
+                for (i, &mem_address) in addresses.iter().enumerate() {
+                    self.write_image_byte(image, mem_address, bytes[4 + i]);
+                    written_by[mem_address] = line_number;
                 }
             } else if record_type == 0x01 {
                 // 文件结束记录
                 break;
+            } else if record_type == 0x02 || record_type == 0x04 {
+                // 扩展段地址记录：段地址 ×16 为基地址；扩展线性地址记录：两个数据字节是地址的高16位
+                if byte_count != 2 {
+                    return Err(invalid("扩展地址记录必须包含 2 个数据字节".to_string()));
+                }
+                let value = ((bytes[4] as usize) << 8) | bytes[5] as usize;
+                // 另一种方式设置的非零基地址仍然有效时，无法确定数据应该放在哪里
+                if value != 0 && base_address != 0 && base_record != Some(record_type) {
+                    return Err(invalid("不能混用扩展段地址 (02) 和扩展线性地址 (04) 记录".to_string()));
+                }
+                base_address = if record_type == 0x02 { value << 4 } else { value << 16 };
+                base_record = Some(record_type);
+            } else if record_type == 0x03 || record_type == 0x05 {
+                // 起始段地址记录：CS×16+IP；起始线性地址记录：32位入口地址
+                if byte_count != 4 {
+                    return Err(invalid("起始地址记录必须包含 4 个数据字节".to_string()));
+                }
+                let high = ((bytes[4] as usize) << 8) | bytes[5] as usize;
+                let low = ((bytes[6] as usize) << 8) | bytes[7] as usize;
+                let entry = if record_type == 0x03 { (high << 4) + low } else { (high << 16) | low };
+                if entry >= self.rom.len() {
+                    return Err(invalid(format!("入口地址 {:#X} 超出 64KB 程序存储器范围", entry)));
+                }
+                start_address = Some(entry as u16);
             }
         }
 
+        // 有起始地址记录时从该地址开始执行，否则照常从 0x0000 开始（加载多个文件时最后出现的起始地址记录有效）
+        if let Some(entry) = start_address {
+            self.registers.pc = entry;
+        }
+
         Ok(())
     }
+}
 
-    // 更新定时器（每个机器周期调用一次）
-    pub fn update_timers(&mut self) {
-        let tmod = self.sfr[0x09]; // TMOD寄存器 (0x89 - 0x80)
-        let tcon = self.sfr[0x08]; // TCON寄存器 (0x88 - 0x80)
-
-        // 定时器0更新
-        let tr0 = (tcon & 0x10) != 0; // TR0位：定时器0运行控制
-        if tr0 {
-            let mode = tmod & 0x03; // 定时器0模式
-            if mode == 0x01 {
-                // 模式1：16位定时器/计数器
-                let th0 = self.sfr[0x0C]; // TH0
-                let tl0 = self.sfr[0x0A]; // TL0
-                let mut count = ((th0 as u16) << 8) | (tl0 as u16);
-                
-                count = count.wrapping_add(1);
-                
-                // 检查溢出 (从0xFFFF回到0x0000)
-                if count == 0 {
-                    // 溢出，设置TF0标志
-                    self.sfr[0x08] |= 0x20; // 设置TF0位(bit 5)
-                }
-                
-                // 更新TH0和TL0的值（反映当前计数）
-                self.sfr[0x0C] = (count >> 8) as u8; // TH0
-                self.sfr[0x0A] = (count & 0xFF) as u8; // TL0
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Emulator;
+    use crate::test_support::capture_logs;
+    use log::Level;
+
+    fn load(hex: &str) -> io::Result<CPU> {
+        let mut cpu = CPU::new();
+        cpu.load_hex(hex.as_bytes())?;
+        Ok(cpu)
     }
 
-    // 获取定时器还需要多少个周期才会溢出（用于快进优化）
-    // 返回0表示定时器未运行或已经溢出
-    pub fn get_cycles_until_timer_overflow(&self) -> u64 {
-        let tmod = self.sfr[0x09]; // TMOD寄存器
-        let tcon = self.sfr[0x08]; // TCON寄存器
-        
-        // 检查定时器0
-        let tr0 = (tcon & 0x10) != 0;
-        if tr0 {
-            let mode = tmod & 0x03;
-            if mode == 0x01 {
-                // 模式1：16位定时器
-                let th0 = self.sfr[0x0C];
-                let tl0 = self.sfr[0x0A];
-                let count = ((th0 as u16) << 8) | (tl0 as u16);
-                
-                // 从当前值到0xFFFF需要的周期数（再加1就会溢出到0）
-                let cycles = (0xFFFF - count) as u64 + 1;
-                return cycles;
-            }
-        }
-        
-        0 // 定时器未运行
+    #[test]
+    fn program_size_is_the_highest_loaded_address() {
+        let cpu = load(":0301000074010483\n:0200100080FE70\n:00000001FF\n").unwrap();
+        assert_eq!(cpu.rom_end, Some(0x0102));
+        assert_eq!(cpu.program_size(), 0x0103);
+        assert!(cpu.is_loaded(0x0011) && !cpu.is_loaded(0x0012) && cpu.is_loaded(0x0100));
+        assert_eq!(CPU::new().program_size(), 0);
     }
 
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn valid_hex_loads_byte_identically() {
+        let mut cpu = CPU::new();
+        cpu.load_hex_program(&fixture("blink.hex")).unwrap();
+        assert_eq!(cpu.rom[..11], [0x74, 0xFE, 0xF5, 0x90, 0x23, 0x7F, 0x00, 0xDF, 0xFE, 0x80, 0xF7]);
+        assert_eq!(cpu.rom[0x100..0x104], [0xFE, 0xFD, 0xFB, 0xF7]);
+        assert_eq!(cpu.rom_end, Some(0x0103));
+    }
+
+    #[test]
+    fn corrupted_checksum_names_line_and_values() {
+        let error = CPU::new().load_hex_program(&fixture("blink_bad_checksum.hex")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "第 2 行: 校验和错误（应为 0x0E，实际为 0x0F）: :04010000FEFDFBF70F");
+    }
+
+    #[test]
+    fn byte_count_must_match_record_length() {
+        let error = CPU::new().load_hex_program(&fixture("blink_bad_length.hex")).unwrap_err();
+        assert_eq!(error.to_string(), "第 1 行: 长度字段为 12 字节，实际数据为 11 字节: :0C00000074FEF590237F00DFFE80F708");
+    }
+
+    #[test]
+    fn hex_from_a_reader_stops_at_the_end_of_file_record() {
+        // 文件结束记录之后的内容（这里是一条校验和错误的记录）不再读取
+        let text = ":0300000075FF2A5F\n:00000001FF\n:0300000075FF2A00\n";
+        let mut cpu = CPU::new();
+        cpu.load_hex(io::Cursor::new(text)).unwrap();
+        assert_eq!(cpu.rom[..3], [0x75, 0xFF, 0x2A]);
+
+        // 没有文件结束记录时读到输入结束为止
+        let cpu = load(":0300000075FF2A5F\n:0200100080FE70").unwrap();
+        assert_eq!(cpu.rom_end, Some(0x0011));
+    }
+
+    // 第 2 行的记录覆盖第 1 行写入的 0x0001-0x0002
+    const OVERLAPPING: &str = ":0300000074010484\n:020001000204F7\n:00000001FF\n";
+
+    #[test]
+    fn differing_overlap_warns_with_both_lines_and_keeps_the_later_record() {
+        let mut cpu = CPU::new();
+        let logs = capture_logs(|| cpu.load_hex(OVERLAPPING.as_bytes()).unwrap());
+        assert_eq!(
+            logs,
+            [(Level::Warn, "[警告] 第 2 行: 与第 1 行的记录在 0x0001-0x0001 重叠且内容不同（1 字节），使用后出现的记录".to_string())]
+        );
+        assert_eq!(cpu.rom[..3], [0x74, 0x02, 0x04]);
+    }
+
+    #[test]
+    fn identical_overlap_is_not_reported() {
+        let mut cpu = CPU::new();
+        let logs = capture_logs(|| cpu.load_hex(":0300000074010484\n:020001000104F8\n".as_bytes()).unwrap());
+        assert!(logs.is_empty(), "{:?}", logs);
+        assert_eq!(cpu.rom[..3], [0x74, 0x01, 0x04]);
+    }
+
+    #[test]
+    fn strict_load_rejects_differing_overlap() {
+        let mut cpu = CPU::new();
+        cpu.strict_load = true;
+        let error = cpu.load_hex(OVERLAPPING.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "第 2 行: 与第 1 行的记录在 0x0001-0x0001 重叠且内容不同（1 字节）: :020001000204F7"
+        );
+        // 出错的记录不写入
+        assert_eq!(cpu.rom[1], 0x01);
+    }
 
+    #[test]
+    fn linear_base_records_do_not_land_in_rom() {
+        // SDCC/packihx 风格的映像，前后各有一条基地址为 0 的 04 记录
+        let mut cpu = CPU::new();
+        cpu.load_hex_program(&fixture("sdcc_linear_base.hex")).unwrap();
+        assert_eq!(cpu.rom[..3], [0x02, 0x00, 0x08]);
+        assert_eq!(cpu.rom[0x08..0x14], [0x75, 0x81, 0x07, 0x12, 0x00, 0x10, 0x80, 0xFE, 0x75, 0x90, 0x55, 0x22]);
+        assert!(!cpu.is_loaded(0x0003));
+        assert_eq!(cpu.rom_end, Some(0x0013));
+    }
+
+    #[test]
+    fn data_above_64k_is_an_error() {
+        let error = CPU::new().load_hex_program(&fixture("sdcc_above_64k.hex")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "第 5 行: 4 字节超出 64KB 程序存储器范围（第一个越界地址 0x10000）: :0400000012345678E8"
+        );
+    }
+
+    #[test]
+    fn record_crossing_0xffff_reports_the_overflowing_bytes() {
+        // 0xFFFE 处的 4 字节记录：后两个字节落在 64KB 之外，整条记录都不加载
+        let mut cpu = CPU::new();
+        let error = cpu.load_hex(":04FFFE0001020304F5\n:00000001FF\n".as_bytes()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "第 1 行: 2 字节超出 64KB 程序存储器范围（第一个越界地址 0x10000）: :04FFFE0001020304F5"
+        );
+        assert!(!cpu.is_loaded(0xFFFE) && !cpu.is_loaded(0xFFFF));
+        assert_eq!(cpu.rom[0xFFFE..], [0, 0]);
+        assert_eq!(cpu.rom_end, None);
+
+        // 扩展段地址方式下偏移在段内回绕，同一条记录不越界
+        let mut cpu = CPU::new();
+        cpu.load_hex(":020000020000FC\n:04FFFE0001020304F5\n:00000001FF\n".as_bytes()).unwrap();
+        assert_eq!((cpu.rom[0xFFFE], cpu.rom[0xFFFF], cpu.rom[0], cpu.rom[1]), (1, 2, 3, 4));
+    }
+
+    #[test]
+    fn segment_base_record_places_code_at_0x1000() {
+        // LJMP 0x1000；02 记录把段设为 0x0100，后面的记录从 0x1000 开始
+        let mut cpu = CPU::new();
+        cpu.load_hex_program(&fixture("asem_segment.hex")).unwrap();
+        assert_eq!(cpu.rom[..3], [0x02, 0x10, 0x00]);
+        assert_eq!(cpu.rom[0x1000..0x1005], [0x75, 0x90, 0xAA, 0x80, 0xFE]);
+        assert!(!cpu.is_loaded(0x0003) && !cpu.is_loaded(0x0005));
+        assert_eq!(cpu.rom_end, Some(0x1004));
+    }
+
+    #[test]
+    fn mixed_segment_and_linear_bases_are_rejected() {
+        let error = CPU::new().load_hex_program(&fixture("mixed_bases.hex")).unwrap_err();
+        assert!(error.to_string().starts_with("第 3 行: 不能混用扩展段地址 (02) 和扩展线性地址 (04) 记录"), "{}", error);
+    }
+
+    #[test]
+    fn start_address_record_sets_the_initial_pc() {
+        let mut emulator = Emulator::new(false);
+        emulator.cpu.load_hex_program(&fixture("entry_0800.hex")).unwrap();
+        assert_eq!(emulator.cpu.registers.pc, 0x0800);
+        emulator.step();
+        assert_eq!(emulator.cpu.registers.acc, 0x5A);
+        assert_eq!(emulator.cpu.registers.pc, 0x0802);
+
+        // 03 记录：CS=0x0080、IP=0 即 0x0800；没有起始地址记录时仍从 0x0000 开始
+        assert_eq!(load(":040000030080000079\n:00000001FF\n").unwrap().registers.pc, 0x0800);
+        assert_eq!(load(":0200100080FE70\n:00000001FF\n").unwrap().registers.pc, 0x0000);
+    }
+
+    // 写一个临时文件，返回路径
+    fn temp_file(name: &str, data: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("mcs51-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn load_program_puts_the_binary_in_rom_and_runs_it() {
+        // MOV A,#0x42; MOV R2,A; SJMP $，之后补到 300 字节（超过内部RAM的 256 字节）
+        let mut image = vec![0x74, 0x42, 0xFA, 0x80, 0xFE];
+        image.resize(300, 0xA5);
+        let path = temp_file("load_program.bin", &image);
+        let mut emulator = Emulator::new(false);
+        emulator.cpu.load_program(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(emulator.cpu.rom[..300], image[..]);
+        assert_eq!(emulator.cpu.rom_end, Some(299));
+        assert!(emulator.cpu.ram[..5].iter().all(|&b| b == 0));
+
+        emulator.step();
+        emulator.step();
+        assert_eq!(emulator.cpu.registers.acc, 0x42);
+        assert_eq!(emulator.cpu.ram[2], 0x42);
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn load_program_rejects_images_larger_than_rom() {
+        let path = temp_file("too_big.bin", &vec![0; 0x10001]);
+        let error = CPU::new().load_program(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // 引导程序：LJMP 0x2000；应用程序放在 0x2000：MOV A,#0x5A; SJMP $
+    const BOOTLOADER: &str = ":03000000022000DB\n:00000001FF\n";
+    const APP: &str = ":04200000745A80FE90\n:00000001FF\n";
+
+    #[test]
+    fn bootloader_jumps_into_separately_loaded_app() {
+        let boot = temp_file("boot.hex", BOOTLOADER.as_bytes());
+        let app = temp_file("app.hex", APP.as_bytes());
+        let mut emulator = Emulator::new(false);
+        emulator.cpu.load_hex_program(&boot).unwrap();
+        emulator.cpu.load_hex_program(&app).unwrap();
+        fs::remove_file(&boot).unwrap();
+        fs::remove_file(&app).unwrap();
+        assert_eq!(emulator.cpu.rom_end, Some(0x2003));
+
+        emulator.step();
+        assert_eq!(emulator.cpu.registers.pc, 0x2000);
+        emulator.step();
+        assert_eq!(emulator.cpu.registers.acc, 0x5A);
+    }
+
+    #[test]
+    fn conflicting_images_name_the_earlier_file() {
+        let boot = temp_file("boot_conflict.hex", BOOTLOADER.as_bytes());
+        let mut cpu = CPU::new();
+        cpu.load_hex_program(&boot).unwrap();
+        fs::remove_file(&boot).unwrap();
+        // 第二个映像的复位向量跳到 0x3000，与引导程序不同
+        let error = cpu.load_hex(":03000000023000CB\n".as_bytes()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("第 1 行: 与之前加载的 {} 在 0x0001-0x0001 重叠且内容不同（1 字节）: :03000000023000CB", boot)
+        );
+        assert_eq!(cpu.rom[..3], [0x02, 0x20, 0x00]);
+        // 内容相同的重叠照常加载
+        cpu.load_hex(BOOTLOADER.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn last_start_address_record_decides_the_entry() {
+        let mut cpu = CPU::new();
+        cpu.load_hex(":0400000500000100F6\n:00000001FF\n".as_bytes()).unwrap();
+        cpu.load_hex(":0400000500002000D7\n:00000001FF\n".as_bytes()).unwrap();
+        assert_eq!(cpu.registers.pc, 0x2000);
+    }
+
+    #[test]
+    fn binary_dump_loads_at_offset_and_runs_from_entry() {
+        // 4KB 的转储：开头是 MOV A,#0x77; SJMP $，其余为 0xFF
+        let mut dump = vec![0xFF; 0x1000];
+        dump[..4].copy_from_slice(&[0x74, 0x77, 0x80, 0xFE]);
+        let path = temp_file("dump.bin", &dump);
+        let mut emulator = Emulator::new(false);
+        let loaded = emulator.cpu.load_binary_program(&path, 0x1000);
+        assert_eq!(loaded.unwrap(), 0x1000);
+        assert_eq!(emulator.cpu.rom[0x1000..0x2000], dump[..]);
+        assert_eq!(emulator.cpu.rom_end, Some(0x1FFF));
+
+        // 相当于 --entry 0x1000
+        emulator.cpu.registers.pc = 0x1000;
+        emulator.step();
+        assert_eq!(emulator.cpu.registers.acc, 0x77);
+
+        // 放在 0xF001 处超出 64KB
+        let error = CPU::new().load_binary_program(&path, 0xF001).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -1,40 +1,133 @@
 // 8051 外设模块
 // 实现 I/O 端口 (P0-P3) 和其他外设功能
 
-use super::CPU;
+use super::timers::TR1;
+use super::{Verbosity, CPU};
+use crate::memory_dump::MemorySpace;
+use log::info;
+use std::io::Write;
 
 // SFR 地址定义
 pub const P0: u8 = 0x80;  // 端口 0
+pub const SP: u8 = 0x81;  // 堆栈指针
+pub const DPL: u8 = 0x82; // 数据指针低字节
+pub const DPH: u8 = 0x83; // 数据指针高字节
+pub const PCON: u8 = 0x87; // 电源控制寄存器
+pub const TCON: u8 = 0x88; // 定时器控制寄存器
+pub const TMOD: u8 = 0x89; // 定时器模式寄存器
+pub const TL0: u8 = 0x8A;  // 定时器0低字节
+pub const TL1: u8 = 0x8B;  // 定时器1低字节
+pub const TH0: u8 = 0x8C;  // 定时器0高字节
+pub const TH1: u8 = 0x8D;  // 定时器1高字节
 pub const P1: u8 = 0x90;  // 端口 1
+pub const SCON: u8 = 0x98; // 串口控制寄存器
+pub const SBUF: u8 = 0x99; // 串口数据缓冲器
 pub const P2: u8 = 0xA0;  // 端口 2
+pub const WDTRST: u8 = 0xA6; // 看门狗复位寄存器，只写 (AT89S52)
+pub const IE: u8 = 0xA8;  // 中断使能寄存器
 pub const P3: u8 = 0xB0;  // 端口 3
+pub const IP: u8 = 0xB8;  // 中断优先级寄存器
+pub const T2CON: u8 = 0xC8; // 定时器2控制寄存器 (8052)
+pub const RCAP2L: u8 = 0xCA; // 定时器2重装/捕获低字节 (8052)
+pub const RCAP2H: u8 = 0xCB; // 定时器2重装/捕获高字节 (8052)
+pub const TL2: u8 = 0xCC;  // 定时器2低字节 (8052)
+pub const TH2: u8 = 0xCD;  // 定时器2高字节 (8052)
 pub const PSW: u8 = 0xD0; // 程序状态字
 pub const ACC: u8 = 0xE0; // 累加器
 pub const B: u8 = 0xF0;   // 寄存器 B
 
+// PCON 位定义
+pub const PCON_IDL: u8 = 0x01; // 空闲模式
+pub const PCON_PD: u8 = 0x02;  // 掉电模式
+
+/// 挂接在端口引脚上的外部器件（例如 74HC595 移位寄存器、LED）
+/// 单片机驱动的引脚电平变化时收到通知；需要向单片机输入数据的器件可以实现 sample_input
+pub trait PinDevice {
+    /// 引脚输出电平变化：port 0-3 对应 P0-P3，bit 0-7
+    fn pin_changed(&mut self, port: u8, bit: u8, high: bool);
+
+    /// 单片机采样输入引脚时由器件提供电平，返回 None 表示该器件不驱动此引脚
+    fn sample_input(&mut self, _port: u8, _bit: u8) -> Option<bool> {
+        None
+    }
+
+    /// 程序写端口锁存器（每次写入都会通知，即使电平没有变化）
+    fn port_written(&mut self, _port: u8, _value: u8) {}
+}
+
+/// 端口控制台：把程序写到指定端口的每个字节原样输出，
+/// 用于把端口当作字符输出口的旧测试程序（默认不挂接，端口只有普通的 I/O 语义）
+pub struct PortConsole {
+    port: u8,
+    sink: Box<dyn Write>,
+}
+
+impl PortConsole {
+    /// port 0-3 对应 P0-P3
+    pub fn new(port: u8, sink: Box<dyn Write>) -> Self {
+        PortConsole { port, sink }
+    }
+}
+
+impl PinDevice for PortConsole {
+    fn pin_changed(&mut self, _port: u8, _bit: u8, _high: bool) {}
+
+    fn port_written(&mut self, port: u8, value: u8) {
+        if port == self.port {
+            let _ = self.sink.write_all(&[value]);
+            let _ = self.sink.flush();
+        }
+    }
+}
+
+// 电源管理状态（由 PCON 的 IDL/PD 位决定）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerMode {
+    Normal,    // 正常运行
+    Idle,      // 空闲：CPU 停止取指，定时器、串口和中断系统继续工作
+    PowerDown, // 掉电：振荡器停止，只有复位才能恢复
+}
+
 impl CPU {
+    /// 当前电源管理状态（PD 优先于 IDL）
+    pub fn power_mode(&self) -> PowerMode {
+        let pcon = self.sfr[(PCON - 0x80) as usize];
+        if (pcon & PCON_PD) != 0 {
+            PowerMode::PowerDown
+        } else if (pcon & PCON_IDL) != 0 {
+            PowerMode::Idle
+        } else {
+            PowerMode::Normal
+        }
+    }
+
     /// 读取 SFR 寄存器（带外设处理）
     pub fn read_sfr(&self, address: u8) -> u8 {
         match address {
+            // 读端口返回引脚电平：锁存器与外部驱动的线与
             P0 => {
                 // println!("读取P0端口: {:#04x}", self.sfr[(P0 - 0x80) as usize]);
-                self.sfr[(P0 - 0x80) as usize]
+                self.read_port(0)
             }
             P1 => {
                 // println!("读取P1端口: {:#04x}", self.sfr[(P1 - 0x80) as usize]);
-                self.sfr[(P1 - 0x80) as usize]
+                self.read_port(1)
             }
             P2 => {
                 // println!("读取P2端口: {:#04x}", self.sfr[(P2 - 0x80) as usize]);
-                self.sfr[(P2 - 0x80) as usize]
+                self.read_port(2)
             }
             P3 => {
                 // println!("读取P3端口: {:#04x}", self.sfr[(P3 - 0x80) as usize]);
-                self.sfr[(P3 - 0x80) as usize]
+                self.read_port(3)
             }
+            SBUF => self.serial_read_sbuf(), // 读取接收缓冲器
             ACC => self.registers.acc, // 累加器映射到 SFR
             B => self.registers.b,     // B 寄存器映射到 SFR
-            0x81 => self.registers.sp, // SP (Stack Pointer)
+            SP => self.registers.sp,   // SP (Stack Pointer)
+            DPL => (self.registers.dptr & 0xFF) as u8, // DPTR 低字节
+            DPH => (self.registers.dptr >> 8) as u8,   // DPTR 高字节
+            WDTRST if self.variant.has_watchdog() => 0xFF, // 只写寄存器
             _ => {
                 if address >= 0x80 {
                     self.sfr[(address - 0x80) as usize]
@@ -45,49 +138,87 @@ impl CPU {
         }
     }
 
+    /// 查看 SFR 的值而不产生任何副作用（用于转储和显示）：
+    /// 端口返回锁存器而不是引脚电平（不采样外部器件，悬空引脚也不消耗随机数），其余与 read_sfr 相同
+    pub fn peek_sfr(&self, address: u8) -> u8 {
+        match address {
+            P0 | P1 | P2 | P3 => self.sfr[(address - 0x80) as usize],
+            _ => self.read_sfr(address),
+        }
+    }
+
     /// 写入 SFR 寄存器（带外设处理）
+    /// 所有 SFR 写入都经过这里，观察点记录写入前后的值（端口为锁存器）
     pub fn write_sfr(&mut self, address: u8, value: u8) {
+        let old = self.peek_sfr(address);
         match address {
-            P0 => {
-                if !self.debug {
-                    println!("写入P0端口: {:#04x} (二进制: {:08b})", value, value);
-                }
-                self.sfr[(P0 - 0x80) as usize] = value;
-                self.handle_port_output(0, value);
-            }
-            P1 => {
-                if !self.debug {
-                    println!("写入P1端口: {:#04x} (二进制: {:08b})", value, value);
-                }
-                self.sfr[(P1 - 0x80) as usize] = value;
-                self.handle_port_output(1, value);
+            _ if self.exit_port == Some(address) => {
+                // 退出端口：记录程序给出的退出码，Emulator 在这条指令之后停机
+                self.sfr[(address - 0x80) as usize] = value;
+                self.exit_code = Some(value);
             }
-            P2 => {
-                if !self.debug {
-                    println!("写入P2端口: {:#04x} (二进制: {:08b})", value, value);
-                }
-                self.sfr[(P2 - 0x80) as usize] = value;
-                self.handle_port_output(2, value);
-            }
-            P3 => {
-                if !self.debug {
-                    println!("写入P3端口: {:#04x} (二进制: {:08b})", value, value);
-                }
-                self.sfr[(P3 - 0x80) as usize] = value;
-                self.handle_port_output(3, value);
+            P0 | P1 | P2 | P3 => {
+                let port = (address - P0) >> 4;
+                self.report_port_write(port, value);
+                let old = self.sfr[(address - 0x80) as usize];
+                self.sfr[(address - 0x80) as usize] = value;
+                self.handle_port_output(port, old, value);
             }
-            ACC => {
-                self.registers.acc = value; // 累加器映射到 SFR
-                self.sfr[(ACC - 0x80) as usize] = value;
+            SBUF => {
+                // 写入发送缓冲器，启动串口发送
+                self.sfr[(SBUF - 0x80) as usize] = value;
+                self.serial_write_sbuf(value);
             }
+            ACC => self.set_acc(value), // 累加器映射到 SFR，位操作 (ACC.0-ACC.7) 同样经过这里，奇偶标志随之更新
             B => {
                 self.registers.b = value;   // B 寄存器映射到 SFR
                 self.sfr[(B - 0x80) as usize] = value;
             }
-            0x81 => {
+            SP => {
                 // SP (Stack Pointer)
                 self.registers.sp = value;
-                self.sfr[(0x81 - 0x80) as usize] = value;
+                self.sfr[(SP - 0x80) as usize] = value;
+            }
+            DPL => {
+                // DPL 与 DPTR 低字节是同一个寄存器
+                self.registers.dptr = (self.registers.dptr & 0xFF00) | value as u16;
+                self.sfr[(DPL - 0x80) as usize] = value;
+            }
+            DPH => {
+                // DPH 与 DPTR 高字节是同一个寄存器
+                self.registers.dptr = (self.registers.dptr & 0x00FF) | ((value as u16) << 8);
+                self.sfr[(DPH - 0x80) as usize] = value;
+            }
+            SCON | TMOD | TH1 | PCON => {
+                // 这些寄存器决定串口波特率
+                self.sfr[(address - 0x80) as usize] = value;
+                self.serial_config_changed();
+            }
+            WDTRST if self.variant.has_watchdog() => {
+                // WDTRST 只写，不保存写入的值
+                self.watchdog_write(value);
+            }
+            _ if self.adccon_address() == Some(address) => {
+                // 片上 ADC 的控制寄存器：写 ADCS 启动转换
+                self.adc_write_con(value);
+            }
+            TCON => {
+                // 软件可以直接写 TF0/TF1/IE0/IE1：写0撤销请求（例如轮询方式下清除 TF0），写1产生请求；
+                // 中断不在这里响应，下一次 check_interrupts 按新值判断
+                let old = self.sfr[(TCON - 0x80) as usize];
+                self.sfr[(TCON - 0x80) as usize] = value;
+                // IT0/IT1 决定 IE0/IE1 怎样产生，按新的触发方式整理
+                self.external_interrupt_mode_written(old);
+                // 定时器1是串口模式1/3的波特率发生器，TR1 变化时波特率随之出现或消失
+                if (old ^ value) & TR1 != 0 {
+                    self.serial_config_changed();
+                }
+            }
+            IE | IP => {
+                // 写 IE/IP 之后至少再执行一条指令才会响应中断：
+                // 使能时已经挂起的请求（如 TF0 已置位后才置位 EA 和 ET0）在下一条指令之后响应
+                self.sfr[(address - 0x80) as usize] = value;
+                self.interrupt_blocked = true;
             }
             _ => {
                 if address >= 0x80 {
@@ -95,10 +226,30 @@ impl CPU {
                 }
             }
         }
+        self.watch_write(MemorySpace::Sfr, address as u16, old, self.peek_sfr(address));
+    }
+
+    /// 提示端口写入：调试模式下指令的跟踪输出已经包含这些信息，Quiet 时不输出
+    fn report_port_write(&self, port: u8, value: u8) {
+        if !self.debug && self.verbosity != Verbosity::Quiet {
+            info!("写入P{}端口: {:#04x} (二进制: {:08b})", port, value, value);
+        }
     }
 
     /// 处理端口输出（模拟外设行为）
-    fn handle_port_output(&self, port_num: u8, value: u8) {
+    fn handle_port_output(&mut self, port_num: u8, old: u8, value: u8) {
+        for device in self.devices.get_mut().iter_mut() {
+            device.port_written(port_num, value);
+        }
+
+        // 锁存器变化的位通知外部器件
+        let changed = old ^ value;
+        for bit in 0..8 {
+            if (changed >> bit) & 1 != 0 {
+                self.notify_pin(port_num, bit, (value >> bit) & 1 != 0);
+            }
+        }
+
         // 这里可以添加更多的外设模拟逻辑
         // 例如：LED显示、LCD控制、继电器开关等
         
@@ -116,6 +267,83 @@ impl CPU {
         }
     }
 
+    /// 挂接外部器件，之后的引脚输出变化都会通知它
+    pub fn attach_device(&mut self, device: Box<dyn PinDevice>) {
+        self.devices.get_mut().push(device);
+    }
+
+    /// 通知所有外部器件引脚电平变化
+    pub(crate) fn notify_pin(&mut self, port: u8, bit: u8, high: bool) {
+        for device in self.devices.get_mut().iter_mut() {
+            device.pin_changed(port, bit, high);
+        }
+    }
+
+    /// 采样输入引脚的外部电平：先询问外部器件，没有器件驱动时使用 set_pin 设置的电平（不含锁存器）
+    /// 读端口、INT0/INT1、T2EX 和串口接收都经过这里，所以器件驱动的引脚对所有输入路径都可见
+    pub(crate) fn sample_pin(&self, port: u8, bit: u8) -> bool {
+        for device in self.devices.borrow_mut().iter_mut() {
+            if let Some(level) = device.sample_input(port, bit) {
+                return level;
+            }
+        }
+        (self.port_pins[(port & 0x03) as usize] >> (bit & 0x07)) & 1 != 0
+    }
+
+    /// 是否有外部器件正在驱动该引脚（它随时可能改变电平，例如拉低 INT0/INT1）
+    pub(crate) fn device_drives(&self, port: u8, bit: u8) -> bool {
+        self.devices.borrow_mut().iter_mut().any(|device| device.sample_input(port, bit).is_some())
+    }
+
+    /// 端口 8 个引脚的外部电平（没有挂接器件时就是 set_pin 设置的电平）
+    fn sample_port(&self, port: usize) -> u8 {
+        if self.devices.borrow().is_empty() {
+            return self.port_pins[port];
+        }
+        (0..8).filter(|&bit| self.sample_pin(port as u8, bit)).fold(0, |pins, bit| pins | (1 << bit))
+    }
+
+    /// 读端口：锁存器与外部驱动的线与；锁存器为1的悬空引脚没有确定的电平，读入随机值
+    fn read_port(&self, port: usize) -> u8 {
+        let latch = self.sfr[(P0 as usize + port * 0x10) - 0x80];
+        let value = latch & self.sample_port(port);
+        let floating = latch & self.floating_pins[port];
+        if floating == 0 {
+            return value;
+        }
+        (value & !floating) | (self.random_u8() & floating)
+    }
+
+    /// 设置引脚是否悬空（没有任何外部连接，例如 P0 没有上拉电阻时）
+    /// 悬空引脚读入的电平来自伪随机数发生器，用 set_seed 可以让结果复现
+    pub fn set_pin_floating(&mut self, port: u8, bit: u8, floating: bool) {
+        let mask = 1 << (bit & 0x07);
+        let pins = &mut self.floating_pins[(port & 0x03) as usize];
+        if floating {
+            *pins |= mask;
+        } else {
+            *pins &= !mask;
+        }
+    }
+
+    /// 设置外部电路对引脚的驱动电平（供宿主程序或激励脚本注入输入信号）
+    /// port: 0-3 对应 P0-P3，bit: 0-7；high=false 表示外部把引脚拉低
+    pub fn set_pin(&mut self, port: u8, bit: u8, high: bool) {
+        let mask = 1 << (bit & 0x07);
+        let pins = &mut self.port_pins[(port & 0x03) as usize];
+        if high {
+            *pins |= mask;
+        } else {
+            *pins &= !mask;
+        }
+    }
+
+    /// 读取引脚的实际电平（准双向口：锁存器为1且外部器件或 set_pin 未拉低时为高）
+    pub fn pin_level(&self, port: u8, bit: u8) -> bool {
+        let latch = self.sfr[((P0 as usize) + (port & 0x03) as usize * 0x10) - 0x80];
+        (latch >> (bit & 0x07)) & 1 != 0 && self.sample_pin(port, bit)
+    }
+
     /// 初始化所有端口为默认值
     pub fn init_ports(&mut self) {
         // 8051 复位后，所有端口默认为 0xFF (全高)
@@ -125,3 +353,167 @@ impl CPU {
         self.sfr[(P3 - 0x80) as usize] = 0xFF;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PinDevice, PortConsole, P1};
+    use crate::emulator::Emulator;
+    use crate::cpu::Verbosity;
+    use crate::test_support::{capture_logs, capture_serial, emulator_with, emulator_with_blocks, run_steps, shared_output, PinDriver};
+    use log::Level;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// 复位后跳到 0x0030：edge 时 SETB IT0（下降沿触发），否则两条 NOP；然后 MOV IE,#0x81; SJMP $
+    /// INT0 服务程序 (0x0003)：INC 0x40; RETI
+    fn int0_program(edge: bool) -> Emulator {
+        let mode: &[u8] = if edge { &[0xD2, 0x88] } else { &[0x00, 0x00] };
+        let main = [mode, &[0x75, 0xA8, 0x81, 0x80, 0xFE]].concat();
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x0003, &[0x05, 0x40, 0x32]), (0x0030, &main)]);
+        run_steps(&mut emulator, 4);
+        emulator
+    }
+
+    #[test]
+    fn device_pulse_on_int0_enters_edge_isr_once() {
+        let mut emulator = int0_program(true);
+        let int0 = PinDriver::attach(&mut emulator, 3, 2);
+        int0.set(false);
+        run_steps(&mut emulator, 20);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+
+        int0.set(true);
+        run_steps(&mut emulator, 5);
+        int0.set(false);
+        run_steps(&mut emulator, 5);
+        assert_eq!(emulator.cpu.interrupt_entries, 2);
+    }
+
+    #[test]
+    fn device_holding_int0_low_reenters_level_isr() {
+        let mut emulator = int0_program(false);
+        let int0 = PinDriver::attach(&mut emulator, 3, 2);
+        int0.set(false);
+        run_steps(&mut emulator, 20);
+        let entries = emulator.cpu.interrupt_entries;
+        assert!(entries >= 3, "只进入了 {} 次", entries);
+
+        int0.set(true);
+        run_steps(&mut emulator, 3);
+        let entries = emulator.cpu.interrupt_entries;
+        run_steps(&mut emulator, 20);
+        assert_eq!(emulator.cpu.interrupt_entries, entries);
+    }
+
+    #[test]
+    fn mov_a_p3_reads_device_driven_pin() {
+        // MOV A,P3; CLR P3.5; MOV A,P3
+        let mut emulator = emulator_with(&[0xE5, 0xB0, 0xC2, 0xB5, 0xE5, 0xB0]);
+        let pin = PinDriver::attach(&mut emulator, 3, 4);
+        pin.set(false);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.acc, 0xEF);
+        assert!(!emulator.cpu.pin_level(3, 4));
+        assert!(emulator.cpu.pin_level(3, 5));
+
+        // 锁存器为 0 的引脚读入 0，与器件无关
+        pin.set(true);
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.acc, 0xDF);
+    }
+
+    #[test]
+    fn dph_and_dpl_alias_dptr() {
+        // MOV DPH,#0x12; MOV DPL,#0x34; MOV DPTR,#0xABCD; MOV A,DPH; MOV R0,DPL
+        let mut emulator = emulator_with(&[0x75, 0x83, 0x12, 0x75, 0x82, 0x34, 0x90, 0xAB, 0xCD, 0xE5, 0x83, 0xA8, 0x82]);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.dptr, 0x1200);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.registers.dptr, 0x1234);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.registers.acc, 0xAB);
+        assert_eq!(emulator.cpu.ram[0], 0xCD);
+    }
+
+    /// 记录程序写端口锁存器的器件
+    struct PortRecorder(Rc<RefCell<Vec<(u8, u8)>>>);
+
+    impl PinDevice for PortRecorder {
+        fn pin_changed(&mut self, _port: u8, _bit: u8, _high: bool) {}
+
+        fn port_written(&mut self, port: u8, value: u8) {
+            self.0.borrow_mut().push((port, value));
+        }
+    }
+
+    /// MOV P1,#'H'; MOV P1,#'i'; MOV P2,#'!'
+    const PORT_WRITES: [u8; 9] = [0x75, 0x90, b'H', 0x75, 0x90, b'i', 0x75, 0xA0, b'!'];
+
+    #[test]
+    fn p1_writes_are_plain_port_activity_by_default() {
+        let mut emulator = emulator_with(&PORT_WRITES);
+        let serial = capture_serial(&mut emulator);
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        emulator.cpu.attach_device(Box::new(PortRecorder(writes.clone())));
+        run_steps(&mut emulator, 3);
+        assert_eq!(*writes.borrow(), [(1, b'H'), (1, b'i'), (2, b'!')]);
+        assert_eq!(emulator.cpu.peek_sfr(P1), b'i');
+        assert!(serial.borrow().is_empty());
+    }
+
+    #[test]
+    fn port_console_echoes_only_its_port() {
+        let mut emulator = emulator_with(&PORT_WRITES);
+        let (console, out) = shared_output();
+        emulator.cpu.attach_device(Box::new(PortConsole::new(1, out)));
+        run_steps(&mut emulator, 3);
+        assert_eq!(console.borrow().as_slice(), b"Hi");
+    }
+
+    /// 用给定的种子运行：P1.0-P1.3 悬空，MOV 0x30+i,P1 连续读 8 次端口
+    fn floating_reads(seed: u64) -> Vec<u8> {
+        let program: Vec<u8> = (0..8).flat_map(|i| [0x85, P1, 0x30 + i]).collect();
+        let mut emulator = emulator_with(&program);
+        emulator.set_seed(seed);
+        for bit in 0..4 {
+            emulator.cpu.set_pin_floating(1, bit, true);
+        }
+        run_steps(&mut emulator, 8);
+        emulator.cpu.ram[0x30..0x38].to_vec()
+    }
+
+    #[test]
+    fn same_seed_gives_same_floating_input_sequence() {
+        let reads = floating_reads(42);
+        assert_eq!(reads, floating_reads(42));
+        assert_ne!(reads, floating_reads(43));
+        // 没有悬空的高4位照常读入锁存器的 1，悬空的低4位不是固定值
+        assert!(reads.iter().all(|&value| value & 0xF0 == 0xF0));
+        assert!(reads.iter().any(|&value| value != reads[0]));
+    }
+
+    #[test]
+    fn pins_do_not_float_by_default() {
+        let mut emulator = emulator_with(&[0x85, P1, 0x30]);
+        emulator.set_seed(42);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.ram[0x30], 0xFF);
+    }
+
+    #[test]
+    fn quiet_verbosity_silences_port_write_reports() {
+        // MOV P1,#0x55; MOV P1,#0xAA
+        let program = [0x75, P1, 0x55, 0x75, P1, 0xAA];
+        let mut emulator = emulator_with(&program);
+        let logs = capture_logs(|| run_steps(&mut emulator, 2));
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0], (Level::Info, "写入P1端口: 0x55 (二进制: 01010101)".to_string()));
+
+        let mut emulator = emulator_with(&program);
+        emulator.set_verbosity(Verbosity::Quiet);
+        let logs = capture_logs(|| run_steps(&mut emulator, 2));
+        assert!(logs.is_empty(), "{:?}", logs);
+        assert_eq!(emulator.cpu.peek_sfr(P1), 0xAA);
+    }
+}
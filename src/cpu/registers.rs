@@ -6,6 +6,12 @@ pub struct Registers {
     pub dptr: u16, // 数据指针 DPTR
 }
 
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Registers {
     pub fn new() -> Self {
         Registers {
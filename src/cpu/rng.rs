@@ -0,0 +1,35 @@
+// 可设定种子的伪随机数发生器（SplitMix64）
+// 模拟真实硬件中不确定的行为（悬空引脚、噪声输入等）时都从这里取随机数，
+// 同样的种子总是得到同样的序列，保证运行结果可以复现
+
+// 默认种子：没有调用 set_seed 时每次运行的结果也相同
+pub const DEFAULT_SEED: u64 = 0x8051_8051_8051_8051;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(DEFAULT_SEED)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
@@ -0,0 +1,773 @@
+// 8051 串行口 (UART) 模块
+// 模式1/3下发送和接收时序都由定时器1溢出驱动：每次溢出推进波特率时钟，
+// SMOD=0 时32次溢出为一位，SMOD=1 时16次溢出为一位；
+// 模式2的位时钟固定为 Fosc/64（SMOD=1 时为 Fosc/32）
+// 模式2/3为9位数据：发送时 TB8 作为第9位，接收时第9位存入 RB8；
+// SM2=1 时只有第9位为1（地址帧）才会置位 RI，用于多机通信
+// 发送与自由运行的位时钟同步：写 SBUF 后在下一个位边界开始发送起始位，
+// 停止位开始时置位 TI，所以连续发送时相邻两次 TI 正好相隔一帧（10位）
+// 接收的数据来自宿主程序：直接放入接收队列，或者由后台线程从文件/标准输入读取
+// 模式0为同步移位寄存器：RXD (P3.0) 收发数据、TXD (P3.1) 输出移位时钟，
+// 每个机器周期移动一位（Fosc/12），低位在前；数据通过引脚回调交给挂接的外部器件
+//
+// 标志和缓冲规则（与硬件一致，中断驱动的环形缓冲区驱动程序依赖这些细节）：
+// - TI/RI 只由硬件置位，硬件从不清除它们（包括响应中断时），必须由软件清除；
+//   软件也可以直接置位 TI/RI 来触发串口中断
+// - 接收是双缓冲的：RI 未清除时下一帧照常移入，帧结束时 RI 仍为1则新字节丢弃并记为一次溢出，
+//   SBUF 中保留的是尚未读取的旧字节
+// - 发送不排队：数据位移出期间写 SBUF 的字节被丢弃并记为一次发送冲突；
+//   停止位期间（TI 已置位）写入的字节在停止位结束后发送
+
+use super::peripherals::{PCON, SCON, TCON, TH1, TMOD};
+use super::timers::TR1;
+use super::CPU;
+use log::debug;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// SCON 位定义
+pub const SCON_SM2: u8 = 0x20; // 多机通信控制
+pub const SCON_REN: u8 = 0x10; // 允许接收
+pub const SCON_TB8: u8 = 0x08; // 模式2/3发送的第9位
+pub const SCON_RB8: u8 = 0x04; // 模式2/3接收到的第9位（模式1为停止位）
+pub const SCON_TI: u8 = 0x02; // 发送中断标志
+pub const SCON_RI: u8 = 0x01; // 接收中断标志
+
+// PCON 位定义
+pub const PCON_SMOD: u8 = 0x80; // 波特率加倍
+
+// 模式1一帧：1位起始位 + 8位数据 + 1位停止位
+const MODE1_FRAME_BITS: u32 = 10;
+
+// 模式2/3一帧：1位起始位 + 8位数据 + 第9位 + 1位停止位
+const NINE_BIT_FRAME_BITS: u32 = 11;
+
+// 接收队列中第9位的位置（模式2/3的地址帧标志）
+pub const NINTH_BIT: u16 = 0x100;
+
+// 模式0每次移位8位数据
+const MODE0_SHIFT_BITS: u32 = 8;
+
+// 模式0使用的引脚：P3.0 为数据线，P3.1 为移位时钟
+const RXD_PIN: u8 = 0;
+const TXD_PIN: u8 = 1;
+
+// 串口内部状态（SBUF 实际上是发送和接收两个独立的寄存器）
+pub struct Serial {
+    pub tx_busy: bool,          // 发送移位寄存器是否正在发送（含等待位边界）
+    pub tx_data: u8,            // 正在发送的字节
+    tx_pending: bool,           // 已写入 SBUF，等待下一个位边界开始发送
+    tx_bits_remaining: u32,     // 本帧剩余的位数
+    baud_ticks: u32,            // 位时钟分频计数（定时器1溢出次数）
+    pub rx_buffer: u8,          // 接收缓冲器
+    rx_busy: bool,              // 接收移位寄存器是否正在接收一帧
+    rx_data: u8,                // 正在接收的字节
+    rx_ninth_bit: bool,         // 正在接收的帧的第9位
+    rx_ticks_remaining: u32,    // 本帧剩余的接收时钟数
+    pub rx_pending: VecDeque<u16>, // 等待进入串口线路的数据（bit 8 为模式2/3的第9位）
+    pub rx_source: Option<Receiver<u8>>, // 后台读取的输入流（接收队列为空时从这里取）
+    pub tx_sink: Box<dyn Write>, // 发送完成的字节输出到这里（默认标准输出）
+    reported_baud: Option<f64>, // 调试模式下上一次显示的波特率
+    pub rx_overruns: u64,       // RI 未清除时又收到一帧而丢弃的字节数
+    pub tx_collisions: u64,     // 发送过程中写 SBUF 而丢弃的字节数
+    mode2_clocks: u32,          // 模式2：位时钟分频计数（振荡周期）
+    shift_bits_remaining: u32,  // 模式0：本次移位剩余的位数（0表示空闲）
+    shift_data: u8,             // 模式0：移位寄存器
+    shift_receiving: bool,      // 模式0：当前是接收（否则为发送）
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Serial {
+            tx_busy: false,
+            tx_data: 0,
+            tx_pending: false,
+            tx_bits_remaining: 0,
+            baud_ticks: 0,
+            rx_buffer: 0,
+            rx_busy: false,
+            rx_data: 0,
+            rx_ninth_bit: false,
+            rx_ticks_remaining: 0,
+            rx_pending: VecDeque::new(),
+            rx_source: None,
+            tx_sink: Box::new(io::stdout()),
+            reported_baud: None,
+            rx_overruns: 0,
+            tx_collisions: 0,
+            mode2_clocks: 0,
+            shift_bits_remaining: 0,
+            shift_data: 0,
+            shift_receiving: false,
+        }
+    }
+
+    // 复位串口状态，保留输入来源、输出目标和溢出/冲突计数
+    pub fn reset(&mut self) {
+        self.tx_busy = false;
+        self.tx_data = 0;
+        self.tx_pending = false;
+        self.tx_bits_remaining = 0;
+        self.baud_ticks = 0;
+        self.rx_buffer = 0;
+        self.rx_busy = false;
+        self.rx_data = 0;
+        self.rx_ninth_bit = false;
+        self.rx_ticks_remaining = 0;
+        self.reported_baud = None;
+        self.mode2_clocks = 0;
+        self.shift_bits_remaining = 0;
+        self.shift_data = 0;
+        self.shift_receiving = false;
+    }
+
+    // 取下一个要接收的数据：先取接收队列，再取输入流（输入流的第9位为0）
+    fn next_rx_word(&mut self) -> Option<u16> {
+        if let Some(word) = self.rx_pending.pop_front() {
+            return Some(word);
+        }
+        self.rx_source.as_ref().and_then(|source| source.try_recv().ok()).map(u16::from)
+    }
+}
+
+// 在后台线程中逐字节读取输入流（例如标准输入），读到的字节通过通道交给串口
+// 这样等待输入时不会阻塞模拟
+pub fn spawn_reader<R: Read + Send + 'static>(mut reader: R) -> Receiver<u8> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 256];
+        // 读到文件末尾、读取出错或模拟器已退出时结束
+        while let Ok(count) = reader.read(&mut buffer) {
+            if count == 0 || buffer[..count].iter().any(|&byte| sender.send(byte).is_err()) {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+impl CPU {
+    // 串口工作模式（SCON 的 SM0/SM1 位）
+    pub fn serial_mode(&self) -> u8 {
+        self.sfr[(SCON - 0x80) as usize] >> 6
+    }
+
+    // 每一位需要的定时器1溢出次数（由 PCON.SMOD 决定是32还是16）
+    fn timer1_overflows_per_bit(&self) -> u32 {
+        if (self.sfr[(PCON - 0x80) as usize] & PCON_SMOD) != 0 {
+            16
+        } else {
+            32
+        }
+    }
+
+    // 模式2每一位的振荡周期数（由 PCON.SMOD 决定是64还是32）
+    fn mode2_clocks_per_bit(&self) -> u32 {
+        self.timer1_overflows_per_bit() * 2
+    }
+
+    // 当前工作模式下一帧的位数
+    fn frame_bits(&self) -> u32 {
+        if self.serial_mode() == 1 {
+            MODE1_FRAME_BITS
+        } else {
+            NINE_BIT_FRAME_BITS
+        }
+    }
+
+    // 当前配置下的有效波特率（仅在能够确定时返回）
+    // 模式0固定为 Fosc/12，模式2为 Fosc/64 或 Fosc/32；
+    // 模式1/3要求定时器1工作在模式2（8位自动重装）并且正在运行，这是标准的波特率发生器配置
+    pub fn serial_baud_rate(&self) -> Option<f64> {
+        match self.serial_mode() {
+            0 => return Some(self.clock_frequency as f64 / 12.0),
+            2 => return Some(self.clock_frequency as f64 / self.mode2_clocks_per_bit() as f64),
+            _ => {}
+        }
+        let t1_mode = (self.sfr[(TMOD - 0x80) as usize] >> 4) & 0x03;
+        // 定时器0处于模式3时定时器1不受 TR1 控制，一直运行
+        let t1_running = (self.sfr[(TCON - 0x80) as usize] & TR1) != 0 || (self.sfr[(TMOD - 0x80) as usize] & 0x03) == 3;
+        if t1_mode != 2 || !t1_running {
+            return None;
+        }
+        let reload = self.sfr[(TH1 - 0x80) as usize] as f64;
+        let machine_cycle_rate = self.clock_frequency as f64 / 12.0;
+        let overflow_rate = machine_cycle_rate / (256.0 - reload);
+        Some(overflow_rate / self.timer1_overflows_per_bit() as f64)
+    }
+
+    // 设置串口发送输出（例如写入文件或内存缓冲区）
+    pub fn set_serial_sink(&mut self, sink: Box<dyn Write>) {
+        self.serial.tx_sink = sink;
+    }
+
+    // 调试模式下，SCON/TMOD/TH1/PCON 被写入后显示变化了的波特率
+    pub(crate) fn serial_config_changed(&mut self) {
+        if !self.debug {
+            return;
+        }
+        let baud = self.serial_baud_rate();
+        if baud != self.serial.reported_baud {
+            match baud {
+                Some(baud) if self.serial_mode() == 1 || self.serial_mode() == 3 => debug!(
+                    "[串口] 波特率 {:.0} bps（定时器1每 {} 次溢出为一位）",
+                    baud,
+                    self.timer1_overflows_per_bit()
+                ),
+                Some(baud) => debug!("[串口] 波特率 {:.0} bps", baud),
+                None => debug!("[串口] 当前配置无法确定波特率"),
+            }
+            self.serial.reported_baud = baud;
+        }
+    }
+
+    // 写 SBUF：准备发送一帧
+    // 数据位发送过程中写入的字节被丢弃；停止位期间（TI 已置位）写入的字节在停止位结束后发送
+    pub(crate) fn serial_write_sbuf(&mut self, value: u8) {
+        if self.serial_mode() == 0 {
+            self.serial_shift_start_tx(value);
+            return;
+        }
+        if self.serial.tx_pending || (self.serial.tx_busy && self.serial.tx_bits_remaining > 1) {
+            self.serial.tx_collisions += 1;
+            if self.debug {
+                debug!("[串口] 发送未完成，忽略写入 {:#04x}（发送冲突）", value);
+            }
+            return;
+        }
+
+        self.serial.tx_data = value;
+        self.serial.tx_pending = true;
+        self.serial.tx_busy = true;
+
+        if self.debug {
+            let ninth = if self.serial_mode() == 1 {
+                String::new()
+            } else {
+                let tb8 = (self.sfr[(SCON - 0x80) as usize] & SCON_TB8) != 0;
+                format!("（第9位 TB8={}）", tb8 as u8)
+            };
+            match self.serial_baud_rate() {
+                Some(baud) => debug!("[串口] 开始发送 {:#04x}{}，波特率 {:.0} bps", value, ninth, baud),
+                None => debug!("[串口] 开始发送 {:#04x}{}，波特率无法确定", value, ninth),
+            }
+        }
+    }
+
+    // 向串口线路送入数据，REN 置位后按波特率逐字节接收（模式2/3下第9位为0）
+    pub fn serial_receive(&mut self, data: &[u8]) {
+        self.serial.rx_pending.extend(data.iter().map(|&byte| u16::from(byte)));
+    }
+
+    // 向串口线路送入9位数据（模式2/3），NINTH_BIT 置位表示地址帧
+    pub fn serial_receive_9bit(&mut self, data: &[u16]) {
+        self.serial.rx_pending.extend(data.iter().map(|&word| word & 0x1FF));
+    }
+
+    // 设置串口输入流（由 spawn_reader 创建）
+    pub fn set_serial_source(&mut self, source: Receiver<u8>) {
+        self.serial.rx_source = Some(source);
+    }
+
+    // 读 SBUF：返回接收缓冲器
+    pub(crate) fn serial_read_sbuf(&self) -> u8 {
+        self.serial.rx_buffer
+    }
+
+    // 定时器1溢出：模式1/3下推进位时钟和接收移位寄存器
+    pub(crate) fn serial_timer1_overflow(&mut self) {
+        let mode = self.serial_mode();
+        if mode != 1 && mode != 3 {
+            return;
+        }
+        let ticks_per_bit = self.timer1_overflows_per_bit();
+        self.serial.baud_ticks += 1;
+        if self.serial.baud_ticks >= ticks_per_bit {
+            self.serial.baud_ticks = 0;
+            self.serial_tx_bit();
+        }
+        self.serial_rx_tick(ticks_per_bit);
+    }
+
+    // 模式2位时钟（每个机器周期即12个振荡周期推进一次）
+    fn serial_mode2_cycle(&mut self) {
+        self.serial.mode2_clocks += 12;
+        let clocks_per_bit = self.mode2_clocks_per_bit();
+        if self.serial.mode2_clocks >= clocks_per_bit {
+            self.serial.mode2_clocks -= clocks_per_bit;
+            self.serial_tx_bit();
+            self.serial_rx_tick(1);
+        }
+    }
+
+    // 发送：每个位边界移出一位，停止位开始时输出字节并由硬件置位 TI
+    fn serial_tx_bit(&mut self) {
+        if self.serial.tx_bits_remaining > 0 {
+            self.serial.tx_bits_remaining -= 1;
+            if self.serial.tx_bits_remaining == 1 {
+                let data = self.serial.tx_data;
+                // 输出失败不影响模拟（例如管道已关闭）
+                let _ = self.serial.tx_sink.write_all(&[data]);
+                let _ = self.serial.tx_sink.flush();
+                self.sfr[(SCON - 0x80) as usize] |= SCON_TI;
+            }
+        }
+
+        if self.serial.tx_bits_remaining == 0 && self.serial.tx_pending {
+            // 发送起始位
+            self.serial.tx_pending = false;
+            self.serial.tx_bits_remaining = self.frame_bits();
+        }
+        self.serial.tx_busy = self.serial.tx_pending || self.serial.tx_bits_remaining > 0;
+    }
+
+    // 接收：REN 置位时有数据就开始接收一帧，整帧结束后装入接收缓冲器并置位 RI
+    // ticks_per_bit 为每一位的接收时钟数（模式1/3为定时器1溢出次数，模式2为1）
+    fn serial_rx_tick(&mut self, ticks_per_bit: u32) {
+        if !self.serial.rx_busy {
+            let scon = self.sfr[(SCON - 0x80) as usize];
+            if (scon & SCON_REN) == 0 {
+                return;
+            }
+            match self.serial.next_rx_word() {
+                Some(word) => {
+                    self.serial.rx_data = word as u8;
+                    // 模式1的第9位是停止位，总是1
+                    self.serial.rx_ninth_bit = self.serial_mode() == 1 || (word & NINTH_BIT) != 0;
+                    self.serial.rx_busy = true;
+                    self.serial.rx_ticks_remaining = self.frame_bits() * ticks_per_bit;
+                }
+                None => return,
+            }
+        }
+
+        self.serial.rx_ticks_remaining = self.serial.rx_ticks_remaining.saturating_sub(1);
+        if self.serial.rx_ticks_remaining == 0 {
+            self.serial.rx_busy = false;
+            self.serial_rx_complete();
+        }
+    }
+
+    // 一帧接收完毕：RI=0 且（SM2=0 或第9位为1）时装入 SBUF、RB8 并置位 RI，否则丢弃
+    fn serial_rx_complete(&mut self) {
+        let data = self.serial.rx_data;
+        let ninth = self.serial.rx_ninth_bit;
+        let scon = &mut self.sfr[(SCON - 0x80) as usize];
+        if (*scon & SCON_RI) != 0 {
+            self.serial.rx_overruns += 1;
+            if self.debug {
+                debug!("[串口] 接收溢出：RI 未清除，丢弃接收到的 {:#04x}", data);
+            }
+            return;
+        }
+        if (*scon & SCON_SM2) != 0 && !ninth {
+            if self.debug {
+                debug!("[串口] SM2=1，忽略第9位为0的数据帧 {:#04x}", data);
+            }
+            return;
+        }
+        self.serial.rx_buffer = data;
+        if ninth {
+            *scon |= SCON_RB8;
+        } else {
+            *scon &= !SCON_RB8;
+        }
+        *scon |= SCON_RI;
+    }
+
+    // 模式0写 SBUF：开始移出8位数据（移位进行中的写入被丢弃）
+    fn serial_shift_start_tx(&mut self, value: u8) {
+        if self.serial.shift_bits_remaining > 0 {
+            self.serial.tx_collisions += 1;
+            if self.debug {
+                debug!("[串口] 移位未完成，忽略写入 {:#04x}（发送冲突）", value);
+            }
+            return;
+        }
+        self.serial.shift_data = value;
+        self.serial.shift_receiving = false;
+        self.serial.shift_bits_remaining = MODE0_SHIFT_BITS;
+        self.serial.tx_busy = true;
+        self.serial.tx_data = value;
+
+        if self.debug {
+            debug!("[串口] 模式0开始移出 {:#04x}", value);
+        }
+    }
+
+    // 模式0移位（每个机器周期调用一次）
+    // 空闲时 REN=1 且 RI=0 即开始接收；每个周期先在 RXD 上给出/采样数据，
+    // 再在 TXD 上输出一个时钟脉冲（外部器件在上升沿锁存），8位结束后置位 TI 或 RI
+    pub(crate) fn serial_machine_cycle(&mut self) {
+        match self.serial_mode() {
+            0 => {}
+            2 => return self.serial_mode2_cycle(),
+            _ => return,
+        }
+
+        if self.serial.shift_bits_remaining == 0 {
+            let scon = self.sfr[(SCON - 0x80) as usize];
+            if (scon & SCON_REN) == 0 || (scon & SCON_RI) != 0 {
+                return;
+            }
+            self.serial.shift_data = 0;
+            self.serial.shift_receiving = true;
+            self.serial.shift_bits_remaining = MODE0_SHIFT_BITS;
+            self.serial.rx_busy = true;
+        }
+
+        let index = MODE0_SHIFT_BITS - self.serial.shift_bits_remaining;
+        if self.serial.shift_receiving {
+            self.notify_pin(3, TXD_PIN, false);
+            self.notify_pin(3, TXD_PIN, true);
+            if self.sample_pin(3, RXD_PIN) {
+                self.serial.shift_data |= 1 << index;
+            }
+        } else {
+            let bit = (self.serial.shift_data >> index) & 1 != 0;
+            self.notify_pin(3, RXD_PIN, bit);
+            self.notify_pin(3, TXD_PIN, false);
+            self.notify_pin(3, TXD_PIN, true);
+        }
+
+        self.serial.shift_bits_remaining -= 1;
+        if self.serial.shift_bits_remaining > 0 {
+            return;
+        }
+
+        if self.serial.shift_receiving {
+            self.serial.rx_busy = false;
+            self.serial.rx_buffer = self.serial.shift_data;
+            self.sfr[(SCON - 0x80) as usize] |= SCON_RI;
+        } else {
+            // 移位结束后 RXD 回到高电平
+            self.notify_pin(3, RXD_PIN, true);
+            self.serial.tx_busy = false;
+            self.sfr[(SCON - 0x80) as usize] |= SCON_TI;
+        }
+    }
+
+    // 距离下一次不由定时器驱动的串口事件还有多少个机器周期（没有返回0，用于快进优化）
+    // 模式0为移位剩余的位数；模式2在收发进行中或有待接收的数据时为下一个位边界
+    pub(crate) fn cycles_until_serial_event(&self) -> u64 {
+        match self.serial_mode() {
+            0 => self.serial.shift_bits_remaining as u64,
+            2 if self.serial.tx_busy || self.serial.rx_busy || !self.serial.rx_pending.is_empty() => {
+                let clocks = self.mode2_clocks_per_bit() - self.serial.mode2_clocks;
+                clocks.div_ceil(12) as u64
+            }
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::peripherals::PinDevice;
+    use crate::emulator::{Emulator, HaltReason};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::test_support::{capture_serial, emulator_with_blocks, load, run_steps};
+
+    // MOV TMOD,#0x20; MOV TH1,#th1; MOV TL1,#th1; MOV PCON,#pcon; MOV SCON,#0x40; SETB TR1
+    // 循环: MOV SBUF,#0x41; JNB TI,$; CLR TI; SJMP 循环
+    fn transmit_loop(clock: u32, th1: u8, pcon: u8) -> Emulator {
+        let program = [
+            0x75, 0x89, 0x20, 0x75, 0x8D, th1, 0x75, 0x8B, th1, 0x75, 0x87, pcon, 0x75, 0x98, 0x40, 0xD2, 0x8E,
+            0x75, 0x99, 0x41, 0x30, 0x99, 0xFD, 0xC2, 0x99, 0x80, 0xF6,
+        ];
+        let mut emulator = Emulator::builder().clock(clock).build();
+        emulator.cpu.set_serial_sink(Box::new(std::io::sink()));
+        load(&mut emulator, &program);
+        emulator
+    }
+
+    // 写 SBUF 的机器周期（第一条 MOV SBUF 执行完时）和之后 count 次 TI 置位的机器周期
+    fn ti_events(emulator: &mut Emulator, count: usize) -> (u64, Vec<u64>) {
+        let mut written = None;
+        let mut events = Vec::new();
+        let mut ti = false;
+        while events.len() < count {
+            let pc = emulator.cpu.registers.pc;
+            emulator.step();
+            if pc == 0x0011 && written.is_none() {
+                written = Some(emulator.clock_cycles / 12);
+            }
+            let now = emulator.cpu.peek_sfr(SCON) & SCON_TI != 0;
+            if now && !ti {
+                events.push(emulator.clock_cycles / 12);
+            }
+            ti = now;
+        }
+        (written.unwrap(), events)
+    }
+
+    // 两次 TI 之间的机器周期数
+    fn gaps(events: &[u64]) -> Vec<u64> {
+        events.windows(2).map(|pair| pair[1] - pair[0]).collect()
+    }
+
+    // TI 要等 JNB TI,$（2 个机器周期）执行完才看得到，单次间隔可能差 1 个机器周期，但不会累积
+    fn assert_frame_gaps(events: &[u64], frame: u64) {
+        for gap in gaps(events) {
+            assert!(gap.abs_diff(frame) <= 1, "帧间隔 {} 个机器周期", gap);
+        }
+        let total = events[events.len() - 1] - events[0];
+        assert!(total.abs_diff(frame * (events.len() as u64 - 1)) <= 1, "{} 帧共 {} 个机器周期", events.len() - 1, total);
+    }
+
+    #[test]
+    fn timer1_reload_fd_at_11_0592mhz_is_9600_baud() {
+        let mut emulator = transmit_loop(11_059_200, 0xFD, 0);
+        let (_, events) = ti_events(&mut emulator, 4);
+        let baud = emulator.cpu.serial_baud_rate().unwrap();
+        assert!((baud - 9600.0).abs() < 96.0, "波特率 {}", baud);
+        // 一帧 10 位，每位 32 次溢出，每次溢出 3 个机器周期
+        for gap in gaps(&events) {
+            assert!(gap.abs_diff(960) <= 9, "帧间隔 {} 个机器周期", gap);
+        }
+    }
+
+    #[test]
+    fn ti_events_are_one_frame_apart_at_2404_baud() {
+        // 12MHz、TH1=0xF3：每次溢出 13 个机器周期，一位 32 次溢出，一帧 10 位
+        let mut emulator = transmit_loop(12_000_000, 0xF3, 0);
+        let (_, events) = ti_events(&mut emulator, 4);
+        assert_eq!(emulator.cpu.serial_baud_rate().map(|baud| baud.round()), Some(2404.0));
+        assert_frame_gaps(&events, 4160);
+    }
+
+    #[test]
+    fn smod_halves_the_frame_time() {
+        let mut emulator = transmit_loop(12_000_000, 0xF3, PCON_SMOD);
+        let (_, events) = ti_events(&mut emulator, 3);
+        assert_eq!(emulator.cpu.serial_baud_rate().map(|baud| baud.round()), Some(4808.0));
+        assert_frame_gaps(&events, 2080);
+    }
+
+    #[test]
+    fn baud_rate_needs_timer1_running() {
+        // SETB TR1 是第 6 条指令
+        let mut emulator = transmit_loop(11_059_200, 0xFD, 0);
+        for _ in 0..5 {
+            emulator.step();
+        }
+        assert_eq!(emulator.cpu.serial_baud_rate(), None);
+        emulator.step();
+        assert!(emulator.cpu.serial_baud_rate().is_some());
+        emulator.cpu.write_sfr(TCON, 0);
+        assert_eq!(emulator.cpu.serial_baud_rate(), None);
+    }
+
+    #[test]
+    fn sbuf_write_sets_ti_one_frame_later() {
+        let mut emulator = transmit_loop(11_059_200, 0xFD, 0);
+        let (written, events) = ti_events(&mut emulator, 1);
+        // 写入后要等到位时钟的下一个边界才开始发送，所以最多多出一位（96 个机器周期）
+        let cost = events[0] - written;
+        assert!((960 - 96..=960 + 96).contains(&cost), "发送一个字节用了 {} 个机器周期", cost);
+        assert!(!emulator.cpu.serial.tx_pending);
+    }
+
+    // MOV TMOD,#0x20; MOV TH1,#0xFD; MOV SCON,#0x40; SETB TR1; 然后对 "Hello" 的每个字符: MOV A,#字符; LCALL 0x0030
+    // 最后 SJMP $；发送子程序 (0x0030): MOV SBUF,A; JNB TI,$; CLR TI; RET
+    const HELLO_HEX: &str = "\
+:10000000758920758DFD759840D28E744812003028
+:100010007465120030746C120030746C120030740D
+:060020006F12003080FEAB
+:08003000F5993099FDC29922F7
+:00000001FF
+";
+
+    #[test]
+    fn hello_program_prints_hello() {
+        let mut emulator = Emulator::new(false);
+        emulator.load_hex(HELLO_HEX).unwrap();
+        let output = capture_serial(&mut emulator);
+        let mut ti_count = 0;
+        let mut ti = false;
+        // 运行到最后的 SJMP $ (0x0024)
+        while emulator.cpu.registers.pc != 0x0024 {
+            assert_eq!(emulator.step(), HaltReason::Running);
+            let now = emulator.cpu.peek_sfr(SCON) & SCON_TI != 0;
+            if now && !ti {
+                ti_count += 1;
+            }
+            ti = now;
+        }
+        assert_eq!(output.borrow().as_slice(), b"Hello");
+        assert_eq!(ti_count, 5);
+    }
+
+    // MOV TMOD,#0x20; MOV TH1,#0xFD; MOV SCON,#0x50（模式1，REN）; SETB TR1
+    // 循环 (0x000B): JNB RI,$; MOV A,SBUF; CLR RI; MOV SBUF,A; JNB TI,$; CLR TI; SJMP 循环
+    const ECHO: [u8; 27] = [
+        0x75, 0x89, 0x20, 0x75, 0x8D, 0xFD, 0x75, 0x98, 0x50, 0xD2, 0x8E, 0x30, 0x98, 0xFD, 0xE5, 0x99, 0xC2, 0x98, 0xF5,
+        0x99, 0x30, 0x99, 0xFD, 0xC2, 0x99, 0x80, 0xF0,
+    ];
+
+    #[test]
+    fn received_bytes_are_echoed_back() {
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        load(&mut emulator, &ECHO);
+        let output = capture_serial(&mut emulator);
+        emulator.cpu.serial_receive(b"AT+1\r");
+        while output.borrow().len() < 5 {
+            assert_eq!(emulator.step(), HaltReason::Running);
+            assert!(emulator.clock_cycles < 12 * 20_000, "已回显 {:?}", output.borrow());
+        }
+        assert_eq!(output.borrow().as_slice(), b"AT+1\r");
+        assert_eq!(emulator.cpu.serial.rx_overruns, 0);
+    }
+
+    #[test]
+    fn byte_arriving_while_ri_is_set_is_discarded() {
+        // 只做初始化，然后 SJMP $：固件从不读 SBUF
+        let mut program = ECHO[..11].to_vec();
+        program.extend([0x80, 0xFE]);
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        load(&mut emulator, &program);
+        emulator.cpu.serial_receive(b"xy");
+        while emulator.clock_cycles < 12 * 3_000 {
+            emulator.step();
+        }
+        assert_ne!(emulator.cpu.peek_sfr(SCON) & SCON_RI, 0);
+        assert_eq!(emulator.cpu.serial_read_sbuf(), b'x');
+        assert_eq!(emulator.cpu.serial.rx_overruns, 1);
+    }
+
+    // 移位寄存器（如 74HC595）：在 TXD 的上升沿锁存 RXD 上的数据位
+    struct ShiftCapture {
+        data: bool,
+        bits: Rc<RefCell<Vec<bool>>>,
+    }
+
+    impl PinDevice for ShiftCapture {
+        fn pin_changed(&mut self, port: u8, bit: u8, high: bool) {
+            match (port, bit) {
+                (3, RXD_PIN) => self.data = high,
+                (3, TXD_PIN) if high => self.bits.borrow_mut().push(self.data),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn mode0_shifts_byte_out_lsb_first() {
+        // MOV SCON,#0x00（模式0）; MOV SBUF,#0xA5; JNB TI,$; SJMP $
+        let mut emulator = Emulator::new(false);
+        load(&mut emulator, &[0x75, 0x98, 0x00, 0x75, 0x99, 0xA5, 0x30, 0x99, 0xFD, 0x80, 0xFE]);
+        let bits = Rc::new(RefCell::new(Vec::new()));
+        emulator.cpu.attach_device(Box::new(ShiftCapture { data: true, bits: bits.clone() }));
+        while emulator.cpu.registers.pc != 0x0009 {
+            emulator.step();
+            assert!(emulator.clock_cycles < 12 * 100);
+        }
+
+        let bits = bits.borrow();
+        assert_eq!(*bits, [true, false, true, false, false, true, false, true]);
+        let byte = bits.iter().enumerate().fold(0u8, |byte, (index, &bit)| byte | (u8::from(bit) << index));
+        assert_eq!(byte, 0xA5);
+        assert_ne!(emulator.cpu.peek_sfr(SCON) & SCON_TI, 0);
+    }
+
+    // MOV TMOD,#0x20; MOV TH1,#0xFD; MOV SCON,#0xF0（模式3，SM2，REN）; SETB TR1; SJMP $
+    // 送入 words，运行 4000 个机器周期（每帧 11 位约 1056 个机器周期）
+    fn sm2_receiver(words: &[u16]) -> Emulator {
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        load(&mut emulator, &[0x75, 0x89, 0x20, 0x75, 0x8D, 0xFD, 0x75, 0x98, 0xF0, 0xD2, 0x8E, 0x80, 0xFE]);
+        emulator.cpu.serial_receive_9bit(words);
+        while emulator.clock_cycles < 12 * 4_000 {
+            emulator.step();
+        }
+        emulator
+    }
+
+    #[test]
+    fn sm2_receiver_ignores_data_frames() {
+        let emulator = sm2_receiver(&[0x42, 0x43]);
+        assert_eq!(emulator.cpu.peek_sfr(SCON) & SCON_RI, 0);
+        assert_eq!(emulator.cpu.serial_read_sbuf(), 0x00);
+    }
+
+    #[test]
+    fn address_frame_wakes_sm2_receiver() {
+        let emulator = sm2_receiver(&[0x42, NINTH_BIT | 0x07]);
+        let scon = emulator.cpu.peek_sfr(SCON);
+        assert_ne!(scon & SCON_RI, 0);
+        assert_ne!(scon & SCON_RB8, 0);
+        assert_eq!(emulator.cpu.serial_read_sbuf(), 0x07);
+    }
+
+    // 串口初始化（模式1，REN，9600 波特）之后是 main，main 从 0x000B 开始
+    fn uart_program(main: &[u8]) -> (Emulator, Rc<RefCell<Vec<u8>>>) {
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        load(&mut emulator, &[&ECHO[..11], main].concat());
+        let output = capture_serial(&mut emulator);
+        (emulator, output)
+    }
+
+    fn run_until_pc(emulator: &mut Emulator, pc: u16) {
+        while emulator.cpu.registers.pc != pc {
+            emulator.step();
+            assert!(emulator.clock_cycles < 12 * 10_000);
+        }
+    }
+
+    #[test]
+    fn sbuf_write_during_data_bits_is_dropped() {
+        // MOV SBUF,#'A'; MOV SBUF,#'B'; JNB TI,$; SJMP $
+        let (mut emulator, output) = uart_program(&[0x75, 0x99, b'A', 0x75, 0x99, b'B', 0x30, 0x99, 0xFD, 0x80, 0xFE]);
+        run_until_pc(&mut emulator, 0x0014);
+        assert_eq!(output.borrow().as_slice(), b"A");
+        assert_eq!(emulator.cpu.serial.tx_collisions, 1);
+    }
+
+    #[test]
+    fn sbuf_write_during_stop_bit_is_sent_next() {
+        // MOV SBUF,#'A'; JNB TI,$; MOV SBUF,#'B'; CLR TI; JNB TI,$; SJMP $
+        let main = [0x75, 0x99, b'A', 0x30, 0x99, 0xFD, 0x75, 0x99, b'B', 0xC2, 0x99, 0x30, 0x99, 0xFD, 0x80, 0xFE];
+        let (mut emulator, output) = uart_program(&main);
+        run_until_pc(&mut emulator, 0x0019);
+        assert_eq!(output.borrow().as_slice(), b"AB");
+        assert_eq!(emulator.cpu.serial.tx_collisions, 0);
+    }
+
+    #[test]
+    fn next_frame_is_kept_when_ri_is_cleared_in_time() {
+        // JNB RI,$; MOV R7,#50; DJNZ R7,$（100 个机器周期）; CLR RI; JNB RI,$; SJMP $
+        let main = [0x30, 0x98, 0xFD, 0x7F, 0x32, 0xDF, 0xFE, 0xC2, 0x98, 0x30, 0x98, 0xFD, 0x80, 0xFE];
+        let (mut emulator, _) = uart_program(&main);
+        emulator.cpu.serial_receive(b"xy");
+        // RI 还没有清除，第二帧已经在移入
+        run_until_pc(&mut emulator, 0x0012);
+        assert!(emulator.cpu.serial.rx_busy);
+        assert_ne!(emulator.cpu.peek_sfr(SCON) & SCON_RI, 0);
+        assert_eq!(emulator.cpu.serial_read_sbuf(), b'x');
+        run_until_pc(&mut emulator, 0x0017);
+        assert_eq!(emulator.cpu.serial_read_sbuf(), b'y');
+        assert_eq!(emulator.cpu.serial.rx_overruns, 0);
+    }
+
+    #[test]
+    fn ti_is_not_cleared_by_interrupt_entry() {
+        // 0x0000: LJMP 0x0030；串口服务程序 (0x0023)：SJMP $（不清除 TI）
+        // 0x0030: 串口初始化; MOV IE,#0x90; MOV SBUF,#'A'; SJMP $
+        let main = [&ECHO[..11], &[0x75, 0xA8, 0x90, 0x75, 0x99, b'A', 0x80, 0xFE]].concat();
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x0023, &[0x80, 0xFE]), (0x0030, &main)]);
+        emulator.cpu.set_serial_sink(Box::new(std::io::sink()));
+        run_until_pc(&mut emulator, 0x0023);
+        run_steps(&mut emulator, 3);
+        assert_ne!(emulator.cpu.peek_sfr(SCON) & SCON_TI, 0);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+    }
+}
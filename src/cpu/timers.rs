@@ -0,0 +1,332 @@
+// 8051 定时器模块
+// 实现定时器0/1的四种工作模式，定时器1溢出同时驱动串口波特率时钟
+// 8052 型号另有定时器2（16位自动重装/捕获，T2EX 引脚 P1.1 可触发捕获或重装）
+
+use super::peripherals::{RCAP2H, RCAP2L, SCON, T2CON, TCON, TH0, TH1, TH2, TL0, TL1, TL2, TMOD};
+use super::serial::{SCON_REN, SCON_RI};
+use super::CPU;
+
+// TCON 位定义
+pub const TF1: u8 = 0x80; // 定时器1溢出标志
+pub const TR1: u8 = 0x40; // 定时器1运行控制
+pub const TF0: u8 = 0x20; // 定时器0溢出标志
+pub const TR0: u8 = 0x10; // 定时器0运行控制
+
+// T2CON 位定义
+pub const T2CON_TF2: u8 = 0x80;   // 定时器2溢出标志（需软件清除）
+pub const T2CON_EXF2: u8 = 0x40;  // 定时器2外部标志：T2EX 下降沿触发捕获/重装（需软件清除）
+const T2CON_EXEN2: u8 = 0x08;     // 允许 T2EX 下降沿触发捕获/重装
+pub const T2CON_TR2: u8 = 0x04;   // 定时器2运行控制
+const T2CON_CT2: u8 = 0x02;       // 计数器模式
+const T2CON_CP_RL2: u8 = 0x01;    // 1=捕获模式，0=自动重装模式
+
+// TMOD 每个定时器占4位：GATE | C/T | M1 | M0
+const TMOD_CT: u8 = 0x04; // 计数器模式（对外部引脚计数）
+
+impl CPU {
+    // 更新定时器（每个机器周期调用一次）
+    pub fn update_timers(&mut self) {
+        let tmod = self.sfr[(TMOD - 0x80) as usize];
+        let tcon = self.sfr[(TCON - 0x80) as usize];
+        let t0_mode = tmod & 0x03;
+        let t1_mode = (tmod >> 4) & 0x03;
+        let t0_timing = (tmod & TMOD_CT) == 0;
+        let t1_timing = ((tmod >> 4) & TMOD_CT) == 0;
+
+        // 定时器0更新
+        if t0_mode == 3 {
+            // 模式3：TL0 为受 TR0 控制的8位定时器，TH0 为受 TR1 控制的8位定时器并占用 TF1
+            if (tcon & TR0) != 0 && t0_timing {
+                let tl0 = self.read_timer_byte(TL0).wrapping_add(1);
+                self.write_timer_byte(TL0, tl0);
+                if tl0 == 0 {
+                    self.sfr[(TCON - 0x80) as usize] |= TF0;
+                }
+            }
+            if (tcon & TR1) != 0 {
+                let th0 = self.read_timer_byte(TH0).wrapping_add(1);
+                self.write_timer_byte(TH0, th0);
+                if th0 == 0 {
+                    self.sfr[(TCON - 0x80) as usize] |= TF1;
+                }
+            }
+        } else if (tcon & TR0) != 0 && t0_timing && self.tick_timer(0, t0_mode) {
+            self.sfr[(TCON - 0x80) as usize] |= TF0;
+        }
+
+        // 定时器1更新（模式3下定时器1停止计数）
+        // 定时器0处于模式3时 TR1 被 TH0 占用，定时器1持续运行，只能作为波特率发生器使用
+        let t1_running = if t0_mode == 3 { true } else { (tcon & TR1) != 0 };
+        if t1_mode != 3 && t1_running && t1_timing && self.tick_timer(1, t1_mode) {
+            if t0_mode != 3 {
+                self.sfr[(TCON - 0x80) as usize] |= TF1;
+            }
+            // 定时器1溢出推进串口的波特率时钟
+            self.serial_timer1_overflow();
+        }
+
+        if self.variant.has_timer2() {
+            self.update_timer2();
+        }
+
+        // 串口模式0/2的时钟来自振荡器而不是定时器1
+        self.serial_machine_cycle();
+
+        self.update_watchdog();
+        self.update_adc();
+    }
+
+    // 定时器2更新（仅 8052）
+    // 自动重装模式：溢出时从 RCAP2H/RCAP2L 重装 TH2/TL2；EXEN2=1 时 T2EX 下降沿也触发重装
+    // 捕获模式：16位自由计数，溢出后从0继续；EXEN2=1 时 T2EX 下降沿把 TH2/TL2 捕获到 RCAP2H/RCAP2L
+    // 溢出置位 TF2，T2EX 触发置位 EXF2，两者都不会在进入中断时被硬件清除
+    fn update_timer2(&mut self) {
+        let t2con = self.read_timer_byte(T2CON);
+
+        // T2EX 引脚的下降沿检测与定时器是否运行无关
+        let t2ex = self.pin_level(1, 1);
+        let t2ex_fell = self.t2ex_pin_last && !t2ex;
+        self.t2ex_pin_last = t2ex;
+        if t2ex_fell && (t2con & T2CON_EXEN2) != 0 {
+            if (t2con & T2CON_CP_RL2) != 0 {
+                let th2 = self.read_timer_byte(TH2);
+                let tl2 = self.read_timer_byte(TL2);
+                self.write_timer_byte(RCAP2H, th2);
+                self.write_timer_byte(RCAP2L, tl2);
+            } else {
+                let th2 = self.read_timer_byte(RCAP2H);
+                let tl2 = self.read_timer_byte(RCAP2L);
+                self.write_timer_byte(TH2, th2);
+                self.write_timer_byte(TL2, tl2);
+            }
+            self.sfr[(T2CON - 0x80) as usize] |= T2CON_EXF2;
+        }
+
+        if (t2con & T2CON_TR2) == 0 || (t2con & T2CON_CT2) != 0 {
+            return;
+        }
+
+        let count = (((self.read_timer_byte(TH2) as u16) << 8) | self.read_timer_byte(TL2) as u16)
+            .wrapping_add(1);
+        if count == 0 {
+            if (t2con & T2CON_CP_RL2) == 0 {
+                let th2 = self.read_timer_byte(RCAP2H);
+                let tl2 = self.read_timer_byte(RCAP2L);
+                self.write_timer_byte(TH2, th2);
+                self.write_timer_byte(TL2, tl2);
+            } else {
+                self.write_timer_byte(TH2, 0);
+                self.write_timer_byte(TL2, 0);
+            }
+            self.sfr[(T2CON - 0x80) as usize] |= T2CON_TF2;
+        } else {
+            self.write_timer_byte(TH2, (count >> 8) as u8);
+            self.write_timer_byte(TL2, (count & 0xFF) as u8);
+        }
+    }
+
+    // 按工作模式推进一个定时器，返回是否溢出
+    fn tick_timer(&mut self, timer: u8, mode: u8) -> bool {
+        let (tl_addr, th_addr) = if timer == 0 { (TL0, TH0) } else { (TL1, TH1) };
+        let tl = self.read_timer_byte(tl_addr);
+        let th = self.read_timer_byte(th_addr);
+
+        match mode {
+            0 => {
+                // 模式0：13位定时器，TL 的低5位作为预分频
+                let count = (((th as u16) << 5) | (tl as u16 & 0x1F)).wrapping_add(1) & 0x1FFF;
+                self.write_timer_byte(th_addr, (count >> 5) as u8);
+                self.write_timer_byte(tl_addr, (tl & 0xE0) | (count as u8 & 0x1F));
+                count == 0
+            }
+            1 => {
+                // 模式1：16位定时器/计数器
+                let count = (((th as u16) << 8) | (tl as u16)).wrapping_add(1);
+                self.write_timer_byte(th_addr, (count >> 8) as u8);
+                self.write_timer_byte(tl_addr, (count & 0xFF) as u8);
+                count == 0
+            }
+            _ => {
+                // 模式2：8位自动重装，TL 溢出时从 TH 重装
+                let new_tl = tl.wrapping_add(1);
+                if new_tl == 0 {
+                    self.write_timer_byte(tl_addr, th);
+                    true
+                } else {
+                    self.write_timer_byte(tl_addr, new_tl);
+                    false
+                }
+            }
+        }
+    }
+
+    fn read_timer_byte(&self, address: u8) -> u8 {
+        self.sfr[(address - 0x80) as usize]
+    }
+
+    fn write_timer_byte(&mut self, address: u8, value: u8) {
+        self.sfr[(address - 0x80) as usize] = value;
+    }
+
+    // 获取定时器还需要多少个周期才会溢出（用于快进优化）
+    // 返回0表示定时器未运行或已经溢出
+    pub fn get_cycles_until_timer_overflow(&self) -> u64 {
+        let tmod = self.sfr[(TMOD - 0x80) as usize];
+        let tcon = self.sfr[(TCON - 0x80) as usize];
+        let t0_mode = tmod & 0x03;
+        let t1_mode = (tmod >> 4) & 0x03;
+
+        let mut nearest = 0u64;
+        let mut consider = |cycles: u64| {
+            if nearest == 0 || cycles < nearest {
+                nearest = cycles;
+            }
+        };
+
+        if (tcon & TR0) != 0 && (tmod & TMOD_CT) == 0 {
+            consider(self.cycles_until_overflow(TL0, TH0, t0_mode));
+        }
+        if t0_mode == 3 && (tcon & TR1) != 0 {
+            consider(0x100 - self.read_timer_byte(TH0) as u64);
+        }
+        let t1_running = if t0_mode == 3 { true } else { (tcon & TR1) != 0 };
+        if t1_mode != 3 && t1_running && ((tmod >> 4) & TMOD_CT) == 0 {
+            consider(self.cycles_until_overflow(TL1, TH1, t1_mode));
+        }
+        let t2con = self.read_timer_byte(T2CON);
+        if self.variant.has_timer2() && (t2con & T2CON_TR2) != 0 && (t2con & T2CON_CT2) == 0 {
+            consider(self.cycles_until_overflow(TL2, TH2, 1));
+        }
+        let serial = self.cycles_until_serial_event();
+        if serial > 0 {
+            consider(serial);
+        }
+        let watchdog = self.cycles_until_watchdog_reset();
+        if watchdog > 0 {
+            consider(watchdog);
+        }
+        let adc = self.cycles_until_adc_done();
+        if adc > 0 {
+            consider(adc);
+        }
+
+        nearest
+    }
+
+    // 定时器、串口、看门狗和 ADC 在之后的机器周期里都不会改变任何状态（用于快进优化：这样的周期可以一次跳过）
+    // 计数器模式和 T2EX 触发依赖引脚电平，只要允许就不算空闲；串口模式0允许接收且 RI 为0时会立即开始移位
+    pub(crate) fn peripherals_idle(&self) -> bool {
+        let tmod = self.sfr[(TMOD - 0x80) as usize];
+        let tcon = self.sfr[(TCON - 0x80) as usize];
+        let scon = self.sfr[(SCON - 0x80) as usize];
+        let t2con = self.read_timer_byte(T2CON);
+        let timers_stopped = (tcon & (TR0 | TR1)) == 0 && (tmod & 0x03) != 3;
+        let timer2_stopped = !self.variant.has_timer2() || (t2con & (T2CON_TR2 | T2CON_EXEN2)) == 0;
+        let mode0_idle = self.serial_mode() != 0 || (scon & SCON_REN) == 0 || (scon & SCON_RI) != 0;
+        timers_stopped && timer2_stopped && mode0_idle && self.get_cycles_until_timer_overflow() == 0
+    }
+
+    fn cycles_until_overflow(&self, tl_addr: u8, th_addr: u8, mode: u8) -> u64 {
+        let tl = self.read_timer_byte(tl_addr) as u64;
+        let th = self.read_timer_byte(th_addr) as u64;
+        match mode {
+            // 13位：从当前值到0x1FFF，再加1溢出
+            0 => 0x2000 - ((th << 5) | (tl & 0x1F)),
+            // 16位：从当前值到0xFFFF，再加1溢出到0
+            1 => 0x10000 - ((th << 8) | tl),
+            // 8位（模式2的 TL，或模式3的 TL0）
+            _ => 0x100 - tl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::Variant;
+    use crate::emulator::Emulator;
+    use crate::test_support::{load, run_steps};
+
+    fn emulator_8052(program: &[u8]) -> Emulator {
+        let mut emulator = Emulator::builder().variant(Variant::I8052).build();
+        load(&mut emulator, program);
+        emulator
+    }
+
+    #[test]
+    fn timer2_reloads_from_rcap2_on_overflow() {
+        // MOV RCAP2H,#0xFF; MOV RCAP2L,#0xF0; MOV TH2,#0xFF; MOV TL2,#0xFE; SETB TR2; NOP; NOP
+        let mut emulator =
+            emulator_8052(&[0x75, 0xCB, 0xFF, 0x75, 0xCA, 0xF0, 0x75, 0xCD, 0xFF, 0x75, 0xCC, 0xFE, 0xD2, 0xCA, 0x00, 0x00]);
+        run_steps(&mut emulator, 5);
+        assert_eq!(emulator.cpu.peek_sfr(TL2), 0xFF);
+        assert_eq!(emulator.cpu.peek_sfr(T2CON) & T2CON_TF2, 0);
+
+        // 第 2 个机器周期溢出：从 RCAP2 重装为 0xFFF0 并置位 TF2
+        run_steps(&mut emulator, 1);
+        assert_eq!((emulator.cpu.peek_sfr(TH2), emulator.cpu.peek_sfr(TL2)), (0xFF, 0xF0));
+        assert_ne!(emulator.cpu.peek_sfr(T2CON) & T2CON_TF2, 0);
+        run_steps(&mut emulator, 1);
+        assert_eq!(emulator.cpu.peek_sfr(TL2), 0xF1);
+    }
+
+    #[test]
+    fn t2ex_falling_edge_captures_timer2() {
+        // MOV T2CON,#0x0D（捕获模式、EXEN2、TR2）; NOP; NOP; NOP
+        let mut emulator = emulator_8052(&[0x75, 0xC8, 0x0D, 0x00, 0x00, 0x00]);
+        run_steps(&mut emulator, 3);
+        emulator.cpu.set_pin(1, 1, false);
+        run_steps(&mut emulator, 1);
+        // MOV T2CON 的 2 个机器周期和两条 NOP 共计数 4 次；下降沿在同一个机器周期里先于计数采样
+        assert_eq!((emulator.cpu.peek_sfr(RCAP2H), emulator.cpu.peek_sfr(RCAP2L)), (0x00, 0x04));
+        assert_ne!(emulator.cpu.peek_sfr(T2CON) & T2CON_EXF2, 0);
+        assert_eq!(emulator.cpu.peek_sfr(TL2), 0x05);
+    }
+
+    // 0x0030: MOV RCAP2H,#0xFF; MOV RCAP2L,#0xF0; MOV TH2,#0xFF; MOV TL2,#0xF0; MOV IE,#0xA0; SETB TR2; SJMP $
+    // 定时器2服务程序 (0x002B)：INC 0x40; clear 时 CLR TF2; RETI
+    // 运行 2000 个机器周期，返回每次进入服务程序时的时钟周期数
+    fn timer2_isr_entries(variant: Variant, clear: bool) -> (Emulator, Vec<u64>) {
+        let isr: &[u8] = if clear { &[0x05, 0x40, 0xC2, 0xCF, 0x32] } else { &[0x05, 0x40, 0x32] };
+        let main = [0x75, 0xCB, 0xFF, 0x75, 0xCA, 0xF0, 0x75, 0xCD, 0xFF, 0x75, 0xCC, 0xF0, 0x75, 0xA8, 0xA0, 0xD2, 0xCA, 0x80, 0xFE];
+        let mut emulator = Emulator::builder().variant(variant).build();
+        emulator.cpu.rom[..3].copy_from_slice(&[0x02, 0x00, 0x30]);
+        emulator.cpu.rom[0x2B..0x2B + isr.len()].copy_from_slice(isr);
+        emulator.cpu.rom[0x30..0x30 + main.len()].copy_from_slice(&main);
+        let mut entries = Vec::new();
+        while emulator.clock_cycles < 2000 * 12 {
+            emulator.step();
+            if emulator.cpu.registers.pc == 0x002B {
+                entries.push(emulator.clock_cycles);
+            }
+        }
+        (emulator, entries)
+    }
+
+    #[test]
+    fn timer2_interrupt_period_follows_rcap2() {
+        let (emulator, entries) = timer2_isr_entries(Variant::I8052, true);
+        assert!(entries.len() > 100);
+        // 从 0xFFF0 重装，每 16 个机器周期溢出一次；进入中断要等 SJMP 执行完，单次最多偏差 1 个机器周期
+        for (index, &entry) in entries.iter().enumerate() {
+            let expected = entries[0] + index as u64 * 16 * 12;
+            assert!(entry.abs_diff(expected) <= 12, "第 {} 次进入中断在 {}，应为 {}", index, entry, expected);
+        }
+        assert_eq!(emulator.cpu.ram[0x40] as usize, entries.len() % 256);
+    }
+
+    #[test]
+    fn timer2_isr_must_clear_tf2_itself() {
+        // 硬件不清除 TF2：RETI 之后执行一条指令就再次进入服务程序
+        let (emulator, entries) = timer2_isr_entries(Variant::I8052, false);
+        assert!(entries.windows(2).all(|pair| pair[1] - pair[0] < 16 * 12));
+        assert_ne!(emulator.cpu.peek_sfr(T2CON) & T2CON_TF2, 0);
+    }
+
+    #[test]
+    fn timer2_is_absent_on_8051() {
+        let (emulator, entries) = timer2_isr_entries(Variant::I8051, true);
+        assert!(entries.is_empty());
+        assert_eq!(emulator.cpu.interrupt_entries, 0);
+    }
+}
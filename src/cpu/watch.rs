@@ -0,0 +1,188 @@
+// 数据观察点
+// 指令读或写被观察的存储单元时记录一次命中（存储空间、地址、原值和新值），由 Emulator 在指令执行后取出并报告
+// 内部RAM、SFR 和外部数据存储器的访问都经过 read_internal/write_internal、read_indirect/write_indirect、
+// 寄存器和位操作、write_sfr 以及 read_xram/write_xram（MOVX @DPTR 和 MOVX @Ri），检查只在这里进行
+// 直接修改累加器、B、SP、DPTR 的指令（如 MOV A,#data、INC DPTR）不经过这些路径，不会命中 SFR 观察点
+
+use super::CPU;
+use crate::memory_dump::MemorySpace;
+
+// 观察的访问类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAccess {
+    Read,  // 读取
+    Write, // 写入（即使写入的值与原值相同）
+}
+
+impl WatchAccess {
+    pub fn name(self) -> &'static str {
+        match self {
+            WatchAccess::Read => "读",
+            WatchAccess::Write => "写",
+        }
+    }
+}
+
+// 一个观察点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub space: MemorySpace,
+    pub address: u16,
+    pub access: WatchAccess,
+}
+
+impl Watchpoint {
+    // 检查地址是否在存储空间范围内：内部RAM 0x00-0xFF，SFR 0x80-0xFF，外部数据存储器 0x0000-0xFFFF
+    pub fn new(space: MemorySpace, address: usize, access: WatchAccess) -> Result<Self, String> {
+        let valid = match space {
+            MemorySpace::Ram => address <= 0xFF,
+            MemorySpace::Sfr => (0x80..=0xFF).contains(&address),
+            MemorySpace::Xram => address <= 0xFFFF,
+        };
+        if !valid {
+            return Err(format!("地址 {:#X} 超出{}范围", address, space.name()));
+        }
+        Ok(Watchpoint { space, address: address as u16, access })
+    }
+
+    // 带存储空间前缀的地址，例如 i:0x35、s:0x90、x:0x1000
+    pub fn location(&self) -> String {
+        let width = if self.space == MemorySpace::Xram { 6 } else { 4 };
+        format!("{}:{:#0width$x}", self.space.prefix(), self.address, width = width)
+    }
+}
+
+// 一次命中：同一条指令多次访问同一单元只记录一次，原值取第一次访问前、新值取最后一次访问后
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watchpoint: Watchpoint,
+    pub old: u8, // 访问前的值
+    pub new: u8, // 访问后的值（读取时与原值相同）
+}
+
+impl CPU {
+    // 添加观察点，已经存在时返回 false
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        if self.watchpoints.contains(&watchpoint) {
+            return false;
+        }
+        self.watchpoints.push(watchpoint);
+        true
+    }
+
+    // 删除某个单元上的所有观察点，返回删除的个数
+    pub fn remove_watchpoints(&mut self, space: MemorySpace, address: u16) -> usize {
+        let before = self.watchpoints.len();
+        self.watchpoints.retain(|w| w.space != space || w.address != address);
+        before - self.watchpoints.len()
+    }
+
+    // 取出当前指令的命中
+    pub(crate) fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(self.watch_hits.get_mut())
+    }
+
+    // 记录读取（读路径只有 &self，命中列表用 RefCell）
+    pub(crate) fn watch_read(&self, space: MemorySpace, address: u16, value: u8) {
+        self.record_watch_hit(space, address, WatchAccess::Read, value, value);
+    }
+
+    // 记录写入
+    pub(crate) fn watch_write(&self, space: MemorySpace, address: u16, old: u8, new: u8) {
+        self.record_watch_hit(space, address, WatchAccess::Write, old, new);
+    }
+
+    fn record_watch_hit(&self, space: MemorySpace, address: u16, access: WatchAccess, old: u8, new: u8) {
+        let Some(&watchpoint) = self
+            .watchpoints
+            .iter()
+            .find(|w| w.space == space && w.address == address && w.access == access)
+        else {
+            return;
+        };
+        let mut hits = self.watch_hits.borrow_mut();
+        match hits.iter_mut().find(|hit| hit.watchpoint == watchpoint) {
+            Some(hit) => hit.new = new,
+            None => hits.push(WatchHit { watchpoint, old, new }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::{Emulator, HaltReason};
+    use crate::test_support::{emulator_with, run_steps};
+
+    fn watched(program: &[u8], watchpoint: Watchpoint) -> Emulator {
+        let mut emulator = emulator_with(program);
+        emulator.cpu.add_watchpoint(watchpoint);
+        emulator.watch_pause = true;
+        emulator
+    }
+
+    fn watchpoint(space: MemorySpace, address: usize, access: WatchAccess) -> Watchpoint {
+        Watchpoint::new(space, address, access).unwrap()
+    }
+
+    #[test]
+    fn direct_write_hits_once_with_old_and_new_values() {
+        // MOV 0x35,#0x11; INC 0x35; MOV 0x36,#0x01
+        let program = [0x75, 0x35, 0x11, 0x05, 0x35, 0x75, 0x36, 0x01];
+        let mut emulator = watched(&program, watchpoint(MemorySpace::Ram, 0x35, WatchAccess::Write));
+        assert_eq!(emulator.step(), HaltReason::WatchpointHit);
+        assert_eq!(emulator.watch_events.len(), 1);
+        assert_eq!((emulator.watch_events[0].hit.old, emulator.watch_events[0].hit.new), (0x00, 0x11));
+        assert_eq!(emulator.watch_events[0].pc, 0x0000);
+
+        // INC 读改写同一单元，只报告一次
+        assert_eq!(emulator.step(), HaltReason::WatchpointHit);
+        assert_eq!(emulator.watch_events.len(), 1);
+        assert_eq!((emulator.watch_events[0].hit.old, emulator.watch_events[0].hit.new), (0x11, 0x12));
+
+        assert_eq!(emulator.step(), HaltReason::Running);
+    }
+
+    #[test]
+    fn indirect_write_hits() {
+        // MOV R0,#0x35; MOV A,#0x33; MOV @R0,A
+        let program = [0x78, 0x35, 0x74, 0x33, 0xF6];
+        let mut emulator = watched(&program, watchpoint(MemorySpace::Ram, 0x35, WatchAccess::Write));
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.step(), HaltReason::WatchpointHit);
+        assert_eq!(emulator.watch_events[0].hit.new, 0x33);
+        assert_eq!(emulator.watch_events[0].pc, 0x0004);
+    }
+
+    #[test]
+    fn sfr_read_hits() {
+        // MOV A,P1
+        let mut emulator = watched(&[0xE5, 0x90], watchpoint(MemorySpace::Sfr, 0x90, WatchAccess::Read));
+        assert_eq!(emulator.step(), HaltReason::WatchpointHit);
+        assert_eq!(emulator.watch_events[0].hit.new, 0xFF);
+    }
+
+    #[test]
+    fn movx_hits_xram_watchpoints() {
+        // MOV DPTR,#0x1000; MOV A,#0x42; MOVX @DPTR,A; MOVX A,@DPTR
+        let program = [0x90, 0x10, 0x00, 0x74, 0x42, 0xF0, 0xE0];
+        let mut emulator = watched(&program, watchpoint(MemorySpace::Xram, 0x1000, WatchAccess::Write));
+        emulator.cpu.add_watchpoint(watchpoint(MemorySpace::Xram, 0x1000, WatchAccess::Read));
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.step(), HaltReason::WatchpointHit);
+        let hit = emulator.watch_events[0].hit;
+        assert_eq!((hit.watchpoint.access, hit.old, hit.new), (WatchAccess::Write, 0x00, 0x42));
+        assert_eq!(emulator.watch_events[0].to_string().split('，').next(), Some("[观察点] 写 x:0x1000: 0x00 -> 0x42"));
+
+        assert_eq!(emulator.step(), HaltReason::WatchpointHit);
+        let hit = emulator.watch_events[0].hit;
+        assert_eq!((hit.watchpoint.access, hit.new), (WatchAccess::Read, 0x42));
+    }
+
+    #[test]
+    fn rejects_addresses_outside_the_space() {
+        assert!(Watchpoint::new(MemorySpace::Ram, 0x100, WatchAccess::Write).is_err());
+        assert!(Watchpoint::new(MemorySpace::Sfr, 0x7F, WatchAccess::Read).is_err());
+        assert!(Watchpoint::new(MemorySpace::Xram, 0x10000, WatchAccess::Read).is_err());
+    }
+}
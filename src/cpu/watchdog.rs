@@ -0,0 +1,117 @@
+// 看门狗定时器（AT89S52）
+// 向 WDTRST 依次写入 0x1E、0xE1 启动看门狗，之后每次写入同样的序列即为喂狗；
+// 14位计数器每个机器周期加1，溢出时复位单片机。看门狗一旦启动只能由复位关闭
+
+use super::peripherals::WDTRST;
+use super::CPU;
+use log::debug;
+
+// 启动/喂狗序列
+const WDT_SEQ_FIRST: u8 = 0x1E;
+const WDT_SEQ_SECOND: u8 = 0xE1;
+
+// 14位计数器：从喂狗开始 16384 个机器周期后溢出
+pub const WDT_PERIOD: u32 = 1 << 14;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Watchdog {
+    pub enabled: bool,       // 看门狗是否已启动
+    pub counter: u32,        // 14位计数器
+    seq_armed: bool,         // 已写入序列的第一个字节
+    pub(crate) reset_pending: bool, // 计数器溢出，等待复位
+}
+
+impl CPU {
+    // 写 WDTRST：识别 0x1E、0xE1 序列，启动或喂狗
+    pub(crate) fn watchdog_write(&mut self, value: u8) {
+        if self.watchdog.seq_armed && value == WDT_SEQ_SECOND {
+            if self.debug && !self.watchdog.enabled {
+                debug!("[看门狗] 已启动");
+            }
+            self.watchdog.enabled = true;
+            self.watchdog.counter = 0;
+        }
+        self.watchdog.seq_armed = value == WDT_SEQ_FIRST;
+    }
+
+    // 喂狗：向 WDTRST 写入 0x1E、0xE1 序列，与程序执行的写入效果相同
+    // 第一次调用即启动看门狗；没有看门狗的型号上不起作用
+    pub fn feed_watchdog(&mut self) {
+        self.write_sfr(WDTRST, WDT_SEQ_FIRST);
+        self.write_sfr(WDTRST, WDT_SEQ_SECOND);
+    }
+
+    // 看门狗计数（每个机器周期调用一次）
+    pub(crate) fn update_watchdog(&mut self) {
+        if !self.watchdog.enabled {
+            return;
+        }
+        self.watchdog.counter += 1;
+        if self.watchdog.counter >= WDT_PERIOD {
+            self.watchdog.counter = 0;
+            self.watchdog.reset_pending = true;
+        }
+    }
+
+    // 看门狗距离溢出还剩多少个机器周期（未启动返回0，用于快进优化）
+    pub(crate) fn cycles_until_watchdog_reset(&self) -> u64 {
+        if self.watchdog.enabled {
+            (WDT_PERIOD - self.watchdog.counter) as u64
+        } else {
+            0
+        }
+    }
+
+    // 看门狗是否已经溢出、需要复位
+    pub fn watchdog_reset_pending(&self) -> bool {
+        self.watchdog.reset_pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::Variant;
+    use crate::emulator::{Emulator, HaltReason};
+    use crate::test_support::load;
+
+    // NOP; SJMP $：程序自己从不喂狗，复位后 PC 回到 0x0000
+    fn idle_s52() -> Emulator {
+        let mut emulator = Emulator::builder().variant(Variant::AT89S52).build();
+        load(&mut emulator, &[0x00, 0x80, 0xFE]);
+        emulator.cpu.feed_watchdog();
+        emulator
+    }
+
+    #[test]
+    fn host_feeding_keeps_the_program_running() {
+        let mut emulator = idle_s52();
+        let mut last_feed = 0;
+        while emulator.clock_cycles < 100_000 * 12 {
+            if emulator.clock_cycles - last_feed >= 10_000 * 12 {
+                emulator.cpu.feed_watchdog();
+                last_feed = emulator.clock_cycles;
+            }
+            assert_eq!(emulator.step(), HaltReason::Running);
+            assert_ne!(emulator.cpu.registers.pc, 0x0000);
+        }
+    }
+
+    #[test]
+    fn missing_feed_resets_pc_to_zero() {
+        let mut emulator = idle_s52();
+        emulator.step();
+        while emulator.cpu.registers.pc != 0x0000 {
+            assert_eq!(emulator.step(), HaltReason::Running);
+            assert!(emulator.clock_cycles <= (16384 + 2) * 12, "看门狗没有复位");
+        }
+        assert!(emulator.clock_cycles >= 16384 * 12);
+    }
+
+    #[test]
+    fn feeding_does_nothing_without_a_watchdog() {
+        let mut emulator = Emulator::new(false);
+        emulator.cpu.feed_watchdog();
+        assert!(!emulator.cpu.watchdog.enabled);
+        assert_eq!(emulator.cpu.cycles_until_watchdog_reset(), 0);
+    }
+}
@@ -0,0 +1,381 @@
+// 交互式调试器 - 命令行程序的 --interactive 模式
+// 每次停下时显示下一条指令的反汇编，然后读入一条命令：
+//   s [n]            单步执行 n 条指令（默认 1）
+//   c                继续运行直到停机
+//   r                显示 A、B、PSW、SP、DPTR、PC 和当前寄存器组的 R0-R7
+//   x <地址> [长度]  转储存储器，地址前缀 i: 内部RAM（默认）、s: SFR、x: 外部数据存储器
+//   u [地址] [条数]  反汇编程序存储器（默认从 PC 开始）
+//   b [地址]         设置断点（不带地址时列出所有断点）
+//   d <地址>         删除断点
+//   w [位置]         写观察点：指令写入该单元后停下（不带位置时列出所有观察点）
+//   rw <位置>        读观察点：指令读取该单元后停下
+//   dw <位置>        删除该单元上的观察点
+//   q                退出
+// 命令从任意 BufRead 读入、结果写到任意 Write，宿主程序和脚本可以用字符串驱动调试器
+
+use crate::cpu::watch::{WatchAccess, Watchpoint};
+use crate::disassembler;
+use crate::emulator::{Emulator, HaltReason};
+use crate::memory_dump::MemorySpace;
+use std::io::{self, BufRead, Write};
+
+// x 命令默认转储的字节数
+const DEFAULT_DUMP_LENGTH: usize = 64;
+
+// u 命令默认反汇编的指令条数
+const DEFAULT_DISASM_COUNT: usize = 10;
+
+// 一条调试命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Step(u64),                        // 单步执行 n 条指令
+    Continue,                         // 继续运行直到停机
+    Registers,                        // 显示寄存器
+    Examine(MemorySpace, usize, usize), // 转储存储器：空间、起始地址、长度
+    Disassemble(Option<u16>, usize),  // 反汇编：起始地址（None 表示 PC）、指令条数
+    Break(Option<u16>),               // 设置断点，None 表示列出所有断点
+    Delete(u16),                      // 删除断点
+    Watch(Option<Watchpoint>),        // 设置观察点，None 表示列出所有观察点
+    DeleteWatch(MemorySpace, u16),    // 删除某个单元上的观察点
+    Help,                             // 显示命令列表
+    Quit,                             // 退出
+}
+
+// 解析一行命令，格式错误时返回说明
+// 各命令也可以写全名：step、continue、regs、mem、disasm、break、delete、watch、rwatch、help、quit
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err("空命令".to_string());
+    };
+    let command = match name {
+        "s" | "step" => match words.next() {
+            None => Command::Step(1),
+            Some(count) => Command::Step(count.parse().map_err(|_| format!("无效的步数: {}", count))?),
+        },
+        "c" | "continue" => Command::Continue,
+        "r" | "regs" => Command::Registers,
+        "x" | "mem" => {
+            let address = words.next().ok_or("用法: x <地址> [长度]，地址前缀 i:/s:/x:")?;
+            let (space, address) = parse_location(address)?;
+            let length = match words.next() {
+                None => DEFAULT_DUMP_LENGTH,
+                Some(length) => parse_number(length).ok_or_else(|| format!("无效的长度: {}", length))?,
+            };
+            Command::Examine(space, address, length)
+        }
+        "u" | "disasm" => {
+            let address = match words.next() {
+                None => None,
+                Some(address) => Some(parse_code_address(address)?),
+            };
+            let count = match words.next() {
+                None => DEFAULT_DISASM_COUNT,
+                Some(count) => parse_number(count).ok_or_else(|| format!("无效的条数: {}", count))?,
+            };
+            Command::Disassemble(address, count)
+        }
+        "b" | "break" => match words.next() {
+            None => Command::Break(None),
+            Some(address) => Command::Break(Some(parse_code_address(address)?)),
+        },
+        "d" | "delete" => {
+            let address = words.next().ok_or("用法: d <地址>")?;
+            Command::Delete(parse_code_address(address)?)
+        }
+        "w" | "watch" => match words.next() {
+            None => Command::Watch(None),
+            Some(location) => Command::Watch(Some(parse_watchpoint(location, WatchAccess::Write)?)),
+        },
+        "rw" | "rwatch" => {
+            let location = words.next().ok_or("用法: rw <位置>，位置前缀 i:/s:/x:")?;
+            Command::Watch(Some(parse_watchpoint(location, WatchAccess::Read)?))
+        }
+        "dw" => {
+            let location = words.next().ok_or("用法: dw <位置>")?;
+            let watchpoint = parse_watchpoint(location, WatchAccess::Write)?;
+            Command::DeleteWatch(watchpoint.space, watchpoint.address)
+        }
+        "h" | "help" | "?" => Command::Help,
+        "q" | "quit" => Command::Quit,
+        _ => return Err(format!("未知命令: {}（输入 h 查看命令列表）", name)),
+    };
+    if let Some(extra) = words.next() {
+        return Err(format!("多余的参数: {}", extra));
+    }
+    Ok(command)
+}
+
+// 解析带存储空间前缀的地址，例如 i:0x30、s:0x90、x:0x1000，没有前缀时为内部RAM
+pub(crate) fn parse_location(text: &str) -> Result<(MemorySpace, usize), String> {
+    let (space, address) = match text.split_once(':') {
+        None => (MemorySpace::Ram, text),
+        Some(("i", address)) => (MemorySpace::Ram, address),
+        Some(("s", address)) => (MemorySpace::Sfr, address),
+        Some(("x", address)) => (MemorySpace::Xram, address),
+        Some((prefix, _)) => return Err(format!("未知的存储空间前缀: {}:（可选 i:、s:、x:）", prefix)),
+    };
+    let address = parse_number(address).ok_or_else(|| format!("无效的地址: {}", address))?;
+    Ok((space, address))
+}
+
+// 观察点的位置，格式与 x 命令的地址相同（命令行的 --watch-write/--watch-read 也用它）
+pub fn parse_watchpoint(text: &str, access: WatchAccess) -> Result<Watchpoint, String> {
+    let (space, address) = parse_location(text)?;
+    Watchpoint::new(space, address, access)
+}
+
+// 程序存储器地址 (0x0000-0xFFFF)
+pub(crate) fn parse_code_address(text: &str) -> Result<u16, String> {
+    parse_number(text)
+        .and_then(|address| u16::try_from(address).ok())
+        .ok_or_else(|| format!("无效的地址: {}", text))
+}
+
+// 0x 前缀的十六进制数或十进制数
+pub(crate) fn parse_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+// 运行交互式调试器，直到输入 q 或输入结束
+pub fn run<R: BufRead, W: Write>(emulator: &mut Emulator, input: R, output: &mut W) -> io::Result<()> {
+    // 观察点命中时回到提示符
+    emulator.watch_pause = true;
+    show_next_instruction(emulator, output)?;
+    prompt(output)?;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            prompt(output)?;
+            continue;
+        }
+        match parse_command(&line) {
+            Ok(Command::Quit) => break,
+            Ok(command) => execute(emulator, command, output)?,
+            Err(message) => writeln!(output, "{}", message)?,
+        }
+        prompt(output)?;
+    }
+    Ok(())
+}
+
+fn prompt<W: Write>(output: &mut W) -> io::Result<()> {
+    write!(output, "(mcs51) ")?;
+    output.flush()
+}
+
+// 执行一条命令（Quit 由调用方处理）
+fn execute<W: Write>(emulator: &mut Emulator, command: Command, output: &mut W) -> io::Result<()> {
+    match command {
+        Command::Step(count) => {
+            for _ in 0..count {
+                if emulator.step() != HaltReason::Running {
+                    break;
+                }
+            }
+            report_stop(emulator, output)?;
+        }
+        Command::Continue => {
+            while emulator.step() == HaltReason::Running {}
+            report_stop(emulator, output)?;
+        }
+        Command::Registers => show_registers(emulator, output)?,
+        Command::Examine(space, address, length) => {
+            let dump = emulator.dump_memory(space, address..address.saturating_add(length));
+            if dump.is_empty() {
+                writeln!(output, "地址超出{}范围", space.name())?;
+            } else {
+                write!(output, "{}", dump)?;
+            }
+        }
+        Command::Disassemble(address, count) => {
+            let mut address = address.unwrap_or(emulator.cpu.registers.pc);
+            for _ in 0..count {
+                let line = disassembler::disassemble(&emulator.cpu.rom, address, &emulator.symbols);
+                writeln!(output, "{:04X}: {:<9} {}", line.address, line.hex_bytes(), line.text)?;
+                address = address.wrapping_add(line.length as u16);
+            }
+        }
+        Command::Break(Some(address)) => {
+            emulator.breakpoints.insert(address);
+            writeln!(output, "断点设在 {:#06X}", address)?;
+        }
+        Command::Break(None) => {
+            let mut addresses: Vec<u16> = emulator.breakpoints.iter().copied().collect();
+            addresses.sort_unstable();
+            if addresses.is_empty() {
+                writeln!(output, "没有断点")?;
+            }
+            for address in addresses {
+                writeln!(output, "{:#06X}", address)?;
+            }
+        }
+        Command::Delete(address) => {
+            if emulator.breakpoints.remove(&address) {
+                writeln!(output, "已删除断点 {:#06X}", address)?;
+            } else {
+                writeln!(output, "{:#06X} 处没有断点", address)?;
+            }
+        }
+        Command::Watch(Some(watchpoint)) => {
+            if emulator.cpu.add_watchpoint(watchpoint) {
+                writeln!(output, "{}观察点设在 {}", watchpoint.access.name(), watchpoint.location())?;
+            } else {
+                writeln!(output, "{} 上已有{}观察点", watchpoint.location(), watchpoint.access.name())?;
+            }
+        }
+        Command::Watch(None) => {
+            if emulator.cpu.watchpoints.is_empty() {
+                writeln!(output, "没有观察点")?;
+            }
+            for watchpoint in &emulator.cpu.watchpoints {
+                writeln!(output, "{} {}", watchpoint.access.name(), watchpoint.location())?;
+            }
+        }
+        Command::DeleteWatch(space, address) => {
+            let location = format!("{}:{:#x}", space.prefix(), address);
+            if emulator.cpu.remove_watchpoints(space, address) > 0 {
+                writeln!(output, "已删除 {} 上的观察点", location)?;
+            } else {
+                writeln!(output, "{} 上没有观察点", location)?;
+            }
+        }
+        Command::Help => {
+            writeln!(output, "s [n]            单步执行 n 条指令（默认 1）")?;
+            writeln!(output, "c                继续运行直到停机")?;
+            writeln!(output, "r                显示寄存器")?;
+            writeln!(output, "x <地址> [长度]  转储存储器，前缀 i: 内部RAM（默认）、s: SFR、x: 外部数据存储器")?;
+            writeln!(output, "u [地址] [条数]  反汇编，默认从 PC 开始 10 条")?;
+            writeln!(output, "b [地址]         设置断点，不带地址时列出所有断点")?;
+            writeln!(output, "d <地址>         删除断点")?;
+            writeln!(output, "w [位置]         写观察点，位置前缀同 x 命令；不带位置时列出所有观察点")?;
+            writeln!(output, "rw <位置>        读观察点")?;
+            writeln!(output, "dw <位置>        删除该单元上的观察点")?;
+            writeln!(output, "q                退出")?;
+            writeln!(output, "命令也可以写全名：step、continue、regs、mem、disasm、break、delete、watch、rwatch、help、quit")?;
+        }
+        Command::Quit => {}
+    }
+    Ok(())
+}
+
+// 停下时显示停机原因（如果已经停机）和下一条指令
+pub(crate) fn report_stop<W: Write>(emulator: &Emulator, output: &mut W) -> io::Result<()> {
+    match emulator.halt_reason {
+        HaltReason::Running => {}
+        HaltReason::BreakpointHit => writeln!(output, "到达断点 {:#06X}", emulator.cpu.registers.pc)?,
+        HaltReason::UntilReached(address) => writeln!(output, "到达 until 地址 {:#06X}", address)?,
+        HaltReason::WatchpointHit => {
+            for event in &emulator.watch_events {
+                writeln!(output, "{}", event)?;
+            }
+        }
+        reason => writeln!(output, "已停机: {:?}", reason)?,
+    }
+    show_next_instruction(emulator, output)
+}
+
+// 显示下一条指令的反汇编
+pub fn show_next_instruction<W: Write>(emulator: &Emulator, output: &mut W) -> io::Result<()> {
+    let next = disassembler::disassemble(&emulator.cpu.rom, emulator.cpu.registers.pc, &emulator.symbols);
+    writeln!(output, "=> {:04X}: {:<9} {}", next.address, next.hex_bytes(), next.text)
+}
+
+// 显示寄存器：A、B、PSW、SP、DPTR、PC、当前寄存器组的 R0-R7 和 PSW 的各个标志
+pub fn show_registers<W: Write>(emulator: &Emulator, output: &mut W) -> io::Result<()> {
+    let cpu = &emulator.cpu;
+    let flags = cpu.psw_flags();
+    writeln!(
+        output,
+        "A={:02X} B={:02X} PSW={:02X} SP={:02X} DPTR={:04X} PC={:04X}",
+        cpu.registers.acc,
+        cpu.registers.b,
+        flags.to_byte(),
+        cpu.registers.sp,
+        cpu.registers.dptr,
+        cpu.registers.pc
+    )?;
+    let base = flags.bank as usize * 8;
+    let registers: Vec<String> = (0..8).map(|n| format!("R{}={:02X}", n, cpu.ram[base + n])).collect();
+    writeln!(output, "组{} {}", flags.bank, registers.join(" "))?;
+    writeln!(
+        output,
+        "CY={} AC={} F0={} OV={} P={}",
+        flags.cy as u8, flags.ac as u8, flags.f0 as u8, flags.ov as u8, flags.p as u8
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MOV A,#0x12; MOV 0x30,A; SETB RS0; MOV R2,#0x77; MOV DPTR,#0x1234; MOVX @DPTR,A; SJMP $
+    const PROGRAM: [u8; 15] = [0x74, 0x12, 0xF5, 0x30, 0xD2, 0xD3, 0x7A, 0x77, 0x90, 0x12, 0x34, 0xF0, 0x80, 0xFE, 0x00];
+
+    fn session(script: &str) -> (Emulator, String) {
+        let mut emulator = Emulator::new(false);
+        emulator.cpu.rom[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+        let mut output = Vec::new();
+        run(&mut emulator, script.as_bytes(), &mut output).unwrap();
+        (emulator, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn parses_short_and_long_commands() {
+        assert_eq!(parse_command("s"), Ok(Command::Step(1)));
+        assert_eq!(parse_command("step 4"), Ok(Command::Step(4)));
+        assert_eq!(parse_command("c"), Ok(Command::Continue));
+        assert_eq!(parse_command("r"), Ok(Command::Registers));
+        assert_eq!(parse_command("x 0x30"), Ok(Command::Examine(MemorySpace::Ram, 0x30, DEFAULT_DUMP_LENGTH)));
+        assert_eq!(parse_command("x s:0xD0 1"), Ok(Command::Examine(MemorySpace::Sfr, 0xD0, 1)));
+        assert_eq!(parse_command("x x:0x1234 2"), Ok(Command::Examine(MemorySpace::Xram, 0x1234, 2)));
+        assert_eq!(parse_command("q"), Ok(Command::Quit));
+        assert!(parse_command("x").is_err());
+        assert!(parse_command("x y:0x10").is_err());
+        assert!(parse_command("s many").is_err());
+        assert!(parse_command("r now").is_err());
+    }
+
+    #[test]
+    fn scripted_session_steps_and_inspects() {
+        let (emulator, output) = session("s 3\nr\nx i:0x30 1\ns 3\nr\nx x:0x1234 1\nq\ns\n");
+
+        // 开始时和每次单步之后显示下一条指令
+        assert!(output.starts_with("=> 0000: 74 12     mov A, #0x12\n(mcs51) "), "{}", output);
+        assert!(output.contains("=> 0006: 7A 77     mov R2, #0x77"), "{}", output);
+        assert!(output.contains("A=12 B=00 PSW=08 SP=07 DPTR=0000 PC=0006"), "{}", output);
+        // SETB RS0 之后 r 显示第 1 组寄存器
+        assert!(output.contains("组1 R0=00 R1=00 R2=77"), "{}", output);
+        assert!(output.contains("DPTR=1234 PC=000C"), "{}", output);
+        // 内部RAM和外部数据存储器的转储
+        assert!(output.contains("(mcs51) 0030: 12 "), "{}", output);
+        assert!(output.contains("(mcs51) 1234: 12 "), "{}", output);
+        // quit 之后的命令不再执行
+        assert_eq!(emulator.cpu.registers.pc, 0x000C);
+        assert_eq!(emulator.cpu.xram[0x1234], 0x12);
+    }
+
+    #[test]
+    fn continue_runs_to_halt_and_bad_commands_keep_the_prompt() {
+        let (emulator, output) = session("bogus\nc\nq\n");
+        assert!(output.contains("未知命令: bogus"));
+        assert!(output.contains("已停机: ProgramEnd"), "{}", output);
+        assert_eq!(emulator.cpu.registers.pc, 0x000C);
+    }
+
+    #[test]
+    fn breakpoint_commands_stop_continue_at_the_address() {
+        let (emulator, output) = session("b 0x0006\nb\nc\nr\nd 0x0006\nd 0x0006\nb\nq\n");
+        assert!(output.contains("断点设在 0x0006\n(mcs51) 0x0006\n"), "{}", output);
+        assert!(output.contains("到达断点 0x0006\n=> 0006: 7A 77     mov R2, #0x77"), "{}", output);
+        assert!(output.contains("A=12 B=00 PSW=08 SP=07 DPTR=0000 PC=0006"), "{}", output);
+        assert!(output.contains("已删除断点 0x0006"));
+        assert!(output.contains("0x0006 处没有断点"));
+        assert!(output.contains("没有断点"));
+        assert!(emulator.breakpoints.is_empty());
+    }
+}
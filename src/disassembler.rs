@@ -0,0 +1,254 @@
+// 反汇编器 - 把程序存储器中的机器码翻译成汇编文本
+// 独立于指令的执行实现，覆盖全部 255 条 MCS-51 指令，用于跟踪输出和反汇编列表
+
+use crate::symbols::{self, SymbolTable};
+
+// 一条反汇编结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub address: u16,   // 指令地址
+    pub length: u8,     // 指令长度（1-3字节）
+    pub bytes: Vec<u8>, // 指令的机器码
+    pub text: String,   // 汇编文本，例如 "mov A, #0x04"
+}
+
+impl Disassembly {
+    // 机器码的十六进制文本，例如 "74 04"
+    pub fn hex_bytes(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// 每个操作码的指令长度
+pub fn instruction_length(opcode: u8) -> u8 {
+    match opcode {
+        // 三字节指令
+        0x02 | 0x10 | 0x12 | 0x20 | 0x30 | 0x43 | 0x53 | 0x63 | 0x75 | 0x85 | 0x90 | 0xB4..=0xBF
+        | 0xD5 => 3,
+        // 两字节指令
+        0x01 | 0x11 | 0x21 | 0x31 | 0x41 | 0x51 | 0x61 | 0x71 | 0x81 | 0x91 | 0xA1 | 0xB1 | 0xC1
+        | 0xD1 | 0xE1 | 0xF1 => 2,
+        0x05 | 0x15 | 0x24 | 0x25 | 0x34 | 0x35 | 0x40 | 0x42 | 0x44 | 0x45 | 0x50 | 0x52 | 0x54
+        | 0x55 | 0x60 | 0x62 | 0x64 | 0x65 | 0x70 | 0x72 | 0x74 | 0x76 | 0x77 | 0x80 | 0x82
+        | 0x86 | 0x87 | 0x88..=0x8F | 0x92 | 0x94 | 0x95 | 0xA0 | 0xA2 | 0xA6 | 0xA7 | 0xA8..=0xAF
+        | 0xB0 | 0xB2 | 0xC0 | 0xC2 | 0xC5 | 0xD0 | 0xD2 | 0xD8..=0xDF | 0xE5 | 0xF5 => 2,
+        0x78..=0x7F => 2,
+        _ => 1,
+    }
+}
+
+// 反汇编 address 处的一条指令（地址超过 0xFFFF 时回绕）
+pub fn disassemble(code: &[u8], address: u16, symbols: &SymbolTable) -> Disassembly {
+    let byte = |offset: u16| -> u8 {
+        let index = address.wrapping_add(offset) as usize;
+        code.get(index).copied().unwrap_or(0xFF)
+    };
+
+    let opcode = byte(0);
+    let length = instruction_length(opcode);
+    let b1 = byte(1);
+    let b2 = byte(2);
+    let next_pc = address.wrapping_add(length as u16);
+
+    let label = |target: u16| symbols::format_address(symbols, target);
+    let rel = |offset: u8| label(next_pc.wrapping_add(offset as i8 as u16));
+    let addr11 = || label((next_pc & 0xF800) | (((opcode >> 5) as u16) << 8) | b1 as u16);
+    let addr16 = || label(((b1 as u16) << 8) | b2 as u16);
+    let reg = opcode & 0x07;
+    let ri = opcode & 0x01;
+
+    let text = match opcode {
+        0x00 => "nop".to_string(),
+        0x01 | 0x21 | 0x41 | 0x61 | 0x81 | 0xA1 | 0xC1 | 0xE1 => format!("ajmp {}", addr11()),
+        0x11 | 0x31 | 0x51 | 0x71 | 0x91 | 0xB1 | 0xD1 | 0xF1 => format!("acall {}", addr11()),
+        0x02 => format!("ljmp {}", addr16()),
+        0x12 => format!("lcall {}", addr16()),
+        0x03 => "rr A".to_string(),
+        0x04 => "inc A".to_string(),
+        0x05 => format!("inc {}", direct_name(b1)),
+        0x06 | 0x07 => format!("inc @R{}", ri),
+        0x08..=0x0F => format!("inc R{}", reg),
+        0x10 => format!("jbc {}, {}", bit_name(b1), rel(b2)),
+        0x13 => "rrc A".to_string(),
+        0x14 => "dec A".to_string(),
+        0x15 => format!("dec {}", direct_name(b1)),
+        0x16 | 0x17 => format!("dec @R{}", ri),
+        0x18..=0x1F => format!("dec R{}", reg),
+        0x20 => format!("jb {}, {}", bit_name(b1), rel(b2)),
+        0x22 => "ret".to_string(),
+        0x23 => "rl A".to_string(),
+        0x30 => format!("jnb {}, {}", bit_name(b1), rel(b2)),
+        0x32 => "reti".to_string(),
+        0x33 => "rlc A".to_string(),
+        0x40 => format!("jc {}", rel(b1)),
+        0x50 => format!("jnc {}", rel(b1)),
+        0x60 => format!("jz {}", rel(b1)),
+        0x70 => format!("jnz {}", rel(b1)),
+        0x80 => format!("sjmp {}", rel(b1)),
+        0x24..=0x2F | 0x34..=0x3F | 0x44..=0x4F | 0x54..=0x5F | 0x64..=0x6F | 0x94..=0x9F => {
+            let mnemonic = match opcode >> 4 {
+                0x2 => "add",
+                0x3 => "addc",
+                0x4 => "orl",
+                0x5 => "anl",
+                0x6 => "xrl",
+                _ => "subb",
+            };
+            format!("{} A, {}", mnemonic, source_operand(opcode, b1))
+        }
+        0x42 | 0x52 | 0x62 => format!("{} {}, A", logic_mnemonic(opcode), direct_name(b1)),
+        0x43 | 0x53 | 0x63 => {
+            format!("{} {}, #{:#04x}", logic_mnemonic(opcode), direct_name(b1), b2)
+        }
+        0x72 => format!("orl C, {}", bit_name(b1)),
+        0x82 => format!("anl C, {}", bit_name(b1)),
+        0xA0 => format!("orl C, /{}", bit_name(b1)),
+        0xB0 => format!("anl C, /{}", bit_name(b1)),
+        0x73 => "jmp @A+DPTR".to_string(),
+        0x74 => format!("mov A, #{:#04x}", b1),
+        0x75 => format!("mov {}, #{:#04x}", direct_name(b1), b2),
+        0x76 | 0x77 => format!("mov @R{}, #{:#04x}", ri, b1),
+        0x78..=0x7F => format!("mov R{}, #{:#04x}", reg, b1),
+        0x83 => "movc A, @A+PC".to_string(),
+        0x84 => "div AB".to_string(),
+        // MOV direct, direct 的编码是源地址在前、目的地址在后
+        0x85 => format!("mov {}, {}", direct_name(b2), direct_name(b1)),
+        0x86 | 0x87 => format!("mov {}, @R{}", direct_name(b1), ri),
+        0x88..=0x8F => format!("mov {}, R{}", direct_name(b1), reg),
+        0x90 => format!("mov DPTR, #{:#06x}", ((b1 as u16) << 8) | b2 as u16),
+        0x92 => format!("mov {}, C", bit_name(b1)),
+        0x93 => "movc A, @A+DPTR".to_string(),
+        0xA2 => format!("mov C, {}", bit_name(b1)),
+        0xA3 => "inc DPTR".to_string(),
+        0xA4 => "mul AB".to_string(),
+        0xA5 => "db 0xa5".to_string(), // 保留操作码
+        0xA6 | 0xA7 => format!("mov @R{}, {}", ri, direct_name(b1)),
+        0xA8..=0xAF => format!("mov R{}, {}", reg, direct_name(b1)),
+        0xB2 => format!("cpl {}", bit_name(b1)),
+        0xB3 => "cpl C".to_string(),
+        0xB4 => format!("cjne A, #{:#04x}, {}", b1, rel(b2)),
+        0xB5 => format!("cjne A, {}, {}", direct_name(b1), rel(b2)),
+        0xB6 | 0xB7 => format!("cjne @R{}, #{:#04x}, {}", ri, b1, rel(b2)),
+        0xB8..=0xBF => format!("cjne R{}, #{:#04x}, {}", reg, b1, rel(b2)),
+        0xC0 => format!("push {}", direct_name(b1)),
+        0xC2 => format!("clr {}", bit_name(b1)),
+        0xC3 => "clr C".to_string(),
+        0xC4 => "swap A".to_string(),
+        0xC5 => format!("xch A, {}", direct_name(b1)),
+        0xC6 | 0xC7 => format!("xch A, @R{}", ri),
+        0xC8..=0xCF => format!("xch A, R{}", reg),
+        0xD0 => format!("pop {}", direct_name(b1)),
+        0xD2 => format!("setb {}", bit_name(b1)),
+        0xD3 => "setb C".to_string(),
+        0xD4 => "da A".to_string(),
+        0xD5 => format!("djnz {}, {}", direct_name(b1), rel(b2)),
+        0xD6 | 0xD7 => format!("xchd A, @R{}", ri),
+        0xD8..=0xDF => format!("djnz R{}, {}", reg, rel(b1)),
+        0xE0 => "movx A, @DPTR".to_string(),
+        0xE2 | 0xE3 => format!("movx A, @R{}", ri),
+        0xE4 => "clr A".to_string(),
+        0xE5 => format!("mov A, {}", direct_name(b1)),
+        0xE6 | 0xE7 => format!("mov A, @R{}", ri),
+        0xE8..=0xEF => format!("mov A, R{}", reg),
+        0xF0 => "movx @DPTR, A".to_string(),
+        0xF2 | 0xF3 => format!("movx @R{}, A", ri),
+        0xF4 => "cpl A".to_string(),
+        0xF5 => format!("mov {}, A", direct_name(b1)),
+        0xF6 | 0xF7 => format!("mov @R{}, A", ri),
+        0xF8..=0xFF => format!("mov R{}, A", reg),
+    };
+
+    Disassembly {
+        address,
+        length,
+        bytes: (0..length as u16).map(byte).collect(),
+        text,
+    }
+}
+
+// 从 start 开始逐条反汇编到 end（含）为止，用于反汇编列表
+// 最后一条指令可以越过 end；地址回绕到 0 时停止
+pub fn disassemble_range(code: &[u8], start: u16, end: u16, symbols: &SymbolTable) -> Vec<Disassembly> {
+    let mut listing = Vec::new();
+    let mut address = start as u32;
+    while address <= end as u32 {
+        let disassembly = disassemble(code, address as u16, symbols);
+        address += disassembly.length as u32;
+        listing.push(disassembly);
+    }
+    listing
+}
+
+// ADD/ADDC/ORL/ANL/XRL/SUBB 的源操作数（由操作码低4位决定寻址方式）
+fn source_operand(opcode: u8, b1: u8) -> String {
+    match opcode & 0x0F {
+        0x04 => format!("#{:#04x}", b1),
+        0x05 => direct_name(b1),
+        0x06 | 0x07 => format!("@R{}", opcode & 0x01),
+        _ => format!("R{}", opcode & 0x07),
+    }
+}
+
+fn logic_mnemonic(opcode: u8) -> &'static str {
+    match opcode >> 4 {
+        0x4 => "orl",
+        0x5 => "anl",
+        _ => "xrl",
+    }
+}
+
+// 直接地址：SFR 显示寄存器名，内部RAM显示十六进制地址
+pub fn direct_name(address: u8) -> String {
+    match sfr_name(address) {
+        Some(name) => name.to_string(),
+        None => format!("{:#04x}", address),
+    }
+}
+
+// 位地址：0x00-0x7F 位于内部RAM 0x20-0x2F，0x80 以上位于可位寻址的 SFR
+pub fn bit_name(bit: u8) -> String {
+    if bit < 0x80 {
+        format!("{:#04x}.{}", 0x20 + (bit >> 3), bit & 0x07)
+    } else {
+        format!("{}.{}", direct_name(bit & 0xF8), bit & 0x07)
+    }
+}
+
+// 标准 8051/8052 的 SFR 名称
+pub fn sfr_name(address: u8) -> Option<&'static str> {
+    let name = match address {
+        0x80 => "P0",
+        0x81 => "SP",
+        0x82 => "DPL",
+        0x83 => "DPH",
+        0x87 => "PCON",
+        0x88 => "TCON",
+        0x89 => "TMOD",
+        0x8A => "TL0",
+        0x8B => "TL1",
+        0x8C => "TH0",
+        0x8D => "TH1",
+        0x90 => "P1",
+        0x98 => "SCON",
+        0x99 => "SBUF",
+        0xA0 => "P2",
+        0xA6 => "WDTRST",
+        0xA8 => "IE",
+        0xB0 => "P3",
+        0xB8 => "IP",
+        0xC8 => "T2CON",
+        0xCA => "RCAP2L",
+        0xCB => "RCAP2H",
+        0xCC => "TL2",
+        0xCD => "TH2",
+        0xD0 => "PSW",
+        0xE0 => "ACC",
+        0xF0 => "B",
+        _ => return None,
+    };
+    Some(name)
+}
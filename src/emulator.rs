@@ -1,34 +1,917 @@
 // 模拟器包装层 - 负责执行优化、调试、性能统计等非硬件功能
-use crate::cpu::CPU;
+use crate::cpu::adc::IE_EADC;
+use crate::cpu::instructions::{PSW_CY, RESERVED_OPCODE};
+use crate::cpu::instructions::interrupt::{
+    INTERRUPT_VECTOR_CYCLES, IE_EA, IE_ES, IE_ET0, IE_ET1, IE_ET2, IE_EX0, IE_EX1,
+};
+use crate::cpu::peripherals::{PowerMode, IE, P0, P1, P2, P3, PSW, SCON, T2CON, TCON, TMOD};
+use crate::cpu::serial::{SCON_REN, SCON_RI, SCON_TI};
+use crate::cpu::timers::{T2CON_EXF2, T2CON_TF2, T2CON_TR2, TF0, TF1, TR0, TR1};
+use crate::cpu::watch::{WatchAccess, WatchHit};
+use crate::cpu::{Variant, Verbosity, CPU, DEFAULT_CLOCK_FREQUENCY, DEFAULT_XRAM_SIZE};
+use crate::coverage::Coverage;
+use crate::disassembler;
 use crate::loop_detector::LoopDetector;
+use crate::memory_dump::{self, MemorySpace};
+use crate::pacing::Pacer;
+use crate::profiler::Profiler;
+use crate::serial_script::{self, ScriptEvent};
+use crate::state_json;
+use crate::symbols::{self, SymbolTable};
+use crate::vcd::VcdWriter;
+use log::{debug, error, info, warn};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 pub struct Emulator {
     pub cpu: CPU,
-    pub debug: bool,                    // 调试模式
+    pub trace: bool,                    // 跟踪模式：每条指令输出一行反汇编和执行后的 A/PSW
     pub clock_cycles: u64,              // 时钟周期计数
     pub loop_detector: LoopDetector,    // 循环检测器
-    pub delay_skip_counter: u32,        // 延迟跳过计数器（用于优化特定函数）
+    loop_snapshot: Option<LoopSnapshot>, // 上次判断死循环时的 CPU 状态（见 loop_state_repeats）
     pub instruction_count: u64,         // 总指令执行计数
-    pub is_halted: bool,                // 是否已停机（死循环或错误）
+    pub max_instructions: u64,          // 指令执行数上限（0 表示不限制）
+    pub max_cycles: u64,                // 机器周期数上限（0 表示不限制）
+    pub strict_opcodes: bool,           // 遇到未知操作码时停机（否则输出"未知指令"后跳过继续执行）
+    pub halt_reason: HaltReason,        // 运行状态：Running 表示仍在运行，其余为停机原因
+    pub power_down_wake: bool,          // 掉电后允许已使能的外部中断唤醒（AT89S52），否则掉电即停机
+    pub started_at: Instant,            // 模拟器创建时间（用于计算主机运行速度）
+    pub symbols: Rc<SymbolTable>,       // 符号表（地址→标号），调试输出中代替跳转目标地址
+    pub serial_script: VecDeque<ScriptEvent>, // 尚未到达的串口输入激励（按时间排序）
+    runaway_nops: u32,                  // 在已加载映像之外连续执行的 NOP 数
+    pub pc_counts: Option<HashMap<u16, u64>>, // 每个地址的指令执行次数（None 表示未开启统计）
+    pub breakpoints: HashSet<u16>,      // 断点地址：执行这些地址上的指令之前停机 (BreakpointHit)
+    breakpoint_resume: Option<u16>,     // 从断点继续运行时，断点处的指令照常执行一次
+    pub until: HashMap<u16, u64>,       // 运行到这些地址时停机 (UntilReached)：地址 → 还要经过几次（第几次到达时停机），到达后删除
+    pub watch_pause: bool,              // 观察点命中时停机 (WatchpointHit)，否则只输出报告后继续运行
+    pub watch_events: Vec<WatchEvent>,  // 最近一条命中观察点的指令的命中记录
+    call_frames: Vec<(u8, u16)>,        // 每层调用压入返回地址后的 SP（指向返回地址高字节）和被调用的入口地址，外层在前
+    pacer: Option<Pacer>,               // 实时节拍（None 表示尽快运行）
+    pub coverage: Option<Coverage>,     // ROM 代码覆盖率（None 表示未开启，enable_coverage 设置）
+    pub profiler: Option<Profiler>,     // 按地址和函数的时钟周期分析（None 表示未开启，enable_profiler 设置）
+    pub turbo: bool,                    // 极速模式：不做循环检测和快进、逐条调试输出和实时节拍（enable_turbo 设置）
+    vcd: Option<VcdWriter>,             // VCD 波形输出（None 表示未开启，start_vcd 设置）
+    tick_hook: Option<Box<dyn FnMut(u64)>>, // 时钟回调：每一步之后以消耗的时钟周期数调用（set_tick_hook 设置）
+    trace_out: Box<dyn Write>,          // 逐条指令输出（调试模式和跟踪模式）的目标，默认是标准输出
+}
+
+// 判断多条指令的循环是否会自己结束时记录的 CPU 状态
+#[derive(PartialEq, Eq)]
+struct LoopSnapshot {
+    pc: u16,
+    acc: u8,
+    b: u8,
+    sp: u8,
+    dptr: u16,
+    ram: [u8; 256],
+    sfr: [u8; 128],
+}
+
+// 在已加载映像之外连续执行这么多条 NOP 即认为程序跑飞
+const RUNAWAY_NOP_LIMIT: u32 = 16;
+
+// 停机原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    Running,          // 仍在运行
+    ProgramEnd,       // 程序正常结束：没有可响应的中断时停在自跳转指令上（如 sjmp $）
+    Deadlock,         // 死循环：反复等待永远不会发生的事件，或进入空闲模式后没有可唤醒的中断
+    BreakpointHit,    // 到达断点
+    UntilReached(u16), // 第 n 次到达 until 中的地址（在执行该地址的指令之前停机），可以继续运行
+    WatchpointHit,    // 指令访问了被观察的存储单元（仅在 watch_pause 时停机，停在该指令之后）
+    InstructionLimit, // 达到指令执行数上限 (max_instructions)
+    CycleLimit,       // 达到机器周期数上限 (max_cycles)
+    UnknownOpcode(u16, u8), // 遇到未实现或保留的操作码（地址、操作码），仅在 strict_opcodes 时停机
+    RanOffEnd,        // 程序跑出已加载的映像，在未初始化的 ROM（全是 NOP）中执行
+    PowerDown,        // 进入掉电模式
+    ExitCode(u8),     // 程序写了退出端口 (cpu.exit_port)，带写入的退出码
+}
+
+impl HaltReason {
+    // 机器可读的名称（用于 --state-json），不随提示文字变化
+    pub fn id(self) -> &'static str {
+        match self {
+            HaltReason::Running => "running",
+            HaltReason::ProgramEnd => "program_end",
+            HaltReason::Deadlock => "deadlock",
+            HaltReason::BreakpointHit => "breakpoint",
+            HaltReason::UntilReached(_) => "until",
+            HaltReason::WatchpointHit => "watchpoint",
+            HaltReason::InstructionLimit => "instruction_limit",
+            HaltReason::CycleLimit => "cycle_limit",
+            HaltReason::UnknownOpcode(..) => "unknown_opcode",
+            HaltReason::RanOffEnd => "ran_off_end",
+            HaltReason::PowerDown => "power_down",
+            HaltReason::ExitCode(_) => "exit_code",
+        }
+    }
+}
+
+// 运行统计
+#[derive(Debug, Clone, Copy)]
+pub struct EmulatorStats {
+    pub instructions: u64,      // 已执行指令数
+    pub clock_cycles: u64,      // 时钟周期数
+    pub machine_cycles: u64,    // 机器周期数（12个时钟周期为一个机器周期）
+    pub simulated_time: f64,    // 模拟经过的时间（秒）
+    pub host_time: Duration,    // 主机实际运行时间
+    pub mips: f64,              // 主机上的执行速度（百万条指令/秒）
+}
+
+impl EmulatorStats {
+    // 由计数值计算统计数据
+    pub fn new(instructions: u64, clock_cycles: u64, clock_frequency: u32, host_time: Duration) -> Self {
+        let host_secs = host_time.as_secs_f64();
+        EmulatorStats {
+            instructions,
+            clock_cycles,
+            machine_cycles: clock_cycles / 12,
+            simulated_time: clock_cycles as f64 / clock_frequency as f64,
+            host_time,
+            mips: if host_secs > 0.0 { instructions as f64 / host_secs / 1_000_000.0 } else { 0.0 },
+        }
+    }
+}
+
+// 观察点命中报告：在哪条指令、第几个时钟周期访问了被观察的单元
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub hit: WatchHit,
+    pub pc: u16,              // 访问该单元的指令地址
+    pub instruction: String,  // 该指令的反汇编
+    pub clock_cycles: u64,    // 指令执行完时的时钟周期数
+}
+
+impl fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let watchpoint = &self.hit.watchpoint;
+        write!(f, "[观察点] {} {}", watchpoint.access.name(), watchpoint.location())?;
+        match watchpoint.access {
+            WatchAccess::Read => write!(f, " = {:#04x}", self.hit.new)?,
+            WatchAccess::Write => write!(f, ": {:#04x} -> {:#04x}", self.hit.old, self.hit.new)?,
+        }
+        write!(f, "，{:#06x}: {}，第 {} 个时钟周期", self.pc, self.instruction, self.clock_cycles)
+    }
+}
+
+// 执行次数最多的地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotAddress {
+    pub address: u16,          // 指令地址
+    pub count: u64,            // 执行次数
+    pub label: Option<String>, // 所在的标号，不在标号处时带偏移，例如 "DELAY+0x3"
+}
+
+// 一个操作码的执行次数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeCount {
+    pub opcode: u8,
+    pub mnemonic: Option<&'static str>, // 指令表中的助记符，未实现和保留的操作码为 None
+    pub count: u64,
+}
+
+// 构造 Emulator 时的配置，未设置的项与 Emulator::new 相同：
+// let emulator = Emulator::builder().clock(11_059_200).variant(Variant::I8052).max_cycles(1_000_000).build();
+#[derive(Debug, Clone, Copy)]
+pub struct EmulatorBuilder {
+    debug: bool,
+    clock_frequency: u32,
+    variant: Variant,
+    vector_base: u16,
+    strict_opcodes: bool,
+    strict_load: bool,
+    max_instructions: u64,
+    max_cycles: u64,
+    verbosity: Verbosity,
+    turbo: bool,
+    xram_size: usize,
+}
+
+impl Default for EmulatorBuilder {
+    fn default() -> Self {
+        EmulatorBuilder {
+            debug: false,
+            clock_frequency: DEFAULT_CLOCK_FREQUENCY,
+            variant: Variant::I8051,
+            vector_base: 0,
+            strict_opcodes: false,
+            strict_load: false,
+            max_instructions: 0,
+            max_cycles: 0,
+            verbosity: Verbosity::Normal,
+            turbo: false,
+            xram_size: DEFAULT_XRAM_SIZE,
+        }
+    }
+}
+
+impl EmulatorBuilder {
+    // 调试模式（每条指令输出一行）
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    // 晶振频率 (Hz)
+    pub fn clock(mut self, frequency: u32) -> Self {
+        self.clock_frequency = frequency;
+        self
+    }
+
+    // 单片机型号
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    // 向量表基地址（复位后从这里开始执行）
+    pub fn vector_base(mut self, base: u16) -> Self {
+        self.vector_base = base;
+        self
+    }
+
+    // 遇到未实现或保留的操作码时停机
+    pub fn strict_opcodes(mut self, strict: bool) -> Self {
+        self.strict_opcodes = strict;
+        self
+    }
+
+    // 加载 HEX 文件时记录重叠且内容不同按错误处理
+    pub fn strict_load(mut self, strict: bool) -> Self {
+        self.strict_load = strict;
+        self
+    }
+
+    // 指令执行数上限（0 表示不限制）
+    pub fn max_instructions(mut self, limit: u64) -> Self {
+        self.max_instructions = limit;
+        self
+    }
+
+    // 机器周期数上限（0 表示不限制）
+    pub fn max_cycles(mut self, limit: u64) -> Self {
+        self.max_cycles = limit;
+        self
+    }
+
+    // 运行信息的详细程度
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    // 极速模式（见 Emulator::enable_turbo），调试模式同时被关闭
+    pub fn turbo(mut self, turbo: bool) -> Self {
+        self.turbo = turbo;
+        self
+    }
+
+    // 外部数据存储器的字节数（默认 64KB，超过 64KB 的部分 MOVX 无法寻址，按 64KB 计）
+    pub fn xram_size(mut self, size: usize) -> Self {
+        self.xram_size = size.min(DEFAULT_XRAM_SIZE);
+        self
+    }
+
+    pub fn build(self) -> Emulator {
+        let mut emulator = Emulator::new(self.debug);
+        emulator.cpu.clock_frequency = self.clock_frequency;
+        emulator.cpu.variant = self.variant;
+        emulator.cpu.set_vector_base(self.vector_base);
+        emulator.cpu.strict_load = self.strict_load;
+        emulator.cpu.xram = vec![0; self.xram_size];
+        emulator.strict_opcodes = self.strict_opcodes;
+        emulator.max_instructions = self.max_instructions;
+        emulator.max_cycles = self.max_cycles;
+        emulator.set_verbosity(self.verbosity);
+        if self.turbo {
+            emulator.enable_turbo();
+        }
+        emulator
+    }
 }
 
 impl Emulator {
+    // 用构造器设置晶振频率、型号、执行上限等，例如 Emulator::builder().clock(11_059_200).build()
+    pub fn builder() -> EmulatorBuilder {
+        EmulatorBuilder::default()
+    }
+
+    // 调试模式由 CPU 保存（cpu.debug），指令处理函数和模拟器的调试输出都看这一个标志
     pub fn new(debug: bool) -> Self {
+        let mut cpu = CPU::new();
+        cpu.debug = debug;
         Emulator {
-            cpu: CPU::new(),
-            debug,
+            cpu,
+            trace: false,
             clock_cycles: 0,
             loop_detector: LoopDetector::new(),
-            delay_skip_counter: 0,
+            loop_snapshot: None,
             instruction_count: 0,
-            is_halted: false,
+            max_instructions: 0,
+            max_cycles: 0,
+            strict_opcodes: false,
+            halt_reason: HaltReason::Running,
+            power_down_wake: false,
+            started_at: Instant::now(),
+            symbols: Rc::new(SymbolTable::new()),
+            serial_script: VecDeque::new(),
+            runaway_nops: 0,
+            pc_counts: None,
+            breakpoints: HashSet::new(),
+            breakpoint_resume: None,
+            until: HashMap::new(),
+            watch_pause: false,
+            watch_events: Vec::new(),
+            call_frames: Vec::new(),
+            coverage: None,
+            profiler: None,
+            pacer: None,
+            turbo: false,
+            vcd: None,
+            tick_hook: None,
+            trace_out: Box::new(io::stdout()),
         }
     }
 
-    // 执行单条指令（带优化和调试）
+    // 从 HEX 文本加载程序，可以多次调用叠加多个映像（如引导程序加应用程序）：
+    // 与之前加载的内容重叠时后加载的字节生效，已加载的最高地址取所有映像的最大值
+    pub fn load_hex(&mut self, hex: &str) -> io::Result<()> {
+        self.cpu.patch_hex(hex.as_bytes())
+    }
+
+    // 加载符号文件，返回读取到的符号数
+    pub fn load_symbols(&mut self, path: &str) -> io::Result<usize> {
+        self.set_symbols(symbols::load_symbols(path)?);
+        Ok(self.symbols.len())
+    }
+
+    // 设置符号表（CPU 的调试输出共享同一份）
+    pub fn set_symbols(&mut self, table: SymbolTable) {
+        self.symbols = Rc::new(table);
+        self.cpu.symbols = Rc::clone(&self.symbols);
+    }
+
+    // 提示信息中的地址：有符号表时显示 标号 或 标号+偏移
+    fn format_address(&self, address: u16) -> String {
+        symbols::format_address(&self.symbols, address)
+    }
+
+    // 设置运行信息的详细程度（CPU 的端口写入提示和模拟器的 [信息] 提示）
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.cpu.verbosity = verbosity;
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.cpu.verbosity
+    }
+
+    // 开启实时节拍：按晶振频率和墙上时间同步运行，而不是尽快运行
+    pub fn enable_realtime(&mut self) {
+        self.pacer = Some(Pacer::new(self.clock_cycles));
+    }
+
+    // 极速模式：用于批量回归测试，尽可能快地执行
+    // 关闭实时节拍、调试和跟踪输出，不再记录 PC 历史（因此也没有循环快进和死循环判断，只靠执行上限兜底），
+    // 程序停在自跳转上且不会再有中断时直接判定结束；定时器和中断仍按机器周期推进，执行结果与逐条执行完全相同
+    pub fn enable_turbo(&mut self) {
+        self.turbo = true;
+        self.pacer = None;
+        self.trace = false;
+        self.cpu.debug = false;
+    }
+
+    // 设置逐条指令输出的目标（例如带缓冲的文件），程序自己的端口/串口输出仍在标准输出上
+    pub fn set_trace_output(&mut self, out: Box<dyn Write>) {
+        self.trace_out = out;
+    }
+
+    // 设置时钟回调：每执行一条指令（以及空闲模式推进、循环快进）之后，以这一步消耗的时钟周期数调用，
+    // 中断响应的周期计入同一步；回调收到的周期数之和等于 clock_cycles 的增量
+    pub fn set_tick_hook(&mut self, hook: Box<dyn FnMut(u64)>) {
+        self.tick_hook = Some(hook);
+    }
+
+    // 开始输出 VCD 波形：写出文件头和初值，之后每一步比较一次端口锁存器（with_bytes 时另有 TH0/TL0/ACC）
+    // 与观察点一样，开启后不再快进循环（自跳转除外），以免漏掉循环里的端口变化
+    pub fn start_vcd(&mut self, out: Box<dyn Write>, with_bytes: bool) -> io::Result<()> {
+        self.vcd = Some(VcdWriter::new(out, &self.cpu, with_bytes)?);
+        Ok(())
+    }
+
+    // 这一步从 start 开始消耗了时钟周期时调用时钟回调，并记录 VCD 波形的变化
+    fn tick(&mut self, start: u64) {
+        let elapsed = self.clock_cycles - start;
+        if elapsed == 0 {
+            return;
+        }
+        if let Some(hook) = &mut self.tick_hook {
+            hook(elapsed);
+        }
+        if let Some(vcd) = &mut self.vcd
+            && let Err(e) = vcd.sample(&self.cpu, self.clock_cycles)
+        {
+            warn!("[警告] 写入 VCD 波形失败，停止输出: {}", e);
+            self.vcd = None;
+        }
+    }
+
+    // 把跟踪输出中缓冲的内容写出（结束运行前调用）
+    pub fn flush_trace(&mut self) -> io::Result<()> {
+        self.trace_out.flush()
+    }
+
+    // 加载串口输入激励脚本，返回事件数（毫秒按当前晶振频率换算）
+    pub fn load_serial_script(&mut self, path: &str) -> io::Result<usize> {
+        self.serial_script = serial_script::load_serial_script(path, self.cpu.clock_frequency)?;
+        Ok(self.serial_script.len())
+    }
+
+    // 把已经到达时间的激励数据送入串口线路
+    fn deliver_serial_script(&mut self) {
+        while let Some(event) = self.serial_script.front() {
+            if event.clock_cycle > self.clock_cycles {
+                break;
+            }
+            if self.cpu.debug {
+                debug!("\n[串口脚本] 第 {} 个时钟周期送入 {:02X?}", self.clock_cycles, event.data);
+            }
+            self.cpu.serial_receive(&event.data);
+            self.serial_script.pop_front();
+        }
+    }
+
+    // 距离下一个激励事件还有多少个机器周期（没有事件返回 None）
+    fn cycles_until_script_event(&self) -> Option<u64> {
+        self.serial_script
+            .front()
+            .map(|event| event.clock_cycle.saturating_sub(self.clock_cycles).div_ceil(12))
+    }
+
+    // 以十六进制加 ASCII 的形式转储一段存储器，range 超出存储空间的部分被截掉
+    // SFR 的地址为 0x80-0xFF，读取时没有副作用（端口显示锁存器）；外部数据存储器即 MOVX 访问的 cpu.xram
+    pub fn dump_memory(&self, space: MemorySpace, range: Range<usize>) -> String {
+        let (base, size) = match space {
+            MemorySpace::Ram => (0, if self.cpu.variant.has_upper_ram() { 0x100 } else { 0x80 }),
+            MemorySpace::Sfr => (0x80, 0x80),
+            MemorySpace::Xram => (0, self.cpu.xram.len()),
+        };
+        let start = range.start.clamp(base, base + size);
+        let end = range.end.clamp(start, base + size);
+        let data: Vec<u8> = match space {
+            MemorySpace::Ram => self.cpu.ram[start..end].to_vec(),
+            MemorySpace::Sfr => (start..end).map(|address| self.cpu.peek_sfr(address as u8)).collect(),
+            MemorySpace::Xram => self.cpu.xram[start..end].to_vec(),
+        };
+        memory_dump::hex_dump(start, &data)
+    }
+
+    // 有名称的 SFR 及其当前值，每行一个：地址、名称、十六进制和二进制值，例如 "89 TMOD   = 20 00100000"
+    // 只列出当前型号上存在的寄存器（定时器2、看门狗、片上 ADC），读取时没有副作用（端口显示锁存器）
+    pub fn annotate_sfrs(&self) -> String {
+        let mut out = String::new();
+        for address in 0x80..=0xFF {
+            let Some(name) = self.sfr_label(address) else {
+                continue;
+            };
+            let value = self.cpu.peek_sfr(address);
+            out.push_str(&format!("{:02X} {:<7}= {:02X} {:08b}\n", address, name, value, value));
+        }
+        out
+    }
+
+    // SFR 在当前型号上的名称（定时器2、看门狗、片上 ADC 只在存在时有名称）
+    pub fn sfr_label(&self, address: u8) -> Option<&'static str> {
+        let adc = self.cpu.adc.as_ref().map(|adc| adc.config);
+        match disassembler::sfr_name(address) {
+            Some("WDTRST") if !self.cpu.variant.has_watchdog() => None,
+            Some("T2CON" | "RCAP2L" | "RCAP2H" | "TL2" | "TH2") if !self.cpu.variant.has_timer2() => None,
+            Some(name) => Some(name),
+            None if adc.is_some_and(|adc| adc.adccon == address) => Some("ADCCON"),
+            None if adc.is_some_and(|adc| adc.adcdata == address) => Some("ADCDATA"),
+            None => None,
+        }
+    }
+
+    // 完整的机器状态（寄存器、SFR、内部RAM、计数器和停机原因），格式见 state_json 模块
+    pub fn state_json(&self) -> String {
+        state_json::to_json(self)
+    }
+
+    // 获取运行统计（主机时间从模拟器创建时开始计算）
+    pub fn stats(&self) -> EmulatorStats {
+        EmulatorStats::new(
+            self.instruction_count,
+            self.clock_cycles,
+            self.cpu.clock_frequency,
+            self.started_at.elapsed(),
+        )
+    }
+
+    // 设定伪随机数种子，所有不确定的外设行为（如悬空引脚）都从同一个发生器取数
+    pub fn set_seed(&mut self, seed: u64) {
+        self.cpu.set_seed(seed);
+    }
+
+    // 开启按地址统计指令执行次数（清除之前的统计）
+    pub fn enable_profiling(&mut self) {
+        self.pc_counts = Some(HashMap::new());
+    }
+
+    // 执行次数最多的 top_n 个地址，按次数从多到少排列（次数相同按地址排列）
+    // 循环快进跳过的迭代同样计入循环指令的地址；没有开启统计时返回空列表
+    pub fn hot_addresses(&self, top_n: usize) -> Vec<HotAddress> {
+        let Some(counts) = &self.pc_counts else {
+            return Vec::new();
+        };
+        let mut hot: Vec<(u16, u64)> = counts.iter().map(|(&address, &count)| (address, count)).collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.truncate(top_n);
+
+        hot.into_iter()
+            .map(|(address, count)| HotAddress {
+                address,
+                count,
+                label: symbols::symbolize(&self.symbols, address),
+            })
+            .collect()
+    }
+
+    // 执行过的操作码及其次数，按次数从多到少排列（次数相同时按操作码）
+    // 计数在 CPU 的指令分派处进行，总是开启（包括极速模式）；循环快进跳过的迭代同样计入
+    pub fn opcode_counts(&self) -> Vec<OpcodeCount> {
+        let mut counts: Vec<OpcodeCount> = (0..=0xFF)
+            .filter(|&opcode| self.cpu.opcode_counts[opcode as usize] > 0)
+            .map(|opcode| OpcodeCount {
+                opcode,
+                mnemonic: CPU::mnemonic(opcode),
+                count: self.cpu.opcode_counts[opcode as usize],
+            })
+            .collect();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then(a.opcode.cmp(&b.opcode)));
+        counts
+    }
+
+    // 开启时钟周期分析（清除之前的结果）
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    // 时钟周期分析报告，列出前 top_n 个地址，格式见 profiler 模块；没有开启分析时返回 None
+    pub fn profile_report(&self, top_n: usize) -> Option<String> {
+        self.profiler.as_ref().map(|profiler| profiler.report(&self.cpu.rom, &self.symbols, top_n))
+    }
+
+    // 开启代码覆盖率统计（清除之前的结果）
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    // 代码覆盖率报告：已加载映像中执行过的字节数和未执行的范围，格式见 coverage 模块；没有开启统计时返回 None
+    pub fn coverage_report(&self) -> Option<String> {
+        self.coverage.as_ref().map(|coverage| coverage.report(|address| self.cpu.is_loaded(address), &self.symbols))
+    }
+
+    // 当前函数：最内层调用的入口地址，不在调用里时为 0x0000
+    fn current_function(&self) -> u16 {
+        self.call_frames.last().map_or(0, |&(_, entry)| entry)
+    }
+
+    // 把 pc 处消耗的时钟周期计入当前函数
+    fn profile(&mut self, pc: u16, clocks: u64) {
+        let function = self.current_function();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(pc, function, clocks);
+        }
+    }
+
+    // 当前的调用栈：每层调用（LCALL/ACALL 和响应中断）的返回地址，最外层在前
+    // 返回地址从堆栈中读出；子程序 RET/RETI 或者移动 SP 越过某层的返回地址后，该层即被丢弃
+    pub fn call_stack(&self) -> Vec<u16> {
+        self.call_frames
+            .iter()
+            .map(|&(sp, _)| {
+                let high = self.cpu.read_indirect(sp) as u16;
+                let low = self.cpu.read_indirect(sp.wrapping_sub(1)) as u16;
+                (high << 8) | low
+            })
+            .collect()
+    }
+
+    // 记录新的调用层，丢弃已经返回的调用层
+    fn track_call_frames(&mut self, opcode: u8, sp_before: u8) {
+        let sp = self.cpu.registers.sp;
+        while self.call_frames.last().is_some_and(|&(frame, _)| frame > sp) {
+            self.call_frames.pop();
+        }
+        // LCALL/ACALL 先压低字节再压高字节，SP 加2
+        let is_call = opcode == 0x12 || opcode & 0x1F == 0x11;
+        if is_call && sp == sp_before.wrapping_add(2) {
+            self.call_frames.push((sp, self.cpu.registers.pc));
+        }
+    }
+
+    // 是否已停机
+    pub fn is_halted(&self) -> bool {
+        self.halt_reason != HaltReason::Running
+    }
+
+    // 执行一步：取指执行一条指令，然后更新定时器并检查中断，返回执行后的运行状态
+    // 空闲模式下不取指，只推进一个机器周期；掉电模式下停机；
+    // 已执行的指令数达到 max_instructions、机器周期数达到 max_cycles 时停机；
+    // PC 到达断点（或第 n 次到达 until 地址）时在执行之前停机，再次调用 step 从该处继续；观察点命中（watch_pause 时）同样可以继续
+    pub fn step(&mut self) -> HaltReason {
+        // 停在断点上时可以继续运行，断点处的指令照常执行
+        if self.halt_reason == HaltReason::BreakpointHit {
+            self.halt_reason = HaltReason::Running;
+            self.breakpoint_resume = Some(self.cpu.registers.pc);
+        }
+        if self.halt_reason == HaltReason::WatchpointHit {
+            self.halt_reason = HaltReason::Running;
+            self.watch_events.clear();
+        }
+        if matches!(self.halt_reason, HaltReason::UntilReached(_)) {
+            self.halt_reason = HaltReason::Running;
+        }
+        if self.is_halted() {
+            return self.halt_reason;
+        }
+        let start = self.clock_cycles;
+        self.step_instruction();
+        self.tick(start);
+        self.pace();
+        self.halt_reason
+    }
+
+    // 实时节拍：模拟时间超前于墙上时间时等待
+    // 停在自跳转上且不会再有中断时程序其实已经结束，只是在等待结束判定，这段时间不必等待
+    fn pace(&mut self) {
+        let Some(mut pacer) = self.pacer.take() else {
+            return;
+        };
+        if pacer.due(self.clock_cycles) {
+            if self.is_self_jump(self.cpu.registers.pc) && !self.waiting_for_interrupt() {
+                pacer.realign(self.clock_cycles);
+            } else {
+                pacer.sync(self.clock_cycles, self.cpu.clock_frequency);
+            }
+        }
+        self.pacer = Some(pacer);
+    }
+
+    // 运行至少 cycles 个机器周期：按指令边界执行，直到时钟推进了足够的周期或者停机，
+    // 返回实际推进的机器周期数（最后一条指令、中断响应或循环快进可能超出请求的数量）
+    pub fn run_cycles(&mut self, cycles: u64) -> u64 {
+        let start = self.clock_cycles;
+        let target = start.saturating_add(cycles.saturating_mul(12));
+        while self.clock_cycles < target && self.step() == HaltReason::Running {}
+        (self.clock_cycles - start) / 12
+    }
+
+    // 极速模式下运行到停机：指令数上限、机器周期数上限和下一个串口激励事件每批算一次，
+    // 批内逐条执行指令、推进定时器、检查中断，不再经过 step 的逐条检查；
+    // 遇到断点或 until 地址、空闲/掉电、跑出映像、严格模式下的未知操作码或到达批的边界时交给 step 处理这一条
+    pub fn run_turbo(&mut self) -> HaltReason {
+        while !self.is_halted() {
+            let instruction_limit = match self.max_instructions {
+                0 => u64::MAX,
+                limit => limit,
+            };
+            let mut cycle_limit = match self.max_cycles {
+                0 => u64::MAX,
+                limit => limit.saturating_mul(12),
+            };
+            if let Some(event) = self.serial_script.front() {
+                cycle_limit = cycle_limit.min(event.clock_cycle);
+            }
+
+            while self.instruction_count < instruction_limit && self.clock_cycles < cycle_limit {
+                let pc = self.cpu.registers.pc;
+                let opcode = self.cpu.rom[pc as usize];
+                if self.cpu.power_mode() != PowerMode::Normal
+                    || (opcode == 0x00 && self.cpu.rom_end.is_some_and(|end| pc > end))
+                    || (self.strict_opcodes && !CPU::is_implemented(opcode))
+                    || self.stops_at(pc)
+                {
+                    break;
+                }
+                self.runaway_nops = 0;
+
+                let start = self.clock_cycles;
+                let sp_before = self.cpu.registers.sp;
+                self.execute_instruction(opcode);
+                self.track_call_frames(opcode, sp_before);
+                self.advance_peripherals(opcode);
+                self.check_interrupts();
+                self.check_watchdog();
+                self.tick(start);
+                if self.is_halted() {
+                    break;
+                }
+            }
+
+            // 批的边界和特殊情况按逐条执行处理
+            if !self.is_halted() {
+                self.step();
+            }
+        }
+        self.halt_reason
+    }
+
+    // 单步跳过：当前指令是 LCALL/ACALL 时一直运行到子程序返回，否则只执行一步
+    // 按调用层数判断返回，子程序执行期间响应的中断（同样增加调用层数）不会提前结束；
+    // 子程序通过修改 SP 丢弃返回地址时同样视为返回
+    pub fn step_over(&mut self) -> HaltReason {
+        let opcode = self.cpu.rom[self.cpu.registers.pc as usize];
+        if opcode != 0x12 && opcode & 0x1F != 0x11 {
+            return self.step();
+        }
+
+        let depth = self.call_frames.len();
+        loop {
+            let reason = self.step();
+            if reason != HaltReason::Running || self.call_frames.len() <= depth {
+                return reason;
+            }
+        }
+    }
+
+    fn step_instruction(&mut self) {
+        // 指令执行数上限（防止真正的无限循环）
+        if self.max_instructions != 0 && self.instruction_count >= self.max_instructions {
+            self.halt_reason = HaltReason::InstructionLimit;
+            return;
+        }
+        if self.max_cycles != 0 && self.clock_cycles / 12 >= self.max_cycles {
+            self.halt_reason = HaltReason::CycleLimit;
+            return;
+        }
+
+        self.deliver_serial_script();
+
+        match self.cpu.power_mode() {
+            PowerMode::PowerDown => {
+                // 振荡器停止：时钟、定时器和串口都不前进，只有外部中断引脚还能唤醒
+                if self.power_down_wake && self.cpu.external_wake_enabled() {
+                    if self.cpu.wake_from_power_down() {
+                        if self.cpu.debug {
+                            debug!("\n[信息] 外部中断唤醒掉电模式 (PC={:#06x})", self.cpu.registers.pc);
+                        }
+                        self.check_interrupts();
+                    }
+                    return;
+                }
+                if self.verbosity() != Verbosity::Quiet {
+                    info!("\n[信息] 进入掉电模式 (PC={:#06x})，振荡器停止", self.cpu.registers.pc);
+                }
+                self.halt_reason = HaltReason::PowerDown;
+                return;
+            }
+            PowerMode::Idle => {
+                // 没有任何可能到来的中断时，空闲模式只能靠复位退出
+                if !self.waiting_for_interrupt() {
+                    warn!("\n[信息] 进入空闲模式 (PC={:#06x})，但没有可以唤醒CPU的中断", self.cpu.registers.pc);
+                    self.halt_reason = HaltReason::Deadlock;
+                    return;
+                }
+                // 空闲时不执行指令，直接把时间推进到下一次定时器溢出的前一个机器周期，
+                // 最后一个周期照常检查中断
+                // 下一个串口激励事件同样要唤醒串口接收
+                let mut skip = self.cpu.get_cycles_until_timer_overflow().saturating_sub(1);
+                if let Some(cycles) = self.cycles_until_script_event() {
+                    skip = skip.min(cycles.saturating_sub(1));
+                }
+                for _ in 0..skip {
+                    self.clock_cycles += 12;
+                    self.cpu.update_timers();
+                }
+                self.clock_cycles += 12;
+                self.cpu.update_timers();
+                self.profile(self.cpu.registers.pc, (skip + 1) * 12);
+                self.check_interrupts();
+                self.check_watchdog();
+                return;
+            }
+            PowerMode::Normal => {}
+        }
+
+        let pc = self.cpu.registers.pc;
+        let opcode = self.cpu.rom[pc as usize];
+
+        // 断点：在执行断点处的指令之前停机；中断响应和循环快进改变 PC 之后同样在下一条指令之前检查
+        let resume = self.breakpoint_resume.take();
+        if self.breakpoints.contains(&pc) && resume != Some(pc) {
+            self.halt_reason = HaltReason::BreakpointHit;
+            return;
+        }
+        if self.until_reached(pc) {
+            self.halt_reason = HaltReason::UntilReached(pc);
+            return;
+        }
+
+        // 未初始化的 ROM 全是 0x00 (NOP)，程序跑出映像后会一直滑行到指令数上限
+        if opcode == 0x00 && self.cpu.rom_end.is_some_and(|end| pc > end) {
+            self.runaway_nops += 1;
+            if self.runaway_nops >= RUNAWAY_NOP_LIMIT {
+                warn!(
+                    "\n[警告] 程序跑出已加载的映像（最高地址 {:#06x}），在 {:#06x} 处执行未初始化的 ROM",
+                    self.cpu.rom_end.unwrap_or(0),
+                    pc
+                );
+                self.halt_reason = HaltReason::RanOffEnd;
+                return;
+            }
+        } else {
+            self.runaway_nops = 0;
+        }
+
+        // 严格模式下停在未知操作码处，PC 仍指向它；默认跳过它继续执行（与以前的行为相同）
+        if self.strict_opcodes && !CPU::is_implemented(opcode) {
+            let kind = if opcode == RESERVED_OPCODE { "保留操作码" } else { "未知操作码" };
+            error!("\n[错误] 在 {} 处遇到{} {:#04x}", self.format_address(pc), kind, opcode);
+            self.halt_reason = HaltReason::UnknownOpcode(pc, opcode);
+            return;
+        }
+
+        // 循环快进：跳过的迭代已经推进了外设、检查了中断，这一步到此为止
+        if self.fast_forward_loop(pc, opcode) {
+            return;
+        }
+
+        let sp_before = self.cpu.registers.sp;
+        self.execute_instruction(opcode);
+        self.track_call_frames(opcode, sp_before);
+
+        // 更新定时器（每条指令执行后，按指令的机器周期数推进）
+        self.advance_peripherals(opcode);
+
+        // 检查并处理中断
+        self.check_interrupts();
+
+        // 看门狗溢出时复位
+        self.check_watchdog();
+    }
+
+    // 是否有断点或 until 地址在 pc 上（极速模式的批内检查，交给 step 处理）
+    fn stops_at(&self, pc: u16) -> bool {
+        (!self.breakpoints.is_empty() && self.breakpoints.contains(&pc))
+            || (!self.until.is_empty() && self.until.contains_key(&pc))
+    }
+
+    // PC 到达 until 中的地址：计一次，到了指定的次数时删除该地址并返回 true
+    fn until_reached(&mut self, pc: u16) -> bool {
+        let Some(remaining) = self.until.get_mut(&pc) else {
+            return false;
+        };
+        *remaining = remaining.saturating_sub(1);
+        if *remaining > 0 {
+            return false;
+        }
+        self.until.remove(&pc);
+        true
+    }
+
+    // 看门狗溢出：复位 CPU（保留程序存储器），从复位向量重新执行
+    fn check_watchdog(&mut self) {
+        if !self.cpu.watchdog_reset_pending() {
+            return;
+        }
+        warn!(
+            "\n[看门狗] 计数器溢出，在第 {} 个时钟周期复位 (PC={:#06x})",
+            self.clock_cycles, self.cpu.registers.pc
+        );
+        self.cpu.reset();
+        self.call_frames.clear();
+        self.loop_detector = LoopDetector::new();
+    }
+
+    // 指令执行完后推进定时器、串口、看门狗和 ADC：指令有几个机器周期就推进几次
+    fn advance_peripherals(&mut self, opcode: u8) {
+        for _ in 0..CPU::machine_cycles(opcode) {
+            self.cpu.update_timers();
+        }
+    }
+
+    // 检查中断，响应时计入查询周期和硬件 LCALL 的机器周期（期间定时器照常计数）
+    fn check_interrupts(&mut self) {
+        if self.cpu.check_interrupts() {
+            // 响应中断与 LCALL 一样压入返回地址
+            self.call_frames.push((self.cpu.registers.sp, self.cpu.registers.pc));
+            for _ in 0..INTERRUPT_VECTOR_CYCLES {
+                self.clock_cycles += 12;
+                self.cpu.update_timers();
+            }
+            self.profile(self.cpu.registers.pc, INTERRUPT_VECTOR_CYCLES * 12);
+        }
+    }
+
+    // 执行单条指令（带优化和调试），已停机时什么都不做
     pub fn execute_instruction(&mut self, opcode: u8) {
         // 检查是否已停机
-        if self.is_halted {
+        if self.halt_reason != HaltReason::Running {
             return;
         }
 
@@ -38,140 +921,1162 @@ impl Emulator {
         // 保存当前 PC 用于调试输出
         let pc_before = self.cpu.registers.pc;
 
-        // 循环检测：如果检测到紧密循环超过阈值，快进
-        if self.loop_detector.record_pc(pc_before) {
-            self.loop_detector.increment_fast_forward();
-            
-            // 计算循环大小
-            let loop_size = if self.loop_detector.loop_end >= self.loop_detector.loop_start {
-                ((self.loop_detector.loop_end - self.loop_detector.loop_start) / 2) as u32 // 估算指令数
-            } else {
-                1
-            };
-            self.loop_detector.set_loop_size(loop_size.max(1));
-            
-            let multiplier = self.loop_detector.get_fast_forward_multiplier();
-            let has_io = self.loop_detector.has_io_in_loop;
-
-            // 只在调试模式且非单指令死循环时输出快进信息
-            // 或者在单指令死循环的前几次快进时输出
-            let should_print = self.debug && (
-                !self.loop_detector.is_program_end() || 
-                self.loop_detector.same_loop_fast_forward_count < 3
+        // 指令消耗的时钟周期：机器周期数（1、2 或 4）× 12
+        let clocks = CPU::machine_cycles(opcode) as u64 * 12;
+        self.clock_cycles += clocks;
+        self.profile(pc_before, clocks);
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark(pc_before, disassembler::instruction_length(opcode));
+        }
+
+        if let Some(counts) = &mut self.pc_counts {
+            *counts.entry(pc_before).or_insert(0) += 1;
+        }
+
+        // 跟踪模式需要在执行前反汇编（执行后 PC 已经改变）
+        let disassembly = if self.trace {
+            Some(disassembler::disassemble(&self.cpu.rom, pc_before, &self.symbols))
+        } else {
+            None
+        };
+
+        // 执行真实的CPU指令
+        self.cpu.execute_instruction(opcode);
+
+        // 在 debug 模式下，输出 [时钟周期][地址] 前缀和指令处理函数给出的反汇编文本
+        if self.cpu.debug {
+            let line = &self.cpu.trace_line;
+            let _ = write!(self.trace_out, "[{}][{:#06x}] {}", self.clock_cycles, pc_before, line);
+            if !line.ends_with('\n') {
+                let _ = writeln!(self.trace_out);
+            }
+        }
+
+        if let Some(disassembly) = disassembly {
+            let _ = writeln!(
+                self.trace_out,
+                "{:04X}: {:<9} {:<28} A={:02X} PSW={:02X}",
+                disassembly.address,
+                disassembly.hex_bytes(),
+                disassembly.text,
+                self.cpu.registers.acc,
+                self.cpu.read_sfr(PSW)
             );
+        }
 
-            if should_print {
-                let loop_type = if has_io { "I/O循环" } else { "纯延时循环" };
-                println!(
-                    "\n[LOOP FAST-FORWARD] 检测到{} ({:#06x}-{:#06x})，已执行 {} 次，I/O次数: {}，快进 {} 个周期 ({:.2} ms @ 12MHz)...",
-                    loop_type,
-                    self.loop_detector.loop_start,
-                    self.loop_detector.loop_end,
-                    self.loop_detector.loop_count,
-                    self.loop_detector.io_operation_count,
-                    multiplier,
-                    multiplier as f64 / 12_000_000.0 * 1000.0
-                );
+        // 极速模式不做循环检测，在自跳转上直接判断程序结束
+        if self.turbo
+            && self.cpu.registers.pc == pc_before
+            && self.is_self_jump(pc_before)
+            && !self.waiting_for_interrupt()
+        {
+            self.halt_reason = HaltReason::ProgramEnd;
+        }
+
+        // 程序写了退出端口：在这条指令之后停机
+        if let Some(code) = self.cpu.exit_code {
+            self.halt_reason = HaltReason::ExitCode(code);
+        }
+
+        let hits = self.cpu.take_watch_hits();
+        if !hits.is_empty() {
+            self.report_watch_hits(pc_before, hits);
+        }
+    }
+
+    // 报告观察点命中：watch_pause 时停机，由调用方显示 watch_events，否则直接输出
+    fn report_watch_hits(&mut self, pc: u16, hits: Vec<WatchHit>) {
+        let instruction = disassembler::disassemble(&self.cpu.rom, pc, &self.symbols).text;
+        self.watch_events = hits
+            .into_iter()
+            .map(|hit| WatchEvent { hit, pc, instruction: instruction.clone(), clock_cycles: self.clock_cycles })
+            .collect();
+        if self.watch_pause {
+            self.halt_reason = HaltReason::WatchpointHit;
+        } else {
+            for event in &self.watch_events {
+                info!("{}", event);
             }
+        }
+    }
 
-            // 快进：增加大量时钟周期，并且要模拟定时器的计数
-            // 但不能直接循环50万次，需要智能地模拟定时器溢出
-            let cycles_to_fast_forward = multiplier;
-            let mut cycles_forwarded = 0;
-            
-            while cycles_forwarded < cycles_to_fast_forward {
-                // 检查定时器还需要多少周期才会溢出
-                let cycles_until_overflow = self.cpu.get_cycles_until_timer_overflow();
-                
-                if cycles_until_overflow > 0 && cycles_until_overflow < (cycles_to_fast_forward - cycles_forwarded) {
-                    // 定时器会在快进期间溢出
-                    // 快进到溢出点
-                    for _ in 0..cycles_until_overflow {
-                        self.cpu.update_timers();
-                    }
-                    cycles_forwarded += cycles_until_overflow;
-                    
-                    // 检查中断
-                    if self.cpu.check_interrupts() {
-                        // 中断触发，跳出快进
-                        self.clock_cycles += cycles_forwarded;
-                        break;
-                    }
-                } else {
-                    // 定时器不会溢出，或者已经没有运行的定时器
-                    // 直接跳过剩余周期
-                    let remaining = cycles_to_fast_forward - cycles_forwarded;
-                    for _ in 0..remaining.min(10000) { // 限制一次循环不超过1万次
-                        self.cpu.update_timers();
-                        if self.cpu.check_interrupts() {
-                            break;
-                        }
-                    }
-                    cycles_forwarded += remaining.min(10000);
-                    if remaining <= 10000 {
-                        break;
+    // 循环检测：检测到紧密循环时判断程序是否已经结束或陷入死循环，并跳过单指令循环的迭代（见 skip_iterations）
+    // 看门狗启动后不做循环检测：在自跳转上判断程序结束会漏掉看门狗复位；
+    // 设有观察点、until 地址或者输出 VCD 波形时只检测不访问存储器的自跳转，以免漏掉循环里的命中、到达次数和信号变化
+    // 返回 true 表示这一步已经处理完（跳过了迭代或者已经停机），不再执行 pc 处的指令
+    fn fast_forward_loop(&mut self, pc: u16, opcode: u8) -> bool {
+        if self.turbo
+            || self.cpu.watchdog.enabled
+            || !((self.cpu.watchpoints.is_empty() && self.until.is_empty() && self.vcd.is_none())
+                || self.is_self_jump(pc))
+            || !self.loop_detector.record_pc(pc)
+        {
+            return false;
+        }
+        self.loop_detector.increment_fast_forward();
+
+        // 计算循环大小
+        let loop_size = if self.loop_detector.loop_end >= self.loop_detector.loop_start {
+            ((self.loop_detector.loop_end - self.loop_detector.loop_start) / 2) as u32 // 估算指令数
+        } else {
+            1
+        };
+        self.loop_detector.set_loop_size(loop_size.max(1));
+
+        // 只跳过单指令循环；多条指令的循环逐条执行，这里只判断死循环
+        let start = self.clock_cycles;
+        let skipped = if self.loop_detector.is_program_end() && self.loop_detector.loop_start == pc {
+            self.skip_iterations(pc, opcode, self.loop_detector.get_fast_forward_multiplier())
+        } else {
+            0
+        };
+
+        // 只在调试模式且非单指令死循环时输出快进信息
+        // 或者在单指令死循环的前几次快进时输出
+        let should_print = self.cpu.debug
+            && (!self.loop_detector.is_program_end() || self.loop_detector.same_loop_fast_forward_count < 3);
+        if should_print {
+            let elapsed = self.clock_cycles - start;
+            debug!(
+                "\n[LOOP FAST-FORWARD] 检测到循环 ({}-{})，已执行 {} 次，跳过 {} 次迭代，共 {} 个时钟周期 ({:.2} ms @ {:.4}MHz)",
+                self.format_address(self.loop_detector.loop_start),
+                self.format_address(self.loop_detector.loop_end),
+                self.loop_detector.loop_count,
+                skipped,
+                elapsed,
+                elapsed as f64 / self.cpu.clock_frequency as f64 * 1000.0,
+                self.cpu.clock_frequency as f64 / 1_000_000.0
+            );
+        }
+
+        // 快进后重置并检测死循环
+        self.loop_detector.after_fast_forward();
+
+        // 检测死循环
+        if self.loop_detector.is_deadlock() {
+            // 判断是程序正常结束还是真正的死循环
+            if self.loop_detector.is_program_end() {
+                // 单指令循环：无条件自跳转（如 sjmp $）且没有可响应的中断时程序已经结束；
+                // 否则是在等待中断或标志位（如 JNB RI,$），继续运行但不再输出快进信息
+                if self.is_self_jump(self.loop_detector.loop_start) && !self.waiting_for_interrupt() {
+                    if self.cpu.debug {
+                        debug!("\n[信息] 程序到达结束点 {}", self.format_address(self.loop_detector.loop_start));
                     }
+                    self.halt_reason = HaltReason::ProgramEnd;
+                } else if self.cpu.debug && self.loop_detector.same_loop_fast_forward_count == 51 {
+                    // 只在第一次检测到时输出一次
+                    debug!("\n[信息] 程序到达结束点 {} (进入待机循环)",
+                        self.format_address(self.loop_detector.loop_start));
                 }
-            }
-            
-            self.clock_cycles += cycles_forwarded;
-
-            // 如果是单指令等待循环（loop_size <= 1），不要修改PC，让它继续执行以便中断能触发
-            // 否则跳到循环结束之后继续
-            if loop_size > 1 {
-                self.cpu.registers.pc = self.loop_detector.loop_end.wrapping_add(1);
-            }
-
-            // 快进后重置并检测死循环
-            self.loop_detector.after_fast_forward();
-            
-            // 检测死循环
-            if self.loop_detector.is_deadlock() {
-                // 判断是程序正常结束还是真正的死循环
-                if self.loop_detector.is_program_end() {
-                    // 单指令死循环，通常是程序正常结束（如 sjmp $）
-                    // 继续运行但不再输出快进信息（可能在等待中断）
-                    if self.debug && self.loop_detector.same_loop_fast_forward_count == 51 {
-                        // 只在第一次检测到时输出一次
-                        println!("\n[信息] 程序到达结束点 {:#06x} (进入待机循环)", 
-                            self.loop_detector.loop_start);
-                    }
-                    // 不停机，继续运行
-                } else {
-                    // 真正的死循环错误
-                    println!("\n[警告] 检测到死循环在 {:#06x}-{:#06x}，程序可能在等待永远不会发生的事件（如中断或外部输入）", 
-                        self.loop_detector.loop_start, 
-                        self.loop_detector.loop_end);
-                    println!("提示: 程序在地址 {:#06x} 处陷入无限等待", pc_before);
-                    self.is_halted = true;
+            } else if self.waiting_for_interrupt() || self.waiting_for_serial() {
+                // 循环在等待中断（如轮询定时器中断服务程序设置的标志）或串口数据，继续运行
+                if self.cpu.debug && self.loop_detector.same_loop_fast_forward_count == 51 {
+                    debug!("\n[信息] 循环 {}-{} 正在等待中断或串口数据",
+                        self.format_address(self.loop_detector.loop_start),
+                        self.format_address(self.loop_detector.loop_end));
                 }
+            } else if self.loop_state_repeats(pc) {
+                // 真正的死循环错误
+                warn!("\n[警告] 检测到死循环在 {}-{}，程序可能在等待永远不会发生的事件（如中断或外部输入）",
+                    self.format_address(self.loop_detector.loop_start),
+                    self.format_address(self.loop_detector.loop_end));
+                warn!("提示: 程序在地址 {} 处陷入无限等待", self.format_address(pc));
+                self.halt_reason = HaltReason::Deadlock;
             }
+        }
 
-            return;
+        skipped > 0 || self.is_halted()
+    }
+
+    // 多条指令的循环是否已经不会自己结束：外设空闲，且这次回到循环开头时寄存器、内部 RAM 和 SFR
+    // 与上次回到这里时完全相同（之后的每次迭代都会重复同样的过程）
+    fn loop_state_repeats(&mut self, pc: u16) -> bool {
+        let registers = &self.cpu.registers;
+        let snapshot = LoopSnapshot {
+            pc,
+            acc: registers.acc,
+            b: registers.b,
+            sp: registers.sp,
+            dptr: registers.dptr,
+            ram: self.cpu.ram,
+            sfr: self.cpu.sfr,
+        };
+        let repeats = self.loop_snapshot.as_ref() == Some(&snapshot);
+        self.loop_snapshot = Some(snapshot);
+        repeats && self.cpu.peripherals_idle()
+    }
+
+    // 跳过 pc 处单指令循环的迭代，最多推进约 budget 个时钟周期，返回跳过的迭代数
+    // 只跳过结果确定的迭代：自跳转、条件没有改变的原地等待（见 waits_in_place），以及 DJNZ 计数器减到 1 之前的迭代
+    // （最后一次迭代照常执行）。每次迭代与逐条执行一样计入时钟周期、指令数和执行统计，推进外设、检查中断和看门狗，
+    // 中断响应、条件改变、到达指令数或机器周期数上限、下一个串口激励事件到期时停下；
+    // 外设空闲时后面的迭代不会再有任何事件，一次跳过
+    fn skip_iterations(&mut self, pc: u16, opcode: u8, budget: u64) -> u64 {
+        let counter = self.delay_counter(pc);
+        if (counter.is_none() && !self.waits_in_place(pc)) || self.stops_at(pc) {
+            return 0;
         }
+        let clocks = CPU::machine_cycles(opcode) as u64 * 12;
+        let end = self.clock_cycles.saturating_add(budget.max(clocks));
+        let mut skipped = 0;
+        while self.clock_cycles < end && self.may_skip(pc, counter) {
+            if skipped > 0 && self.cpu.peripherals_idle() {
+                let iterations = self.idle_iterations(counter, clocks, end);
+                self.skip_iteration(pc, opcode, counter, iterations);
+                skipped += iterations;
+                break;
+            }
+            self.skip_iteration(pc, opcode, counter, 1);
+            self.advance_peripherals(opcode);
+            self.check_interrupts();
+            self.check_watchdog();
+            skipped += 1;
+            if self.cpu.registers.pc != pc {
+                break;
+            }
+        }
+        skipped
+    }
 
-        // 每条指令消耗12个时钟周期（简化）
-        self.clock_cycles += 12;
+    // 计入 iterations 次迭代（不推进外设）：时钟周期、指令数、执行统计，以及 DJNZ 的计数器
+    fn skip_iteration(&mut self, pc: u16, opcode: u8, counter: Option<usize>, iterations: u64) {
+        let clocks = CPU::machine_cycles(opcode) as u64 * 12 * iterations;
+        self.clock_cycles += clocks;
+        self.instruction_count += iterations;
+        self.cpu.opcode_counts[opcode as usize] += iterations;
+        self.profile(pc, clocks);
+        if let Some(counts) = &mut self.pc_counts {
+            *counts.entry(pc).or_insert(0) += iterations;
+        }
+        if let Some(address) = counter {
+            self.cpu.ram[address] = self.cpu.ram[address].wrapping_sub(iterations as u8);
+        }
+    }
 
-        // 在 debug 模式下，打印 [时钟周期][地址] 前缀
-        if self.debug {
-            print!("[{}][{:#06x}] ", self.clock_cycles, pc_before);
+    // 下一次迭代能否跳过：没有到达指令数和机器周期数上限，没有到期的串口激励事件，且循环条件仍然成立
+    fn may_skip(&self, pc: u16, counter: Option<usize>) -> bool {
+        (self.max_instructions == 0 || self.instruction_count < self.max_instructions)
+            && (self.max_cycles == 0 || self.clock_cycles / 12 < self.max_cycles)
+            && self.serial_script.front().is_none_or(|event| event.clock_cycle > self.clock_cycles)
+            && match counter {
+                Some(address) => self.cpu.ram[address] != 1,
+                None => self.waits_in_place(pc),
+            }
+    }
+
+    // 外设空闲时可以一次跳过的迭代数：与逐条跳过时 may_skip 和 end 给出的界限相同
+    fn idle_iterations(&self, counter: Option<usize>, clocks: u64, end: u64) -> u64 {
+        let until = |limit: u64| limit.saturating_sub(self.clock_cycles).div_ceil(clocks);
+        let mut iterations = until(end);
+        if self.max_instructions != 0 {
+            iterations = iterations.min(self.max_instructions - self.instruction_count);
+        }
+        if self.max_cycles != 0 {
+            iterations = iterations.min(until(self.max_cycles.saturating_mul(12)));
+        }
+        if let Some(event) = self.serial_script.front() {
+            iterations = iterations.min(until(event.clock_cycle));
+        }
+        if let Some(address) = counter {
+            // 计数器为 0 时还要迭代 256 次
+            let count = match self.cpu.ram[address] {
+                0 => 256,
+                count => count as u64,
+            };
+            iterations = iterations.min(count - 1);
         }
+        iterations
+    }
 
-        // 执行真实的CPU指令
-        self.cpu.execute_instruction(opcode, self.debug, &mut self.delay_skip_counter);
+    // pc 处为 DJNZ Rn,$ 或者 DJNZ direct,$（direct 在内部 RAM 的低 128 字节）时返回计数器的 RAM 地址
+    fn delay_counter(&self, pc: u16) -> Option<usize> {
+        let rom = &self.cpu.rom;
+        let byte = |offset: u16| rom[pc.wrapping_add(offset) as usize];
+        match byte(0) {
+            opcode @ 0xD8..=0xDF if byte(1) == 0xFE => Some(self.cpu.get_register_address(opcode & 0x07)),
+            0xD5 if byte(2) == 0xFD && byte(1) < 0x80 => Some(byte(1) as usize),
+            _ => None,
+        }
     }
 
-    // 执行带调试信息的端口写入
-    pub fn write_sfr(&mut self, addr: u8, value: u8) {
-        if self.debug && addr == 0x90 {
-            // P1端口输出 (串口数据输出)
-            if value >= 0x20 && value <= 0x7E {
-                print!("[串口输出] 字符: {} (ASCII {:#04x})", value as char, value);
+    // pc 处的指令现在执行是否原地跳转且不改变任何状态：自跳转，以及条件仍然成立的
+    // JB/JNB bit,$（位不在端口上，端口位读的是引脚电平）、JC/JNC $ 和 JZ/JNZ $
+    fn waits_in_place(&self, pc: u16) -> bool {
+        let rom = &self.cpu.rom;
+        let byte = |offset: u16| rom[pc.wrapping_add(offset) as usize];
+        match byte(0) {
+            opcode @ (0x20 | 0x30) if byte(2) == 0xFD => {
+                let bit = byte(1);
+                let port = bit >= 0x80 && matches!(bit & 0xF8, P0 | P1 | P2 | P3);
+                !port && self.cpu.read_bit_latch(bit) == (opcode == 0x20)
+            }
+            opcode @ (0x40 | 0x50) if byte(1) == 0xFE => {
+                (self.cpu.peek_sfr(PSW) & PSW_CY != 0) == (opcode == 0x40)
+            }
+            opcode @ (0x60 | 0x70) if byte(1) == 0xFE => (self.cpu.registers.acc == 0) == (opcode == 0x60),
+            _ => self.is_self_jump(pc),
+        }
+    }
+
+    // 地址处是否为跳转到自身的无条件跳转（SJMP $、AJMP $、LJMP $）
+    fn is_self_jump(&self, pc: u16) -> bool {
+        let rom = &self.cpu.rom;
+        let byte = |offset: u16| rom[pc.wrapping_add(offset) as usize];
+        let opcode = byte(0);
+        match opcode {
+            0x80 => byte(1) == 0xFE,
+            0x02 => (((byte(1) as u16) << 8) | byte(2) as u16) == pc,
+            _ if opcode & 0x1F == 0x01 => {
+                let next_pc = pc.wrapping_add(2);
+                ((next_pc & 0xF800) | (((opcode >> 5) as u16) << 8) | byte(1) as u16) == pc
+            }
+            _ => false,
+        }
+    }
+
+    // 串口是否还有数据可能到来（或正在发送）：
+    // 接收已允许且有待接收的数据、输入流（如 TCP 连接、标准输入）或激励事件，或者发送尚未完成
+    fn waiting_for_serial(&self) -> bool {
+        let serial = &self.cpu.serial;
+        let receiving = (self.cpu.read_sfr(SCON) & SCON_REN) != 0
+            && (!serial.rx_pending.is_empty() || serial.rx_source.is_some() || !self.serial_script.is_empty());
+        receiving || serial.tx_busy
+    }
+
+    // 是否还有中断可能到来：EA 置位，且某个已使能的中断源已经有请求，
+    // 或者它的硬件正在运行（定时器在计数、串口在收发、外部中断引脚为低或由外部器件驱动）
+    fn waiting_for_interrupt(&self) -> bool {
+        let ie = self.cpu.read_sfr(IE);
+        if (ie & IE_EA) == 0 {
+            return false;
+        }
+        let tcon = self.cpu.read_sfr(TCON);
+        let tmod = self.cpu.read_sfr(TMOD);
+        let scon = self.cpu.read_sfr(SCON);
+        let t2con = self.cpu.read_sfr(T2CON);
+        // 定时器0处于模式3时 TH0 由 TR1 控制并占用 TF1
+        let t1_armed = (tcon & TR1) != 0 || ((tmod & 0x03) == 3 && (tcon & TR0) != 0);
+        let serial = &self.cpu.serial;
+
+        let armed = [
+            (IE_EX0, self.cpu.external_interrupt_possible(0)),
+            (IE_ET0, (tcon & (TR0 | TF0)) != 0),
+            (IE_EX1, self.cpu.external_interrupt_possible(1)),
+            (IE_ET1, t1_armed || (tcon & TF1) != 0),
+            (IE_ES, (scon & (SCON_REN | SCON_RI | SCON_TI)) != 0 || serial.tx_busy),
+            (IE_ET2, self.cpu.variant.has_timer2() && (t2con & (T2CON_TR2 | T2CON_TF2 | T2CON_EXF2)) != 0),
+            (IE_EADC, self.cpu.cycles_until_adc_done() > 0 || self.cpu.adc_flag() != 0),
+        ];
+        armed.iter().any(|&(enable, active)| (ie & enable) != 0 && active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Emulator, EmulatorStats, HaltReason, OpcodeCount, IE, RUNAWAY_NOP_LIMIT, TCON};
+    use crate::cpu::peripherals::{PowerMode, TL0};
+    use crate::cpu::{Variant, CPU, DEFAULT_XRAM_SIZE};
+    use crate::memory_dump::MemorySpace;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use crate::test_support::{capture_logs, emulator_with, emulator_with_blocks, run_steps, run_to_halt, shared_output, PinDriver};
+
+    // MOV P2,#0; MOV R0,#0x10; MOV R7,#4; 循环: MOV A,R7; MOVX @R0,A; INC R0; DJNZ R7,循环; MOV P1,#0x5A; SJMP $
+    const FILL_XRAM: [u8; 17] =
+        [0x75, 0xA0, 0x00, 0x78, 0x10, 0x7F, 0x04, 0xEF, 0xF2, 0x08, 0xDF, 0xFB, 0x75, 0x90, 0x5A, 0x80, 0xFE];
+
+    // 执行到 SJMP $ 为止的步数
+    const FILL_XRAM_STEPS: usize = 20;
+
+    #[test]
+    fn dumps_xram_filled_by_movx() {
+        let mut emulator = emulator_with(&FILL_XRAM);
+        run_steps(&mut emulator, FILL_XRAM_STEPS);
+        let dump = emulator.dump_memory(MemorySpace::Xram, 0x10..0x14);
+        assert_eq!(dump, format!("0010: 04 03 02 01{}  |....|\n", "   ".repeat(12)));
+        // 程序存储器不受 MOVX 写入影响
+        let code = emulator.dump_memory(MemorySpace::Xram, 0x00..0x02);
+        assert!(code.starts_with("0000: 00 00 "));
+    }
+
+    #[test]
+    fn dumps_ram_and_annotated_sfrs() {
+        let mut emulator = emulator_with(&FILL_XRAM);
+        run_steps(&mut emulator, FILL_XRAM_STEPS);
+        assert_eq!(emulator.dump_memory(MemorySpace::Ram, 0x00..0x01), format!("0000: 14{}  |.|\n", "   ".repeat(15)));
+        assert_eq!(emulator.dump_memory(MemorySpace::Sfr, 0x90..0x91), format!("0090: 5A{}  |Z|\n", "   ".repeat(15)));
+        let sfrs = emulator.annotate_sfrs();
+        assert!(sfrs.contains("90 P1     = 5A 01011010\n"));
+        assert!(sfrs.contains("A0 P2     = 00 00000000\n"));
+    }
+
+    #[test]
+    fn xram_dump_is_clamped_to_xram_size() {
+        let mut emulator = emulator_with(&FILL_XRAM);
+        emulator.cpu.xram = vec![0xAB; 0x20];
+        assert_eq!(emulator.dump_memory(MemorySpace::Xram, 0x1F..0x100).lines().count(), 1);
+        assert!(emulator.dump_memory(MemorySpace::Xram, 0x20..0x30).is_empty());
+    }
+
+    #[test]
+    fn builder_sets_configured_fields() {
+        let emulator = Emulator::builder()
+            .clock(11_059_200)
+            .variant(Variant::I8052)
+            .xram_size(0x800)
+            .vector_base(0x2000)
+            .strict_opcodes(true)
+            .max_cycles(1000)
+            .build();
+        assert_eq!(emulator.cpu.clock_frequency, 11_059_200);
+        assert_eq!(emulator.cpu.variant, Variant::I8052);
+        assert_eq!(emulator.cpu.xram.len(), 0x800);
+        assert_eq!(emulator.cpu.registers.pc, 0x2000);
+        assert!(emulator.strict_opcodes);
+        assert_eq!(emulator.max_cycles, 1000);
+        assert_eq!(Emulator::builder().build().cpu.xram.len(), DEFAULT_XRAM_SIZE);
+        assert_eq!(Emulator::builder().xram_size(0x20000).build().cpu.xram.len(), DEFAULT_XRAM_SIZE);
+    }
+
+    // MOV IE,#0x81（EA 和 EX0）; SJMP $
+    const SJMP_WITH_EX0: [u8; 5] = [0x75, 0xA8, 0x81, 0x80, 0xFE];
+
+    #[test]
+    fn self_jump_with_idle_int0_enabled_is_program_end() {
+        let mut emulator = emulator_with(&SJMP_WITH_EX0);
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::ProgramEnd);
+    }
+
+    #[test]
+    fn self_jump_waits_while_a_device_drives_int0() {
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x0003, &[0x05, 0x40, 0x32]), (0x0030, &SJMP_WITH_EX0)]);
+        let int0 = PinDriver::attach(&mut emulator, 3, 2);
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::Running);
+        int0.set(false);
+        run_steps(&mut emulator, 3);
+        assert!(emulator.cpu.ram[0x40] > 0);
+    }
+
+    #[test]
+    fn self_jump_with_int0_held_low_keeps_running() {
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x0003, &[0x32]), (0x0030, &SJMP_WITH_EX0)]);
+        emulator.cpu.set_pin(3, 2, false);
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::Running);
+        assert!(emulator.cpu.interrupt_entries > 1);
+    }
+
+    #[test]
+    fn poll_loop_escapes_when_timer0_isr_sets_flag() {
+        // 0x0030: MOV TMOD,#0x01; MOV TH0,#0xFF; MOV TL0,#0x00; SETB TR0; MOV IE,#0x82
+        //         循环: MOV A,0x30; JZ 循环; MOV P1,#0x55; SJMP $
+        // 定时器0服务程序 (0x000B)：MOV 0x30,#1; CLR TR0; RETI
+        let main = [
+            0x75, 0x89, 0x01, 0x75, 0x8C, 0xFF, 0x75, 0x8A, 0x00, 0xD2, 0x8C, 0x75, 0xA8, 0x82, 0xE5, 0x30, 0x60, 0xFC,
+            0x75, 0x90, 0x55, 0x80, 0xFE,
+        ];
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x02, 0x00, 0x30]),
+            (0x000B, &[0x75, 0x30, 0x01, 0xC2, 0x8C, 0x32]),
+            (0x0030, &main),
+        ]);
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::ProgramEnd);
+        assert_eq!(emulator.cpu.ram[0x30], 1);
+        assert_eq!(emulator.cpu.peek_sfr(0x90), 0x55);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+    }
+
+    #[test]
+    fn self_jump_is_program_end() {
+        let mut emulator = emulator_with(&[0x74, 0x01, 0x80, 0xFE]);
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::ProgramEnd);
+        assert_eq!(emulator.cpu.registers.pc, 0x0002);
+    }
+
+    #[test]
+    fn poll_loop_nobody_satisfies_is_deadlock() {
+        // 循环: MOV A,0x30; JZ 循环（没有中断，也没有别的东西写 0x30）
+        let mut emulator = emulator_with(&[0xE5, 0x30, 0x60, 0xFC]);
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::Deadlock);
+    }
+
+    #[test]
+    fn limits_breakpoints_and_runaway_stop_with_their_reasons() {
+        let mut emulator = Emulator::builder().max_instructions(3).build();
+        crate::test_support::load(&mut emulator, &[0x00, 0x00, 0x00, 0x00, 0x80, 0xFE]);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::InstructionLimit);
+        assert_eq!(emulator.instruction_count, 3);
+
+        let mut emulator = emulator_with(&[0x00, 0x00, 0x80, 0xFE]);
+        emulator.breakpoints.insert(0x0002);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::BreakpointHit);
+        assert_eq!(emulator.cpu.registers.pc, 0x0002);
+
+        // MOV A,#1 之后是未加载的 ROM
+        let mut emulator = emulator_with(&[0x74, 0x01]);
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::RanOffEnd);
+    }
+
+    #[test]
+    fn breakpoint_in_loop_stops_on_each_iteration() {
+        // MOV R7,#3; 循环: INC A; DJNZ R7,循环; SJMP $
+        let mut emulator = emulator_with(&[0x7F, 0x03, 0x04, 0xDF, 0xFD, 0x80, 0xFE]);
+        emulator.breakpoints.insert(0x0002);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::BreakpointHit);
+        assert_eq!((emulator.cpu.registers.pc, emulator.cpu.registers.acc, emulator.cpu.ram[7]), (0x0002, 0, 3));
+
+        // 从断点继续：断点处的指令照常执行，下一次迭代再停下
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::BreakpointHit);
+        assert_eq!((emulator.cpu.registers.pc, emulator.cpu.registers.acc, emulator.cpu.ram[7]), (0x0002, 1, 2));
+
+        emulator.breakpoints.clear();
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::ProgramEnd);
+        assert_eq!(emulator.cpu.registers.acc, 3);
+    }
+
+    #[test]
+    fn breakpoint_on_interrupt_vector_stops_after_vectoring() {
+        // 0x0000：LJMP 0x0030；定时器0中断 (0x000B)：INC 0x40; RETI
+        // 0x0030：MOV TMOD,#0x02; MOV TH0,#0xF0; SETB TR0; MOV IE,#0x82; SJMP $
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x02, 0x00, 0x30]),
+            (0x000B, &[0x05, 0x40, 0x32]),
+            (0x0030, &[0x75, 0x89, 0x02, 0x75, 0x8C, 0xF0, 0xD2, 0x8C, 0x75, 0xA8, 0x82, 0x80, 0xFE]),
+        ]);
+        emulator.breakpoints.insert(0x000B);
+        assert_eq!(run_to_halt(&mut emulator, 1000), HaltReason::BreakpointHit);
+        assert_eq!(emulator.cpu.registers.pc, 0x000B);
+        // 返回地址已经压栈，服务程序还没有执行
+        assert_eq!(emulator.cpu.registers.sp, 0x09);
+        assert_eq!(emulator.cpu.ram[0x40], 0);
+    }
+
+    #[test]
+    fn run_cycles_stops_on_an_instruction_boundary() {
+        // MOV R7,#60（1 个机器周期）; DJNZ R7,$（每次 2 个）
+        let mut emulator = emulator_with(&[0x7F, 0x3C, 0xDF, 0xFE]);
+        let cycles = emulator.run_cycles(100);
+        assert_eq!(cycles, 101);
+        assert_eq!(emulator.clock_cycles, 101 * 12);
+        assert_eq!(emulator.cpu.ram[7], 10);
+        assert_eq!(emulator.instruction_count, 51);
+    }
+
+    #[test]
+    fn instructions_charge_their_machine_cycles() {
+        // MOV A,#2（1）; MOV B,#3（2）; MUL AB（4）; SJMP $（2）
+        let mut emulator = emulator_with(&[0x74, 0x02, 0x75, 0xF0, 0x03, 0xA4, 0x80, 0xFE]);
+        let mut clocks = Vec::new();
+        for _ in 0..4 {
+            emulator.step();
+            clocks.push(emulator.clock_cycles / 12);
+        }
+        assert_eq!(clocks, [1, 3, 7, 9]);
+        assert_eq!(emulator.run_cycles(5), 6);
+        assert_eq!(emulator.cpu.registers.acc, 6);
+    }
+
+    #[test]
+    fn timer0_counts_every_machine_cycle_of_an_instruction() {
+        // MOV TMOD,#0x01; SETB TR0; MUL AB; DIV AB; NOP
+        let mut emulator = emulator_with(&[0x75, 0x89, 0x01, 0xD2, 0x8C, 0xA4, 0x84, 0x00]);
+        run_steps(&mut emulator, 2);
+        let start = emulator.cpu.peek_sfr(0x8A);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.peek_sfr(0x8A) - start, 4 + 4 + 1);
+    }
+
+    #[test]
+    fn machine_cycle_table() {
+        for (opcode, cycles) in [(0x00, 1), (0x74, 1), (0xE5, 1), (0x75, 2), (0x85, 2), (0x80, 2), (0x12, 2), (0x22, 2),
+            (0xD8, 2), (0xB4, 2), (0xE0, 2), (0xF2, 2), (0x93, 2), (0xC0, 2), (0x92, 2), (0xA2, 1), (0xA4, 4), (0x84, 4)]
+        {
+            assert_eq!(CPU::machine_cycles(opcode), cycles, "操作码 {:#04x}", opcode);
+        }
+    }
+
+    // 定时器0中断: MOV TH0,#0xFC; INC 0x30; CPL P1.0; RETI
+    const TIMER0_ISR: [u8; 8] = [0x75, 0x8C, 0xFC, 0x05, 0x30, 0xB2, 0x90, 0x32];
+
+    // MOV TMOD,#0x11; MOV TH0,#0xFC; SETB TR0; SETB TR1; MOV IE,#0x82
+    // 外层: MOV R6,#5; L: MOV R7,#0; DJNZ R7,$; DJNZ R6,L; JNB TF1,$; CLR TF1; INC 0x31; SJMP 外层
+    const TIMED_LOOPS: [u8; 30] = [
+        0x75, 0x89, 0x11, 0x75, 0x8C, 0xFC, 0xD2, 0x8C, 0xD2, 0x8E, 0x75, 0xA8, 0x82, 0x7E, 0x05, 0x7F, 0x00, 0xDF,
+        0xFE, 0xDE, 0xFA, 0x30, 0x8F, 0xFD, 0xC2, 0x8F, 0x05, 0x31, 0x80, 0xEF,
+    ];
+
+    // MOV 0x40,#3; L: MOV R7,#0; DJNZ R7,$; DJNZ 0x40,L; INC 0x41; SJMP 0（外设一直空闲）
+    const IDLE_LOOPS: [u8; 14] = [0x75, 0x40, 0x03, 0x7F, 0x00, 0xDF, 0xFE, 0xD5, 0x40, 0xF9, 0x05, 0x41, 0x80, 0xF2];
+
+    // 运行到机器周期数上限，返回模拟器和调用 step 的次数
+    fn run_to_cycle_limit(mut emulator: Emulator, turbo: bool, cycles: u64) -> (Emulator, usize) {
+        emulator.turbo = turbo;
+        emulator.max_cycles = cycles;
+        let mut steps = 0;
+        while emulator.step() == HaltReason::Running {
+            steps += 1;
+        }
+        assert_eq!(emulator.halt_reason, HaltReason::CycleLimit);
+        (emulator, steps)
+    }
+
+    // 比较两次运行的全部可见状态
+    fn assert_same_state(fast: &Emulator, exact: &Emulator) {
+        let registers = |emulator: &Emulator| {
+            let r = &emulator.cpu.registers;
+            (r.acc, r.b, r.pc, r.sp, r.dptr)
+        };
+        assert_eq!(registers(fast), registers(exact));
+        assert_eq!(fast.cpu.ram, exact.cpu.ram);
+        assert_eq!(fast.cpu.sfr, exact.cpu.sfr);
+        assert_eq!(fast.clock_cycles, exact.clock_cycles);
+        assert_eq!(fast.instruction_count, exact.instruction_count);
+        assert_eq!(fast.cpu.interrupt_entries, exact.cpu.interrupt_entries);
+        assert_eq!(fast.cpu.opcode_counts, exact.cpu.opcode_counts);
+    }
+
+    #[test]
+    fn loop_fast_forward_matches_turbo_with_timer_interrupts() {
+        let blocks: [(u16, &[u8]); 3] = [(0x0000, &[0x02, 0x00, 0x30]), (0x000B, &TIMER0_ISR), (0x0030, &TIMED_LOOPS)];
+        for cycles in [1_000, 65_536, 200_003] {
+            let (fast, fast_steps) = run_to_cycle_limit(emulator_with_blocks(&blocks), false, cycles);
+            let (exact, exact_steps) = run_to_cycle_limit(emulator_with_blocks(&blocks), true, cycles);
+            assert_same_state(&fast, &exact);
+            // 定时器1从 0 开始计满 65536 个机器周期才溢出，之后 JNB TF1,$ 后面的 INC 0x31 才会执行
+            assert_eq!(exact.cpu.ram[0x31] > 0, cycles > 65_536);
+            if cycles > 1_000 {
+                assert!(fast_steps < exact_steps, "快进 {} 步，逐条 {} 步", fast_steps, exact_steps);
+                assert!(exact.cpu.interrupt_entries > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn idle_loop_fast_forward_matches_turbo() {
+        for cycles in [700, 100_000, 1_000_001] {
+            let (fast, fast_steps) = run_to_cycle_limit(emulator_with(&IDLE_LOOPS), false, cycles);
+            let (exact, exact_steps) = run_to_cycle_limit(emulator_with(&IDLE_LOOPS), true, cycles);
+            assert_same_state(&fast, &exact);
+            if cycles > 700 {
+                // 每次 DJNZ R7,$ 先逐条执行 100 次才触发快进，余下的 155 次一步跳过
+                assert!(fast_steps * 2 < exact_steps, "快进 {} 步，逐条 {} 步", fast_steps, exact_steps);
+            }
+        }
+    }
+
+    #[test]
+    fn loop_fast_forward_stops_at_instruction_limit() {
+        // MOV R7,#0; DJNZ R7,$ 执行到第 150 条指令停机，R7 减了 149 次
+        let mut emulator = emulator_with(&[0x7F, 0x00, 0xDF, 0xFE, 0x80, 0xFE]);
+        emulator.max_instructions = 150;
+        assert_eq!(run_to_halt(&mut emulator, 1_000), HaltReason::InstructionLimit);
+        assert_eq!(emulator.instruction_count, 150);
+        assert_eq!(emulator.cpu.ram[7], (256 - 149) as u8);
+        assert_eq!(emulator.clock_cycles, (1 + 149 * 2) * 12);
+    }
+
+    #[test]
+    fn exit_port_is_only_intercepted_when_configured() {
+        // MOV 0xFF,#2; MOV A,#1; SJMP $
+        let program = [0x75, 0xFF, 0x02, 0x74, 0x01, 0x80, 0xFE];
+        let mut emulator = emulator_with(&program);
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::ProgramEnd);
+        assert_eq!(emulator.cpu.registers.acc, 1);
+
+        let mut emulator = emulator_with(&program);
+        emulator.cpu.exit_port = Some(0xFF);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::ExitCode(2));
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+    }
+
+    #[test]
+    fn unknown_opcode_warns_through_the_logger() {
+        // 0xA3 (INC DPTR) 没有实现：默认跳过并输出警告
+        let mut emulator = emulator_with(&[0xA3, 0x00]);
+        let logs = capture_logs(|| run_steps(&mut emulator, 1));
+        assert_eq!(logs, [(log::Level::Warn, "未知指令: 操作码 = 0xa3".to_string())]);
+        assert_eq!(emulator.cpu.registers.pc, 0x0001);
+    }
+
+    // 0x0030: MOV TMOD,#0x01; MOV TH0,#0xFF; MOV TL0,#0x00; MOV IE,#0x82; SETB TR0; ORL PCON,#0x01（进入空闲）;
+    //         MOV 0x41,#0x55; SJMP $
+    // 定时器0服务程序 (0x000B)：INC 0x40; RETI
+    // 运行到 ORL PCON 执行完
+    fn idle_timer0_program() -> Emulator {
+        let main = [
+            0x75, 0x89, 0x01, 0x75, 0x8C, 0xFF, 0x75, 0x8A, 0x00, 0x75, 0xA8, 0x82, 0xD2, 0x8C, 0x43, 0x87, 0x01, 0x75, 0x41,
+            0x55, 0x80, 0xFE,
+        ];
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x000B, &[0x05, 0x40, 0x32]), (0x0030, &main)]);
+        run_steps(&mut emulator, 7);
+        emulator
+    }
+
+    #[test]
+    fn timer0_interrupt_wakes_idle_cpu() {
+        let mut emulator = idle_timer0_program();
+        assert_eq!(emulator.cpu.power_mode(), PowerMode::Idle);
+        assert_eq!(emulator.cpu.registers.pc, 0x0041);
+        let idle_at = emulator.clock_cycles;
+
+        // 空闲期间定时器继续计数，溢出后的中断唤醒 CPU，RETI 后执行 ORL PCON 的下一条指令
+        emulator.step();
+        assert_eq!(emulator.cpu.power_mode(), PowerMode::Normal);
+        assert_eq!(emulator.cpu.registers.pc, 0x000B);
+        assert!(emulator.clock_cycles - idle_at >= 250 * 12);
+        run_steps(&mut emulator, 3);
+        assert_eq!(emulator.cpu.ram[0x40], 1);
+        assert_eq!(emulator.cpu.ram[0x41], 0x55);
+        assert_eq!(emulator.halt_reason, HaltReason::Running);
+    }
+
+    #[test]
+    fn stats_convert_cycles_to_simulated_time() {
+        let stats = EmulatorStats::new(3_000_000, 24_000_000, 12_000_000, Duration::from_secs(2));
+        assert_eq!(stats.machine_cycles, 2_000_000);
+        assert_eq!(stats.simulated_time, 2.0);
+        assert_eq!(stats.mips, 1.5);
+
+        // 4 个 NOP 在 11.0592MHz 下：48 个时钟周期
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        run_steps(&mut emulator, 4);
+        let stats = emulator.stats();
+        assert_eq!((stats.instructions, stats.clock_cycles, stats.machine_cycles), (4, 48, 4));
+        assert!((stats.simulated_time - 48.0 / 11_059_200.0).abs() < 1e-15);
+    }
+
+    // NOP; 循环: MOV WDTRST,#0x1E; MOV WDTRST,#0xE1（启动或喂狗），然后 feed 时 DJNZ R7,$ 延时（512 个机器周期）后
+    // 回到循环，否则 SJMP $；只有复位才会回到 0x0000。返回 AT89S52 模拟器
+    fn watchdog_program(feed: bool) -> Emulator {
+        let start = [0x00, 0x75, 0xA6, 0x1E, 0x75, 0xA6, 0xE1];
+        let tail: &[u8] = if feed { &[0xDF, 0xFE, 0x80, 0xF6] } else { &[0x80, 0xFE] };
+        let mut emulator = Emulator::builder().variant(Variant::AT89S52).build();
+        emulator.cpu.rom[..7].copy_from_slice(&start);
+        emulator.cpu.rom[7..7 + tail.len()].copy_from_slice(tail);
+        emulator
+    }
+
+    #[test]
+    fn fed_watchdog_never_resets() {
+        let mut emulator = watchdog_program(true);
+        emulator.step();
+        while emulator.clock_cycles < 200_000 * 12 {
+            assert_eq!(emulator.step(), HaltReason::Running);
+            assert_ne!(emulator.cpu.registers.pc, 0x0000);
+        }
+    }
+
+    #[test]
+    fn starved_watchdog_resets_after_16384_machine_cycles() {
+        let mut emulator = watchdog_program(false);
+        emulator.step();
+        loop {
+            assert_eq!(emulator.step(), HaltReason::Running);
+            if emulator.cpu.registers.pc == 0x0000 {
+                break;
+            }
+        }
+        // 启动看门狗的第二条 MOV 从第 3 个机器周期开始，它本身的周期也计入计数
+        assert_eq!(emulator.clock_cycles / 12, 3 + 16384);
+        assert!(!emulator.cpu.watchdog.enabled);
+    }
+
+    #[test]
+    fn idle_executes_nothing_until_tf0() {
+        let mut emulator = idle_timer0_program();
+        let executed = emulator.instruction_count;
+        let mut steps = 0;
+        while emulator.cpu.registers.pc != 0x000B {
+            emulator.step();
+            steps += 1;
+            assert!(steps < 300);
+            assert_eq!(emulator.instruction_count, executed);
+            assert_eq!(emulator.cpu.ram[0x41], 0);
+        }
+        assert_eq!(emulator.cpu.ram[0x40], 0);
+
+        // 服务程序返回后从 ORL PCON 的下一条指令继续
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.pc, 0x0041);
+        run_steps(&mut emulator, 1);
+        assert_eq!((emulator.cpu.ram[0x40], emulator.cpu.ram[0x41]), (1, 0x55));
+    }
+
+    // 0x0030: MOV TMOD,#0x01; SETB TR0; MOV IE,#0x81（EX0）; ORL PCON,#0x02（掉电）; MOV 0x41,#0x55; SJMP $
+    // INT0 服务程序 (0x0003)：INC 0x40; RETI
+    fn power_down_program(wake: bool) -> Emulator {
+        let main = [0x75, 0x89, 0x01, 0xD2, 0x8C, 0x75, 0xA8, 0x81, 0x43, 0x87, 0x02, 0x75, 0x41, 0x55, 0x80, 0xFE];
+        let mut emulator = emulator_with_blocks(&[(0x0000, &[0x02, 0x00, 0x30]), (0x0003, &[0x05, 0x40, 0x32]), (0x0030, &main)]);
+        emulator.power_down_wake = wake;
+        run_steps(&mut emulator, 5);
+        assert_eq!(emulator.cpu.power_mode(), PowerMode::PowerDown);
+        emulator
+    }
+
+    #[test]
+    fn power_down_halts_by_default() {
+        let mut emulator = power_down_program(false);
+        let (clocks, tl0) = (emulator.clock_cycles, emulator.cpu.peek_sfr(TL0));
+        assert_eq!(emulator.step(), HaltReason::PowerDown);
+        assert_eq!(emulator.cpu.registers.pc, 0x003B);
+        assert_eq!((emulator.clock_cycles, emulator.cpu.peek_sfr(TL0)), (clocks, tl0));
+        assert_eq!(emulator.cpu.ram[0x41], 0);
+    }
+
+    #[test]
+    fn enabled_int0_wakes_from_power_down() {
+        let mut emulator = power_down_program(true);
+        let (clocks, tl0) = (emulator.clock_cycles, emulator.cpu.peek_sfr(TL0));
+        // 振荡器停止：引脚没有变化时什么都不前进
+        for _ in 0..5 {
+            assert_eq!(emulator.step(), HaltReason::Running);
+        }
+        assert_eq!((emulator.clock_cycles, emulator.cpu.peek_sfr(TL0)), (clocks, tl0));
+        assert_eq!(emulator.cpu.registers.pc, 0x003B);
+
+        emulator.cpu.set_pin(3, 2, false);
+        emulator.step();
+        assert_eq!(emulator.cpu.power_mode(), PowerMode::Normal);
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+        emulator.cpu.set_pin(3, 2, true);
+        run_steps(&mut emulator, 3);
+        assert_eq!((emulator.cpu.ram[0x40], emulator.cpu.ram[0x41]), (1, 0x55));
+    }
+
+    #[test]
+    fn trace_prints_one_disassembled_line_per_instruction() {
+        // MOV A,#0x12; INC A; MOV R0,A; NOP
+        let mut emulator = emulator_with(&[0x74, 0x12, 0x04, 0xF8, 0x00]);
+        let (output, out) = shared_output();
+        emulator.set_trace_output(out);
+        emulator.trace = true;
+        run_steps(&mut emulator, 4);
+
+        let output = String::from_utf8(output.borrow().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("0000: 74 12"), "{}", lines[0]);
+        assert!(lines[1].starts_with("0002: 04") && lines[1].contains("inc A") && lines[1].ends_with("A=13 PSW=01"));
+        assert!(lines[3].starts_with("0004: 00") && lines[3].contains("nop"));
+    }
+
+    #[test]
+    fn debug_emulator_makes_the_cpu_emit_debug_lines() {
+        // MOV A,#0x12; INC A
+        for (debug, expected) in [(true, 2), (false, 0)] {
+            let mut emulator = Emulator::new(debug);
+            crate::test_support::load(&mut emulator, &[0x74, 0x12, 0x04]);
+            assert_eq!(emulator.cpu.debug, debug);
+            let (output, out) = shared_output();
+            emulator.set_trace_output(out);
+            run_steps(&mut emulator, 2);
+
+            let output = String::from_utf8(output.borrow().clone()).unwrap();
+            let lines: Vec<&str> = output.lines().filter(|line| line.starts_with('[')).collect();
+            assert_eq!(lines.len(), expected, "{}", output);
+            if debug {
+                assert!(lines[0].starts_with("[12][0x0000] mov A, #0x12"), "{}", lines[0]);
+            }
+        }
+        assert!(Emulator::builder().debug(true).build().cpu.debug);
+    }
+
+    #[test]
+    fn instruction_limit_stops_an_endless_loop() {
+        // 循环: INC A; SJMP 循环
+        let mut emulator = Emulator::builder().max_instructions(5).build();
+        emulator.cpu.rom[..3].copy_from_slice(&[0x04, 0x80, 0xFD]);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::InstructionLimit);
+        assert_eq!(emulator.instruction_count, 5);
+        assert_eq!(emulator.cpu.registers.acc, 3);
+    }
+
+    #[test]
+    fn cycle_limit_is_not_overshot_by_fast_forward() {
+        // SJMP $：循环快进一次可以跳过很多迭代，但不能越过上限
+        let mut emulator = Emulator::builder().max_cycles(1000).build();
+        emulator.cpu.rom[..2].copy_from_slice(&[0x80, 0xFE]);
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::CycleLimit);
+        assert!((1000..=1001).contains(&(emulator.clock_cycles / 12)), "{}", emulator.clock_cycles / 12);
+    }
+
+    #[test]
+    fn zero_limits_mean_unlimited() {
+        // 循环: INC A; SJMP 循环
+        let mut emulator = Emulator::builder().max_instructions(0).max_cycles(0).build();
+        emulator.cpu.rom[..3].copy_from_slice(&[0x04, 0x80, 0xFD]);
+        run_steps(&mut emulator, 1000);
+        assert_eq!(emulator.halt_reason, HaltReason::Running);
+    }
+
+    // NOP; NOP; 保留操作码 0xA5; INC A; SJMP $
+    const RESERVED_OPCODE: [u8; 6] = [0x00, 0x00, 0xA5, 0x04, 0x80, 0xFE];
+
+    #[test]
+    fn strict_mode_halts_on_reserved_opcode() {
+        let mut emulator = Emulator::builder().strict_opcodes(true).build();
+        emulator.cpu.rom[..RESERVED_OPCODE.len()].copy_from_slice(&RESERVED_OPCODE);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::UnknownOpcode(0x0002, 0xA5));
+        assert_eq!(emulator.cpu.registers.pc, 0x0002);
+        assert_eq!(emulator.instruction_count, 2);
+        assert_eq!(emulator.cpu.registers.acc, 0);
+    }
+
+    #[test]
+    fn default_mode_skips_reserved_opcode() {
+        let mut emulator = emulator_with(&RESERVED_OPCODE);
+        run_steps(&mut emulator, 4);
+        assert_eq!(emulator.halt_reason, HaltReason::Running);
+        assert_eq!(emulator.cpu.registers.acc, 1);
+        assert_eq!(emulator.cpu.registers.pc, 0x0004);
+    }
+
+    #[test]
+    fn running_past_loaded_image_halts_quickly() {
+        // MOV A,#1; INC A，之后是未初始化的 ROM
+        let mut emulator = Emulator::new(false);
+        emulator.load_hex(":0300000074010484\n:00000001FF\n").unwrap();
+        assert_eq!(emulator.cpu.rom_end, Some(0x0002));
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::RanOffEnd);
+        assert_eq!(emulator.cpu.registers.acc, 2);
+        assert!(emulator.instruction_count <= 2 + RUNAWAY_NOP_LIMIT as u64);
+
+        // 映像内部的 NOP 不算跑出映像
+        let mut program = vec![0x00; RUNAWAY_NOP_LIMIT as usize + 10];
+        program.extend([0x80, 0xFE]);
+        let mut emulator = emulator_with(&program);
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::ProgramEnd);
+    }
+
+    #[test]
+    fn tight_loop_addresses_rank_highest() {
+        // MOV R7,#50; 循环: INC A; DJNZ R7,循环; SJMP $
+        let mut emulator = emulator_with(&[0x7F, 0x32, 0x04, 0xDF, 0xFD, 0x80, 0xFE]);
+        emulator.set_symbols([(0x0002, "LOOP".to_string())].into_iter().collect());
+        emulator.enable_profiling();
+        while emulator.cpu.registers.pc != 0x0005 {
+            emulator.step();
+        }
+
+        let hot = emulator.hot_addresses(2);
+        let ranked: Vec<(u16, u64)> = hot.iter().map(|hot| (hot.address, hot.count)).collect();
+        assert_eq!(ranked, [(0x0002, 50), (0x0003, 50)]);
+        assert_eq!(hot[0].label.as_deref(), Some("LOOP"));
+        assert_eq!(hot[1].label.as_deref(), Some("LOOP+0x1"));
+
+        // MOV R7,#50; DJNZ R7,$; SJMP $：快进跳过的迭代也计入
+        let mut emulator = emulator_with(&[0x7F, 0x32, 0xDF, 0xFE, 0x80, 0xFE]);
+        emulator.enable_profiling();
+        while emulator.cpu.registers.pc != 0x0004 {
+            emulator.step();
+        }
+        assert_eq!(emulator.hot_addresses(1)[0].address, 0x0002);
+        assert_eq!(emulator.hot_addresses(1)[0].count, 50);
+    }
+
+    // 0x0000: LCALL 0x0010; SJMP $
+    // 0x0010: LCALL 0x0020; RET
+    // 0x0020: INC A; RET
+    fn nested_calls() -> Emulator {
+        emulator_with_blocks(&[
+            (0x0000, &[0x12, 0x00, 0x10, 0x80, 0xFE]),
+            (0x0010, &[0x12, 0x00, 0x20, 0x22]),
+            (0x0020, &[0x04, 0x22]),
+        ])
+    }
+
+    #[test]
+    fn call_stack_lists_return_addresses_of_nested_calls() {
+        let mut emulator = nested_calls();
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.cpu.registers.pc, 0x0020);
+        assert_eq!(emulator.call_stack(), [0x0003, 0x0013]);
+        // 返回地址按低字节在前压栈
+        assert_eq!(emulator.cpu.registers.sp, 0x0B);
+        assert_eq!(emulator.cpu.ram[0x08..0x0C], [0x03, 0x00, 0x13, 0x00]);
+
+        run_steps(&mut emulator, 2);
+        assert_eq!(emulator.call_stack(), [0x0003]);
+        run_steps(&mut emulator, 1);
+        assert!(emulator.call_stack().is_empty());
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+    }
+
+    #[test]
+    fn step_over_runs_subroutine_to_its_return() {
+        let mut emulator = nested_calls();
+        assert_eq!(emulator.step_over(), HaltReason::Running);
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+        assert_eq!(emulator.cpu.registers.acc, 1);
+        assert_eq!(emulator.instruction_count, 5);
+    }
+
+    #[test]
+    fn interrupt_during_step_over_does_not_end_it_early() {
+        // 定时器0服务程序 (0x000B)：INC 0x40; RETI；进入子程序之前 TF0 已经置位
+        let mut emulator = nested_calls();
+        emulator.cpu.rom[0x0B..0x0E].copy_from_slice(&[0x05, 0x40, 0x32]);
+        emulator.cpu.write_sfr(IE, 0x82);
+        emulator.cpu.write_sfr(TCON, 0x20);
+        assert_eq!(emulator.step_over(), HaltReason::Running);
+        assert_eq!(emulator.cpu.registers.pc, 0x0003);
+        assert_eq!((emulator.cpu.ram[0x40], emulator.cpu.registers.acc), (1, 1));
+        assert!(emulator.call_stack().is_empty());
+    }
+
+    // 定时器0方式1每次计 10000 个机器周期（12MHz 下 10ms），轮询 TF0 十次，共约 100ms，然后写退出端口
+    // MOV TMOD,#0x01; MOV R7,#10; 循环: MOV TH0,#0xD8; MOV TL0,#0xF0; SETB TR0; JNB TF0,$; CLR TR0; CLR TF0;
+    // DJNZ R7,循环; MOV 0xFF,#0; SJMP $
+    const DELAY_100MS: [u8; 27] = [
+        0x75, 0x89, 0x01, 0x7F, 0x0A, 0x75, 0x8C, 0xD8, 0x75, 0x8A, 0xF0, 0xD2, 0x8C, 0x30, 0x8D, 0xFD, 0xC2, 0x8C,
+        0xC2, 0x8D, 0xDF, 0xEF, 0x75, 0xFF, 0x00, 0x80, 0xFE,
+    ];
+
+    #[test]
+    fn realtime_timer_delay_takes_its_emulated_time() {
+        let mut emulator = emulator_with(&DELAY_100MS);
+        emulator.cpu.exit_port = Some(0xFF);
+        emulator.enable_realtime();
+        let start = std::time::Instant::now();
+        assert_eq!(run_to_halt(&mut emulator, 1_000_000), HaltReason::ExitCode(0));
+        let wall = start.elapsed().as_secs_f64();
+
+        // JNB TF0,$ 的等待由循环快进跳过，节拍仍按推进后的时钟睡眠
+        let emulated = emulator.clock_cycles as f64 / 12_000_000.0;
+        assert!((0.100..0.101).contains(&emulated), "模拟时间 {}", emulated);
+        assert!((0.090..0.110).contains(&wall), "墙上时间 {}", wall);
+    }
+
+    #[test]
+    fn same_delay_runs_unpaced_by_default() {
+        let mut emulator = emulator_with(&DELAY_100MS);
+        emulator.cpu.exit_port = Some(0xFF);
+        let start = std::time::Instant::now();
+        assert_eq!(run_to_halt(&mut emulator, 1_000_000), HaltReason::ExitCode(0));
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn load_hex_overlays_a_patch_on_the_base_image() {
+        // 基础映像：MOV A,#0x11; MOV 0x30,A; SJMP $；补丁把立即数改为 0x22，另在 0x0010 加两个字节
+        let mut emulator = Emulator::new(false);
+        emulator.load_hex(":060000007411F53080FED2\n:00000001FF\n").unwrap();
+        emulator.load_hex(":0100010022DC\n:020010000000EE\n:00000001FF\n").unwrap();
+        assert_eq!(emulator.cpu.rom[..6], [0x74, 0x22, 0xF5, 0x30, 0x80, 0xFE]);
+        assert_eq!(emulator.cpu.rom_end, Some(0x0011));
+
+        run_steps(&mut emulator, 2);
+        assert_eq!((emulator.cpu.registers.acc, emulator.cpu.ram[0x30]), (0x22, 0x22));
+
+        // 打过补丁的字节属于补丁：之后按普通方式加载不同的内容时报告的是补丁
+        let error = emulator.cpu.load_hex(":0100010033CB\n".as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("与之前加载的 HEX 补丁 在 0x0001-0x0001 重叠"), "{}", error);
+    }
+
+    // CLR A; MOV R7,#10; 循环: ADD A,R7; DJNZ R7,循环; SJMP $
+    const SUM_LOOP: [u8; 8] = [0xE4, 0x7F, 0x0A, 0x2F, 0xDF, 0xFD, 0x80, 0xFE];
+
+    #[test]
+    fn until_count_stops_on_the_nth_arrival() {
+        for turbo in [false, true] {
+            let mut emulator = Emulator::builder().turbo(turbo).build();
+            emulator.cpu.rom[..SUM_LOOP.len()].copy_from_slice(&SUM_LOOP);
+            emulator.until.insert(0x0003, 5);
+            let reason = if turbo { emulator.run_turbo() } else { run_to_halt(&mut emulator, 1000) };
+
+            // 停在第五次 ADD 之前：已经加了 10+9+8+7
+            assert_eq!(reason, HaltReason::UntilReached(0x0003), "turbo={}", turbo);
+            assert_eq!(emulator.cpu.registers.pc, 0x0003);
+            assert_eq!((emulator.cpu.ram[7], emulator.cpu.registers.acc), (6, 34), "turbo={}", turbo);
+            assert!(emulator.until.is_empty());
+        }
+    }
+
+    #[test]
+    fn several_until_addresses_stop_in_turn_and_resume() {
+        let mut emulator = emulator_with(&SUM_LOOP);
+        emulator.until.insert(0x0004, 2);
+        emulator.until.insert(0x0006, 1);
+        assert_eq!(run_to_halt(&mut emulator, 1000), HaltReason::UntilReached(0x0004));
+        assert_eq!(emulator.cpu.ram[7], 9);
+
+        // 从 until 地址继续运行，到达的地址已经删除，不会再停在循环里
+        assert_eq!(run_to_halt(&mut emulator, 1000), HaltReason::UntilReached(0x0006));
+        assert_eq!((emulator.cpu.ram[7], emulator.cpu.registers.acc), (0, 55));
+        assert_eq!(run_to_halt(&mut emulator, 10_000), HaltReason::ProgramEnd);
+    }
+
+    // 0x0000: LJMP 0x0030；定时器0服务程序 (0x000B)：RETI
+    // 0x0030: MOV IE,#0x82; SETB TF0; MOV R6,#3; 外层: MOV R7,#0; DJNZ R7,$; INC A; DJNZ R6,外层; MOV 0xFF,#0
+    fn counted_program() -> Emulator {
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x02, 0x00, 0x30]),
+            (0x000B, &[0x32]),
+            (0x0030, &[0x75, 0xA8, 0x82, 0xD2, 0x8D, 0x7E, 0x03, 0x7F, 0x00, 0xDF, 0xFE, 0x04, 0xDE, 0xF9, 0x75, 0xFF, 0x00]),
+        ]);
+        emulator.cpu.exit_port = Some(0xFF);
+        emulator
+    }
+
+    #[test]
+    fn opcode_counts_include_fast_forwarded_iterations() {
+        let mut emulator = counted_program();
+        let mut steps = 0;
+        while emulator.step() == HaltReason::Running {
+            steps += 1;
+        }
+        assert_eq!(emulator.halt_reason, HaltReason::ExitCode(0));
+        // DJNZ R7,$ 的大部分迭代由循环快进跳过，但每次迭代都计入
+        assert!(steps < 768, "{}", steps);
+
+        let counts = emulator.opcode_counts();
+        assert_eq!(counts[0], OpcodeCount { opcode: 0xDF, mnemonic: Some("DJNZ"), count: 768 });
+        let count = |opcode: u8| counts.iter().find(|entry| entry.opcode == opcode).map_or(0, |entry| entry.count);
+        assert_eq!((count(0x7F), count(0x04), count(0xDE), count(0x75), count(0x32)), (3, 3, 3, 2, 1));
+        assert_eq!(counts.iter().map(|entry| entry.count).sum::<u64>(), emulator.instruction_count);
+        assert_eq!(emulator.instruction_count, 783);
+        assert_eq!(emulator.cpu.interrupt_entries, 1);
+
+        // 极速模式下计数相同
+        let mut turbo = counted_program();
+        turbo.turbo = true;
+        turbo.run_turbo();
+        assert_eq!(turbo.cpu.opcode_counts, emulator.cpu.opcode_counts);
+        assert_eq!(turbo.cpu.interrupt_entries, 1);
+    }
+
+    #[test]
+    fn tick_hook_total_matches_clock_cycles() {
+        for turbo in [false, true] {
+            let mut emulator = counted_program();
+            emulator.turbo = turbo;
+            let ticks = Rc::new(RefCell::new(Vec::new()));
+            let recorded = Rc::clone(&ticks);
+            emulator.set_tick_hook(Box::new(move |cycles| recorded.borrow_mut().push(cycles)));
+            if turbo {
+                emulator.run_turbo();
             } else {
-                print!("[串口输出] 数据: {:#04x}", value);
+                run_to_halt(&mut emulator, 10_000);
             }
+            assert_eq!(emulator.halt_reason, HaltReason::ExitCode(0));
+
+            // LJMP; MOV IE,#0x82; SETB TF0 加上响应定时器0中断的三个机器周期
+            let ticks = ticks.borrow();
+            assert_eq!(ticks[..3], [24, 24, 48], "turbo={}", turbo);
+            assert_eq!(ticks.iter().sum::<u64>(), emulator.clock_cycles, "turbo={}", turbo);
         }
-        self.cpu.write_sfr(addr, value);
+    }
+
+    #[test]
+    fn execute_instruction_does_nothing_after_a_halt() {
+        // MOV 0xFF,#3; INC A
+        let mut emulator = emulator_with(&[0x75, 0xFF, 0x03, 0x04]);
+        emulator.cpu.exit_port = Some(0xFF);
+        assert_eq!(emulator.step(), HaltReason::ExitCode(3));
+        let (count, clocks) = (emulator.instruction_count, emulator.clock_cycles);
+
+        emulator.execute_instruction(0x04);
+        assert_eq!((emulator.instruction_count, emulator.clock_cycles), (count, clocks));
+        assert_eq!((emulator.cpu.registers.pc, emulator.cpu.registers.acc), (0x0003, 0));
     }
 }
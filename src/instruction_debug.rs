@@ -1,9 +1,8 @@
 // 指令表调试和统计工具
 // 独立于CPU实现，用于显示和分析指令表
 
-use crate::cpu::instructions::{InstructionInfo, InstructionTable};
+use crate::cpu::instructions::{InstructionInfo, InstructionTable, RESERVED_OPCODE};
 use crate::cpu::instructions::{arithmetic, branch, data_transfer, interrupt, logical};
-use crate::cpu::CPU;
 
 // 构建指令查找表
 pub fn build_instruction_table() -> InstructionTable {
@@ -40,6 +39,8 @@ pub fn dump_instruction_table() {
             let opcode = (row * 16 + col) as usize;
             if let Some(info) = &table[opcode] {
                 print!(" {:>5}", info.mnemonic);
+            } else if opcode == RESERVED_OPCODE as usize {
+                print!("  (rs)"); // 保留操作码，按单字节空操作执行
             } else {
                 print!("  ----");
             }
@@ -0,0 +1,25 @@
+// MCS-51 模拟器核心库
+// CPU 核心与模拟器包装层，命令行程序 (main.rs) 和外部宿主程序都通过这里使用模拟器
+
+#![allow(clippy::upper_case_acronyms, clippy::needless_range_loop)]
+
+pub mod coverage;
+pub mod cpu;
+pub mod debugger;
+pub mod disassembler;
+pub mod emulator;
+pub mod instruction_debug;
+pub mod loop_detector;
+pub mod memory_dump;
+pub mod monitor;
+pub mod omf;
+pub mod pacing;
+pub mod profiler;
+pub mod serial_bridge;
+pub mod serial_script;
+pub mod state_json;
+pub mod symbols;
+pub mod vcd;
+
+#[cfg(test)]
+mod test_support;
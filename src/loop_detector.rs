@@ -1,8 +1,10 @@
 // 循环检测器：跟踪PC历史，识别紧密循环并智能快进
 // 这是一个性能优化工具，用于加速模拟器执行
 
+use std::collections::VecDeque;
+
 pub struct LoopDetector {
-    pc_history: Vec<u16>,       // 最近的PC历史（用于检测循环）
+    pc_history: VecDeque<u16>,  // 最近的PC历史（用于检测循环），每条指令都要进出一次，用双端队列避免移动整个数组
     pub loop_count: u32,            // 当前循环已执行次数
     pub loop_start: u16,            // 循环起始地址
     pub loop_end: u16,              // 循环结束地址
@@ -11,16 +13,21 @@ pub struct LoopDetector {
     pub has_io_in_loop: bool,       // 循环中是否有I/O操作
     pub io_operation_count: u32,    // 循环中I/O操作计数
     instructions_in_loop: u32,  // 循环中的指令数
-    last_fast_forward_time: u64, // 上次快进时的时钟周期
     pub same_loop_fast_forward_count: u32, // 同一循环快进次数（检测死循环）
     last_loop_start: u16,       // 上次循环的起始地址
     last_loop_end: u16,         // 上次循环的结束地址
 }
 
+impl Default for LoopDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LoopDetector {
     pub fn new() -> Self {
         LoopDetector {
-            pc_history: Vec::with_capacity(100), // 存储最近100个PC
+            pc_history: VecDeque::with_capacity(100), // 存储最近100个PC
             loop_count: 0,                       // 当前循环计数
             loop_start: 0,                       // 循环起始地址
             loop_end: 0,                         // 循环结束地址
@@ -29,7 +36,6 @@ impl LoopDetector {
             has_io_in_loop: false,               // 默认无I/O
             io_operation_count: 0,               // I/O操作计数
             instructions_in_loop: 0,             // 循环指令数
-            last_fast_forward_time: 0,           // 上次快进时间
             same_loop_fast_forward_count: 0,     // 同一循环快进次数
             last_loop_start: 0,                  // 上次循环起始
             last_loop_end: 0,                    // 上次循环结束
@@ -39,8 +45,7 @@ impl LoopDetector {
     // 记录PC并检测循环模式
     pub fn record_pc(&mut self, pc: u16) -> bool {
         // 检测简单的后向跳转（循环的标志）
-        if self.pc_history.len() > 0 {
-            let last_pc = self.pc_history[self.pc_history.len() - 1];
+        if let Some(&last_pc) = self.pc_history.back() {
 
             // 检测后向跳转（pc <= last_pc），增大检测范围以捕获外层循环
             if pc <= last_pc && last_pc.saturating_sub(pc) < 50 {
@@ -72,9 +77,9 @@ impl LoopDetector {
 
         // 保持历史记录在合理大小
         if self.pc_history.len() > 50 {
-            self.pc_history.remove(0);
+            self.pc_history.pop_front();
         }
-        self.pc_history.push(pc);
+        self.pc_history.push_back(pc);
         false
     }
 
@@ -153,3 +158,31 @@ impl LoopDetector {
         self.instructions_in_loop = size;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LoopDetector;
+
+    #[test]
+    fn history_stays_bounded_and_backward_jumps_are_counted() {
+        let mut detector = LoopDetector::new();
+        for pc in 0..600u16 {
+            detector.record_pc(pc * 100);
+        }
+        assert_eq!(detector.pc_history.len(), 51);
+        assert_eq!(detector.pc_history.back(), Some(&(599 * 100)));
+
+        // 0x0010-0x0014 的循环：每次跳回 0x0010 计一次，达到阈值时要求快进
+        let mut detector = LoopDetector::new();
+        let mut fast_forward = false;
+        for _ in 0..100 {
+            for pc in [0x0010, 0x0012, 0x0014] {
+                fast_forward = detector.record_pc(pc);
+            }
+        }
+        assert_eq!((detector.loop_start, detector.loop_end), (0x0010, 0x0014));
+        assert_eq!(detector.loop_count, 99);
+        assert!(!fast_forward);
+        assert!(detector.record_pc(0x0010));
+    }
+}
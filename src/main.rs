@@ -1,14 +1,54 @@
-mod cpu;
-mod emulator;
-mod loop_detector;
-mod instruction_debug;
-
-use emulator::Emulator;
+use mcs51_emulator::cpu::peripherals::PortConsole;
+use mcs51_emulator::cpu::instructions::RESERVED_OPCODE;
+use mcs51_emulator::cpu::serial;
+use mcs51_emulator::cpu::watch::WatchAccess;
+use mcs51_emulator::cpu::{Variant, Verbosity};
+use mcs51_emulator::debugger;
+use mcs51_emulator::disassembler;
+use mcs51_emulator::emulator::{Emulator, HaltReason};
+use mcs51_emulator::instruction_debug;
+use mcs51_emulator::memory_dump::MemorySpace;
+use mcs51_emulator::monitor;
+use mcs51_emulator::omf;
+use mcs51_emulator::profiler;
+use mcs51_emulator::serial_bridge;
 use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
 use std::path::Path;
 use std::process;
 
-/**
+// 进程退出码：程序进入掉电模式结束
+const EXIT_POWER_DOWN: i32 = 2;
+// 进程退出码：达到指令数或机器周期数上限被强制停止
+const EXIT_LIMIT: i32 = 3;
+// 默认的退出端口：标准 8051 上没有使用的 SFR 地址
+const DEFAULT_EXIT_PORT: u8 = 0xFF;
+
+// 默认的指令执行数上限
+const DEFAULT_MAX_INSTRUCTIONS: u64 = 100_000_000;
+
+// 命令行程序的日志输出：库里的提示、警告和错误原样输出到标准错误，
+// 不与标准输出上的串口数据、统计和 --debug 的逐条指令输出混在一起；哪些消息会产生仍由 --debug、--quiet 决定
+struct ConsoleLogger;
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        eprintln!("{}", record.args());
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
+/*
  *                             _ooOoo_
  *                            o8888888o
  *                            88" . "88
@@ -34,6 +74,10 @@ use std::process;
 fn main() {
     // 获取命令行参数
     let args: Vec<String> = env::args().collect();
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
     
     // 检查是否是帮助模式
     if args.iter().any(|arg| arg == "--help" || arg == "-h") {
@@ -53,55 +97,679 @@ fn main() {
         process::exit(1);
     }
 
-    let hex_file = &args[1];
+    // 程序文件：--bin 指定的二进制映像，否则是第一个参数（扩展名为 .bin 时同样按二进制加载）
+    let (program_file, binary) = match option_value(&args, "--bin") {
+        Some(path) => (path, true),
+        None => (args[1].as_str(), args[1].to_ascii_lowercase().ends_with(".bin")),
+    };
     let debug_mode = args.iter().any(|arg| arg == "--debug" || arg == "debug");
+    // 安静模式：不输出加载、端口写入、运行统计等信息，只留下程序自己的输出、错误和警告
+    let quiet = args.iter().any(|arg| arg == "--quiet");
+    // 极速模式：用于批量回归测试，与逐条输出、实时节拍和交互式调试互斥
+    let turbo = args.iter().any(|arg| arg == "--turbo");
+    if turbo {
+        let conflicts = ["--debug", "debug", "--trace", "--realtime", "--interactive", "--monitor"];
+        if let Some(option) = args.iter().find(|arg| conflicts.contains(&arg.as_str())) {
+            eprintln!("--turbo 不能与 {} 同时使用", option);
+            process::exit(1);
+        }
+    }
+
+    // 单片机型号（默认标准 8051）
+    let variant = match option_value(&args, "--variant") {
+        None | Some("8051") => Variant::I8051,
+        Some("8052") => Variant::I8052,
+        Some("89s52") | Some("89S52") => Variant::AT89S52,
+        Some(other) => {
+            eprintln!("不支持的型号: {}（可选 8051、8052、89s52）", other);
+            process::exit(1);
+        }
+    };
+
+    // 指令执行数和机器周期数上限（0 表示不限制）
+    let max_instructions = limit_option(&args, "--max-instructions").unwrap_or(DEFAULT_MAX_INSTRUCTIONS);
+    let max_cycles = limit_option(&args, "--max-cycles").unwrap_or(0);
 
     // 初始化模拟器
-    let mut emulator = Emulator::new(debug_mode);
+    let mut emulator = Emulator::builder()
+        .debug(debug_mode)
+        .variant(variant)
+        .max_instructions(max_instructions)
+        .max_cycles(max_cycles)
+        .strict_opcodes(args.iter().any(|arg| arg == "--strict-opcodes"))
+        .strict_load(args.iter().any(|arg| arg == "--strict-load"))
+        .verbosity(if quiet { Verbosity::Quiet } else { Verbosity::Normal })
+        .turbo(turbo)
+        .build();
+    // 按地址和函数的时钟周期分析，运行结束后写入 --profile 指定的文件
+    if option_value(&args, "--profile").is_some() {
+        emulator.enable_profiler();
+    }
+    // 代码覆盖率，运行结束后写入 --coverage 指定的文件
+    if option_value(&args, "--coverage").is_some() {
+        emulator.enable_coverage();
+    }
+    // 端口引脚（--vcd-sfr 时另有 TH0/TL0/ACC）的 VCD 波形，边运行边写入
+    if let Some(path) = option_value(&args, "--vcd") {
+        let with_bytes = args.iter().any(|arg| arg == "--vcd-sfr");
+        let started = fs::File::create(path)
+            .and_then(|file| emulator.start_vcd(Box::new(io::BufWriter::new(file)), with_bytes));
+        if let Err(e) = started {
+            eprintln!("创建波形文件 {} 失败: {}", path, e);
+            process::exit(1);
+        }
+    }
+    // 跟踪模式：--trace 后面跟文件名时逐条指令的输出（包括 --debug 的输出）写入该文件，
+    // 标准输出只留给程序自己的端口/串口输出；跟 - 或不跟文件名时照旧输出到标准输出
+    if let Some(position) = args.iter().position(|arg| arg == "--trace") {
+        emulator.trace = true;
+        if let Some(path) = args.get(position + 1)
+            && path != "-"
+            && path != "debug"
+            && !path.starts_with("--")
+        {
+            match fs::File::create(path) {
+                Ok(file) => emulator.set_trace_output(Box::new(io::BufWriter::new(file))),
+                Err(e) => {
+                    eprintln!("创建跟踪文件 {} 失败: {}", path, e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
 
-    //HEX文件加载程序
-    match emulator.cpu.load_hex_program(hex_file) {
-        Ok(_) => println!("程序成功从 {} 加载", hex_file),
+    // 断点（可重复）：执行到这些地址之前停下，显示寄存器和将要执行的指令
+    for value in option_values(&args, "--break") {
+        match parse_address(value) {
+            Some(address) => {
+                emulator.breakpoints.insert(address);
+            }
+            None => {
+                eprintln!("--break 的地址无效: {}（应为 0x0000-0xFFFF）", value);
+                process::exit(1);
+            }
+        }
+    }
+
+    // 运行到指定地址（可重复）：<地址>[:次数]，第几次到达该地址时在执行之前停下，显示寄存器后结束运行
+    for value in option_values(&args, "--until") {
+        let (address, count) = match value.split_once(':') {
+            None => (parse_address(value), Some(1)),
+            Some((address, count)) => (parse_address(address), count.parse::<u64>().ok().filter(|&n| n > 0)),
+        };
+        match (address, count) {
+            (Some(address), Some(count)) => {
+                emulator.until.insert(address, count);
+            }
+            _ => {
+                eprintln!("--until 的参数无效: {}（应为 <地址>[:次数]，例如 0x0456 或 0x0456:3）", value);
+                process::exit(1);
+            }
+        }
+    }
+
+    // 退出端口：程序写这个 SFR 地址后停机，写入的值作为进程退出码（默认 0xFF，--exit-port none 不拦截）
+    // 固件写入的 1、2、3 与加载错误、掉电、执行上限的退出码相同，调用方无法区分，帮助信息的退出码表中有说明
+    let exit_port = match option_value(&args, "--exit-port") {
+        None => Some(DEFAULT_EXIT_PORT),
+        Some("none") => None,
+        Some(value) => match parse_address(value) {
+            Some(address @ 0x80..=0xFF) => Some(address as u8),
+            _ => {
+                eprintln!("--exit-port 的地址无效: {}（应为 SFR 地址 0x80-0xFF，或 none 表示不拦截）", value);
+                process::exit(1);
+            }
+        },
+    };
+    emulator.cpu.exit_port = exit_port;
+
+    // 实时节拍：按晶振频率与墙上时间同步运行
+    if args.iter().any(|arg| arg == "--realtime") {
+        emulator.enable_realtime();
+    }
+
+    // 观察点（可重复）：指令读写这些存储单元时输出一行报告后继续运行，--interactive 时回到调试提示符
+    for (option, access) in [("--watch-write", WatchAccess::Write), ("--watch-read", WatchAccess::Read)] {
+        for value in option_values(&args, option) {
+            match debugger::parse_watchpoint(value, access) {
+                Ok(watchpoint) => {
+                    emulator.cpu.add_watchpoint(watchpoint);
+                }
+                Err(message) => {
+                    eprintln!("{} 的位置无效: {}", option, message);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    // 向量表基地址：在加载程序之前设置，HEX 文件的起始地址记录和 --entry 仍然优先
+    if let Some(base) = address_option(&args, "--vector-base") {
+        emulator.cpu.set_vector_base(base);
+    }
+
+    // 符号文件：调试输出、跟踪和反汇编中用标号代替地址
+    if let Some(path) = option_value(&args, "--symbols") {
+        match emulator.load_symbols(path) {
+            Ok(count) => {
+                if !quiet {
+                    println!("从 {} 读取了 {} 个符号", path, count);
+                }
+            }
+            Err(e) => {
+                eprintln!("读取符号文件 {} 失败: {}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // 二进制映像的加载地址和程序入口地址
+    let offset = address_option(&args, "--offset");
+    let entry = address_option(&args, "--entry");
+    if offset.is_some() && !binary {
+        eprintln!("--offset 只能用于二进制映像（--bin），HEX 文件自带地址");
+        process::exit(1);
+    }
+
+    // --load 追加的程序文件（可重复），依次加载到同一个 ROM 映像中，例如引导程序加应用程序
+    let extra_files = option_values(&args, "--load");
+
+    // 标准输入只能提供程序或串口数据中的一个
+    let stdin_programs = extra_files.iter().chain([&program_file]).filter(|&&file| file == "-").count();
+    if stdin_programs > 1 {
+        eprintln!("只能有一个程序文件从标准输入读取");
+        process::exit(1);
+    }
+    let monitor = args.iter().any(|arg| arg == "--monitor");
+    let interactive = monitor || args.iter().any(|arg| arg == "--interactive");
+    if interactive && (stdin_programs > 0 || option_value(&args, "--serial-in") == Some("-")) {
+        eprintln!("交互式调试从标准输入读取命令，程序和 --serial-in 不能再使用标准输入");
+        process::exit(1);
+    }
+    if stdin_programs > 0 && option_value(&args, "--serial-in") == Some("-") {
+        eprintln!("程序已经从标准输入读取，--serial-in 不能再使用标准输入");
+        process::exit(1);
+    }
+
+    // 加载程序：二进制映像原样复制到 ROM，OMF-51 目标文件（--omf，或按文件头识别）取内容记录，
+    // HEX 文件按记录中的地址加载
+    let loaded = if binary {
+        emulator.cpu.load_binary_program(program_file, offset.unwrap_or(0))
+    } else if args.iter().any(|arg| arg == "--omf") || is_omf_file(program_file) {
+        emulator.cpu.load_omf_program(program_file)
+    } else {
+        emulator.cpu.load_hex_program(program_file).map(|_| emulator.cpu.program_size())
+    };
+    match loaded {
+        Ok(size) => {
+            if !quiet {
+                println!("程序成功从 {} 加载（{} 字节）", program_name(program_file), size);
+            }
+        }
         Err(e) => {
-            eprintln!("加载程序失败: {}", e);
+            eprintln!("从 {} 加载程序失败: {}", program_name(program_file), e);
             process::exit(1);
         }
     }
+    for &file in &extra_files {
+        let loaded = if is_omf_file(file) {
+            emulator.cpu.load_omf_program(file).map(|_| ())
+        } else {
+            emulator.cpu.load_hex_program(file)
+        };
+        match loaded {
+            Ok(()) => {
+                if !quiet {
+                    println!(
+                        "程序成功从 {} 追加加载（已加载到 {:#06X}）",
+                        program_name(file),
+                        emulator.cpu.program_size().saturating_sub(1)
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("从 {} 加载程序失败: {}", program_name(file), e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // --patch 的 HEX 补丁最后加载，覆盖已加载映像中的同一地址
+    for file in option_values(&args, "--patch") {
+        match emulator.cpu.patch_hex_program(file) {
+            Ok(()) => {
+                if !quiet {
+                    println!("补丁 {} 已覆盖到程序映像上", program_name(file));
+                }
+            }
+            Err(e) => {
+                eprintln!("从 {} 加载补丁失败: {}", program_name(file), e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // --entry 优先于 HEX 文件中的起始地址记录
+    if let Some(entry) = entry {
+        emulator.cpu.registers.pc = entry;
+    }
+
+    // 反汇编模式：从入口地址列出到最高已加载地址为止的全部指令，不运行程序
+    if args.iter().any(|arg| arg == "--disasm") {
+        print_listing(&emulator);
+        return;
+    }
+
+    // 串口接收数据来源：文件，或 "-" 表示标准输入
+    match option_value(&args, "--serial-in") {
+        None => {}
+        Some("-") => emulator.cpu.set_serial_source(serial::spawn_reader(io::stdin())),
+        Some(path) => match fs::read(path) {
+            Ok(data) => emulator.cpu.serial_receive(&data),
+            Err(e) => {
+                eprintln!("读取串口输入文件 {} 失败: {}", path, e);
+                process::exit(1);
+            }
+        },
+    }
+
+    // 串口发送输出的目标：默认是标准输出，桥接时改为套接字或伪终端，
+    // --serial-out 另外把发送的字节原样记录到文件
+    let mut serial_sinks: Vec<Box<dyn Write>> = Vec::new();
+
+    // 串口输入激励脚本：数据在指定的模拟时间到达
+    if let Some(path) = option_value(&args, "--serial-script")
+        && let Err(e) = emulator.load_serial_script(path)
+    {
+        eprintln!("加载串口激励脚本 {} 失败: {}", path, e);
+        process::exit(1);
+    }
+
+    // 串口桥接到 TCP 连接：收发都改走套接字
+    if let Some(value) = option_value(&args, "--serial-tcp") {
+        let port = match value.parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                eprintln!("无效的端口号: {}", value);
+                process::exit(1);
+            }
+        };
+        match serial_bridge::listen_tcp(port) {
+            Ok((port, source, sink)) => {
+                println!("串口已桥接到 TCP 127.0.0.1:{}，等待连接", port);
+                emulator.cpu.set_serial_source(source);
+                serial_sinks.push(sink);
+            }
+            Err(e) => {
+                eprintln!("监听 TCP 端口 {} 失败: {}", port, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    // 串口桥接到伪终端（仅 Unix）
+    if args.iter().any(|arg| arg == "--serial-pty") {
+        serial_sinks.push(open_serial_pty(&mut emulator));
+    }
+
+    // 串口发送记录文件；--quiet 时串口数据只写入文件，不再显示在标准输出上
+    let serial_log = match option_value(&args, "--serial-out") {
+        None => None,
+        Some(path) => match serial_bridge::SerialLog::create(path) {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("创建串口输出文件 {} 失败: {}", path, e);
+                process::exit(1);
+            }
+        },
+    };
+    if serial_sinks.is_empty() && !(quiet && serial_log.is_some()) {
+        serial_sinks.push(Box::new(io::stdout()));
+    }
+    if let Some(log) = serial_log {
+        serial_sinks.push(Box::new(log));
+    }
+    emulator.cpu.set_serial_sink(Box::new(serial_bridge::TeeSink(serial_sinks)));
+
+    // 端口控制台：把写到指定端口的字节当作字符输出（用于在端口上输出字符的旧测试程序）
+    if let Some(value) = option_value(&args, "--console-port") {
+        let port = match value {
+            "P0" | "p0" => 0,
+            "P1" | "p1" => 1,
+            "P2" | "p2" => 2,
+            "P3" | "p3" => 3,
+            _ => {
+                eprintln!("无效的端口: {}（可选 P0、P1、P2、P3）", value);
+                process::exit(1);
+            }
+        };
+        emulator.cpu.attach_device(Box::new(PortConsole::new(port, Box::new(io::stdout()))));
+    }
     
-    loop {
-        // 检查是否已停机
-        if emulator.is_halted {
-            if !debug_mode {
-                println!("\n程序执行完成");
-            }
-            break;
-        }
-
-        // 检查PC是否在有效范围内
-        if (emulator.cpu.registers.pc as usize) >= emulator.cpu.rom.len() {
-            println!("程序结束: PC超出内存范围");
-            break;
-        }
-        
-        // 检查指令执行数限制（防止真正的无限循环）
-        if emulator.instruction_count > 100_000_000 {
-            println!("\n警告: 已执行超过1亿条指令，可能存在死循环，强制退出");
-            break;
-        }
-        
-        let pc = emulator.cpu.registers.pc;
-        let opcode = emulator.cpu.rom[pc as usize];
-        emulator.execute_instruction(opcode);
-        
-        // 更新定时器（每条指令执行后）
-        emulator.cpu.update_timers();
-        
-        // 检查并处理中断
-        emulator.cpu.check_interrupts();
+    // 交互式调试：命令从标准输入读入，输入 q 或输入结束时停止运行
+    if monitor {
+        if let Err(e) = monitor::run(&mut emulator, io::stdin().lock(), &mut io::stdout()) {
+            eprintln!("监视器读写失败: {}", e);
+        }
+    } else if interactive {
+        if let Err(e) = debugger::run(&mut emulator, io::stdin().lock(), &mut io::stdout()) {
+            eprintln!("调试器读写失败: {}", e);
+        }
+    } else {
+        loop {
+            // 检查是否已停机
+            match emulator.halt_reason {
+                HaltReason::Running => {}
+                HaltReason::InstructionLimit => {
+                    println!(
+                        "\n警告: 已执行 {} 条指令，达到指令数上限 (--max-instructions)，可能存在死循环，强制退出",
+                        emulator.instruction_count
+                    );
+                    break;
+                }
+                HaltReason::CycleLimit => {
+                    println!(
+                        "\n警告: 已运行 {} 个机器周期，达到机器周期数上限 (--max-cycles)，强制退出",
+                        emulator.clock_cycles / 12
+                    );
+                    break;
+                }
+                HaltReason::BreakpointHit => {
+                    println!("\n到达断点 {:#06x}", emulator.cpu.registers.pc);
+                    let mut stdout = io::stdout();
+                    let _ = debugger::show_registers(&emulator, &mut stdout);
+                    let _ = debugger::show_next_instruction(&emulator, &mut stdout);
+                    break;
+                }
+                HaltReason::UntilReached(address) => {
+                    if !quiet {
+                        println!("\n到达 --until 地址 {:#06x}", address);
+                        let mut stdout = io::stdout();
+                        let _ = debugger::show_registers(&emulator, &mut stdout);
+                        let _ = debugger::show_next_instruction(&emulator, &mut stdout);
+                    }
+                    break;
+                }
+                HaltReason::ExitCode(code) => {
+                    if !quiet {
+                        println!("\n程序写退出端口 {:#04x}，退出码 {}", exit_port.unwrap_or_default(), code);
+                    }
+                    break;
+                }
+                HaltReason::ProgramEnd => {
+                    if !debug_mode && !quiet {
+                        println!("\n程序执行完成");
+                    }
+                    break;
+                }
+                // 其余原因在停机时已经输出了说明
+                _ => break,
+            }
+
+            if turbo {
+                emulator.run_turbo();
+            } else {
+                emulator.step();
+            }
+        }
     }
 
     // 打印最终状态
-    println!("CPU 状态：累加器 = {}, 程序计数器 = {}", emulator.cpu.registers.acc, emulator.cpu.registers.pc);
+    if !quiet {
+        println!("CPU 状态：累加器 = {}, 程序计数器 = {}", emulator.cpu.registers.acc, emulator.cpu.registers.pc);
+    }
+
+    // 存储器转储：内部RAM、SFR（附带有名称的寄存器）以及指定范围的外部数据存储器，
+    // 选项带 =<文件> 时写入文件（多个转储指定同一个文件时依次写入），否则输出到标准输出
+    let mut dumps: Vec<(Option<&str>, String)> = Vec::new();
+    let mut add_dump = |path, space: MemorySpace, text: String| {
+        let section = format!("[{}]\n{}", space.name(), text);
+        match dumps.iter_mut().find(|(target, _)| *target == path) {
+            Some((_, out)) => out.push_str(&section),
+            None => dumps.push((path, section)),
+        }
+    };
+    if let Some((_, path)) = dump_option(&args, "--dump-ram") {
+        add_dump(path, MemorySpace::Ram, emulator.dump_memory(MemorySpace::Ram, 0x00..0x100));
+    }
+    if let Some((_, path)) = dump_option(&args, "--dump-sfr") {
+        let text = emulator.dump_memory(MemorySpace::Sfr, 0x80..0x100) + &emulator.annotate_sfrs();
+        add_dump(path, MemorySpace::Sfr, text);
+    }
+    if let Some((index, path)) = dump_option(&args, "--dump-xram") {
+        let value = args.get(index + 1).map(String::as_str).unwrap_or("");
+        let Some(range) = parse_range(value) else {
+            eprintln!("--dump-xram 的范围无效: {}（应为 起始-结束 或 起始..结束，例如 0x0000-0x00FF、0x0000..0x0100）", value);
+            process::exit(1);
+        };
+        add_dump(path, MemorySpace::Xram, emulator.dump_memory(MemorySpace::Xram, range));
+    }
+    for (path, text) in dumps {
+        match path {
+            None => print!("{}", text),
+            Some(path) => {
+                if let Err(e) = fs::write(path, text) {
+                    eprintln!("写入转储文件 {} 失败: {}", path, e);
+                }
+            }
+        }
+    }
+
+    // 完整的机器状态写成 JSON，供脚本读取
+    if let Some(path) = option_value(&args, "--state-json")
+        && let Err(e) = fs::write(path, emulator.state_json())
+    {
+        eprintln!("写入状态文件 {} 失败: {}", path, e);
+    }
+
+    // 时钟周期分析报告
+    if let Some(path) = option_value(&args, "--profile")
+        && let Some(report) = emulator.profile_report(profiler::DEFAULT_TOP_N)
+        && let Err(e) = fs::write(path, report)
+    {
+        eprintln!("写入分析报告 {} 失败: {}", path, e);
+    }
+
+    // 代码覆盖率报告
+    if let Some(path) = option_value(&args, "--coverage")
+        && let Some(report) = emulator.coverage_report()
+        && let Err(e) = fs::write(path, report)
+    {
+        eprintln!("写入覆盖率报告 {} 失败: {}", path, e);
+    }
+
+    // 打印运行统计
+    if !quiet {
+        let stats = emulator.stats();
+        println!(
+            "运行统计：指令 {} 条，机器周期 {}，模拟时间 {:.6} 秒，主机耗时 {:.3} 秒 ({:.2} MIPS)",
+            stats.instructions,
+            stats.machine_cycles,
+            stats.simulated_time,
+            stats.host_time.as_secs_f64(),
+            stats.mips
+        );
+        if turbo {
+            println!("极速模式：{:.0} 条指令/秒", stats.mips * 1_000_000.0);
+        }
+    }
+
+    // 每个操作码的执行次数（明确要求的输出，--quiet 时同样显示）
+    if args.iter().any(|arg| arg == "--stats") {
+        print_opcode_stats(&emulator);
+    }
+
+    // 串口丢弃过数据时提示（固件没有及时清除 RI 或没有等待 TI），属于运行统计，--quiet 时不输出
+    let serial = &emulator.cpu.serial;
+    if !quiet && (serial.rx_overruns > 0 || serial.tx_collisions > 0) {
+        println!(
+            "串口统计：接收溢出 {} 次，发送冲突 {} 次",
+            serial.rx_overruns, serial.tx_collisions
+        );
+    }
+
+    // 换下串口输出目标，把记录文件中尚未写盘的数据写入（process::exit 不会执行析构）
+    emulator.cpu.set_serial_sink(Box::new(io::sink()));
+    if let Err(e) = emulator.flush_trace() {
+        eprintln!("写入跟踪文件失败: {}", e);
+    }
+
+    // 掉电是固件主动结束运行的方式，用单独的退出码告诉调用方；写退出端口的程序自己决定退出码；
+    // 被执行上限强制停止的运行同样用单独的退出码，脚本可以与正常结束区分
+    match emulator.halt_reason {
+        HaltReason::PowerDown => process::exit(EXIT_POWER_DOWN),
+        HaltReason::ExitCode(code) => process::exit(code as i32),
+        HaltReason::InstructionLimit | HaltReason::CycleLimit => process::exit(EXIT_LIMIT),
+        _ => {}
+    }
+}
+
+#[cfg(unix)]
+fn open_serial_pty(emulator: &mut Emulator) -> Box<dyn Write> {
+    match serial_bridge::open_pty() {
+        Ok((path, source, sink)) => {
+            println!("串口已桥接到伪终端 {}", path);
+            emulator.cpu.set_serial_source(source);
+            sink
+        }
+        Err(e) => {
+            eprintln!("创建伪终端失败: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn open_serial_pty(_emulator: &mut Emulator) -> Box<dyn Write> {
+    eprintln!("--serial-pty 仅支持 Unix 系统");
+    process::exit(1);
+}
+
+// 按执行次数排列的操作码统计表，最后给出未实现/保留操作码的执行次数和中断响应次数
+// 保留操作码显示为 (rs)，未实现的显示为 ----（与 --inst-dump 的指令表相同）
+fn print_opcode_stats(emulator: &Emulator) {
+    let counts = emulator.opcode_counts();
+    let total: u64 = counts.iter().map(|entry| entry.count).sum();
+    println!("操作码统计（共 {} 次）：", total);
+    println!("  操作码  助记符        次数      占比");
+    for entry in &counts {
+        println!(
+            "  {:#04x}    {:<8} {:>12}  {:>6.2}%",
+            entry.opcode,
+            entry.mnemonic.unwrap_or(if entry.opcode == RESERVED_OPCODE { "(rs)" } else { "----" }),
+            entry.count,
+            entry.count as f64 * 100.0 / total as f64
+        );
+    }
+    let unknown: u64 = counts.iter().filter(|entry| entry.mnemonic.is_none()).map(|entry| entry.count).sum();
+    println!("未实现/保留操作码 {} 次，中断响应 {} 次", unknown, emulator.cpu.interrupt_entries);
+}
+
+// 输出反汇编列表：地址、机器码和汇编文本，有标号的地址先输出标号行
+fn print_listing(emulator: &Emulator) {
+    let Some(end) = emulator.cpu.rom_end else {
+        println!("没有加载任何程序");
+        return;
+    };
+    let start = emulator.cpu.registers.pc;
+    for line in disassembler::disassemble_range(&emulator.cpu.rom, start, end, &emulator.symbols) {
+        if let Some(label) = emulator.symbols.get(&line.address) {
+            println!("{}:", label);
+        }
+        println!("{:04X}: {:<9} {}", line.address, line.hex_bytes(), line.text);
+    }
+}
+
+// 提示信息中的程序来源：文件名为 "-" 表示标准输入
+fn program_name(path: &str) -> &str {
+    if path == "-" {
+        "标准输入"
+    } else {
+        path
+    }
+}
+
+// 文件是否以 OMF-51 模块头记录开始（HEX 文件以 ':' 开始）
+fn is_omf_file(path: &str) -> bool {
+    let mut first = [0; 1];
+    fs::File::open(path).and_then(|mut file| file.read_exact(&mut first)).is_ok()
+        && omf::is_omf(&first)
+}
+
+// 读取地址选项，例如 --offset 0x1000；接受 0x 前缀的十六进制数或十进制数
+fn address_option(args: &[String], name: &str) -> Option<u16> {
+    let value = option_value(args, name)?;
+    match parse_address(value) {
+        Some(address) => Some(address),
+        None => {
+            eprintln!("{} 的地址无效: {}（应为 0x0000-0xFFFF）", name, value);
+            process::exit(1);
+        }
+    }
+}
+
+// 读取可以重复的带参数选项的全部值，例如 --load a.hex --load b.hex
+fn option_values<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    args.windows(2)
+        .filter(|pair| pair[0] == name)
+        .map(|pair| pair[1].as_str())
+        .collect()
+}
+
+// 解析地址：0x 前缀的十六进制数或十进制数
+fn parse_address(value: &str) -> Option<u16> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse::<u16>().ok(),
+    }
+}
+
+// 解析地址范围：起始-结束（包含结束地址），例如 0x0000-0x00FF；
+// 或者 起始..结束（不含结束地址），例如 0x0000..0x0100，结束地址可以是 0x10000
+fn parse_range(value: &str) -> Option<std::ops::Range<usize>> {
+    if let Some((start, end)) = value.split_once("..") {
+        let start = parse_address(start)? as usize;
+        let end = match parse_address(end) {
+            Some(end) => end as usize,
+            None if end.eq_ignore_ascii_case("0x10000") || end == "65536" => 0x10000,
+            None => return None,
+        };
+        return (start <= end).then_some(start..end);
+    }
+    let (start, end) = value.split_once('-')?;
+    let (start, end) = (parse_address(start)? as usize, parse_address(end)? as usize);
+    (start <= end).then_some(start..end + 1)
+}
+
+// 运行结束时的转储选项：--dump-ram 或 --dump-ram=<文件>，返回选项的位置和文件名
+fn dump_option<'a>(args: &'a [String], name: &str) -> Option<(usize, Option<&'a str>)> {
+    args.iter().enumerate().find_map(|(index, arg)| {
+        let rest = arg.strip_prefix(name)?;
+        match rest.strip_prefix('=') {
+            Some(path) => Some((index, Some(path))),
+            None => rest.is_empty().then_some((index, None)),
+        }
+    })
+}
+
+// 读取执行上限选项，例如 --max-cycles 1000000；0 表示不限制
+fn limit_option(args: &[String], name: &str) -> Option<u64> {
+    let value = option_value(args, name)?;
+    match value.parse::<u64>() {
+        Ok(limit) => Some(limit),
+        Err(_) => {
+            eprintln!("{} 的值无效: {}（应为非负整数）", name, value);
+            process::exit(1);
+        }
+    }
+}
+
+// 读取带参数的选项值，例如 --variant 8052
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|value| value.as_str())
 }
 
 fn print_help(program_name: &str) {
@@ -113,14 +781,63 @@ fn print_help(program_name: &str) {
     println!("MCS-51 单片机模拟器");
     println!();
     println!("用法:");
-    println!("  {} <程序文件> [选项]          运行 Intel HEX 格式的程序", prog_name);
+    println!("  {} <程序文件> [选项]          运行 Intel HEX 格式的程序（扩展名为 .bin 时按二进制映像加载）", prog_name);
+    println!("  {} - [选项]                   从标准输入读取 Intel HEX 程序，例如 cat prog.hex | {} -", prog_name, prog_name);
+    println!("  {} --bin <文件> [选项]        运行二进制 ROM 映像", prog_name);
     println!("  {} --inst-dump                显示指令实现情况统计表", prog_name);
     println!("  {} --help                     显示此帮助信息", prog_name);
     println!();
     println!("选项:");
     println!("  --debug, debug                启用调试模式，显示每条指令执行信息");
+    println!("  --symbols <文件>              读取符号文件（地址 标号 列表、Keil .M51、SDCC .map/.rst），输出中用 标号+偏移 代替地址");
+    println!("  --disasm                      不运行程序，从入口地址起列出到最高已加载地址为止的反汇编");
+    println!("  --monitor                     监视器：命令 step [n]、continue、break <地址>、regs、mem <地址> [长度]、disasm [地址] [条数]、quit，便于脚本驱动");
+    println!("  --interactive                 交互式调试：在第一条指令前停下，命令 s [n]、c、r、x <地址> [长度]、u [地址] [条数]、b/d <地址>、w/rw/dw <位置>、q（h 查看说明）");
+    println!("  --break <地址>                断点（可重复）：执行到该地址时停下，显示寄存器和将要执行的指令");
+    println!("  --until <地址>[:次数]         运行到该地址（第几次到达，默认第一次）时在执行之前停下，显示寄存器后结束运行（可重复）");
+    println!("  --watch-write <位置>          写观察点（可重复）：指令写入该单元时输出 PC、指令、原值和新值、时钟周期数，位置前缀 i:/s:/x:，例如 i:0x35");
+    println!("  --watch-read <位置>           读观察点（可重复）：指令读取该单元时输出报告，例如 s:0x90");
+    println!("  --trace [文件|-]              跟踪模式，每条指令输出一行反汇编和执行后的 A/PSW；给出文件时写入文件（--debug 的输出也写入）");
+    println!("  --strict-load                 HEX 文件中的记录重叠且内容不同时报错（默认只警告）");
+    println!("  --load <文件>                 追加加载一个 HEX/OMF 程序文件（可重复），与已加载的映像重叠且内容不同时报错");
+    println!("  --patch <文件>                在所有程序文件之后加载一个 HEX 补丁（可重复），与已加载的映像重叠时补丁的字节生效");
+    println!("  --strict-opcodes              遇到未实现或保留的操作码时停机（默认输出\"未知指令\"后跳过继续执行）");
+    println!("  --omf                         按 OMF-51 绝对目标文件加载（Keil BL51 的输出，以模块头记录开始的文件会自动识别）");
+    println!("  --offset <地址>               二进制映像在 ROM 中的加载地址，默认 0x0000");
+    println!("  --vector-base <地址>          向量表基地址：复位从该地址开始，中断向量同样加上它（如 0x2000 时定时器0中断进入 0x200B）");
+    println!("  --entry <地址>                从该地址开始执行（优先于 HEX 文件中的起始地址记录）");
+    println!("  --variant <8051|8052|89s52>   选择单片机型号（8052 带定时器2和高128字节RAM，89s52 另有看门狗），默认 8051");
+    println!("  --serial-in <文件|->          串口接收数据来源（文件，或 - 表示标准输入），REN 置位后按波特率送入 SBUF");
+    println!("  --serial-script <文件>        串口输入激励脚本，每行 <时钟周期> rx <十六进制字节> 或 <毫秒>ms rx \"文本\"");
+    println!("  --serial-tcp <端口>           在 127.0.0.1 上监听一个 TCP 连接，串口收发都桥接到该连接");
+    println!("  --serial-pty                  创建伪终端并显示其路径，串口收发都桥接到该终端（仅 Unix）");
+    println!("  --serial-out <文件>           把串口发送的字节原样记录到文件（可与 --serial-tcp/--serial-pty 同时使用）");
+    println!("  --quiet                       不输出端口写入提示、加载信息和运行统计（错误和警告照常输出）；与 --serial-out 一起使用时串口数据只写入文件");
+    println!("  --console-port <P0-P3>        把程序写到该端口的每个字节当作字符输出到标准输出（用于在端口上输出字符的旧程序）");
+    println!("  --dump-ram[=文件]             运行结束后以十六进制加 ASCII 转储内部RAM，给出文件时写入文件");
+    println!("  --dump-sfr[=文件]             运行结束后转储 SFR，并逐个列出有名称的寄存器的值");
+    println!("  --dump-xram[=文件] <范围>     运行结束后转储外部数据存储器的指定范围，例如 0x0000-0x00FF 或 0x0000..0x0100");
+    println!("  --turbo                       极速模式（批量回归测试用）：不做循环检测和快进、不逐条输出，运行结束时报告每秒执行的指令数；不能与 --debug/--trace/--realtime/--interactive 同时使用");
+    println!("  --stats                       运行结束后按执行次数列出每个操作码的助记符、次数和占比，以及未实现操作码和中断响应的次数");
+    println!("  --profile <文件>              按地址统计消耗的时钟周期，运行结束后把最多的地址（周期数、占比、指令和标号）和按函数的汇总写入文件");
+    println!("  --coverage <文件>             记录执行过的 ROM 字节，运行结束后把已加载映像的覆盖率和未执行的地址范围（带标号）写入文件");
+    println!("  --vcd <文件>                  把 P0.0-P3.7 端口锁存器的变化写成 VCD 波形文件（GTKWave 可以打开），时间按晶振频率换算；开启后不快进延时循环");
+    println!("  --vcd-sfr                     与 --vcd 一起使用，另外记录 TH0、TL0 和 ACC 三个字节信号");
+    println!("  --state-json <文件>           运行结束后把寄存器、全部 SFR、内部RAM、指令数和时钟周期数以及停机原因写成 JSON 文件");
+    println!("  --realtime                    按实际速度运行：模拟时间与墙上时间同步（用于观察 LED 闪烁、串口终端等）");
+    println!("  --exit-port <地址|none>       退出端口：程序写这个 SFR 地址后停止运行，写入的值作为退出码，默认 0xFF；none 表示不拦截");
+    println!("  --max-instructions <n>        最多执行 n 条指令后停止（0 表示不限制），默认 100000000");
+    println!("  --max-cycles <n>              最多运行 n 个机器周期后停止（0 表示不限制），默认不限制");
     println!("  --inst-dump, -i               显示已实现的指令统计表");
     println!("  --help, -h                    显示此帮助信息");
     println!();
+    println!("退出码:");
+    println!("  0                             程序正常结束");
+    println!("  1                             参数或加载错误");
+    println!("  2                             程序进入掉电模式 (PCON.PD)");
+    println!("  3                             达到 --max-instructions 或 --max-cycles 上限被强制停止");
+    println!("  其他                          程序写入退出端口（--exit-port，默认 0xFF）的值");
+    println!("                                注意：程序写入的 1、2、3 与上面的退出码相同，需要区分时固件应避开这几个值");
+    println!();
     println!("项目地址: https://github.com/yuta-sakata/mcs51-emulator");
 }
\ No newline at end of file
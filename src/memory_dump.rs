@@ -0,0 +1,56 @@
+// 存储器转储 - 以十六进制加 ASCII 的形式显示一段存储器内容
+// 每行 16 字节：地址、16 个十六进制字节、ASCII（不可打印字符显示为 '.'），格式固定，便于脚本解析：
+// 0020: 48 65 6C 6C 6F 00 00 00 00 00 00 00 00 00 00 00  |Hello...........|
+
+use std::fmt::Write;
+
+// 每行显示的字节数
+pub const BYTES_PER_LINE: usize = 16;
+
+// 可以转储的存储空间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemorySpace {
+    Ram,  // 内部RAM（8051 为 0x00-0x7F，8052 为 0x00-0xFF）
+    Sfr,  // 特殊功能寄存器 (0x80-0xFF)
+    Xram, // 外部数据存储器（MOVX 访问的 64KB 空间）
+}
+
+impl MemorySpace {
+    // 转储时的段标题
+    pub fn name(self) -> &'static str {
+        match self {
+            MemorySpace::Ram => "RAM",
+            MemorySpace::Sfr => "SFR",
+            MemorySpace::Xram => "XRAM",
+        }
+    }
+
+    // 地址前缀（调试器命令和观察点报告中使用）
+    pub fn prefix(self) -> &'static str {
+        match self {
+            MemorySpace::Ram => "i",
+            MemorySpace::Sfr => "s",
+            MemorySpace::Xram => "x",
+        }
+    }
+}
+
+// 把从 start 地址开始的数据格式化为转储文本，最后一行不足 16 字节时用空格补齐，ASCII 列保持对齐
+pub fn hex_dump(start: usize, data: &[u8]) -> String {
+    let mut out = String::new();
+    for (index, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        let _ = write!(out, "{:04X}:", start + index * BYTES_PER_LINE);
+        for byte in chunk {
+            let _ = write!(out, " {:02X}", byte);
+        }
+        for _ in chunk.len()..BYTES_PER_LINE {
+            out.push_str("   ");
+        }
+        out.push_str("  |");
+        for &byte in chunk {
+            out.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+        }
+        out.push_str("|\n");
+    }
+    out
+}
@@ -0,0 +1,218 @@
+// 监视器 - 命令行程序的 --monitor 模式
+// 比 --interactive 调试器更简单的命令循环，命令一律写全名，每条命令的输出都是完整的几行文本，便于脚本驱动：
+//   step [n]              单步执行 n 条指令（默认 1）
+//   continue              继续运行直到停机或到达断点
+//   break <地址>          在程序存储器地址处设置断点
+//   regs                  显示寄存器
+//   mem <地址> [长度]     转储存储器（默认 16 字节），地址前缀 i: 内部RAM（默认）、s: SFR、x: 外部数据存储器
+//   disasm [地址] [条数]  反汇编（默认从 PC 开始 5 条）
+//   help                  显示命令列表
+//   quit                  退出
+// 只使用 Emulator 的单步、断点、存储器转储和反汇编接口，命令的解析与执行分开，可以单独测试
+
+use crate::debugger::{self, parse_code_address, parse_location, parse_number};
+use crate::disassembler;
+use crate::emulator::{Emulator, HaltReason};
+use crate::memory_dump::MemorySpace;
+use std::io::{self, BufRead, Write};
+
+// mem 命令默认转储的字节数
+const DEFAULT_MEM_LENGTH: usize = 16;
+
+// disasm 命令默认反汇编的指令条数
+const DEFAULT_DISASM_COUNT: usize = 5;
+
+// 一条监视器命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorCommand {
+    Step(u64),                         // 单步执行 n 条指令
+    Continue,                          // 继续运行
+    Break(u16),                        // 设置断点
+    Regs,                              // 显示寄存器
+    Mem(MemorySpace, usize, usize),    // 转储存储器：空间、起始地址、长度
+    Disasm(Option<u16>, usize),        // 反汇编：起始地址（None 表示 PC）、指令条数
+    Help,                              // 显示命令列表
+    Quit,                              // 退出
+}
+
+// 解析一行命令，格式错误时返回说明
+pub fn parse(line: &str) -> Result<MonitorCommand, String> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err("空命令".to_string());
+    };
+    let command = match name {
+        "step" => match words.next() {
+            None => MonitorCommand::Step(1),
+            Some(count) => MonitorCommand::Step(count.parse().map_err(|_| format!("无效的步数: {}", count))?),
+        },
+        "continue" => MonitorCommand::Continue,
+        "break" => {
+            let address = words.next().ok_or("用法: break <地址>")?;
+            MonitorCommand::Break(parse_code_address(address)?)
+        }
+        "regs" => MonitorCommand::Regs,
+        "mem" => {
+            let address = words.next().ok_or("用法: mem <地址> [长度]，地址前缀 i:/s:/x:")?;
+            let (space, address) = parse_location(address)?;
+            let length = match words.next() {
+                None => DEFAULT_MEM_LENGTH,
+                Some(length) => parse_number(length).ok_or_else(|| format!("无效的长度: {}", length))?,
+            };
+            MonitorCommand::Mem(space, address, length)
+        }
+        "disasm" => {
+            let address = match words.next() {
+                None => None,
+                Some(address) => Some(parse_code_address(address)?),
+            };
+            let count = match words.next() {
+                None => DEFAULT_DISASM_COUNT,
+                Some(count) => parse_number(count).ok_or_else(|| format!("无效的条数: {}", count))?,
+            };
+            MonitorCommand::Disasm(address, count)
+        }
+        "help" => MonitorCommand::Help,
+        "quit" | "exit" => MonitorCommand::Quit,
+        _ => return Err(format!("未知命令: {}（输入 help 查看命令列表）", name)),
+    };
+    if let Some(extra) = words.next() {
+        return Err(format!("多余的参数: {}", extra));
+    }
+    Ok(command)
+}
+
+// 运行监视器，直到输入 quit 或输入结束
+pub fn run<R: BufRead, W: Write>(emulator: &mut Emulator, input: R, output: &mut W) -> io::Result<()> {
+    debugger::show_next_instruction(emulator, output)?;
+    prompt(output)?;
+
+    for line in input.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            match parse(&line) {
+                Ok(MonitorCommand::Quit) => break,
+                Ok(command) => execute(emulator, command, output)?,
+                Err(message) => writeln!(output, "{}", message)?,
+            }
+        }
+        prompt(output)?;
+    }
+    Ok(())
+}
+
+fn prompt<W: Write>(output: &mut W) -> io::Result<()> {
+    write!(output, "> ")?;
+    output.flush()
+}
+
+// 执行一条命令（Quit 由调用方处理）
+pub fn execute<W: Write>(emulator: &mut Emulator, command: MonitorCommand, output: &mut W) -> io::Result<()> {
+    match command {
+        MonitorCommand::Step(count) => {
+            for _ in 0..count {
+                if emulator.step() != HaltReason::Running {
+                    break;
+                }
+            }
+            debugger::report_stop(emulator, output)?;
+        }
+        MonitorCommand::Continue => {
+            while emulator.step() == HaltReason::Running {}
+            debugger::report_stop(emulator, output)?;
+        }
+        MonitorCommand::Break(address) => {
+            emulator.breakpoints.insert(address);
+            writeln!(output, "断点设在 {:#06X}", address)?;
+        }
+        MonitorCommand::Regs => debugger::show_registers(emulator, output)?,
+        MonitorCommand::Mem(space, address, length) => {
+            let dump = emulator.dump_memory(space, address..address.saturating_add(length));
+            if dump.is_empty() {
+                writeln!(output, "地址超出{}范围", space.name())?;
+            } else {
+                write!(output, "{}", dump)?;
+            }
+        }
+        MonitorCommand::Disasm(address, count) => {
+            let mut address = address.unwrap_or(emulator.cpu.registers.pc);
+            for _ in 0..count {
+                let line = disassembler::disassemble(&emulator.cpu.rom, address, &emulator.symbols);
+                writeln!(output, "{:04X}: {:<9} {}", line.address, line.hex_bytes(), line.text)?;
+                address = address.wrapping_add(line.length as u16);
+            }
+        }
+        MonitorCommand::Help => {
+            writeln!(output, "step [n]              单步执行 n 条指令（默认 1）")?;
+            writeln!(output, "continue              继续运行直到停机或到达断点")?;
+            writeln!(output, "break <地址>          设置断点")?;
+            writeln!(output, "regs                  显示寄存器")?;
+            writeln!(output, "mem <地址> [长度]     转储存储器（默认 16 字节），前缀 i: 内部RAM（默认）、s: SFR、x: 外部数据存储器")?;
+            writeln!(output, "disasm [地址] [条数]  反汇编，默认从 PC 开始 5 条")?;
+            writeln!(output, "quit                  退出")?;
+        }
+        MonitorCommand::Quit => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MOV A,#0x12; MOV 0x30,A; INC A; MOV R0,A; SJMP $
+    const PROGRAM: [u8; 8] = [0x74, 0x12, 0xF5, 0x30, 0x04, 0xF8, 0x80, 0xFE];
+
+    fn emulator() -> Emulator {
+        let mut emulator = Emulator::new(false);
+        emulator.cpu.rom[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+        emulator
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("step"), Ok(MonitorCommand::Step(1)));
+        assert_eq!(parse("step 3"), Ok(MonitorCommand::Step(3)));
+        assert_eq!(parse("continue"), Ok(MonitorCommand::Continue));
+        assert_eq!(parse("break 0x0004"), Ok(MonitorCommand::Break(4)));
+        assert_eq!(parse("regs"), Ok(MonitorCommand::Regs));
+        assert_eq!(parse("mem 0x30"), Ok(MonitorCommand::Mem(MemorySpace::Ram, 0x30, DEFAULT_MEM_LENGTH)));
+        assert_eq!(parse("mem s:0x90 1"), Ok(MonitorCommand::Mem(MemorySpace::Sfr, 0x90, 1)));
+        assert_eq!(parse("disasm"), Ok(MonitorCommand::Disasm(None, DEFAULT_DISASM_COUNT)));
+        assert_eq!(parse("disasm 0x0002 2"), Ok(MonitorCommand::Disasm(Some(2), 2)));
+        assert_eq!(parse("quit"), Ok(MonitorCommand::Quit));
+        assert!(parse("break").is_err());
+        assert!(parse("regs now").is_err());
+        assert!(parse("s").is_err());
+    }
+
+    #[test]
+    fn scripted_session() {
+        let mut emulator = emulator();
+        let script = "break 0x0004\ncontinue\nregs\nmem 0x30 1\nstep 2\ndisasm 0x0004 1\nquit\nstep\n";
+        let mut output = Vec::new();
+        run(&mut emulator, script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("到达断点 0x0004"));
+        assert!(output.contains("A=12 "));
+        assert!(output.contains("12"));
+        assert!(output.contains("0004: 04        inc A"));
+        // 断点之后又执行了 INC A 和 MOV R0,A，quit 之后的命令不再执行
+        assert_eq!(emulator.cpu.registers.pc, 0x0006);
+        assert_eq!(emulator.cpu.registers.acc, 0x13);
+        assert_eq!(emulator.cpu.ram[0], 0x13);
+        assert_eq!(emulator.cpu.ram[0x30], 0x12);
+    }
+
+    #[test]
+    fn reports_errors_and_keeps_going() {
+        let mut emulator = emulator();
+        let mut output = Vec::new();
+        run(&mut emulator, "bogus\nmem i:0x200\nstep\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("未知命令: bogus"));
+        assert!(output.contains("地址超出"));
+        assert_eq!(emulator.cpu.registers.pc, 0x0002);
+    }
+}
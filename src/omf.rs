@@ -0,0 +1,111 @@
+// OMF-51 绝对目标文件 - Keil BL51/LX51 默认输出的格式（没有扩展名），不用转换成 HEX 也能直接加载
+// 文件由一串记录组成，每条记录为：类型(1字节) 长度(2字节，小端，包含校验和) 内容 校验和
+// 校验和使记录所有字节之和为 0
+// 这里只取内容记录 (0x06) 中的代码，调试、符号等其他记录直接跳过
+
+use std::fs;
+use std::io;
+
+// 记录类型
+pub const MODULE_HEADER: u8 = 0x02; // 模块头：模块名、翻译器 ID
+pub const MODULE_END: u8 = 0x04;    // 模块结束
+pub const CONTENT: u8 = 0x06;       // 内容：段 ID、偏移和代码字节
+
+// 一段连续的代码：从 address 开始放入 data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OmfContent {
+    pub address: u16,
+    pub data: Vec<u8>,
+}
+
+// 文件是否像 OMF-51 目标文件（以模块头记录开始）
+pub fn is_omf(data: &[u8]) -> bool {
+    data.first() == Some(&MODULE_HEADER)
+}
+
+// 从文件读取全部内容记录
+pub fn load_omf(path: &str) -> io::Result<Vec<OmfContent>> {
+    let data = fs::read(path)?;
+    parse_omf(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// 解析 OMF-51 绝对目标文件，出错时返回带记录类型和文件偏移的说明
+pub fn parse_omf(data: &[u8]) -> Result<Vec<OmfContent>, String> {
+    if !is_omf(data) {
+        return Err("不是 OMF-51 目标文件（缺少模块头记录）".to_string());
+    }
+
+    let mut contents = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let record_type = data[offset];
+        let error = |message: &str| format!("记录类型 {:#04X}（偏移 {:#X}）: {}", record_type, offset, message);
+
+        if offset + 3 > data.len() {
+            return Err(error("记录头不完整"));
+        }
+        let length = u16::from_le_bytes([data[offset + 1], data[offset + 2]]) as usize;
+        let end = offset + 3 + length;
+        if length == 0 || end > data.len() {
+            return Err(error(&format!("记录长度 {} 超出文件末尾", length)));
+        }
+        let record = &data[offset..end];
+        if record.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0 {
+            return Err(error("校验和错误"));
+        }
+
+        // 去掉记录头和校验和之后的内容
+        let body = &record[3..record.len() - 1];
+        match record_type {
+            CONTENT => {
+                if body.len() < 3 {
+                    return Err(error("内容记录太短"));
+                }
+                // 绝对模块中所有段都是绝对段，段 ID 为 0
+                if body[0] != 0 {
+                    return Err(error(&format!("段 ID {} 不是绝对段，文件需要先经过 BL51 链接定位", body[0])));
+                }
+                let address = u16::from_le_bytes([body[1], body[2]]);
+                let data = body[3..].to_vec();
+                if address as usize + data.len() > 0x10000 {
+                    return Err(error("内容超出 64KB 程序存储器范围"));
+                }
+                contents.push(OmfContent { address, data });
+            }
+            MODULE_END => break,
+            _ => {} // 模块头、调试和符号记录不影响程序存储器
+        }
+
+        offset = end;
+    }
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CPU;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    // blink.omf 是 blink.hex 的 OMF-51 版本：模块头、一条调试记录、0x0000 和 0x0100 两条内容记录、模块结束
+    #[test]
+    fn omf_fixture_loads_the_same_rom_as_its_hex() {
+        let mut from_omf = CPU::new();
+        assert_eq!(from_omf.load_omf_program(&fixture("blink.omf")).unwrap(), 15);
+        let mut from_hex = CPU::new();
+        from_hex.load_hex_program(&fixture("blink.hex")).unwrap();
+        assert!(from_omf.rom[..] == from_hex.rom[..]);
+        assert_eq!(&from_omf.rom[0x100..0x104], &[0xFE, 0xFD, 0xFB, 0xF7]);
+    }
+
+    #[test]
+    fn corrupted_checksum_names_record_type_and_offset() {
+        let error = load_omf(&fixture("blink_bad_checksum.omf")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "记录类型 0x06（偏移 0x2E）: 校验和错误");
+    }
+}
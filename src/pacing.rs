@@ -0,0 +1,95 @@
+// 实时节拍：让模拟时间（时钟周期 / 晶振频率）跟上墙上时间，用于演示闪烁的 LED、串口终端等
+// 模拟跑得比墙上时间快时睡眠等待；主机跟不上（或者在调试提示符处停留）落后超过追赶窗口时，
+// 不再试图连续快跑补回，而是从当前时刻重新对齐，避免突发执行
+// 循环快进一次推进大量时钟周期，下一次同步时按推进后的时钟睡眠相应的时间，节拍不会跳变
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 每推进这么多模拟时间比较一次（秒）
+const SYNC_INTERVAL: f64 = 0.001;
+
+// 落后墙上时间超过这个窗口时重新对齐（秒）
+const CATCH_UP_WINDOW: f64 = 0.05;
+
+pub struct Pacer {
+    anchor_time: Instant, // 对齐时的墙上时间
+    anchor_cycles: u64,   // 对齐时的时钟周期数
+    next_sync: u64,       // 到达这个时钟周期数时再比较
+}
+
+impl Pacer {
+    // 从当前时刻、当前时钟周期数开始计时
+    pub fn new(clock_cycles: u64) -> Self {
+        Pacer { anchor_time: Instant::now(), anchor_cycles: clock_cycles, next_sync: clock_cycles }
+    }
+
+    // 是否到了比较的时候
+    pub fn due(&self, clock_cycles: u64) -> bool {
+        clock_cycles >= self.next_sync
+    }
+
+    // 从当前时刻重新对齐（这段模拟时间不必等待）
+    pub fn realign(&mut self, clock_cycles: u64) {
+        self.anchor_time = Instant::now();
+        self.anchor_cycles = clock_cycles;
+    }
+
+    // 按当前时钟周期数同步：超前时睡眠，落后超过追赶窗口时重新对齐
+    pub fn sync(&mut self, clock_cycles: u64, clock_frequency: u32) {
+        if !self.due(clock_cycles) {
+            return;
+        }
+        let frequency = clock_frequency.max(1) as f64;
+        self.next_sync = clock_cycles + (frequency * SYNC_INTERVAL).max(1.0) as u64;
+
+        let emulated = clock_cycles.saturating_sub(self.anchor_cycles) as f64 / frequency;
+        let wall = self.anchor_time.elapsed().as_secs_f64();
+        if emulated > wall {
+            thread::sleep(Duration::from_secs_f64(emulated - wall));
+        } else if wall - emulated > CATCH_UP_WINDOW {
+            self.realign(clock_cycles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FREQUENCY: u32 = 12_000_000;
+
+    #[test]
+    fn ahead_of_wall_time_sleeps_until_in_sync() {
+        let mut pacer = Pacer::new(0);
+        let start = Instant::now();
+        // 20ms 的模拟时间
+        pacer.sync(240_000, FREQUENCY);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        // 下一次比较在 1ms 的模拟时间之后
+        assert!(!pacer.due(240_000 + 11_999));
+        assert!(pacer.due(240_000 + 12_000));
+    }
+
+    #[test]
+    fn falling_behind_realigns_instead_of_bursting() {
+        let mut pacer = Pacer::new(0);
+        pacer.anchor_time -= Duration::from_millis(200);
+        pacer.sync(12_000, FREQUENCY);
+        assert_eq!(pacer.anchor_cycles, 12_000);
+
+        // 重新对齐之后按新的起点计时：再推进 10ms 只等待约 10ms，不会补回落后的 200ms
+        let start = Instant::now();
+        pacer.sync(12_000 + 120_000, FREQUENCY);
+        let waited = start.elapsed();
+        assert!(waited >= Duration::from_millis(9) && waited < Duration::from_millis(100), "{:?}", waited);
+    }
+
+    #[test]
+    fn small_lag_is_caught_up_without_realigning() {
+        let mut pacer = Pacer::new(0);
+        pacer.anchor_time -= Duration::from_millis(20);
+        pacer.sync(12_000, FREQUENCY);
+        assert_eq!(pacer.anchor_cycles, 0);
+    }
+}
@@ -0,0 +1,162 @@
+// 按地址的时钟周期分析（--profile）：找出消耗时钟周期最多的指令和函数
+// 每个地址一个 u64 计数（64K 个，共 512KB，只在开启时分配），另按函数入口累计自身的周期数
+// 函数取调用跟踪（LCALL/ACALL 和中断响应）中最内层调用的入口地址，不在任何调用里时为复位入口 0x0000
+// 循环快进跳过的迭代计入循环指令的地址，中断响应的周期计入中断向量，空闲模式的周期计入唤醒后继续执行的地址
+
+use crate::disassembler;
+use crate::symbols::{self, SymbolTable};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// 报告中列出的地址数
+pub const DEFAULT_TOP_N: usize = 20;
+
+pub struct Profiler {
+    cycles: Box<[u64]>,              // 每个地址消耗的时钟周期
+    functions: HashMap<u16, u64>,    // 函数入口地址 → 函数自身（不含调用的子程序）消耗的时钟周期
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler { cycles: vec![0; 0x10000].into_boxed_slice(), functions: HashMap::new() }
+    }
+
+    // 在 function 里执行地址 pc 处的指令，消耗 clocks 个时钟周期
+    pub fn record(&mut self, pc: u16, function: u16, clocks: u64) {
+        self.cycles[pc as usize] += clocks;
+        *self.functions.entry(function).or_insert(0) += clocks;
+    }
+
+    // 地址消耗的时钟周期
+    pub fn cycles_at(&self, pc: u16) -> u64 {
+        self.cycles[pc as usize]
+    }
+
+    // 记录的总时钟周期
+    pub fn total(&self) -> u64 {
+        self.cycles.iter().sum()
+    }
+
+    // 消耗最多的 top_n 个地址，按周期数从多到少排列（相同时按地址）
+    pub fn top(&self, top_n: usize) -> Vec<(u16, u64)> {
+        let mut hot: Vec<(u16, u64)> = self
+            .cycles
+            .iter()
+            .enumerate()
+            .filter(|&(_, &clocks)| clocks > 0)
+            .map(|(address, &clocks)| (address as u16, clocks))
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.truncate(top_n);
+        hot
+    }
+
+    // 各函数自身消耗的时钟周期，按周期数从多到少排列
+    pub fn functions(&self) -> Vec<(u16, u64)> {
+        let mut functions: Vec<(u16, u64)> = self.functions.iter().map(|(&entry, &clocks)| (entry, clocks)).collect();
+        functions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        functions
+    }
+
+    // 文本报告：前 top_n 个地址（周期数、占比、反汇编和所在标号），然后是按函数的汇总
+    pub fn report(&self, code: &[u8], symbols: &SymbolTable, top_n: usize) -> String {
+        let total = self.total();
+        let percent = |clocks: u64| if total == 0 { 0.0 } else { clocks as f64 * 100.0 / total as f64 };
+        let mut out = String::new();
+
+        let _ = writeln!(out, "时钟周期分析（共 {} 个时钟周期）", total);
+        let _ = writeln!(out);
+        let _ = writeln!(out, "消耗最多的 {} 个地址：", top_n);
+        let _ = writeln!(out, "  地址          时钟周期    占比    指令");
+        for (address, clocks) in self.top(top_n) {
+            let text = disassembler::disassemble(code, address, symbols).text;
+            let label = symbols::symbolize(symbols, address).unwrap_or_default();
+            let line = format!("  {:#06x} {:>16}  {:>6.2}%  {:<28} {}", address, clocks, percent(clocks), text, label);
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "按函数（自身的时钟周期，不含调用的子程序）：");
+        let _ = writeln!(out, "  入口          时钟周期    占比    函数");
+        for (entry, clocks) in self.functions() {
+            let _ = writeln!(
+                out,
+                "  {:#06x} {:>16}  {:>6.2}%  {}",
+                entry,
+                clocks,
+                percent(clocks),
+                symbols::format_address(symbols, entry)
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::HaltReason;
+    use crate::symbols::parse_symbols;
+    use crate::test_support::{emulator_with_blocks, run_to_halt};
+
+    // LCALL delay; MOV 0xFF,#0
+    // delay (0x0010): MOV R6,#20; 外层: MOV R7,#0; DJNZ R7,$; DJNZ R6,外层; RET
+    fn delay_program() -> crate::emulator::Emulator {
+        let mut emulator = emulator_with_blocks(&[
+            (0x0000, &[0x12, 0x00, 0x10, 0x75, 0xFF, 0x00]),
+            (0x0010, &[0x7E, 0x14, 0x7F, 0x00, 0xDF, 0xFE, 0xDE, 0xFA, 0x22]),
+        ]);
+        emulator.cpu.exit_port = Some(0xFF);
+        emulator.set_symbols(parse_symbols("0x0010 delay\n"));
+        emulator.enable_profiler();
+        emulator
+    }
+
+    #[test]
+    fn dominant_delay_loop_gets_most_of_the_cycles() {
+        let mut emulator = delay_program();
+        assert_eq!(run_to_halt(&mut emulator, 100_000), HaltReason::ExitCode(0));
+        let profiler = emulator.profiler.as_ref().unwrap();
+
+        // 每个时钟周期都计入了某个地址
+        assert_eq!(profiler.total(), emulator.clock_cycles);
+        // DJNZ R7,$ 执行 20×256 次，每次 24 个时钟周期，其中大部分由循环快进跳过
+        assert_eq!(profiler.cycles_at(0x0014), 20 * 256 * 24);
+        assert!(profiler.cycles_at(0x0014) * 100 > profiler.total() * 90);
+        assert_eq!(profiler.top(1), [(0x0014, 20 * 256 * 24)]);
+
+        // 除了 LCALL 和写退出端口，全部计入 delay
+        let functions = profiler.functions();
+        assert_eq!(functions[0].0, 0x0010);
+        assert_eq!(functions[1], (0x0000, 24 + 24));
+    }
+
+    #[test]
+    fn report_names_the_hot_loop() {
+        let mut emulator = delay_program();
+        run_to_halt(&mut emulator, 100_000);
+        let report = emulator.profile_report(3).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], format!("时钟周期分析（共 {} 个时钟周期）", emulator.clock_cycles));
+        assert!(lines[4].starts_with("  0x0014           122880"), "{}", report);
+        assert!(lines[4].ends_with("delay+0x4"), "{}", report);
+        assert!(report.contains("按函数（自身的时钟周期，不含调用的子程序）："));
+        assert!(report.lines().any(|line| line.starts_with("  0x0010") && line.ends_with("delay")), "{}", report);
+    }
+
+    #[test]
+    fn ties_are_ordered_by_address() {
+        let mut profiler = Profiler::new();
+        profiler.record(0x0020, 0x0000, 24);
+        profiler.record(0x0010, 0x0000, 24);
+        profiler.record(0x0030, 0x0100, 36);
+        assert_eq!(profiler.top(2), [(0x0030, 36), (0x0010, 24)]);
+        assert_eq!(profiler.functions(), [(0x0000, 48), (0x0100, 36)]);
+    }
+}
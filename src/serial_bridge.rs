@@ -0,0 +1,273 @@
+// 串口桥接 - 把模拟的 UART 接到宿主机的 TCP 连接或伪终端上，用于交互式固件（菜单、命令行）
+// 收发都在后台线程中进行：接收的字节通过通道交给串口按波特率送入 SBUF，
+// 发送的字节先进入通道再由写线程写出，所以模拟主循环永远不会阻塞在套接字或终端上
+// 另外提供把发送数据同时写到多个目标的 TeeSink 和记录发送数据的 SerialLog
+
+use crate::cpu::serial::spawn_reader;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 串口记录文件的刷新间隔
+const LOG_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+// 串口发送输出：字节放入通道后立即返回，由写线程负责真正的输出
+pub struct ChannelSink(Sender<u8>);
+
+impl Write for ChannelSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 对端已断开时丢弃数据，不影响模拟
+        for &byte in buf {
+            let _ = self.0.send(byte);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 把发送的字节同时写到多个输出目标（例如终端和记录文件）
+// 某个目标出错不影响其他目标
+pub struct TeeSink(pub Vec<Box<dyn Write>>);
+
+impl Write for TeeSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for sink in self.0.iter_mut() {
+            let _ = sink.write_all(buf);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for sink in self.0.iter_mut() {
+            let _ = sink.flush();
+        }
+        Ok(())
+    }
+}
+
+// 串口记录文件：原样写入发送的字节
+// 串口每发送一个字节都会 flush，这里只在距上次写盘超过刷新间隔时才真正写盘，
+// 其余数据在 SerialLog 被丢弃时写入
+pub struct SerialLog {
+    file: BufWriter<File>,
+    last_flush: Instant,
+}
+
+impl SerialLog {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(SerialLog {
+            file: BufWriter::new(File::create(path)?),
+            last_flush: Instant::now(),
+        })
+    }
+}
+
+impl Write for SerialLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() < LOG_FLUSH_INTERVAL {
+            return Ok(());
+        }
+        self.last_flush = Instant::now();
+        self.file.flush()
+    }
+}
+
+// 把通道中的字节逐个写到输出流，直到输出流出错或模拟器退出
+fn forward_to<W: Write>(receiver: Receiver<u8>, mut writer: W) {
+    while let Ok(byte) = receiver.recv() {
+        let mut pending = vec![byte];
+        pending.extend(receiver.try_iter());
+        if writer.write_all(&pending).and_then(|_| writer.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+// 在 127.0.0.1:port 上监听一个 TCP 连接，返回实际监听的端口、接收数据来源和发送输出
+// 连接建立之前发送的字节会被缓存，连接后一起发出
+pub fn listen_tcp(port: u16) -> io::Result<(u16, Receiver<u8>, Box<dyn Write>)> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    let local_port = listener.local_addr()?.port();
+    let (rx_sender, rx_receiver) = mpsc::channel();
+    let (tx_sender, tx_receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let Ok((stream, _)) = listener.accept() else {
+            return;
+        };
+        let Ok(reader) = stream.try_clone() else {
+            return;
+        };
+        // 套接字读到的字节转交给串口的接收通道
+        let incoming = spawn_reader(reader);
+        thread::spawn(move || {
+            for byte in incoming {
+                if rx_sender.send(byte).is_err() {
+                    break;
+                }
+            }
+        });
+        forward_to(tx_receiver, stream);
+    });
+
+    Ok((local_port, rx_receiver, Box::new(ChannelSink(tx_sender))))
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_int};
+
+    // termios 的布局因平台而异，这里只当作足够大的不透明缓冲区传给 C 库
+    #[repr(C, align(8))]
+    struct Termios([u8; 256]);
+
+    const TCSANOW: c_int = 0;
+
+    unsafe extern "C" {
+        fn grantpt(fd: c_int) -> c_int;
+        fn unlockpt(fd: c_int) -> c_int;
+        fn ptsname(fd: c_int) -> *mut c_char;
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, action: c_int, termios: *const Termios) -> c_int;
+        fn cfmakeraw(termios: *mut Termios);
+    }
+
+    // 把从设备设为原始模式：关闭回显和行缓冲，串口数据原样收发
+    pub fn make_raw(fd: c_int) -> bool {
+        let mut termios = Termios([0; 256]);
+        // SAFETY: 缓冲区大于任何平台上的 struct termios，且按8字节对齐
+        unsafe {
+            if tcgetattr(fd, &mut termios) != 0 {
+                return false;
+            }
+            cfmakeraw(&mut termios);
+            tcsetattr(fd, TCSANOW, &termios) == 0
+        }
+    }
+
+    // 允许访问主设备对应的从设备并返回从设备路径
+    pub fn unlock(fd: c_int) -> Option<String> {
+        // SAFETY: fd 是刚打开的 /dev/ptmx；ptsname 返回的字符串在下一次调用前有效，这里立即复制
+        unsafe {
+            if grantpt(fd) != 0 || unlockpt(fd) != 0 {
+                return None;
+            }
+            let name = ptsname(fd);
+            if name.is_null() {
+                return None;
+            }
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+        }
+    }
+}
+
+// 分配一个伪终端，返回从设备路径（例如 /dev/pts/3）、接收数据来源和发送输出
+// 模拟器自己保持从设备打开，终端程序连接之前和断开之后主设备都不会读到挂断
+#[cfg(unix)]
+pub fn open_pty() -> io::Result<(String, Receiver<u8>, Box<dyn Write>)> {
+    use std::fs::OpenOptions;
+    use std::os::fd::AsRawFd;
+
+    let master = OpenOptions::new().read(true).write(true).open("/dev/ptmx")?;
+    let path = pty::unlock(master.as_raw_fd())
+        .ok_or_else(|| io::Error::other("无法解锁伪终端从设备"))?;
+    let slave = OpenOptions::new().read(true).write(true).open(&path)?;
+    if !pty::make_raw(slave.as_raw_fd()) {
+        return Err(io::Error::last_os_error());
+    }
+
+    let reader = master.try_clone()?;
+    let (tx_sender, tx_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _slave = slave;
+        forward_to(tx_receiver, master);
+    });
+
+    Ok((path, spawn_reader(reader), Box::new(ChannelSink(tx_sender))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Emulator;
+    use crate::test_support::{emulator_with_blocks, load, shared_output};
+    use std::io::{ErrorKind, Read};
+    use std::net::TcpStream;
+
+    // 回显固件：MOV TMOD,#0x20; MOV TH1,#0xFD; MOV SCON,#0x50; SETB TR1
+    // 循环: JNB RI,$; MOV A,SBUF; CLR RI; MOV SBUF,A; JNB TI,$; CLR TI; SJMP 循环
+    const ECHO: [u8; 27] = [
+        0x75, 0x89, 0x20, 0x75, 0x8D, 0xFD, 0x75, 0x98, 0x50, 0xD2, 0x8E, 0x30, 0x98, 0xFD, 0xE5, 0x99, 0xC2, 0x98, 0xF5,
+        0x99, 0x30, 0x99, 0xFD, 0xC2, 0x99, 0x80, 0xF0,
+    ];
+
+    #[test]
+    fn tcp_client_gets_firmware_response() {
+        let (port, source, sink) = listen_tcp(0).unwrap();
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        load(&mut emulator, &ECHO);
+        emulator.cpu.set_serial_source(source);
+        emulator.cpu.set_serial_sink(sink);
+
+        let mut client = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        client.write_all(b"ping\n").unwrap();
+        client.set_nonblocking(true).unwrap();
+
+        let started = Instant::now();
+        let mut response = Vec::new();
+        while response.len() < 5 {
+            assert!(started.elapsed() < Duration::from_secs(10), "只收到 {:?}", response);
+            for _ in 0..1000 {
+                emulator.step();
+            }
+            let mut buffer = [0; 16];
+            match client.read(&mut buffer) {
+                Ok(count) => response.extend_from_slice(&buffer[..count]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+                Err(error) => panic!("{}", error),
+            }
+        }
+        assert_eq!(response, b"ping\n");
+    }
+
+    #[test]
+    fn serial_log_records_raw_bytes_alongside_console() {
+        // 串口初始化后对每个字节: MOV A,#字节; LCALL 0x0080，最后 SJMP $
+        // 发送子程序 (0x0080): MOV SBUF,A; JNB TI,$; CLR TI; RET
+        const MESSAGE: &[u8] = b"OK\xFF\x80\xE4\xB8\xAD\n";
+        let mut main = ECHO[..11].to_vec();
+        for &byte in MESSAGE {
+            main.extend([0x74, byte, 0x12, 0x00, 0x80]);
+        }
+        let end = main.len() as u16;
+        main.extend([0x80, 0xFE]);
+        let mut emulator = emulator_with_blocks(&[(0x0000, &main), (0x0080, &[0xF5, 0x99, 0x30, 0x99, 0xFD, 0xC2, 0x99, 0x22])]);
+
+        let path = std::env::temp_dir().join(format!("mcs51-serial-out-{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let (console, out) = shared_output();
+        emulator.cpu.set_serial_sink(Box::new(TeeSink(vec![Box::new(SerialLog::create(path).unwrap()), out])));
+        while emulator.cpu.registers.pc != end {
+            emulator.step();
+            assert!(emulator.clock_cycles < 12 * 100_000);
+        }
+        // 丢弃模拟器时写入记录文件中尚未写盘的数据
+        drop(emulator);
+
+        let logged = std::fs::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(logged, MESSAGE);
+        assert_eq!(console.borrow().as_slice(), MESSAGE);
+    }
+}
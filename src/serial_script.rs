@@ -0,0 +1,156 @@
+// 串口输入激励脚本 - 让串口数据在指定的模拟时间到达，用于确定性地测试接收程序（如超时处理）
+// 每行一个事件，时间是从复位开始的模拟时间，必须按时间顺序排列：
+//   <时钟周期> rx <十六进制字节>     例如  24000 rx 41
+//   <毫秒>ms rx "文本"              例如  2.5ms rx "AT\r\n"
+// 文本支持 \r \n \t \\ \" \xHH 转义；# 或 ; 开头的行为注释
+// 到达时间后数据进入串口接收队列，每一帧仍按波特率逐位接收
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+
+// 一个激励事件：clock_cycle 时把 data 送入串口线路
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptEvent {
+    pub clock_cycle: u64,
+    pub data: Vec<u8>,
+}
+
+// 从文件加载激励脚本，毫秒按晶振频率换算为时钟周期
+pub fn load_serial_script(path: &str, clock_frequency: u32) -> io::Result<VecDeque<ScriptEvent>> {
+    let text = fs::read_to_string(path)?;
+    parse_serial_script(&text, clock_frequency).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// 解析激励脚本，出错时返回带行号的说明
+pub fn parse_serial_script(text: &str, clock_frequency: u32) -> Result<VecDeque<ScriptEvent>, String> {
+    let mut events = VecDeque::new();
+    let mut last_cycle = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        let event = parse_event(line, clock_frequency).map_err(|e| format!("第 {} 行: {}", line_number, e))?;
+        if event.clock_cycle < last_cycle {
+            return Err(format!(
+                "第 {} 行: 时间 {} 早于上一个事件的 {}（事件必须按时间顺序排列）",
+                line_number, event.clock_cycle, last_cycle
+            ));
+        }
+        last_cycle = event.clock_cycle;
+        events.push_back(event);
+    }
+
+    Ok(events)
+}
+
+fn parse_event(line: &str, clock_frequency: u32) -> Result<ScriptEvent, String> {
+    let (time, rest) = line.split_once(char::is_whitespace).ok_or("缺少事件类型")?;
+    let (kind, data) = rest.trim_start().split_once(char::is_whitespace).ok_or("缺少数据")?;
+    if kind != "rx" {
+        return Err(format!("不支持的事件类型 \"{}\"", kind));
+    }
+
+    let clock_cycle = parse_time(time, clock_frequency)?;
+    let data = data.trim();
+    let data = if data.starts_with('"') {
+        parse_text(data)?
+    } else {
+        let digits = data.strip_prefix("0x").or_else(|| data.strip_prefix("0X")).unwrap_or(data);
+        vec![u8::from_str_radix(digits, 16).map_err(|_| format!("无效的十六进制字节 \"{}\"", data))?]
+    };
+
+    Ok(ScriptEvent { clock_cycle, data })
+}
+
+// 时间：纯数字为时钟周期，带 ms 后缀为毫秒
+fn parse_time(time: &str, clock_frequency: u32) -> Result<u64, String> {
+    match time.strip_suffix("ms") {
+        Some(ms) => {
+            let ms: f64 = ms.parse().map_err(|_| format!("无效的时间 \"{}\"", time))?;
+            if !ms.is_finite() || ms < 0.0 {
+                return Err(format!("无效的时间 \"{}\"", time));
+            }
+            Ok((ms * clock_frequency as f64 / 1000.0).round() as u64)
+        }
+        None => time.parse().map_err(|_| format!("无效的时间 \"{}\"", time)),
+    }
+}
+
+// 解析带引号的文本
+fn parse_text(quoted: &str) -> Result<Vec<u8>, String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .filter(|_| quoted.len() >= 2)
+        .ok_or("文本缺少结束引号")?;
+
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('"') => bytes.push(b'"'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16).map_err(|_| format!("无效的转义 \\x{}", hex))?;
+                bytes.push(byte);
+            }
+            other => return Err(format!("无效的转义 \\{}", other.map(String::from).unwrap_or_default())),
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Emulator;
+    use crate::test_support::load;
+
+    // 串口初始化（模式1，REN，9600 波特）; JNB RI,$; CLR RI; MOV R7,#0
+    // 等待: JB RI,收到; NOP; NOP; DJNZ R7,等待（共 1536 个机器周期）; MOV 0x40,#1（超时）; SJMP $
+    // 收到: MOV 0x40,#2; SJMP $
+    const TIMEOUT_FIRMWARE: [u8; 35] = [
+        0x75, 0x89, 0x20, 0x75, 0x8D, 0xFD, 0x75, 0x98, 0x50, 0xD2, 0x8E, 0x30, 0x98, 0xFD, 0xC2, 0x98, 0x7F, 0x00, 0x20,
+        0x98, 0x09, 0x00, 0x00, 0xDF, 0xF9, 0x75, 0x40, 0x01, 0x80, 0xFE, 0x75, 0x40, 0x02, 0x80, 0xFE,
+    ];
+
+    // 按脚本送入数据，返回固件的结果：1 为超时，2 为收到第二个字节
+    fn run_script(script: &str) -> u8 {
+        let mut emulator = Emulator::builder().clock(11_059_200).build();
+        load(&mut emulator, &TIMEOUT_FIRMWARE);
+        emulator.serial_script = parse_serial_script(script, emulator.cpu.clock_frequency).unwrap();
+        while emulator.cpu.ram[0x40] == 0 {
+            emulator.step();
+            assert!(emulator.clock_cycles < 11_059_200 / 100, "10ms 内没有结果");
+        }
+        emulator.cpu.ram[0x40]
+    }
+
+    #[test]
+    fn gap_longer_than_firmware_timeout_takes_timeout_path() {
+        assert_eq!(run_script("1ms rx 41\n1.5ms rx 42\n"), 2);
+        assert_eq!(run_script("1ms rx 41\n5ms rx 42\n"), 1);
+    }
+
+    #[test]
+    fn out_of_order_events_are_rejected_with_line_number() {
+        let error = parse_serial_script("100 rx 41\n# 注释\n50 rx \"B\"\n", 12_000_000).unwrap_err();
+        assert!(error.starts_with("第 3 行"), "{}", error);
+        let events = parse_serial_script("12000 rx 0x41\n2ms rx \"A\\r\"\n", 12_000_000).unwrap();
+        assert_eq!(events[1], ScriptEvent { clock_cycle: 24_000, data: b"A\r".to_vec() });
+    }
+}
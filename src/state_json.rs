@@ -0,0 +1,166 @@
+// 机器状态的 JSON 输出（--state-json）：供脚本在运行结束后读取最终状态，不必解析给人看的统计行
+// 格式固定（version 变化时才会改动字段），数值一律为十进制整数：
+// {
+//   "version": 1,
+//   "halt": {"reason": "exit_code", "exit_code": 0},
+//   "registers": {"acc": 90, "b": 0, "psw": 0, "sp": 7, "dptr": 0, "pc": 13},
+//   "sfr": [{"address": 128, "name": "P0", "value": 255}, ...],
+//   "ram": [0, 0, ...],
+//   "counters": {"instructions": 5, "clock_cycles": 60, "machine_cycles": 5}
+// }
+// halt.reason 取 HaltReason::id()；unknown_opcode 另有 address 和 opcode，exit_code 另有 exit_code
+// sfr 按地址列出 0x80-0xFF 全部 128 个字节（端口为锁存器），没有名称时 name 为 null
+// ram 的长度是当前型号的内部RAM大小（8051 为 128，8052 为 256）
+// 各部分是带 Serialize 的快照结构，由 serde_json 写出
+
+use crate::cpu::peripherals::PSW;
+use crate::emulator::{Emulator, HaltReason};
+use serde::Serialize;
+
+// 格式版本
+pub const VERSION: u32 = 1;
+
+// 运行结束时的机器状态快照，字段顺序就是 JSON 中的顺序
+#[derive(Debug, Serialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    pub halt: Halt,
+    pub registers: Registers,
+    pub sfr: Vec<Sfr>,
+    pub ram: Vec<u8>,
+    pub counters: Counters,
+}
+
+// 停机原因：reason 取 HaltReason::id()，只有对应的停机原因才带 address/opcode 或 exit_code
+#[derive(Debug, Serialize)]
+pub struct Halt {
+    pub reason: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opcode: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<u8>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Registers {
+    pub acc: u8,
+    pub b: u8,
+    pub psw: u8,
+    pub sp: u8,
+    pub dptr: u16,
+    pub pc: u16,
+}
+
+// 一个 SFR 字节：没有名称时 name 输出为 null
+#[derive(Debug, Serialize)]
+pub struct Sfr {
+    pub address: u8,
+    pub name: Option<&'static str>,
+    pub value: u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Counters {
+    pub instructions: u64,
+    pub clock_cycles: u64,
+    pub machine_cycles: u64,
+}
+
+// 取得模拟器当前状态的快照
+pub fn snapshot(emulator: &Emulator) -> StateSnapshot {
+    let cpu = &emulator.cpu;
+    let reason = emulator.halt_reason;
+    let (address, opcode) = match reason {
+        HaltReason::UnknownOpcode(address, opcode) => (Some(address), Some(opcode)),
+        _ => (None, None),
+    };
+    let exit_code = match reason {
+        HaltReason::ExitCode(code) => Some(code),
+        _ => None,
+    };
+    let registers = &cpu.registers;
+    let ram_size = if cpu.variant.has_upper_ram() { 0x100 } else { 0x80 };
+
+    StateSnapshot {
+        version: VERSION,
+        halt: Halt { reason: reason.id(), address, opcode, exit_code },
+        registers: Registers {
+            acc: registers.acc,
+            b: registers.b,
+            psw: cpu.peek_sfr(PSW),
+            sp: registers.sp,
+            dptr: registers.dptr,
+            pc: registers.pc,
+        },
+        sfr: (0x80..=0xFFu8)
+            .map(|address| Sfr { address, name: emulator.sfr_label(address), value: cpu.peek_sfr(address) })
+            .collect(),
+        ram: cpu.ram[..ram_size].to_vec(),
+        counters: Counters {
+            instructions: emulator.instruction_count,
+            clock_cycles: emulator.clock_cycles,
+            machine_cycles: emulator.clock_cycles / 12,
+        },
+    }
+}
+
+pub fn to_json(emulator: &Emulator) -> String {
+    let mut out = serde_json::to_string_pretty(&snapshot(emulator)).expect("状态快照只含数字和字符串，序列化不会失败");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{emulator_with, run_to_halt};
+    use serde_json::Value;
+
+    #[test]
+    fn round_trips_the_final_state() {
+        // MOV A,#0x5A; MOV B,#3; MOV 0x30,#0x77; MOV DPTR,#0x1234; MOV 0xFF,#7
+        let mut emulator =
+            emulator_with(&[0x74, 0x5A, 0x75, 0xF0, 0x03, 0x75, 0x30, 0x77, 0x90, 0x12, 0x34, 0x75, 0xFF, 0x07]);
+        emulator.cpu.exit_port = Some(0xFF);
+        assert_eq!(run_to_halt(&mut emulator, 100), HaltReason::ExitCode(7));
+
+        let json: Value = serde_json::from_str(&to_json(&emulator)).unwrap();
+        assert_eq!(json["version"], VERSION);
+        assert_eq!(json["halt"]["reason"], "exit_code");
+        assert_eq!(json["halt"]["exit_code"], 7);
+        assert!(json["halt"].get("opcode").is_none());
+
+        let registers = &json["registers"];
+        assert_eq!(registers["acc"], emulator.cpu.registers.acc);
+        assert_eq!(registers["b"], 3);
+        assert_eq!(registers["dptr"], 0x1234);
+        assert_eq!(registers["pc"], emulator.cpu.registers.pc);
+        assert_eq!(registers["psw"], emulator.cpu.peek_sfr(PSW));
+
+        let sfr = json["sfr"].as_array().unwrap();
+        assert_eq!(sfr.len(), 128);
+        assert_eq!(sfr[0x90 - 0x80], serde_json::json!({"address": 0x90, "name": "P1", "value": 0xFF}));
+        assert_eq!(sfr[0xE0 - 0x80]["value"], 0x5A);
+        assert!(sfr[0xFF - 0x80]["name"].is_null());
+
+        let ram = json["ram"].as_array().unwrap();
+        assert_eq!(ram.len(), 0x80);
+        assert_eq!(ram[0x30], 0x77);
+
+        let counters = &json["counters"];
+        assert_eq!(counters["instructions"], emulator.instruction_count);
+        assert_eq!(counters["clock_cycles"], emulator.clock_cycles);
+        assert_eq!(counters["machine_cycles"], emulator.clock_cycles / 12);
+    }
+
+    #[test]
+    fn unknown_opcode_carries_address_and_opcode() {
+        let mut emulator = emulator_with(&[0x00, 0xA5]);
+        emulator.strict_opcodes = true;
+        run_to_halt(&mut emulator, 10);
+        let json: Value = serde_json::from_str(&to_json(&emulator)).unwrap();
+        assert_eq!(json["halt"], serde_json::json!({"reason": "unknown_opcode", "address": 1, "opcode": 0xA5}));
+    }
+}
@@ -0,0 +1,233 @@
+// 符号表 - 从汇编器生成的符号文件中读取 地址→标号 映射，用于调试输出
+// 支持简单的 "地址 标号" 列表，也能读取 Keil .M51 之类的 map 文件中
+// "C:0100H  PUBLIC  MAIN" 形式的行：取行内第一个地址和最后一个标识符
+// SDCC 的 .map（aslink 生成，"C:   00000062  _main   main"）取地址后面的符号名，跳过数据区的符号；
+// SDCC 的 .rst/.lst 列表只取 "0062   139 _main:" 这样的标号定义行
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+pub type SymbolTable = BTreeMap<u16, String>;
+
+// 从文件加载符号表
+pub fn load_symbols(path: &str) -> io::Result<SymbolTable> {
+    let text = fs::read_to_string(path)?;
+    let lower = path.to_ascii_lowercase();
+    if lower.ends_with(".rst") || lower.ends_with(".lst") {
+        return Ok(parse_listing_labels(&text));
+    }
+    Ok(parse_symbols(&text))
+}
+
+// 解析符号文本，无法识别的行直接忽略
+pub fn parse_symbols(text: &str) -> SymbolTable {
+    if text.contains("Defined In Module") {
+        return parse_aslink_map(text);
+    }
+
+    let mut symbols = SymbolTable::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let address = tokens.iter().find_map(|token| parse_address(token));
+        let name = tokens
+            .iter()
+            .rev()
+            .find(|token| parse_address(token).is_none() && is_identifier(token));
+
+        if let (Some(address), Some(name)) = (address, name) {
+            symbols.insert(address, name.to_string());
+        }
+    }
+
+    symbols
+}
+
+// aslink 的 map 文件：符号名紧跟在地址后面，最后一列是模块名
+// 数据区（内部RAM、位、外部RAM）的符号与程序地址无关，跳过
+fn parse_aslink_map(text: &str) -> SymbolTable {
+    const DATA_AREAS: [&str; 11] =
+        ["DSEG", "OSEG", "ISEG", "IABS", "BSEG", "BIT_BANK", "XSEG", "XISEG", "XABS", "PSEG", "SSEG"];
+    let mut symbols = SymbolTable::new();
+    let mut in_data_area = false;
+
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        // 区段表的行："CSEG   00000062   0000012A = ..."，记录之后的符号属于哪个区段
+        if let [area, address, ..] = tokens[..]
+            && is_identifier(area)
+            && parse_address(address).is_some()
+            && line.contains("bytes")
+        {
+            in_data_area = DATA_AREAS.contains(&area) || area.starts_with("REG_BANK");
+            continue;
+        }
+        if in_data_area {
+            continue;
+        }
+
+        let Some(index) = tokens.iter().position(|token| parse_address(token).is_some()) else {
+            continue;
+        };
+        if let (Some(address), Some(name)) = (parse_address(tokens[index]), tokens.get(index + 1))
+            && is_identifier(name)
+        {
+            symbols.insert(address, name.to_string());
+        }
+    }
+
+    symbols
+}
+
+// 汇编列表 (.rst/.lst)：只取以冒号结尾的标号定义，第一列是地址
+// 指令行也以地址开头，不能当成符号；"00101$:" 之类的局部标号不是标识符，同样跳过
+pub fn parse_listing_labels(text: &str) -> SymbolTable {
+    let mut symbols = SymbolTable::new();
+
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let label = tokens
+            .iter()
+            .filter_map(|token| token.strip_suffix(':'))
+            .find(|name| is_identifier(name));
+        if let (Some(address), Some(name)) = (tokens.first().and_then(|token| parse_address(token)), label) {
+            symbols.insert(address, name.to_string());
+        }
+    }
+
+    symbols
+}
+
+// 地址所在的标号：正好在标号处时为标号本身，否则为前面最近的标号加偏移，例如 "DELAY+0x3"
+pub fn symbolize(symbols: &SymbolTable, address: u16) -> Option<String> {
+    let (&start, name) = symbols.range(..=address).next_back()?;
+    if start == address {
+        Some(name.clone())
+    } else {
+        Some(format!("{}+{:#x}", name, address - start))
+    }
+}
+
+// 有标号时显示 标号 或 标号+偏移，否则显示十六进制地址
+pub fn format_address(symbols: &SymbolTable, address: u16) -> String {
+    symbolize(symbols, address).unwrap_or_else(|| format!("{:#06x}", address))
+}
+
+// 识别地址写法：0x0100、0100H、C:0100H，以及不带前后缀的纯十六进制数
+fn parse_address(token: &str) -> Option<u16> {
+    let token = token.strip_prefix("C:").or_else(|| token.strip_prefix("c:")).unwrap_or(token);
+    let (digits, prefixed) = match token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+        Some(rest) => (rest, true),
+        None => (token.strip_suffix('H').or_else(|| token.strip_suffix('h')).unwrap_or(token), false),
+    };
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    // 汇编器的十六进制数必须以数字开头，避免把 "BEACH" 之类的标号当成地址
+    if !prefixed && !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    // aslink 的 map 文件使用8位十六进制地址
+    u32::from_str_radix(digits, 16).ok().and_then(|value| u16::try_from(value).ok())
+}
+
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '?' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '?')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassembler;
+    use crate::test_support::shared_output;
+    use crate::emulator::Emulator;
+    use std::fs;
+
+    #[test]
+    fn ljmp_to_loaded_symbol_renders_label() {
+        let path = std::env::temp_dir().join(format!("mcs51-symbols-{}.txt", std::process::id()));
+        fs::write(&path, "0x0100 MAIN\nC:0200H  PUBLIC  DELAY\n").unwrap();
+        let mut emulator = Emulator::new(false);
+        let loaded = emulator.load_symbols(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), 2);
+
+        // LJMP DELAY; LJMP 0x0050（在所有标号之前）
+        emulator.cpu.rom[..6].copy_from_slice(&[0x02, 0x02, 0x00, 0x02, 0x00, 0x50]);
+        assert_eq!(disassembler::disassemble(&emulator.cpu.rom, 0, &emulator.symbols).text, "ljmp DELAY");
+        assert_eq!(disassembler::disassemble(&emulator.cpu.rom, 3, &emulator.symbols).text, "ljmp 0x0050");
+    }
+
+    #[test]
+    fn lcall_trace_line_names_the_function() {
+        let mut emulator = Emulator::new(false);
+        emulator.set_symbols(parse_symbols("0x0010 delay\n"));
+        // LCALL delay; SJMP $ / delay: NOP; NOP; RET
+        emulator.cpu.rom[..5].copy_from_slice(&[0x12, 0x00, 0x10, 0x80, 0xFE]);
+        emulator.cpu.rom[0x10..0x13].copy_from_slice(&[0x00, 0x00, 0x22]);
+        let (output, out) = shared_output();
+        emulator.set_trace_output(out);
+        emulator.trace = true;
+        for _ in 0..4 {
+            emulator.step();
+        }
+
+        let output = String::from_utf8(output.borrow().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].contains("lcall delay"), "{}", lines[0]);
+        assert!(lines[3].contains("ret"), "{}", lines[3]);
+        assert_eq!(symbolize(&emulator.symbols, 0x0012).as_deref(), Some("delay+0x2"));
+        assert_eq!(format_address(&emulator.symbols, 0x0003), "0x0003");
+    }
+
+    #[test]
+    fn sdcc_map_keeps_code_symbols_only() {
+        let map = "\
+Area                    Addr        Size        Decimal Bytes (Attributes)
+--------------------    ----        ----        ------- ----- ------------
+DSEG                    00000008    00000002 =           2. bytes (REL,CON)
+
+      Value  Global              Global Defined In Module
+      -----  --------------------------------
+     D:  00000008  _counter            main
+
+Area                    Addr        Size        Decimal Bytes (Attributes)
+--------------------    ----        ----        ------- ----- ------------
+CSEG                    00000062    0000012A =         298. bytes (REL,CON,CODE)
+
+      Value  Global              Global Defined In Module
+      -----  --------------------------------
+     C:  00000062  _main               main
+     C:  00000080  _delay              main
+";
+        let symbols = parse_symbols(map);
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[&0x0062], "_main");
+        assert_eq!(symbols[&0x0080], "_delay");
+    }
+
+    #[test]
+    fn sdcc_listing_takes_label_definitions_only() {
+        let rst = "\
+                                     50 ;\tmain.c:5: void main(void)
+      000062                         51 _main:
+      000062 12 00 80         [24]   52 \tlcall\t_delay
+      000065                         53 00101$:
+      000065 80 FE            [24]   54 \tsjmp\t00101$
+";
+        let symbols = parse_listing_labels(rst);
+        assert_eq!(symbols.into_iter().collect::<Vec<_>>(), [(0x0062, "_main".to_string())]);
+    }
+}
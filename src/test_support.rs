@@ -0,0 +1,136 @@
+// 单元测试共用的小工具：把手工汇编的机器码放进 ROM，再逐条执行
+
+use crate::cpu::peripherals::PinDevice;
+use crate::emulator::{Emulator, HaltReason};
+use log::{Level, Log, Metadata, Record};
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::sync::Once;
+
+// 把机器码放在 ROM 的 0x0000 处（与加载映像一样记录最高地址）
+pub(crate) fn load(emulator: &mut Emulator, program: &[u8]) {
+    emulator.cpu.rom[..program.len()].copy_from_slice(program);
+    emulator.cpu.rom_end = Some(program.len().saturating_sub(1) as u16);
+}
+
+// 装好程序的 8051 模拟器（默认配置）
+pub(crate) fn emulator_with(program: &[u8]) -> Emulator {
+    let mut emulator = Emulator::new(false);
+    load(&mut emulator, program);
+    emulator
+}
+
+// 执行 steps 步（停机后不再执行）
+pub(crate) fn run_steps(emulator: &mut Emulator, steps: usize) {
+    for _ in 0..steps {
+        emulator.step();
+    }
+}
+
+// 把几段机器码分别放在各自的地址（例如复位向量、中断向量和主程序）
+pub(crate) fn emulator_with_blocks(blocks: &[(u16, &[u8])]) -> Emulator {
+    let mut emulator = Emulator::new(false);
+    for &(address, code) in blocks {
+        let start = address as usize;
+        emulator.cpu.rom[start..start + code.len()].copy_from_slice(code);
+        let end = (start + code.len()).saturating_sub(1) as u16;
+        emulator.cpu.rom_end = emulator.cpu.rom_end.max(Some(end));
+    }
+    emulator
+}
+
+// 驱动一个输入引脚的外部器件，电平由测试通过共享的 Cell 改变
+pub(crate) struct PinDriver {
+    port: u8,
+    bit: u8,
+    level: Rc<Cell<bool>>,
+}
+
+impl PinDriver {
+    // 挂到模拟器上，返回控制电平的句柄（初始为高电平）
+    pub(crate) fn attach(emulator: &mut Emulator, port: u8, bit: u8) -> Rc<Cell<bool>> {
+        let level = Rc::new(Cell::new(true));
+        emulator.cpu.attach_device(Box::new(PinDriver { port, bit, level: level.clone() }));
+        level
+    }
+}
+
+impl PinDevice for PinDriver {
+    fn pin_changed(&mut self, _port: u8, _bit: u8, _high: bool) {}
+
+    fn sample_input(&mut self, port: u8, bit: u8) -> Option<bool> {
+        (port == self.port && bit == self.bit).then(|| self.level.get())
+    }
+}
+
+// 执行到停机为止（最多 max_steps 步），返回停机原因；步数用完时返回 Running
+pub(crate) fn run_to_halt(emulator: &mut Emulator, max_steps: usize) -> HaltReason {
+    for _ in 0..max_steps {
+        let reason = emulator.step();
+        if reason != HaltReason::Running {
+            return reason;
+        }
+    }
+    HaltReason::Running
+}
+
+// 把写入的字节收集到共享的缓冲区里（串口输出、跟踪输出）
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 新的共享缓冲区：返回缓冲区的句柄和写入它的输出
+pub(crate) fn shared_output() -> (Rc<RefCell<Vec<u8>>>, Box<dyn Write>) {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    (output.clone(), Box::new(SharedOutput(output)))
+}
+
+// 把串口输出接到缓冲区，返回缓冲区的句柄
+pub(crate) fn capture_serial(emulator: &mut Emulator) -> Rc<RefCell<Vec<u8>>> {
+    let (output, sink) = shared_output();
+    emulator.cpu.set_serial_sink(sink);
+    output
+}
+
+// 测试用的 logger：把每个线程产生的日志记录在该线程自己的缓冲区里，并行运行的测试互不干扰
+struct TestLogger;
+
+thread_local! {
+    static CAPTURED: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Log for TestLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        CAPTURED.with(|captured| captured.borrow_mut().push((record.level(), record.args().to_string())));
+    }
+
+    fn flush(&self) {}
+}
+
+static TEST_LOGGER: TestLogger = TestLogger;
+
+// 运行 f，返回这期间当前线程产生的日志（级别和消息）
+pub(crate) fn capture_logs(f: impl FnOnce()) -> Vec<(Level, String)> {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&TEST_LOGGER).expect("测试进程里只安装这一个 logger");
+        log::set_max_level(log::LevelFilter::Trace);
+    });
+    CAPTURED.with(|captured| captured.borrow_mut().clear());
+    f();
+    CAPTURED.with(|captured| captured.take())
+}
@@ -0,0 +1,149 @@
+// VCD 波形输出（--vcd）：把端口锁存器的每一位（以及可选的 TH0/TL0/ACC）写成 GTKWave 等工具可以打开的 VCD 文件
+// 时间单位为 1ns，时刻由 clock_cycles 按晶振频率换算；每一步执行完后比较一次，有变化时写出时刻和变化的信号
+// 每次写出后立即刷新，运行被中断时文件仍然可以打开
+
+use crate::cpu::peripherals::{ACC, P0, P1, P2, P3, TH0, TL0};
+use crate::cpu::CPU;
+use std::io::{self, Write};
+
+// 端口：SFR 地址和信号名前缀
+const PORTS: [(u8, &str); 4] = [(P0, "P0"), (P1, "P1"), (P2, "P2"), (P3, "P3")];
+
+// 可选的字节信号
+const BYTE_SIGNALS: [(u8, &str); 3] = [(TH0, "TH0"), (TL0, "TL0"), (ACC, "ACC")];
+
+pub struct VcdWriter {
+    out: Box<dyn Write>,
+    ports: [u8; 4],         // 上次写出的端口锁存器
+    bytes: Vec<(u8, u8)>,   // 字节信号：SFR 地址和上次写出的值（没有选择时为空）
+    last_time: u64,         // 上次写出的时刻（ns）
+}
+
+// 信号的标识符：端口位依次为 '!'..'@'（32 个），字节信号接在后面
+fn identifier(index: usize) -> char {
+    (b'!' + index as u8) as char
+}
+
+impl VcdWriter {
+    // 写出文件头（信号声明）和所有信号在时刻 0 的初值；with_bytes 时另外记录 TH0、TL0 和 ACC
+    pub fn new(mut out: Box<dyn Write>, cpu: &CPU, with_bytes: bool) -> io::Result<Self> {
+        writeln!(out, "$version mcs51-emulator {} $end", env!("CARGO_PKG_VERSION"))?;
+        writeln!(out, "$timescale 1ns $end")?;
+        writeln!(out, "$scope module mcs51 $end")?;
+        for (port, &(_, name)) in PORTS.iter().enumerate() {
+            for bit in 0..8 {
+                writeln!(out, "$var wire 1 {} {}_{} $end", identifier(port * 8 + bit), name, bit)?;
+            }
+        }
+        let signals: &[(u8, &str)] = if with_bytes { &BYTE_SIGNALS } else { &[] };
+        for (index, &(_, name)) in signals.iter().enumerate() {
+            writeln!(out, "$var wire 8 {} {} $end", identifier(32 + index), name)?;
+        }
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$enddefinitions $end")?;
+
+        let mut writer = VcdWriter {
+            out,
+            ports: PORTS.map(|(address, _)| cpu.peek_sfr(address)),
+            bytes: signals.iter().map(|&(address, _)| (address, cpu.peek_sfr(address))).collect(),
+            last_time: 0,
+        };
+        writeln!(writer.out, "#0")?;
+        writeln!(writer.out, "$dumpvars")?;
+        for port in 0..4 {
+            for bit in 0..8 {
+                writer.write_bit(port, bit)?;
+            }
+        }
+        for index in 0..writer.bytes.len() {
+            writer.write_byte(index)?;
+        }
+        writeln!(writer.out, "$end")?;
+        writer.out.flush()?;
+        Ok(writer)
+    }
+
+    // 比较当前的端口锁存器和字节信号，有变化时写出时刻（clock_cycles 换算为 ns）和变化的信号
+    pub fn sample(&mut self, cpu: &CPU, clock_cycles: u64) -> io::Result<()> {
+        let ports = PORTS.map(|(address, _)| cpu.peek_sfr(address));
+        let changed_bytes: Vec<usize> = (0..self.bytes.len())
+            .filter(|&index| cpu.peek_sfr(self.bytes[index].0) != self.bytes[index].1)
+            .collect();
+        if ports == self.ports && changed_bytes.is_empty() {
+            return Ok(());
+        }
+
+        let time = (clock_cycles as u128 * 1_000_000_000 / cpu.clock_frequency.max(1) as u128) as u64;
+        if time != self.last_time {
+            writeln!(self.out, "#{}", time)?;
+            self.last_time = time;
+        }
+        let old_ports = self.ports;
+        self.ports = ports;
+        for port in 0..4 {
+            let changed = old_ports[port] ^ ports[port];
+            for bit in (0..8).filter(|bit| changed & (1 << bit) != 0) {
+                self.write_bit(port, bit)?;
+            }
+        }
+        for index in changed_bytes {
+            self.bytes[index].1 = cpu.peek_sfr(self.bytes[index].0);
+            self.write_byte(index)?;
+        }
+        self.out.flush()
+    }
+
+    fn write_bit(&mut self, port: usize, bit: usize) -> io::Result<()> {
+        let value = (self.ports[port] >> bit) & 1;
+        writeln!(self.out, "{}{}", value, identifier(port * 8 + bit))
+    }
+
+    fn write_byte(&mut self, index: usize) -> io::Result<()> {
+        writeln!(self.out, "b{:08b} {}", self.bytes[index].1, identifier(32 + index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{emulator_with, run_steps, shared_output};
+
+    // 循环: CPL P1.0; SJMP 循环：每圈 1+2 个机器周期，12MHz 下 P1.0 每 3µs 翻转一次
+    const TOGGLE_P1_0: [u8; 4] = [0xB2, 0x90, 0x80, 0xFC];
+
+    // 第一个变化时刻之后的内容
+    fn changes(vcd: &str) -> Vec<&str> {
+        let body = &vcd[vcd.find("$dumpvars").unwrap()..];
+        body.lines().skip_while(|line| *line != "$end").skip(1).collect()
+    }
+
+    #[test]
+    fn p1_0_toggle_loop_gives_evenly_spaced_edges() {
+        let mut emulator = emulator_with(&TOGGLE_P1_0);
+        let (output, out) = shared_output();
+        emulator.start_vcd(out, false).unwrap();
+        run_steps(&mut emulator, 8);
+        let vcd = String::from_utf8(output.borrow().clone()).unwrap();
+
+        // P1 是第二个端口，P1_0 的标识符为 '!'+8
+        assert!(vcd.contains("$timescale 1ns $end"));
+        assert!(vcd.contains("$var wire 1 ) P1_0 $end"));
+        assert!(vcd.contains("$dumpvars\n"));
+        assert_eq!(
+            changes(&vcd),
+            ["#1000", "0)", "#4000", "1)", "#7000", "0)", "#10000", "1)"]
+        );
+    }
+
+    #[test]
+    fn byte_signals_follow_acc_and_timer0() {
+        // MOV A,#0x5A; MOV TL0,#0x34; SJMP $
+        let mut emulator = emulator_with(&[0x74, 0x5A, 0x75, 0x8A, 0x34, 0x80, 0xFE]);
+        let (output, out) = shared_output();
+        emulator.start_vcd(out, true).unwrap();
+        run_steps(&mut emulator, 2);
+        let vcd = String::from_utf8(output.borrow().clone()).unwrap();
+
+        assert!(vcd.contains("$var wire 8 A TH0 $end\n$var wire 8 B TL0 $end\n$var wire 8 C ACC $end\n"));
+        assert_eq!(changes(&vcd), ["#1000", "b01011010 C", "#3000", "b00110100 B"]);
+    }
+}
@@ -0,0 +1,6 @@
+// 集成测试共用的辅助函数
+
+// tests/fixtures 下的测试文件的路径
+pub fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
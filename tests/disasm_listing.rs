@@ -0,0 +1,33 @@
+// --disasm 的反汇编列表与检入的参考列表逐行比较
+
+mod common;
+
+use common::fixture;
+use std::fs;
+use std::process::Command;
+
+fn disasm(extra: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("listing.hex"))
+        .args(["--disasm", "--quiet", "--symbols"])
+        .arg(fixture("listing.sym"))
+        .args(extra)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// listing.hex 包含各种寻址方式、相对跳转、保留操作码 0xA5 和两个标号
+#[test]
+fn listing_matches_golden_file() {
+    let golden = fs::read_to_string(fixture("listing.lst")).unwrap();
+    assert_eq!(disasm(&[]), golden);
+}
+
+#[test]
+fn listing_starts_at_entry() {
+    let golden = fs::read_to_string(fixture("listing.lst")).unwrap();
+    let from_entry = &golden[golden.find("read_byte:\n").unwrap()..];
+    assert_eq!(disasm(&["--entry", "0x0016"]), from_entry);
+}
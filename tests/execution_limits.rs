@@ -0,0 +1,26 @@
+// 达到 --max-instructions / --max-cycles 上限时用退出码 3 结束，与正常结束区分
+
+mod common;
+
+use common::fixture;
+use std::process::Command;
+
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator")).arg(fixture("endless_loop.hex")).args(args).output().unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// endless_loop.hex：INC A; SJMP 0x0000
+#[test]
+fn instruction_limit_exits_with_code_3() {
+    let (code, stdout) = run(&["--max-instructions", "10"]);
+    assert_eq!(code, Some(3));
+    assert!(stdout.contains("指令 10 条"), "{}", stdout);
+}
+
+#[test]
+fn cycle_limit_exits_with_code_3() {
+    let (code, stdout) = run(&["--max-cycles", "300"]);
+    assert_eq!(code, Some(3));
+    assert!(stdout.contains("--max-cycles"), "{}", stdout);
+}
@@ -0,0 +1,40 @@
+// 退出端口默认是 0xFF，--exit-port 换成别的 SFR 地址，--exit-port none 关闭拦截
+
+mod common;
+
+use common::fixture;
+use std::process::Command;
+
+fn run(args: &[&str]) -> Option<i32> {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("exit_1.hex"))
+        .arg("--quiet")
+        .args(args)
+        .output()
+        .unwrap();
+    output.status.code()
+}
+
+// exit_1.hex：MOV 0xFF,#1; SJMP $
+#[test]
+fn default_exit_port_is_0xff() {
+    assert_eq!(run(&[]), Some(1));
+}
+
+#[test]
+fn exit_port_none_lets_the_program_run_on() {
+    // 不拦截时 MOV 0xFF,#1 只是普通的 SFR 写入，程序停在 SJMP $ 上正常结束
+    assert_eq!(run(&["--exit-port", "none"]), Some(0));
+    assert_eq!(run(&["--exit-port", "0xFE"]), Some(0));
+}
+
+#[test]
+fn invalid_exit_port_is_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("exit_1.hex"))
+        .args(["--exit-port", "0x30"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--exit-port 的地址无效: 0x30"));
+}
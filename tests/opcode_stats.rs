@@ -0,0 +1,32 @@
+// --stats：运行结束后按执行次数列出操作码，--quiet 时同样输出
+
+mod common;
+
+use common::fixture;
+use std::process::Command;
+
+// sum_loop_exit.hex：CLR A; MOV R7,#10; 循环: ADD A,R7; DJNZ R7,循环; MOV 0xFF,#0
+#[test]
+fn stats_table_lists_exact_counts() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("sum_loop_exit.hex"))
+        .args(["--exit-port", "0xFF", "--stats", "--quiet"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "操作码统计（共 23 次）：",
+            "  操作码  助记符        次数      占比",
+            "  0x2f    ADD                10   43.48%",
+            "  0xdf    DJNZ               10   43.48%",
+            "  0x75    MOV                 1    4.35%",
+            "  0x7f    MOV                 1    4.35%",
+            "  0xe4    CLR                 1    4.35%",
+            "未实现/保留操作码 0 次，中断响应 0 次",
+        ]
+    );
+}
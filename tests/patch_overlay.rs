@@ -0,0 +1,30 @@
+// --patch 在所有程序文件之后覆盖已加载的映像；同一个文件用 --load 加载时仍然按冲突报错
+
+mod common;
+
+use common::fixture;
+use std::process::Command;
+
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("exit_1.hex"))
+        .args(["--exit-port", "0xFF", "--quiet"])
+        .args(args)
+        .output()
+        .unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+// exit_1.hex：MOV 0xFF,#1; SJMP $；exit_patch_42.hex 把 0x0002 处的立即数改为 42
+#[test]
+fn patch_changes_the_exit_code() {
+    assert_eq!(run(&[]).0, Some(1));
+    assert_eq!(run(&["--patch", &fixture("exit_patch_42.hex")]).0, Some(42));
+}
+
+#[test]
+fn same_file_through_load_reports_the_conflict() {
+    let (code, stderr) = run(&["--load", &fixture("exit_patch_42.hex")]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("重叠且内容不同"), "{}", stderr);
+}
@@ -0,0 +1,62 @@
+// --quiet 关掉端口写入提示、加载信息和运行统计，程序自己的输出照常
+
+mod common;
+
+use common::fixture;
+use std::process::{Command, Output};
+
+// console_ok.hex 向 P1 写两次，然后写退出端口
+fn run(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("console_ok.hex"))
+        .args(["--exit-port", "0xFF"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    output
+}
+
+#[test]
+fn quiet_port_writing_program_prints_nothing() {
+    let output = run(&["--quiet"]);
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn default_verbosity_reports_port_writes() {
+    let output = run(&[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("写入P1端口: 0x4f"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("程序成功从"));
+}
+
+#[test]
+fn quiet_keeps_console_port_output() {
+    let output = run(&["--quiet", "--console-port", "P1"]);
+    assert_eq!(output.stdout, b"OK");
+}
+
+// serial_overrun.hex：初始化串口（模式1，REN）后只做延时，从不读 SBUF，然后写退出端口；
+// serial_xy.txt 的两个字节中第二个因 RI 未清除而丢弃
+fn run_overrun(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("serial_overrun.hex"))
+        .args(["--serial-in", &fixture("serial_xy.txt")])
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    output
+}
+
+#[test]
+fn quiet_hides_serial_statistics() {
+    let output = run_overrun(&["--quiet"]);
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = run_overrun(&[]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("串口统计：接收溢出 1 次，发送冲突 0 次"));
+}
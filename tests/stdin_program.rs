@@ -0,0 +1,35 @@
+// 程序文件名为 "-" 时从标准输入读取 HEX 记录，例如 汇编器 | mcs51-emulator -
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// MOV 0xFF,#42：写退出端口，退出码为 42
+const EXIT_42: &str = ":0300000075FF2A5F\n:00000001FF\n";
+
+fn run_with_stdin(args: &[&str], input: &str) -> std::process::Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn program_piped_through_stdin_runs() {
+    let output = run_with_stdin(&["-", "--exit-port", "0xFF"], EXIT_42);
+    assert_eq!(output.status.code(), Some(42));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("程序成功从 标准输入 加载（3 字节）"), "{}", stdout);
+}
+
+#[test]
+fn bad_record_on_stdin_names_standard_input() {
+    let output = run_with_stdin(&["-", "--exit-port", "0xFF"], ":0300000075FF2A5E\n");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("从 标准输入 加载程序失败: 第 1 行: 校验和错误"), "{}", stderr);
+}
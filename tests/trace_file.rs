@@ -0,0 +1,58 @@
+// --trace <文件> 把逐条指令的跟踪写入文件，标准输出只留给程序自己的输出
+
+mod common;
+
+use common::fixture;
+use std::fs;
+use std::process::Command;
+
+// console_ok.hex：MOV P1,#'O'; INC A; MOV P1,#'K'; MOV 0xFF,#0（写退出端口）
+fn run(trace: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("console_ok.hex"))
+        .args(["--exit-port", "0xFF", "--quiet", "--console-port", "P1", "--trace", trace])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn trace_file_gets_one_line_per_instruction_and_stdout_keeps_program_output() {
+    let path = std::env::temp_dir().join(format!("mcs51-trace-{}.txt", std::process::id()));
+    let stdout = run(path.to_str().unwrap());
+    let trace = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(stdout, "OK");
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", trace);
+    assert!(lines[0].starts_with("0000: 75 90 4F  mov P1, #0x4f"), "{}", lines[0]);
+    assert!(lines[1].starts_with("0003: 04        inc A"), "{}", lines[1]);
+    assert!(lines[3].starts_with("0007: 75 FF 00"), "{}", lines[3]);
+}
+
+#[test]
+fn trace_dash_keeps_the_trace_on_stdout() {
+    let stdout = run("-");
+    assert!(stdout.contains("0003: 04        inc A"), "{}", stdout);
+    assert!(stdout.contains('O') && stdout.contains('K'));
+}
+
+#[test]
+fn debug_lines_follow_the_trace_into_the_file() {
+    let path = std::env::temp_dir().join(format!("mcs51-debug-trace-{}.txt", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("console_ok.hex"))
+        .args(["--exit-port", "0xFF", "--quiet", "--console-port", "P1", "--debug", "--trace"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let trace = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "OK");
+    // 每条指令一行 --debug 输出（"[时钟周期][地址] ..."）和一行跟踪
+    assert_eq!(trace.lines().filter(|line| line.starts_with('[')).count(), 4, "{}", trace);
+    assert_eq!(trace.lines().count(), 8, "{}", trace);
+}
@@ -0,0 +1,40 @@
+// --until <地址>[:次数]：第几次到达该地址时在执行之前停下，显示寄存器，--state-json 中的停机原因为 until
+
+mod common;
+
+use common::fixture;
+use std::fs;
+use std::process::Command;
+
+// sum_loop.hex：CLR A; MOV R7,#10; 循环: ADD A,R7; DJNZ R7,循环; SJMP $
+#[test]
+fn until_with_count_stops_on_the_fifth_iteration() {
+    let state = std::env::temp_dir().join(format!("until_state_{}.json", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("sum_loop.hex"))
+        .args(["--until", "0x0003:5", "--state-json"])
+        .arg(&state)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains("到达 --until 地址 0x0003"), "{}", stdout);
+    assert!(stdout.contains("A=22 "), "{}", stdout);
+    assert!(stdout.contains("R7=06"), "{}", stdout);
+
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&state).unwrap()).unwrap();
+    fs::remove_file(&state).unwrap();
+    assert_eq!(json["halt"]["reason"], "until");
+    assert_eq!(json["registers"]["pc"], 3);
+}
+
+#[test]
+fn bad_until_argument_is_rejected() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("sum_loop.hex"))
+        .args(["--until", "0x0003:0"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--until 的参数无效: 0x0003:0"));
+}
@@ -0,0 +1,27 @@
+// --vcd：边运行边写入波形，运行被执行上限截断时文件仍然完整可读
+
+mod common;
+
+use common::fixture;
+use std::fs;
+use std::process::Command;
+
+// p1_toggle.hex：循环: CPL P1.0; SJMP 循环
+#[test]
+fn truncated_run_leaves_a_complete_waveform() {
+    let path = std::env::temp_dir().join(format!("p1_toggle_{}.vcd", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_mcs51-emulator"))
+        .arg(fixture("p1_toggle.hex"))
+        .args(["--quiet", "--max-instructions", "8", "--vcd"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+
+    let vcd = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(vcd.starts_with("$version mcs51-emulator "), "{}", vcd);
+    assert!(vcd.contains("$enddefinitions $end\n"));
+    assert!(vcd.ends_with("#10000\n1)\n"), "{}", vcd);
+    assert_eq!(vcd.matches("\n#").count(), 5);
+}